@@ -0,0 +1,42 @@
+//! Shared by the benches: an allocation counter and a generated page.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+pub fn allocations() -> usize {
+    ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+// A page of articles with attributes, entities, comments and long text
+pub fn document(size: usize) -> String {
+    let mut html = String::from("<!DOCTYPE html><html><head><title>Bench</title></head><body>");
+    let mut index = 0;
+    while html.len() < size {
+        html.push_str(&format!(
+            "<article id=\"post-{index}\" class=\"post featured\" data-index={index}>\n  \
+             <h2><a href=\"/posts/{index}?ref=home&amp;page=2\">Post number {index}</a></h2>\n  \
+             <!-- teaser -->\n  <p>Lorem ipsum dolor sit amet, consectetur adipiscing elit, \
+             sed do eiusmod tempor incididunt ut labore et dolore magna aliqua &mdash; \
+             caf\u{e9} na\u{ef}ve 5 &lt; 6.</p>\n  <img src=\"/img/{index}.png\" alt=\"\" \
+             loading=lazy><br/>\n</article>\n"
+        ));
+        index += 1;
+    }
+    html.push_str("</body></html>");
+    html
+}
@@ -0,0 +1,59 @@
+//! Allocations made building a tree, run with
+//! `cargo bench --bench parse [--features smallvec,bumpalo] [-- page.html...]`.
+//! Without pages it parses a generated one.
+
+mod common;
+
+use std::time::Instant;
+
+use html_parser::{parse_document_with, ParseMode, ParseOptions};
+
+#[global_allocator]
+static ALLOCATOR: common::CountingAllocator = common::CountingAllocator;
+
+fn main() {
+    let mut pages: Vec<(String, String)> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(|path| {
+            let html = std::fs::read_to_string(&path).unwrap();
+            (path, html)
+        })
+        .collect();
+    if pages.is_empty() {
+        pages.push(("generated".to_string(), common::document(4 << 20)));
+    }
+    // Real pages are rarely well nested
+    let options = ParseOptions {
+        mode: ParseMode::Lenient,
+        ..Default::default()
+    };
+    for (name, html) in &pages {
+        let before = common::allocations();
+        let start = Instant::now();
+        let document = parse_document_with(html, &options).unwrap();
+        let elapsed = start.elapsed().as_secs_f64();
+        let allocations = common::allocations() - before;
+        drop(document);
+        println!(
+            "parse {name}: {:.1} KiB, {allocations} allocations, {:.1} ms",
+            html.len() as f64 / 1024.0,
+            elapsed * 1000.0
+        );
+
+        #[cfg(feature = "bumpalo")]
+        {
+            let arena = html_parser::Bump::new();
+            let before = common::allocations();
+            let start = Instant::now();
+            let document = html_parser::parse_document_in(html, &options, &arena).unwrap();
+            let elapsed = start.elapsed().as_secs_f64();
+            let allocations = common::allocations() - before;
+            drop(document);
+            println!(
+                "  in an arena: {allocations} allocations, {:.1} ms",
+                elapsed * 1000.0
+            );
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Tokenizer throughput and allocations, run with `cargo bench`.
+
+mod common;
+
+use std::time::Instant;
+
+use html_parser::{start_tags, Tokens};
+
+#[global_allocator]
+static ALLOCATOR: common::CountingAllocator = common::CountingAllocator;
+
+fn main() {
+    let html = common::document(16 << 20);
+    let runs = 5;
+    let mut best = f64::MAX;
+    let mut tokens = 0;
+    let mut allocations = 0;
+    for _ in 0..runs {
+        let start = Instant::now();
+        let mut iter = Tokens::new(&html);
+        let before = common::allocations();
+        tokens = 0;
+        for token in iter.by_ref() {
+            token.unwrap();
+            tokens += 1;
+        }
+        allocations = common::allocations() - before;
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    let megabytes = html.len() as f64 / (1 << 20) as f64;
+    println!(
+        "tokenize: {megabytes:.1} MiB, {tokens} tokens, {:.0} MiB/s, {allocations} allocations",
+        megabytes / best
+    );
+
+    // A scan for tag names only, leaving attributes unparsed
+    let mut best = f64::MAX;
+    let mut tags = 0;
+    for _ in 0..runs {
+        let start = Instant::now();
+        tags = 0;
+        for tag in start_tags(&html) {
+            if tag.unwrap().name() == "a" {
+                tags += 1;
+            }
+        }
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    println!("start tags: {tags} links, {:.0} MiB/s", megabytes / best);
+}
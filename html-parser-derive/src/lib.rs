@@ -0,0 +1,84 @@
+//! `#[derive(FromHtml)]` for html-parser, re-exported from there behind its
+//! `derive` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitStr};
+
+/// Implements `html_parser::FromHtml` for a struct with named fields.
+///
+/// Each field is read from the text of the first element matching its
+/// `#[html(select = "...")]` selector, or from the element itself without
+/// one. `#[html(attr = "...")]` reads that attribute instead of the text.
+/// `Option` fields may be missing and `Vec` fields collect every match.
+#[proc_macro_derive(FromHtml, attributes(html))]
+pub fn derive_from_html(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(&input, "FromHtml needs named fields")),
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input,
+                "FromHtml can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut values = Vec::new();
+    for field in fields {
+        let name = field.ident.as_ref().expect("named field");
+        let mut select = None;
+        let mut attr = None;
+        for attribute in field
+            .attrs
+            .iter()
+            .filter(|attribute| attribute.path().is_ident("html"))
+        {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("select") {
+                    select = Some(meta.value()?.parse::<LitStr>()?);
+                } else if meta.path.is_ident("attr") {
+                    attr = Some(meta.value()?.parse::<LitStr>()?);
+                } else {
+                    return Err(meta.error("expected `select` or `attr`"));
+                }
+                Ok(())
+            })?;
+        }
+        let select = optional(select);
+        let attr = optional(attr);
+        let field_name = name.to_string();
+        values.push(quote! {
+            #name: ::html_parser::__extract_field(element, #field_name, #select, #attr)?
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::html_parser::FromHtml for #ident #type_generics #where_clause {
+            fn from_element(
+                element: &::html_parser::HtmlElement<'_>,
+            ) -> ::core::result::Result<Self, ::html_parser::HtmlError> {
+                ::core::result::Result::Ok(Self { #(#values,)* })
+            }
+        }
+    })
+}
+
+fn optional(value: Option<LitStr>) -> TokenStream2 {
+    match value {
+        Some(value) => quote!(::core::option::Option::Some(#value)),
+        None => quote!(::core::option::Option::None),
+    }
+}
@@ -0,0 +1,45 @@
+/* The C interface of html-parser, built with the `capi` feature.
+ *
+ * Documents and selections are opaque handles freed with their `_free`
+ * function. Strings returned are NUL-terminated UTF-8, freed with
+ * `html_string_free`. Functions that can fail return NULL and, when `error`
+ * isn't NULL, store a message there that's freed the same way.
+ */
+
+#ifndef HTML_PARSER_H
+#define HTML_PARSER_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct HtmlParserDocument html_document;
+typedef struct HtmlParserSelection html_selection;
+
+/* Parses `len` bytes of UTF-8, forgiving malformed markup when `lenient`. */
+html_document *html_parse(const uint8_t *data, size_t len, bool lenient, char **error);
+void html_document_free(html_document *document);
+char *html_document_serialize(const html_document *document);
+
+/* The elements matching a CSS selector, in document order. */
+html_selection *html_document_select(const html_document *document, const char *selector,
+                                     char **error);
+size_t html_selection_len(const html_selection *selection);
+/* These return NULL for an index past the end, and `html_selection_attr`
+ * also when the element doesn't have the attribute. */
+char *html_selection_outer_html(const html_selection *selection, size_t index);
+char *html_selection_text(const html_selection *selection, size_t index);
+char *html_selection_attr(const html_selection *selection, size_t index, const char *name);
+void html_selection_free(html_selection *selection);
+
+void html_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,115 @@
+use alloc::borrow::Cow;
+use core::marker::PhantomData;
+
+#[cfg(feature = "bumpalo")]
+use bumpalo::Bump;
+
+use crate::prelude::*;
+#[cfg(feature = "bumpalo")]
+use crate::{HtmlDocument, HtmlError, ParseOptions};
+
+/// Where the parser puts the strings it has to make, like decoded text and
+/// lowercased names: in a bump arena when one was given, on the heap
+/// otherwise.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Arena<'a> {
+    #[cfg(feature = "bumpalo")]
+    bump: Option<&'a Bump>,
+    marker: PhantomData<&'a str>,
+}
+
+impl<'a> Arena<'a> {
+    pub(crate) fn lowercase(self, name: &'a str) -> Cow<'a, str> {
+        #[cfg(feature = "bumpalo")]
+        if let Some(bump) = self.bump {
+            let lowercase = bump.alloc_str(name);
+            lowercase.make_ascii_lowercase();
+            return Cow::Borrowed(lowercase);
+        }
+        Cow::Owned(name.to_ascii_lowercase())
+    }
+
+    pub(crate) fn decode(self, text: &'a str) -> Cow<'a, str> {
+        #[cfg(feature = "bumpalo")]
+        if let Some(bump) = self.bump {
+            if !text.contains('&') {
+                return Cow::Borrowed(text);
+            }
+            let mut decoded = bumpalo::collections::String::with_capacity_in(text.len(), bump);
+            crate::entities::decode_into(text, &mut decoded);
+            return Cow::Borrowed(decoded.into_bump_str());
+        }
+        crate::decode_entities(text)
+    }
+}
+
+/// Like [`parse_document_with`](crate::parse_document_with), with every
+/// string the parser makes, such as text with its character references
+/// decoded, allocated in `arena` rather than on the heap. They're freed
+/// all at once with the arena, which suits parsing many pages and throwing
+/// each tree away.
+///
+/// The lists of nodes and attributes are still allocated on the heap.
+///
+/// ```
+/// # use html_parser::{parse_document_in, Bump, HtmlNode, ParseOptions};
+/// let mut arena = Bump::new();
+/// for (page, text) in [("<p>caf&#233;</p>", "café"), ("<P>5 &lt; 6</P>", "5 < 6")] {
+///     let document = parse_document_in(page, &ParseOptions::default(), &arena).unwrap();
+///     let paragraph = document.elements().next().unwrap();
+///     assert_eq!(paragraph.children(), [HtmlNode::Text(text.into())]);
+///     drop(document);
+///     arena.reset();
+/// }
+/// ```
+#[cfg(feature = "bumpalo")]
+pub fn parse_document_in<'a>(
+    data: &'a str,
+    options: &ParseOptions,
+    arena: &'a Bump,
+) -> Result<HtmlDocument<'a>, HtmlError> {
+    let arena = Arena {
+        bump: Some(arena),
+        marker: PhantomData,
+    };
+    crate::parser::parse_document_using(data, options, arena)
+}
+
+#[cfg(all(test, feature = "bumpalo"))]
+mod tests {
+    use super::*;
+    use crate::{parse_document_with, ParseMode};
+
+    #[test]
+    fn strings_in_arena() {
+        let html = "<DIV Class=\"a&amp;b\">x &lt; y<br></DIV>";
+        let mut modes = vec![ParseMode::Strict, ParseMode::Lenient];
+        #[cfg(feature = "spec")]
+        modes.push(ParseMode::Spec);
+        for mode in modes {
+            let options = ParseOptions {
+                mode,
+                ..Default::default()
+            };
+            let arena = Bump::new();
+            let document = parse_document_in(html, &options, &arena).unwrap();
+            assert_eq!(document, parse_document_with(html, &options).unwrap());
+            // The spec rules put it in a `body`
+            let root = document.elements().next().unwrap();
+            let element = match root.tag() {
+                "div" => root,
+                _ => root.query_selector("div").unwrap().unwrap(),
+            };
+            assert!(matches!(element.tag, Cow::Borrowed("div")));
+            assert!(matches!(
+                element.attributes[0].value,
+                Some(Cow::Borrowed("a&b"))
+            ));
+            assert!(matches!(
+                element.children()[0],
+                crate::HtmlNode::Text(Cow::Borrowed("x < y"))
+            ));
+            assert!(arena.allocated_bytes() > 0);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use crate::prelude::*;
+use crate::HashMap;
+
+use crate::{extract_metadata, HtmlDocument, HtmlElement, HtmlNode};
+
+// Class and id words that mark page furniture rather than content
+const UNLIKELY: &[&str] = &[
+    "ad-",
+    "banner",
+    "breadcrumb",
+    "comment",
+    "cookie",
+    "footer",
+    "menu",
+    "modal",
+    "nav",
+    "popup",
+    "related",
+    "share",
+    "sidebar",
+    "social",
+    "sponsor",
+];
+
+// Words that rescue an otherwise unlikely candidate and raise its score
+const LIKELY: &[&str] = &[
+    "article", "blog", "body", "content", "entry", "main", "post", "story", "text",
+];
+
+const SKIPPED: &[&str] = &[
+    "aside", "footer", "form", "iframe", "nav", "noscript", "script", "style", "template",
+];
+
+// Elements whose text counts towards their ancestors' scores
+const PARAGRAPHS: &[&str] = &["blockquote", "p", "pre", "td"];
+
+/// The main content of a page, from [`extract_article`].
+#[derive(Debug, Clone)]
+pub struct Article<'e, 'a> {
+    pub title: Option<String>,
+    pub byline: Option<String>,
+    /// The element holding the content.
+    pub content: &'e HtmlElement<'a>,
+}
+
+impl Article<'_, '_> {
+    pub fn text(&self) -> String {
+        self.content.inner_text()
+    }
+}
+
+/// Guesses which element holds a page's article, the way reader modes do:
+/// paragraphs score their parent and grandparent by length and commas, tag
+/// names and class names nudge scores, and heavily linked blocks lose out.
+/// Returns `None` when nothing looks like prose.
+pub fn extract_article<'e, 'a>(document: &'e HtmlDocument<'a>) -> Option<Article<'e, 'a>> {
+    let mut scorer = Scorer::default();
+    let mut ancestors = Vec::new();
+    for element in document.elements() {
+        scorer.visit(element, &mut ancestors);
+    }
+    let content = scorer
+        .candidates
+        .iter()
+        .map(|&(element, score)| (element, score * (1.0 - link_density(element))))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(element, _)| element)?;
+
+    let headline =
+        find(content, &|element| is_one_of(element, &["h1"])).map(|heading| heading.inner_text());
+    let metadata = extract_metadata(document);
+    let title = headline.or_else(|| metadata.title.map(|title| clean_title(&title)));
+    Some(Article {
+        title,
+        byline: byline(document),
+        content,
+    })
+}
+
+#[derive(Default)]
+struct Scorer<'e, 'a> {
+    candidates: Vec<(&'e HtmlElement<'a>, f64)>,
+    index: HashMap<*const HtmlElement<'a>, usize>,
+}
+
+impl<'e, 'a> Scorer<'e, 'a> {
+    fn visit(&mut self, element: &'e HtmlElement<'a>, ancestors: &mut Vec<&'e HtmlElement<'a>>) {
+        if is_one_of(element, SKIPPED) || (is_unlikely(element) && !matches_words(element, LIKELY))
+        {
+            return;
+        }
+        if is_one_of(element, PARAGRAPHS) {
+            let text = element.inner_text();
+            let length = text.chars().count();
+            if length >= 25 {
+                let score = 1.0 + text.matches(',').count() as f64 + (length / 100).min(3) as f64;
+                let mut parents = ancestors.iter().rev();
+                if let Some(&parent) = parents.next() {
+                    self.add(parent, score);
+                }
+                if let Some(&grandparent) = parents.next() {
+                    self.add(grandparent, score / 2.0);
+                }
+            }
+        }
+        ancestors.push(element);
+        for child in element.child_elements() {
+            self.visit(child, ancestors);
+        }
+        ancestors.pop();
+    }
+
+    fn add(&mut self, element: &'e HtmlElement<'a>, score: f64) {
+        let key = element as *const HtmlElement<'a>;
+        let index = *self.index.entry(key).or_insert_with(|| {
+            self.candidates.push((element, initial_score(element)));
+            self.candidates.len() - 1
+        });
+        self.candidates[index].1 += score;
+    }
+}
+
+fn initial_score(element: &HtmlElement<'_>) -> f64 {
+    let tag_score = match element.tag().to_ascii_lowercase().as_str() {
+        "article" | "main" => 10.0,
+        "div" => 5.0,
+        "blockquote" | "pre" | "td" => 3.0,
+        "address" | "dd" | "dl" | "dt" | "li" | "ol" | "ul" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    let class_score = if matches_words(element, LIKELY) {
+        25.0
+    } else if is_unlikely(element) {
+        -25.0
+    } else {
+        0.0
+    };
+    tag_score + class_score
+}
+
+// The share of an element's text that is inside links
+fn link_density(element: &HtmlElement<'_>) -> f64 {
+    fn link_text(nodes: &[HtmlNode<'_>]) -> usize {
+        nodes
+            .iter()
+            .filter_map(HtmlNode::as_element)
+            .map(|element| {
+                if element.tag().eq_ignore_ascii_case("a") {
+                    element.inner_text().chars().count()
+                } else {
+                    link_text(element.children())
+                }
+            })
+            .sum()
+    }
+    let length = element.inner_text().chars().count();
+    if length == 0 {
+        return 0.0;
+    }
+    link_text(element.children()) as f64 / length as f64
+}
+
+fn byline(document: &HtmlDocument<'_>) -> Option<String> {
+    let is_byline = |element: &HtmlElement<'_>| {
+        element
+            .get_attr("rel")
+            .is_some_and(|rel| rel.eq_ignore_ascii_case("author"))
+            || matches_words(element, &["author", "byline"])
+    };
+    document
+        .elements()
+        .find_map(|element| find(element, &is_byline))
+        .map(|element| element.inner_text().trim().to_string())
+        .filter(|text| !text.is_empty() && text.chars().count() < 100)
+}
+
+fn find<'e, 'a>(
+    element: &'e HtmlElement<'a>,
+    predicate: &dyn Fn(&HtmlElement<'a>) -> bool,
+) -> Option<&'e HtmlElement<'a>> {
+    if predicate(element) {
+        return Some(element);
+    }
+    element
+        .child_elements()
+        .find_map(|child| find(child, predicate))
+}
+
+// Drops a site name from titles like `Article - Site`, if enough is left
+fn clean_title(title: &str) -> String {
+    for separator in [" | ", " - ", " — ", " :: "] {
+        if let Some((first, _)) = title.rsplit_once(separator) {
+            if first.split_whitespace().count() >= 3 {
+                return first.trim().to_string();
+            }
+        }
+    }
+    title.trim().to_string()
+}
+
+fn is_unlikely(element: &HtmlElement<'_>) -> bool {
+    !is_one_of(element, &["body", "html"]) && matches_words(element, UNLIKELY)
+}
+
+fn matches_words(element: &HtmlElement<'_>, words: &[&str]) -> bool {
+    let names = format!(
+        "{} {}",
+        element.get_attr("class").unwrap_or_default(),
+        element.get_attr("id").unwrap_or_default()
+    )
+    .to_ascii_lowercase();
+    words.iter().any(|word| names.contains(word))
+}
+
+fn is_one_of(element: &HtmlElement<'_>, tags: &[&str]) -> bool {
+    tags.iter()
+        .any(|tag| element.tag().eq_ignore_ascii_case(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    #[test]
+    fn finds_the_article() {
+        let paragraph = "<p>Rust is a language, fast and safe, that many people write \
+            systems software in, and it keeps growing.</p>";
+        let html = format!(
+            "<html><head><title>Why Rust matters today | Example Blog</title></head><body>\
+            <div class=\"nav\"><a href=\"/\">Home</a><a href=\"/about\">About us and our team</a></div>\
+            <div id=\"story\"><span class=\"byline\">By Ann Smith</span>{paragraph}{paragraph}{paragraph}</div>\
+            <div class=\"links\"><p><a href=\"/x\">A long list of links that are not the content at all</a></p>\
+            </div></body></html>"
+        );
+        let document = parse_document(&html).unwrap();
+        let article = extract_article(&document).unwrap();
+        assert_eq!(article.content.get_attr("id"), Some("story"));
+        assert_eq!(article.title.as_deref(), Some("Why Rust matters today"));
+        assert_eq!(article.byline.as_deref(), Some("By Ann Smith"));
+        assert!(article.text().contains("systems software"));
+
+        let document = parse_document("<p>Short.</p>").unwrap();
+        assert!(extract_article(&document).is_none());
+    }
+}
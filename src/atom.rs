@@ -0,0 +1,320 @@
+use alloc::borrow::Cow;
+use core::fmt;
+use core::ops::Deref;
+
+use crate::prelude::*;
+
+// Common tag and attribute names, sorted so they can be binary searched. An
+// atom is an index into this list.
+const NAMES: &[&str] = &[
+    "a",
+    "abbr",
+    "accept",
+    "action",
+    "address",
+    "align",
+    "alt",
+    "area",
+    "aria-hidden",
+    "aria-label",
+    "article",
+    "aside",
+    "async",
+    "audio",
+    "autocomplete",
+    "b",
+    "base",
+    "blockquote",
+    "body",
+    "border",
+    "br",
+    "button",
+    "canvas",
+    "caption",
+    "charset",
+    "checked",
+    "cite",
+    "class",
+    "code",
+    "col",
+    "colgroup",
+    "cols",
+    "colspan",
+    "content",
+    "controls",
+    "crossorigin",
+    "data",
+    "datetime",
+    "dd",
+    "defer",
+    "del",
+    "details",
+    "dfn",
+    "dialog",
+    "dir",
+    "disabled",
+    "div",
+    "dl",
+    "download",
+    "dt",
+    "em",
+    "embed",
+    "enctype",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "for",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "height",
+    "hidden",
+    "hr",
+    "href",
+    "hreflang",
+    "html",
+    "http-equiv",
+    "i",
+    "id",
+    "iframe",
+    "img",
+    "input",
+    "ins",
+    "integrity",
+    "itemprop",
+    "itemscope",
+    "itemtype",
+    "kbd",
+    "label",
+    "lang",
+    "legend",
+    "li",
+    "link",
+    "loading",
+    "main",
+    "mark",
+    "max",
+    "maxlength",
+    "media",
+    "menu",
+    "meta",
+    "method",
+    "min",
+    "multiple",
+    "name",
+    "nav",
+    "noscript",
+    "object",
+    "ol",
+    "onclick",
+    "onload",
+    "optgroup",
+    "option",
+    "output",
+    "p",
+    "param",
+    "path",
+    "pattern",
+    "picture",
+    "placeholder",
+    "pre",
+    "progress",
+    "property",
+    "q",
+    "readonly",
+    "rel",
+    "required",
+    "role",
+    "rows",
+    "rowspan",
+    "s",
+    "samp",
+    "scope",
+    "script",
+    "section",
+    "select",
+    "selected",
+    "size",
+    "sizes",
+    "slot",
+    "small",
+    "source",
+    "span",
+    "src",
+    "srcset",
+    "start",
+    "step",
+    "strong",
+    "style",
+    "sub",
+    "summary",
+    "sup",
+    "svg",
+    "tabindex",
+    "table",
+    "target",
+    "tbody",
+    "td",
+    "template",
+    "textarea",
+    "tfoot",
+    "th",
+    "thead",
+    "time",
+    "title",
+    "tr",
+    "track",
+    "type",
+    "u",
+    "ul",
+    "value",
+    "var",
+    "video",
+    "viewbox",
+    "wbr",
+    "width",
+    "xmlns",
+];
+
+/// A common tag or attribute name, such as `div`, `class` or `href`, as a
+/// small integer. Atoms compare in O(1), where names compare byte by byte.
+///
+/// Only names in a fixed table of lowercase HTML names have an atom, so
+/// interning can't grow memory however many distinct names the input has.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Atom(u16);
+
+impl Atom {
+    /// The atom for `name`, if it's in the table. Names are matched
+    /// exactly, so `DIV` has none.
+    pub fn new(name: &str) -> Option<Self> {
+        NAMES
+            .binary_search(&name)
+            .ok()
+            .map(|index| Atom(index as u16))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        NAMES[self.0 as usize]
+    }
+}
+
+impl fmt::Debug for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Atom({:?})", self.as_str())
+    }
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An owned name: an atom when it has one, so common names need no
+/// allocation, and a string otherwise.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Name {
+    Atom(Atom),
+    Other(Box<str>),
+}
+
+impl Name {
+    pub(crate) fn new(name: &str) -> Self {
+        match Atom::new(name) {
+            Some(atom) => Name::Atom(atom),
+            None => Name::Other(name.into()),
+        }
+    }
+}
+
+impl Default for Name {
+    fn default() -> Self {
+        Name::Other(Box::default())
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        match Atom::new(&name) {
+            Some(atom) => Name::Atom(atom),
+            None => Name::Other(name.into_boxed_str()),
+        }
+    }
+}
+
+impl From<Cow<'_, str>> for Name {
+    fn from(name: Cow<'_, str>) -> Self {
+        match name {
+            Cow::Borrowed(name) => Name::new(name),
+            Cow::Owned(name) => Name::from(name),
+        }
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Name::Atom(atom) => atom.as_str(),
+            Name::Other(name) => name,
+        }
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Name {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Name {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Name::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atoms() {
+        assert!(NAMES.windows(2).all(|pair| pair[0] < pair[1]));
+        let div = Atom::new("div").unwrap();
+        assert_eq!(div.as_str(), "div");
+        assert_eq!(Some(div), Atom::new("div"));
+        assert_ne!(Atom::new("span"), Some(div));
+        assert_eq!(Atom::new("DIV"), None);
+        assert_eq!(Atom::new("custom-element"), None);
+
+        assert_eq!(Name::new("href"), Name::Atom(Atom::new("href").unwrap()));
+        assert_eq!(&*Name::from("data-x".to_string()), "data-x");
+
+        let mut document =
+            crate::Document::parse("<div><span></span><x-card></x-card></div>").unwrap();
+        assert_eq!(document.find("DIV span, div x-card").unwrap().len(), 2);
+        let span = document.find("span").unwrap().nodes()[0];
+        if let crate::NodeData::Element { tag, .. } = document.get_mut(span).data_mut() {
+            *tag = "b".into();
+        }
+        assert_eq!(document.find("span").unwrap().len(), 0);
+        assert_eq!(document.find("b").unwrap().len(), 1);
+    }
+}
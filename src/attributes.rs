@@ -0,0 +1,251 @@
+//! Attribute storage for the `smallvec` feature: the first few attributes
+//! of an element are kept inline, the rest on the heap.
+
+use alloc::borrow::Cow;
+use core::ops::{Deref, DerefMut};
+
+use crate::prelude::*;
+use crate::HtmlAttribute;
+
+// Most elements have no more than this many attributes
+const INLINE: usize = 3;
+
+// Unlike the `smallvec` crate's `SmallVec`, this is covariant in `'a` like
+// a `Vec`, so enabling the feature can't stop a tree borrowing a
+// `&'static str` from passing for one borrowing something shorter.
+// Unused inline slots hold empty attributes, which don't allocate.
+#[derive(Clone)]
+pub(crate) enum Attributes<'a> {
+    Inline(usize, [HtmlAttribute<'a>; INLINE]),
+    Heap(Vec<HtmlAttribute<'a>>),
+}
+
+fn empty<'a>() -> HtmlAttribute<'a> {
+    HtmlAttribute {
+        name: Cow::Borrowed(""),
+        value: None,
+    }
+}
+
+impl<'a> Attributes<'a> {
+    pub(crate) fn push(&mut self, attribute: HtmlAttribute<'a>) {
+        match self {
+            Attributes::Inline(len, slots) if *len < INLINE => {
+                slots[*len] = attribute;
+                *len += 1;
+            }
+            Attributes::Inline(_, slots) => {
+                let mut heap = Vec::with_capacity(INLINE * 2);
+                heap.extend(
+                    slots
+                        .iter_mut()
+                        .map(|slot| core::mem::replace(slot, empty())),
+                );
+                heap.push(attribute);
+                *self = Attributes::Heap(heap);
+            }
+            Attributes::Heap(heap) => heap.push(attribute),
+        }
+    }
+
+    pub(crate) fn remove(&mut self, index: usize) -> HtmlAttribute<'a> {
+        match self {
+            Attributes::Inline(len, slots) => {
+                assert!(index < *len, "attribute index out of bounds");
+                slots[index..*len].rotate_left(1);
+                *len -= 1;
+                core::mem::replace(&mut slots[*len], empty())
+            }
+            Attributes::Heap(heap) => heap.remove(index),
+        }
+    }
+
+    pub(crate) fn retain(&mut self, mut keep: impl FnMut(&HtmlAttribute<'a>) -> bool) {
+        match self {
+            Attributes::Inline(len, slots) => {
+                let mut kept = 0;
+                for index in 0..*len {
+                    if keep(&slots[index]) {
+                        slots.swap(kept, index);
+                        kept += 1;
+                    }
+                }
+                for slot in &mut slots[kept..*len] {
+                    *slot = empty();
+                }
+                *len = kept;
+            }
+            Attributes::Heap(heap) => heap.retain(keep),
+        }
+    }
+}
+
+impl Default for Attributes<'_> {
+    fn default() -> Self {
+        Attributes::Inline(0, core::array::from_fn(|_| empty()))
+    }
+}
+
+impl<'a> Deref for Attributes<'a> {
+    type Target = [HtmlAttribute<'a>];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Attributes::Inline(len, slots) => &slots[..*len],
+            Attributes::Heap(heap) => heap,
+        }
+    }
+}
+
+impl DerefMut for Attributes<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Attributes::Inline(len, slots) => &mut slots[..*len],
+            Attributes::Heap(heap) => heap,
+        }
+    }
+}
+
+impl core::fmt::Debug for Attributes<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for Attributes<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for Attributes<'_> {}
+
+impl<'a> From<Vec<HtmlAttribute<'a>>> for Attributes<'a> {
+    fn from(attributes: Vec<HtmlAttribute<'a>>) -> Self {
+        if attributes.len() > INLINE {
+            Attributes::Heap(attributes)
+        } else {
+            attributes.into_iter().collect()
+        }
+    }
+}
+
+impl<'a> From<&[HtmlAttribute<'a>]> for Attributes<'a> {
+    fn from(attributes: &[HtmlAttribute<'a>]) -> Self {
+        attributes.iter().cloned().collect()
+    }
+}
+
+impl<'a> FromIterator<HtmlAttribute<'a>> for Attributes<'a> {
+    fn from_iter<I: IntoIterator<Item = HtmlAttribute<'a>>>(iter: I) -> Self {
+        let mut attributes = Attributes::default();
+        attributes.extend(iter);
+        attributes
+    }
+}
+
+impl<'a> Extend<HtmlAttribute<'a>> for Attributes<'a> {
+    fn extend<I: IntoIterator<Item = HtmlAttribute<'a>>>(&mut self, iter: I) {
+        for attribute in iter {
+            self.push(attribute);
+        }
+    }
+}
+
+pub(crate) enum IntoIter<'a> {
+    Inline(core::iter::Take<core::array::IntoIter<HtmlAttribute<'a>, INLINE>>),
+    Heap(alloc::vec::IntoIter<HtmlAttribute<'a>>),
+}
+
+impl<'a> Iterator for IntoIter<'a> {
+    type Item = HtmlAttribute<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            IntoIter::Inline(iter) => iter.next(),
+            IntoIter::Heap(iter) => iter.next(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for Attributes<'a> {
+    type Item = HtmlAttribute<'a>;
+    type IntoIter = IntoIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            Attributes::Inline(len, slots) => IntoIter::Inline(slots.into_iter().take(len)),
+            Attributes::Heap(heap) => IntoIter::Heap(heap.into_iter()),
+        }
+    }
+}
+
+impl<'s, 'a> IntoIterator for &'s Attributes<'a> {
+    type Item = &'s HtmlAttribute<'a>;
+    type IntoIter = core::slice::Iter<'s, HtmlAttribute<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'s, 'a> IntoIterator for &'s mut Attributes<'a> {
+    type Item = &'s mut HtmlAttribute<'a>;
+    type IntoIter = core::slice::IterMut<'s, HtmlAttribute<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Attributes<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de: 'a, 'a> serde::Deserialize<'de> for Attributes<'a> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(Attributes::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_then_heap() {
+        let names = ["a", "b", "c", "d", "e"];
+        let mut attributes: Attributes = names[..3]
+            .iter()
+            .map(|&name| HtmlAttribute::new(name, None))
+            .collect();
+        assert!(matches!(attributes, Attributes::Inline(3, _)));
+        attributes.retain(|attr| attr.name() != "b");
+        assert_eq!(attributes.remove(0).name(), "a");
+        let names_of = |attributes: &Attributes| -> Vec<String> {
+            attributes
+                .iter()
+                .map(|attr| attr.name().to_string())
+                .collect()
+        };
+        assert_eq!(names_of(&attributes), ["c"]);
+
+        attributes.extend(
+            names[3..]
+                .iter()
+                .map(|&name| HtmlAttribute::new(name, None)),
+        );
+        attributes.push(HtmlAttribute::new("f", None));
+        assert!(matches!(attributes, Attributes::Heap(_)));
+        assert_eq!(names_of(&attributes), ["c", "d", "e", "f"]);
+        let owned: Vec<_> = attributes
+            .into_iter()
+            .map(|attr| attr.name().len())
+            .collect();
+        assert_eq!(owned, [1; 4]);
+    }
+}
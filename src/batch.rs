@@ -0,0 +1,46 @@
+use rayon::prelude::*;
+
+use crate::{parse_document_with, HtmlDocument, HtmlError, ParseOptions};
+
+/// Parses each document on rayon's thread pool, returning the results in
+/// the order the documents came in.
+pub fn parse_many<'a>(
+    documents: impl IntoParallelIterator<Item = &'a str>,
+) -> Vec<Result<HtmlDocument<'a>, HtmlError>> {
+    parse_many_with(documents, &ParseOptions::default())
+}
+
+pub fn parse_many_with<'a>(
+    documents: impl IntoParallelIterator<Item = &'a str>,
+    options: &ParseOptions,
+) -> Vec<Result<HtmlDocument<'a>, HtmlError>> {
+    documents
+        .into_par_iter()
+        .map(|document| parse_document_with(document, options))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    #[test]
+    fn parses_in_order() {
+        let pages: Vec<String> = (0..64)
+            .map(|index| match index % 8 {
+                7 => format!("<p>{index}</div>"),
+                _ => format!("<ul><li>{index}</li></ul>"),
+            })
+            .collect();
+        let results = parse_many(pages.par_iter().map(String::as_str));
+        assert_eq!(results.len(), pages.len());
+        for (page, result) in pages.iter().zip(results) {
+            match (result, parse_document(page)) {
+                (Ok(document), Ok(expected)) => assert_eq!(document, expected),
+                (Err(error), Err(expected)) => assert_eq!(error.to_string(), expected.to_string()),
+                (result, expected) => panic!("{result:?} isn't {expected:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! `htmlp`, the parser for shell pipelines:
+//!
+//! ```text
+//! curl -s https://example.com | htmlp query 'a[href]' --text
+//! htmlp fmt page.html
+//! ```
+
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use html_parser::minify::minify_document;
+use html_parser::{
+    document_to_string, document_to_string_pretty, html_to_string, parse_document_with, Document,
+    HtmlDocument, HtmlError, HtmlNode, ParseMode, ParseOptions, PrettyOptions, Selector,
+};
+
+const USAGE: &str = "\
+Usage: htmlp <command> [options] [FILE]...
+
+Commands:
+  parse             print the document as parsed, with inferred end tags
+  query SELECTOR    print the elements matching a CSS selector, one per line
+  fmt               print the document indented, one node per line
+  minify            print the document without insignificant whitespace
+
+Options:
+  --strict          fail on malformed markup instead of recovering like browsers
+  --json            print the tree as JSON (parse, query)
+  --text            print the text of matches instead of their HTML (query)
+  --indent N        spaces per nesting level, 2 by default (fmt)
+  --width N         longest line kept as one, 80 by default (fmt)
+
+FILE defaults to standard input, which `-` also names.";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Parse,
+    Query(String),
+    Fmt,
+    Minify,
+}
+
+#[derive(Debug, PartialEq)]
+enum Output {
+    Html,
+    Text,
+    Json,
+}
+
+#[derive(Debug)]
+struct Args {
+    command: Command,
+    output: Output,
+    mode: ParseMode,
+    pretty: PrettyOptions,
+    files: Vec<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let command = match args.next().as_deref() {
+        Some("parse") => Command::Parse,
+        Some("query") => Command::Query(String::new()),
+        Some("fmt") => Command::Fmt,
+        Some("minify") => Command::Minify,
+        Some(command) => return Err(format!("unknown command `{command}`")),
+        None => return Err("missing command".to_string()),
+    };
+    let mut parsed = Args {
+        command,
+        output: Output::Html,
+        mode: ParseMode::Lenient,
+        pretty: PrettyOptions::default(),
+        files: Vec::new(),
+    };
+    let mut selector = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strict" => parsed.mode = ParseMode::Strict,
+            "--json" => parsed.output = Output::Json,
+            "--text" => parsed.output = Output::Text,
+            "--indent" => parsed.pretty.indent = number(&arg, args.next())?,
+            "--width" => parsed.pretty.max_line_width = number(&arg, args.next())?,
+            option if option.starts_with("--") => return Err(format!("unknown option `{option}`")),
+            _ if matches!(parsed.command, Command::Query(_)) && selector.is_none() => {
+                selector = Some(arg)
+            }
+            _ => parsed.files.push(arg),
+        }
+    }
+
+    let allowed = match parsed.command {
+        Command::Parse => parsed.output != Output::Text,
+        Command::Query(ref mut query) => {
+            *query = selector.ok_or("missing selector")?;
+            true
+        }
+        Command::Fmt | Command::Minify => parsed.output == Output::Html,
+    };
+    if !allowed {
+        return Err("--json and --text only apply to some commands".to_string());
+    }
+    if parsed.files.is_empty() {
+        parsed.files.push("-".to_string());
+    }
+    Ok(parsed)
+}
+
+fn number(option: &str, value: Option<String>) -> Result<usize, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("`{option}` needs a number"))
+}
+
+// What `args` prints for one input, with the problems the parser recovered
+// from going to `warnings`
+fn run(
+    args: &Args,
+    selector: Option<&Selector>,
+    html: &str,
+    warnings: &mut Vec<String>,
+) -> Result<String, HtmlError> {
+    let options = ParseOptions {
+        mode: args.mode,
+        ..Default::default()
+    };
+    let document = parse_document_with(html, &options)?;
+    warnings.extend(document.diagnostics().iter().map(ToString::to_string));
+
+    let mut out = match (&args.command, selector) {
+        (Command::Query(_), Some(selector)) => return Ok(query(args, selector, document)),
+        (Command::Parse, _) if args.output == Output::Json => document.to_json().to_string(),
+        (Command::Parse | Command::Query(_), _) => document_to_string(document),
+        (Command::Fmt, _) => document_to_string_pretty(&document, args.pretty.clone()),
+        (Command::Minify, _) => minify_document(&document),
+    };
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn query(args: &Args, selector: &Selector, document: HtmlDocument<'_>) -> String {
+    let document = Document::from(document);
+    let matches = selector.select(&document).map(|id| document.to_node(id));
+    let elements: Vec<_> = matches
+        .filter_map(|node| match node {
+            HtmlNode::Element(element) => Some(element),
+            _ => None,
+        })
+        .collect();
+
+    let mut out = String::new();
+    match args.output {
+        Output::Json => {
+            let elements = elements.iter().map(|element| element.to_json()).collect();
+            out = serde_json::Value::Array(elements).to_string();
+            out.push('\n');
+        }
+        Output::Text => {
+            for element in &elements {
+                out.push_str(&element.inner_text());
+                out.push('\n');
+            }
+        }
+        Output::Html => {
+            for element in elements {
+                out.push_str(&html_to_string(vec![element]));
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+fn read(file: &str) -> io::Result<String> {
+    let mut html = String::new();
+    match file {
+        "-" => io::stdin().read_to_string(&mut html)?,
+        file => std::fs::File::open(file)?.read_to_string(&mut html)?,
+    };
+    Ok(html)
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1).peekable();
+    if matches!(args.peek().map(String::as_str), Some("-h" | "--help")) {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let args = match parse_args(args) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("htmlp: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let selector = match &args.command {
+        Command::Query(selector) => match Selector::parse(selector) {
+            Ok(selector) => Some(selector),
+            Err(error) => {
+                eprintln!("htmlp: {}", HtmlError::InvalidSelector(error));
+                return ExitCode::from(2);
+            }
+        },
+        _ => None,
+    };
+
+    let mut stdout = io::stdout().lock();
+    let mut status = ExitCode::SUCCESS;
+    for file in &args.files {
+        let name = if file == "-" { "<stdin>" } else { file };
+        let html = match read(file) {
+            Ok(html) => html,
+            Err(error) => {
+                eprintln!("htmlp: {name}: {error}");
+                status = ExitCode::FAILURE;
+                continue;
+            }
+        };
+        let mut warnings = Vec::new();
+        let result = run(&args, selector.as_ref(), &html, &mut warnings);
+        for warning in warnings {
+            eprintln!("htmlp: {name}: warning: {warning}");
+        }
+        match result {
+            Ok(out) => {
+                if let Err(error) = stdout.write_all(out.as_bytes()) {
+                    // A closed pipe means the reader has all it wants
+                    if error.kind() == io::ErrorKind::BrokenPipe {
+                        return status;
+                    }
+                    eprintln!("htmlp: {error}");
+                    return ExitCode::FAILURE;
+                }
+            }
+            Err(error) => {
+                eprintln!("htmlp: {name}: {error}");
+                status = ExitCode::FAILURE;
+            }
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn commands() {
+        let html = "<ul><li class=a>one &amp; two<li>three</ul></b><p>";
+        let output = |list: &[&str]| {
+            let args = args(list).unwrap();
+            let selector = match &args.command {
+                Command::Query(selector) => Some(Selector::parse(selector).unwrap()),
+                _ => None,
+            };
+            let mut warnings = Vec::new();
+            let out = run(&args, selector.as_ref(), html, &mut warnings);
+            out.map(|out| (out, warnings.len()))
+        };
+
+        let (parsed, warnings) = output(&["parse"]).unwrap();
+        assert_eq!(
+            parsed,
+            "<ul><li class=\"a\">one &amp; two</li><li>three</li></ul><p></p>\n"
+        );
+        assert_eq!(warnings, 1);
+        assert!(output(&["parse", "--strict"]).is_err());
+
+        let (items, _) = output(&["query", "li"]).unwrap();
+        assert_eq!(
+            items,
+            "<li class=\"a\">one &amp; two</li>\n<li>three</li>\n"
+        );
+        let (text, _) = output(&["query", "--text", "li + li, .a"]).unwrap();
+        assert_eq!(text, "one & two\nthree\n");
+        assert_eq!(
+            output(&["query", "--json", "li + li"]).unwrap().0,
+            "[{\"tag\":\"li\",\"attrs\":{},\"children\":[\"three\"]}]\n"
+        );
+        let (minified, _) = output(&["minify"]).unwrap();
+        assert_eq!(
+            minified,
+            "<ul><li class=a>one &amp; two</li><li>three</li></ul><p></p>\n"
+        );
+        let (pretty, _) = output(&["fmt", "--width", "20"]).unwrap();
+        assert_eq!(
+            pretty,
+            "<ul>\n  <li class=\"a\">\n    one &amp; two\n  </li>\n  <li>three</li>\n</ul>\n<p></p>\n"
+        );
+
+        assert_eq!(args(&["query"]).unwrap_err(), "missing selector");
+        assert!(args(&["fmt", "--text"]).is_err());
+        assert!(args(&["fmt", "--indent", "x"]).is_err());
+        let query = args(&["query", "p", "a.html", "-"]).unwrap();
+        assert_eq!(query.command, Command::Query("p".to_string()));
+        assert_eq!(query.files, ["a.html", "-"]);
+    }
+}
@@ -0,0 +1,285 @@
+//! A C interface, declared in `include/html_parser.h`. Documents and
+//! selections are opaque handles that the caller frees with the matching
+//! `_free` function, and every string handed out is NUL-terminated UTF-8
+//! freed with `html_string_free`.
+//!
+//! Functions that can fail return null and, when `error` isn't null, store
+//! a message there for the caller to free.
+//!
+//! A library to link against comes from `cargo rustc --release --features
+//! capi --crate-type cdylib` (or `staticlib`).
+
+use std::ffi::{c_char, CStr, CString};
+
+use crate::owned::{borrow_nodes, select_owned};
+use crate::prelude::*;
+use crate::{
+    document_to_string, parse_document_with, write_html, HtmlElementOwned, HtmlNode, HtmlNodeOwned,
+    ParseMode, ParseOptions,
+};
+
+/// A parsed document, `html_document` in C.
+pub struct HtmlParserDocument {
+    nodes: Vec<HtmlNodeOwned>,
+}
+
+/// The elements a selector matched, `html_selection` in C.
+pub struct HtmlParserSelection {
+    elements: Vec<HtmlElementOwned>,
+}
+
+/// Parses `len` bytes of UTF-8 at `data`, forgiving malformed markup the way
+/// browsers do when `lenient` is set.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, and `error` must be null or
+/// point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn html_parse(
+    data: *const u8,
+    len: usize,
+    lenient: bool,
+    error: *mut *mut c_char,
+) -> *mut HtmlParserDocument {
+    let data = match len {
+        0 => &[],
+        _ => core::slice::from_raw_parts(data, len),
+    };
+    let Ok(html) = core::str::from_utf8(data) else {
+        set_error(error, "input isn't valid UTF-8");
+        return core::ptr::null_mut();
+    };
+    let options = ParseOptions {
+        mode: match lenient {
+            true => ParseMode::Lenient,
+            false => ParseMode::Strict,
+        },
+        ..Default::default()
+    };
+    match parse_document_with(html, &options) {
+        Ok(document) => Box::into_raw(Box::new(HtmlParserDocument {
+            nodes: document
+                .nodes
+                .into_iter()
+                .map(HtmlNode::into_owned)
+                .collect(),
+        })),
+        Err(parse_error) => {
+            set_error(error, &parse_error.to_string());
+            core::ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `document` must be null or come from `html_parse`, and isn't used again.
+#[no_mangle]
+pub unsafe extern "C" fn html_document_free(document: *mut HtmlParserDocument) {
+    if !document.is_null() {
+        drop(Box::from_raw(document));
+    }
+}
+
+/// The document as HTML.
+///
+/// # Safety
+///
+/// `document` must come from `html_parse` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn html_document_serialize(
+    document: *const HtmlParserDocument,
+) -> *mut c_char {
+    c_string(document_to_string(borrow_nodes(&(*document).nodes)))
+}
+
+/// The elements matching the CSS selector `selector`, a NUL-terminated
+/// string, in document order.
+///
+/// # Safety
+///
+/// `document` must come from `html_parse` and not have been freed,
+/// `selector` must be a NUL-terminated string and `error` must be null or
+/// point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn html_document_select(
+    document: *const HtmlParserDocument,
+    selector: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut HtmlParserSelection {
+    let Ok(selector) = CStr::from_ptr(selector).to_str() else {
+        set_error(error, "selector isn't valid UTF-8");
+        return core::ptr::null_mut();
+    };
+    match select_owned(&(*document).nodes, selector) {
+        Ok(elements) => Box::into_raw(Box::new(HtmlParserSelection { elements })),
+        Err(select_error) => {
+            set_error(error, &select_error.to_string());
+            core::ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `selection` must come from `html_document_select` and not have been
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn html_selection_len(selection: *const HtmlParserSelection) -> usize {
+    (*selection).elements.len()
+}
+
+/// The element at `index` as HTML, or null past the end.
+///
+/// # Safety
+///
+/// As for `html_selection_len`.
+#[no_mangle]
+pub unsafe extern "C" fn html_selection_outer_html(
+    selection: *const HtmlParserSelection,
+    index: usize,
+) -> *mut c_char {
+    let selection = &*selection;
+    match selection.elements.get(index) {
+        Some(element) => {
+            let mut html = String::new();
+            write_html(&[element.to_borrowed()], &mut html).expect("writing to a String");
+            c_string(html)
+        }
+        None => core::ptr::null_mut(),
+    }
+}
+
+/// The rendered text of the element at `index`, or null past the end.
+///
+/// # Safety
+///
+/// As for `html_selection_len`.
+#[no_mangle]
+pub unsafe extern "C" fn html_selection_text(
+    selection: *const HtmlParserSelection,
+    index: usize,
+) -> *mut c_char {
+    let selection = &*selection;
+    match selection.elements.get(index) {
+        Some(element) => c_string(element.to_borrowed().inner_text()),
+        None => core::ptr::null_mut(),
+    }
+}
+
+/// The value of the attribute `name` on the element at `index`: empty for an
+/// attribute without a value and null when the element doesn't have it.
+///
+/// # Safety
+///
+/// As for `html_selection_len`, and `name` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn html_selection_attr(
+    selection: *const HtmlParserSelection,
+    index: usize,
+    name: *const c_char,
+) -> *mut c_char {
+    let selection = &*selection;
+    let element = selection.elements.get(index);
+    let name = CStr::from_ptr(name).to_str();
+    match (element, name) {
+        (Some(element), Ok(name)) => match element.to_borrowed().get_attr(name) {
+            Some(value) => c_string(value.to_string()),
+            None => core::ptr::null_mut(),
+        },
+        _ => core::ptr::null_mut(),
+    }
+}
+
+/// # Safety
+///
+/// `selection` must be null or come from `html_document_select`, and isn't
+/// used again.
+#[no_mangle]
+pub unsafe extern "C" fn html_selection_free(selection: *mut HtmlParserSelection) {
+    if !selection.is_null() {
+        drop(Box::from_raw(selection));
+    }
+}
+
+/// # Safety
+///
+/// `string` must be null or a string from this library, and isn't used
+/// again.
+#[no_mangle]
+pub unsafe extern "C" fn html_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+// C strings end at the first NUL, so any in the text become U+FFFD, as the
+// HTML parsing rules do with most of them
+fn c_string(mut string: String) -> *mut c_char {
+    if string.contains('\0') {
+        string = string.replace('\0', "\u{fffd}");
+    }
+    CString::new(string).expect("NULs were replaced").into_raw()
+}
+
+unsafe fn set_error(error: *mut *mut c_char, message: &str) {
+    if !error.is_null() {
+        *error = c_string(message.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Takes a string from the library, freeing it
+    unsafe fn take(string: *mut c_char) -> Option<String> {
+        let taken = (!string.is_null()).then(|| CStr::from_ptr(string).to_str().unwrap().into());
+        html_string_free(string);
+        taken
+    }
+
+    #[test]
+    fn c_interface() {
+        let html = "<ul><li class=\"a\">one &amp; two</li><li>th\0ree</ul><div>";
+        unsafe {
+            let mut error = core::ptr::null_mut();
+            let document = html_parse(html.as_ptr(), html.len(), false, &mut error);
+            assert!(document.is_null());
+            assert_eq!(
+                take(error).as_deref(),
+                Some("`<div>` at byte 51 is never closed")
+            );
+
+            let document = html_parse(html.as_ptr(), html.len(), true, &mut error);
+            assert_eq!(
+                take(html_document_serialize(document)).as_deref(),
+                Some(
+                    "<ul><li class=\"a\">one &amp; two</li><li>th\u{fffd}ree</li></ul><div></div>"
+                )
+            );
+
+            let selection = html_document_select(document, c"li".as_ptr(), &mut error);
+            assert_eq!(html_selection_len(selection), 2);
+            assert_eq!(
+                take(html_selection_text(selection, 0)).as_deref(),
+                Some("one & two")
+            );
+            assert_eq!(
+                take(html_selection_attr(selection, 0, c"class".as_ptr())).as_deref(),
+                Some("a")
+            );
+            assert_eq!(
+                take(html_selection_attr(selection, 1, c"class".as_ptr())),
+                None
+            );
+            assert_eq!(take(html_selection_outer_html(selection, 2)), None);
+            html_selection_free(selection);
+
+            let selection = html_document_select(document, c"li[".as_ptr(), &mut error);
+            assert!(selection.is_null() && take(error).is_some());
+            html_document_free(document);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::prelude::*;
+use crate::HtmlElement;
+
+/// Mutable view over the whitespace-separated tokens of a `class` attribute.
+pub struct ClassList<'e, 'a> {
+    element: &'e mut HtmlElement<'a>,
+}
+
+impl<'a> HtmlElement<'a> {
+    pub fn class_list(&mut self) -> ClassList<'_, 'a> {
+        ClassList { element: self }
+    }
+
+    pub fn classes(&self) -> impl Iterator<Item = &str> {
+        self.get_attr("class")
+            .unwrap_or_default()
+            .split_ascii_whitespace()
+    }
+
+    pub fn has_class(&self, class: &str) -> bool {
+        self.classes().any(|name| name == class)
+    }
+}
+
+impl ClassList<'_, '_> {
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.element.classes()
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    pub fn contains(&self, class: &str) -> bool {
+        self.element.has_class(class)
+    }
+
+    pub fn add(&mut self, class: &str) {
+        if !self.contains(class) {
+            let mut classes: Vec<_> = self.iter().collect();
+            classes.push(class);
+            let classes = classes.join(" ");
+            self.element.set_attr("class", classes);
+        }
+    }
+
+    pub fn remove(&mut self, class: &str) {
+        if self.contains(class) {
+            let classes: Vec<_> = self.iter().filter(|name| *name != class).collect();
+            let classes = classes.join(" ");
+            self.element.set_attr("class", classes);
+        }
+    }
+
+    /// Adds the class if missing, removes it otherwise. Returns whether the
+    /// class is present afterwards.
+    pub fn toggle(&mut self, class: &str) -> bool {
+        if self.contains(class) {
+            self.remove(class);
+            false
+        } else {
+            self.add(class);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_html;
+
+    #[test]
+    fn class_list() {
+        let mut elements = parse_html("<div class=\" card  active\"></div>").unwrap();
+        let element = &mut elements[0];
+        assert!(element.has_class("card"));
+
+        let mut classes = element.class_list();
+        assert_eq!(classes.iter().collect::<Vec<_>>(), vec!["card", "active"]);
+        assert!(classes.contains("active"));
+        assert!(!classes.contains("act"));
+
+        classes.add("wide");
+        classes.add("card");
+        classes.remove("active");
+        assert!(!classes.toggle("wide"));
+        assert!(classes.toggle("dark"));
+        assert_eq!(classes.len(), 2);
+
+        assert_eq!(element.get_attr("class"), Some("card dark"));
+    }
+
+    #[test]
+    fn class_list_without_attribute() {
+        let mut element = HtmlElement::new("p");
+        assert!(element.class_list().is_empty());
+
+        element.class_list().add("lead");
+        assert_eq!(element.get_attr("class"), Some("lead"));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::prelude::*;
+use crate::{HtmlDocument, HtmlElement, HtmlNode};
+
+/// A movable focus into a recursive tree that remembers the path it took
+/// and can edit the tree in place around the focused node.
+pub struct TreeCursor<'t, 'a> {
+    roots: &'t mut Vec<HtmlNode<'a>>,
+    // Child index at each level, from the top-level list down to the focus
+    path: Vec<usize>,
+}
+
+impl<'t, 'a> TreeCursor<'t, 'a> {
+    /// Focuses the first node of `roots`, or `None` if there isn't one.
+    pub fn new(roots: &'t mut Vec<HtmlNode<'a>>) -> Option<Self> {
+        (!roots.is_empty()).then(|| Self {
+            roots,
+            path: vec![0],
+        })
+    }
+
+    pub fn node(&self) -> &HtmlNode<'a> {
+        &self.siblings()[self.index()]
+    }
+
+    pub fn node_mut(&mut self) -> &mut HtmlNode<'a> {
+        let index = self.index();
+        &mut self.siblings_mut()[index]
+    }
+
+    pub fn element(&self) -> Option<&HtmlElement<'a>> {
+        self.node().as_element()
+    }
+
+    pub fn element_mut(&mut self) -> Option<&mut HtmlElement<'a>> {
+        self.node_mut().as_element_mut()
+    }
+
+    /// Child indices leading from the top-level list to the focus.
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+
+    /// Zero for top-level nodes.
+    pub fn depth(&self) -> usize {
+        self.path.len() - 1
+    }
+
+    pub fn to_parent(&mut self) -> bool {
+        if self.path.len() > 1 {
+            self.path.pop();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn to_first_child(&mut self) -> bool {
+        self.to_child(0)
+    }
+
+    pub fn to_last_child(&mut self) -> bool {
+        let len = self.element().map_or(0, |element| element.children.len());
+        len > 0 && self.to_child(len - 1)
+    }
+
+    pub fn to_child(&mut self, index: usize) -> bool {
+        let exists = self
+            .element()
+            .is_some_and(|element| index < element.children.len());
+        if exists {
+            self.path.push(index);
+        }
+        exists
+    }
+
+    pub fn to_next_sibling(&mut self) -> bool {
+        let next = self.index() + 1;
+        if next < self.siblings().len() {
+            *self.path.last_mut().unwrap() = next;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn to_prev_sibling(&mut self) -> bool {
+        match self.index().checked_sub(1) {
+            Some(prev) => {
+                *self.path.last_mut().unwrap() = prev;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Swaps the focused node for `node`, returning the old one.
+    pub fn replace(&mut self, node: impl Into<HtmlNode<'a>>) -> HtmlNode<'a> {
+        core::mem::replace(self.node_mut(), node.into())
+    }
+
+    /// Inserts a sibling before the focus. The focus stays on the same node.
+    pub fn insert_before(&mut self, node: impl Into<HtmlNode<'a>>) {
+        let index = self.index();
+        self.siblings_mut().insert(index, node.into());
+        *self.path.last_mut().unwrap() += 1;
+    }
+
+    pub fn insert_after(&mut self, node: impl Into<HtmlNode<'a>>) {
+        let index = self.index();
+        self.siblings_mut().insert(index + 1, node.into());
+    }
+
+    /// Appends a child to the focused element. Returns false if the focus
+    /// isn't an element.
+    pub fn append_child(&mut self, node: impl Into<HtmlNode<'a>>) -> bool {
+        match self.element_mut() {
+            Some(element) => {
+                element.add_child(node);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the focused node and moves to its next sibling, or else its
+    /// previous sibling, or else its parent. The last remaining top-level
+    /// node can't be removed.
+    pub fn remove(&mut self) -> Option<HtmlNode<'a>> {
+        let index = self.index();
+        let len = self.siblings().len();
+        if len == 1 && self.path.len() == 1 {
+            return None;
+        }
+
+        let node = self.siblings_mut().remove(index);
+        if len == 1 {
+            self.path.pop();
+        } else if index == len - 1 {
+            *self.path.last_mut().unwrap() -= 1;
+        }
+        Some(node)
+    }
+
+    fn index(&self) -> usize {
+        *self.path.last().unwrap()
+    }
+
+    fn siblings(&self) -> &Vec<HtmlNode<'a>> {
+        let mut list = &*self.roots;
+        for &i in &self.path[..self.path.len() - 1] {
+            list = &list[i].as_element().unwrap().children;
+        }
+        list
+    }
+
+    fn siblings_mut(&mut self) -> &mut Vec<HtmlNode<'a>> {
+        let mut list = &mut *self.roots;
+        for &i in &self.path[..self.path.len() - 1] {
+            list = list[i].as_element_mut().unwrap().children_vec_mut();
+        }
+        list
+    }
+}
+
+impl<'a> HtmlElement<'a> {
+    /// A cursor over the children of this element.
+    pub fn cursor(&mut self) -> Option<TreeCursor<'_, 'a>> {
+        TreeCursor::new(self.children_vec_mut())
+    }
+}
+
+impl<'a> HtmlDocument<'a> {
+    pub fn cursor(&mut self) -> Option<TreeCursor<'_, 'a>> {
+        TreeCursor::new(self.nodes_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{document_to_string, parse_document};
+
+    #[test]
+    fn cursor_navigation() {
+        let mut document = parse_document("<ul><li>a</li><li>b<b>!</b></li></ul><p></p>").unwrap();
+        let mut cursor = document.cursor().unwrap();
+
+        assert!(cursor.to_first_child());
+        assert!(cursor.to_next_sibling());
+        assert!(!cursor.to_next_sibling());
+        assert!(cursor.to_last_child());
+        assert_eq!(cursor.element().map(HtmlElement::tag), Some("b"));
+        assert_eq!(cursor.path(), &[0, 1, 1]);
+        assert_eq!(cursor.depth(), 2);
+
+        assert!(cursor.to_prev_sibling());
+        assert_eq!(cursor.node().as_text(), Some("b"));
+        assert!(!cursor.to_first_child());
+        assert!(cursor.to_parent() && cursor.to_parent());
+        assert!(!cursor.to_parent());
+        assert!(cursor.to_next_sibling());
+        assert_eq!(cursor.element().map(HtmlElement::tag), Some("p"));
+    }
+
+    #[test]
+    fn cursor_edits() {
+        let mut document = parse_document("<ul><li>a</li><li>b</li></ul>").unwrap();
+        let mut cursor = document.cursor().unwrap();
+
+        cursor.to_first_child();
+        cursor.insert_before(HtmlElement::new("hr"));
+        assert_eq!(cursor.path(), &[0, 1]);
+        cursor.insert_after(HtmlNode::Text("x".into()));
+        cursor.to_next_sibling();
+        cursor.replace(HtmlNode::Comment(" y ".into()));
+
+        cursor.to_next_sibling();
+        assert!(cursor.append_child(HtmlElement::new("br")));
+        assert!(cursor.remove().is_some());
+        assert_eq!(cursor.node(), &HtmlNode::Comment(" y ".into()));
+        assert!(!cursor.append_child(HtmlElement::new("br")));
+
+        cursor.to_parent();
+        assert_eq!(cursor.remove(), None);
+        assert_eq!(
+            document_to_string(document),
+            "<ul><hr><li>a</li><!-- y --></ul>"
+        );
+    }
+}
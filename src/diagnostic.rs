@@ -0,0 +1,117 @@
+use core::fmt;
+
+use crate::prelude::*;
+use crate::{
+    parse_document_with, DuplicateAttributes, HtmlDocument, HtmlWarning, ParseMode, ParseOptions,
+    Span,
+};
+
+/// A problem in the input that parsing recovered from, and where it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic<'a> {
+    pub warning: HtmlWarning<'a>,
+    /// For problems with an element, the element as far as it was parsed.
+    pub span: Span,
+}
+
+impl<'a> Diagnostic<'a> {
+    pub fn new(warning: HtmlWarning<'a>, span: Span) -> Self {
+        Self { warning, span }
+    }
+}
+
+/// Parses `data` leniently, reporting everything the parser had to recover
+/// from instead of failing on the first problem.
+pub fn parse_with_diagnostics(data: &str) -> (HtmlDocument<'_>, Vec<Diagnostic<'_>>) {
+    let options = ParseOptions {
+        mode: ParseMode::Lenient,
+        duplicate_attributes: DuplicateAttributes::Warn,
+        warn_unquoted_attribute_values: true,
+        ..Default::default()
+    };
+    // Without limits, lenient parsing always builds a tree
+    let mut document = parse_document_with(data, &options).unwrap_or_default();
+    let diagnostics = core::mem::take(&mut document.diagnostics);
+    (document, diagnostics)
+}
+
+impl fmt::Display for HtmlWarning<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HtmlWarning::UnquotedAttributeValue { name, value } => {
+                write!(f, "value `{value}` of attribute `{name}` should be quoted")
+            }
+            HtmlWarning::DuplicateAttribute { name } => write!(f, "duplicate attribute `{name}`"),
+            HtmlWarning::UnclosedElement { tag } => write!(f, "`<{tag}>` is never closed"),
+            HtmlWarning::UnexpectedEndTag { tag } => write!(f, "end tag `</{tag}>` closes nothing"),
+            HtmlWarning::MisnestedElement { tag, closed_by } => {
+                write!(f, "`<{tag}>` is closed by `</{closed_by}>`")
+            }
+            HtmlWarning::UnreadableInput => f.write_str("unexpected end of input inside markup"),
+            HtmlWarning::MisplacedElement { tag, parent: None } => {
+                write!(f, "`<{tag}>` isn't allowed at the top level")
+            }
+            HtmlWarning::MisplacedElement {
+                tag,
+                parent: Some(parent),
+            } => write!(f, "`<{tag}>` isn't allowed inside `<{parent}>`"),
+            HtmlWarning::Lint { rule, message } => write!(f, "{message} ({rule})"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.warning, self.span.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_every_problem() {
+        let html = "<div id=a id=b><p><b>bold</p></span><i>";
+        let (document, diagnostics) = parse_with_diagnostics(html);
+        assert_eq!(document.elements().count(), 1);
+        assert_eq!(
+            diagnostics,
+            [
+                Diagnostic::new(
+                    HtmlWarning::DuplicateAttribute { name: "id" },
+                    Span::new(10, 14)
+                ),
+                Diagnostic::new(
+                    HtmlWarning::MisnestedElement {
+                        tag: "b".into(),
+                        closed_by: "p"
+                    },
+                    Span::new(18, 25)
+                ),
+                Diagnostic::new(
+                    HtmlWarning::UnexpectedEndTag { tag: "span" },
+                    Span::new(29, 36)
+                ),
+                Diagnostic::new(
+                    HtmlWarning::UnclosedElement { tag: "i".into() },
+                    Span::new(36, 39)
+                ),
+                Diagnostic::new(
+                    HtmlWarning::UnclosedElement { tag: "div".into() },
+                    Span::new(0, 39)
+                ),
+            ]
+        );
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "`<b>` is closed by `</p>` at byte 18"
+        );
+
+        let (_, diagnostics) = parse_with_diagnostics("<p>text<a href=\"x");
+        assert_eq!(
+            diagnostics[0],
+            Diagnostic::new(HtmlWarning::UnreadableInput, Span::new(7, 17))
+        );
+    }
+}
@@ -0,0 +1,359 @@
+use alloc::borrow::Cow;
+
+use crate::prelude::*;
+use crate::{HtmlAttribute, HtmlDocument, HtmlElement, HtmlError, HtmlNode};
+
+/// One step of an edit script from [`diff`].
+///
+/// A path is a list of child indices from the document's top-level nodes.
+/// Each edit's path points into the tree as the edits before it left it, so
+/// a script is applied in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "smallvec", allow(clippy::large_enum_variant))]
+pub enum DomEdit<'a> {
+    /// Inserts a node so that it ends up at the path.
+    Insert {
+        path: Vec<usize>,
+        #[cfg_attr(feature = "serde", serde(borrow))]
+        node: HtmlNode<'a>,
+    },
+    Remove {
+        path: Vec<usize>,
+    },
+    /// Adds an attribute to the element at the path, or changes its value.
+    SetAttribute {
+        path: Vec<usize>,
+        #[cfg_attr(feature = "serde", serde(borrow))]
+        name: Cow<'a, str>,
+        #[cfg_attr(feature = "serde", serde(borrow))]
+        value: Option<Cow<'a, str>>,
+    },
+    RemoveAttribute {
+        path: Vec<usize>,
+        #[cfg_attr(feature = "serde", serde(borrow))]
+        name: Cow<'a, str>,
+    },
+    /// Replaces the contents of the text node at the path.
+    SetText {
+        path: Vec<usize>,
+        #[cfg_attr(feature = "serde", serde(borrow))]
+        text: Cow<'a, str>,
+    },
+}
+
+impl DomEdit<'_> {
+    pub fn path(&self) -> &[usize] {
+        match self {
+            DomEdit::Insert { path, .. }
+            | DomEdit::Remove { path }
+            | DomEdit::SetAttribute { path, .. }
+            | DomEdit::RemoveAttribute { path, .. }
+            | DomEdit::SetText { path, .. } => path,
+        }
+    }
+}
+
+/// The edits that turn `old` into `new`.
+///
+/// Children are matched up by a longest common subsequence, so a node
+/// added in the middle of a list is one insert rather than a rewrite of
+/// everything after it. Elements with the same tag are edited in place;
+/// anything else that changed is removed and inserted again.
+pub fn diff<'a>(old: &HtmlDocument<'a>, new: &HtmlDocument<'a>) -> Vec<DomEdit<'a>> {
+    let mut edits = Vec::new();
+    diff_children(old.children(), new.children(), &mut Vec::new(), &mut edits);
+    edits
+}
+
+/// Why [`apply_patch`] couldn't apply an edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchConflict {
+    /// The path leads nowhere, or an insert's index is past the end.
+    NoSuchNode,
+    /// An attribute edit's path leads to a node that isn't an element.
+    NotAnElement,
+    /// A `SetText` path leads to a node that isn't text.
+    NotText,
+    /// A `RemoveAttribute` for an attribute the element doesn't have.
+    NoSuchAttribute,
+}
+
+impl core::fmt::Display for PatchConflict {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            PatchConflict::NoSuchNode => "no node at its path",
+            PatchConflict::NotAnElement => "the node at its path isn't an element",
+            PatchConflict::NotText => "the node at its path isn't text",
+            PatchConflict::NoSuchAttribute => "the element doesn't have the attribute",
+        })
+    }
+}
+
+/// Applies an edit script, such as one from [`diff`], in order.
+///
+/// Fails with [`HtmlError::InvalidPatch`] at the first edit that doesn't fit
+/// the tree, which usually means the patch was made against a different
+/// version of it. Edits before that one stay applied.
+pub fn apply_patch<'a>(
+    document: &mut HtmlDocument<'a>,
+    edits: &[DomEdit<'a>],
+) -> Result<(), HtmlError> {
+    for (index, edit) in edits.iter().enumerate() {
+        apply_edit(document.nodes_mut(), edit).map_err(|conflict| HtmlError::InvalidPatch {
+            edit: index,
+            conflict,
+        })?;
+    }
+    Ok(())
+}
+
+fn apply_edit<'a>(nodes: &mut Vec<HtmlNode<'a>>, edit: &DomEdit<'a>) -> Result<(), PatchConflict> {
+    let (&index, parent_path) = edit.path().split_last().ok_or(PatchConflict::NoSuchNode)?;
+    let siblings = siblings_mut(nodes, parent_path).ok_or(PatchConflict::NoSuchNode)?;
+    if let DomEdit::Insert { node, .. } = edit {
+        if index > siblings.len() {
+            return Err(PatchConflict::NoSuchNode);
+        }
+        siblings.insert(index, node.clone());
+        return Ok(());
+    }
+    let node = siblings.get_mut(index).ok_or(PatchConflict::NoSuchNode)?;
+    match edit {
+        DomEdit::Insert { .. } => unreachable!("handled above"),
+        DomEdit::Remove { .. } => {
+            siblings.remove(index);
+        }
+        DomEdit::SetText { text, .. } => match node {
+            HtmlNode::Text(old) => *old = text.clone(),
+            _ => return Err(PatchConflict::NotText),
+        },
+        DomEdit::SetAttribute { name, value, .. } => {
+            let attributes = node
+                .as_element_mut()
+                .ok_or(PatchConflict::NotAnElement)?
+                .attributes_vec_mut();
+            match attributes
+                .iter_mut()
+                .find(|attr| attr.name.eq_ignore_ascii_case(name))
+            {
+                Some(attr) => attr.value = value.clone(),
+                None => attributes.push(HtmlAttribute {
+                    name: name.clone(),
+                    value: value.clone(),
+                }),
+            }
+        }
+        DomEdit::RemoveAttribute { name, .. } => {
+            node.as_element_mut()
+                .ok_or(PatchConflict::NotAnElement)?
+                .remove_attr(name)
+                .ok_or(PatchConflict::NoSuchAttribute)?;
+        }
+    }
+    Ok(())
+}
+
+// The child list of the element at `path`, or the top-level nodes
+fn siblings_mut<'n, 'a>(
+    mut nodes: &'n mut Vec<HtmlNode<'a>>,
+    path: &[usize],
+) -> Option<&'n mut Vec<HtmlNode<'a>>> {
+    for &index in path {
+        nodes = nodes.get_mut(index)?.as_element_mut()?.children_vec_mut();
+    }
+    Some(nodes)
+}
+
+fn diff_children<'a>(
+    old: &[HtmlNode<'a>],
+    new: &[HtmlNode<'a>],
+    path: &mut Vec<usize>,
+    edits: &mut Vec<DomEdit<'a>>,
+) {
+    // scores[i][j] is the best alignment of old[i..] with new[j..], counting
+    // identical nodes above ones that only need editing
+    let mut scores = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            let skip = scores[i + 1][j].max(scores[i][j + 1]);
+            scores[i][j] = match weight(&old[i], &new[j]) {
+                0 => skip,
+                weight => skip.max(scores[i + 1][j + 1] + weight),
+            };
+        }
+    }
+
+    let (mut i, mut j, mut index) = (0, 0, 0);
+    while i < old.len() || j < new.len() {
+        let matched = i < old.len()
+            && j < new.len()
+            && weight(&old[i], &new[j]) > 0
+            && scores[i][j] == scores[i + 1][j + 1] + weight(&old[i], &new[j]);
+        path.push(index);
+        if matched {
+            diff_node(&old[i], &new[j], path, edits);
+            i += 1;
+            j += 1;
+            index += 1;
+        } else if j == new.len() || (i < old.len() && scores[i + 1][j] >= scores[i][j + 1]) {
+            edits.push(DomEdit::Remove { path: path.clone() });
+            i += 1;
+        } else {
+            edits.push(DomEdit::Insert {
+                path: path.clone(),
+                node: new[j].clone(),
+            });
+            j += 1;
+            index += 1;
+        }
+        path.pop();
+    }
+}
+
+// 2 for identical nodes, 1 for nodes that can be edited into each other and
+// 0 for nodes that can't
+fn weight<'a>(old: &HtmlNode<'a>, new: &HtmlNode<'a>) -> u32 {
+    if old == new {
+        return 2;
+    }
+    match (old, new) {
+        (HtmlNode::Text(_), HtmlNode::Text(_)) => 1,
+        (HtmlNode::Element(old), HtmlNode::Element(new))
+            if old.tag() == new.tag()
+                && old.namespace() == new.namespace()
+                // Template contents aren't reachable by a path
+                && old.template_content() == new.template_content() =>
+        {
+            1
+        }
+        _ => 0,
+    }
+}
+
+fn diff_node<'a>(
+    old: &HtmlNode<'a>,
+    new: &HtmlNode<'a>,
+    path: &mut Vec<usize>,
+    edits: &mut Vec<DomEdit<'a>>,
+) {
+    match (old, new) {
+        (HtmlNode::Text(old), HtmlNode::Text(new)) if old != new => {
+            edits.push(DomEdit::SetText {
+                path: path.clone(),
+                text: new.clone(),
+            });
+        }
+        (HtmlNode::Element(old), HtmlNode::Element(new)) => {
+            diff_attributes(old, new, path, edits);
+            diff_children(old.children(), new.children(), path, edits);
+        }
+        _ => {}
+    }
+}
+
+fn diff_attributes<'a>(
+    old: &HtmlElement<'a>,
+    new: &HtmlElement<'a>,
+    path: &[usize],
+    edits: &mut Vec<DomEdit<'a>>,
+) {
+    for attribute in old.attributes() {
+        if !new.has_attr(attribute.name()) {
+            edits.push(DomEdit::RemoveAttribute {
+                path: path.to_vec(),
+                name: attribute.name.clone(),
+            });
+        }
+    }
+    for attribute in new.attributes() {
+        let old_attribute = old
+            .attributes()
+            .iter()
+            .find(|old| old.name.eq_ignore_ascii_case(&attribute.name));
+        if old_attribute.is_none_or(|old| old.value != attribute.value) {
+            edits.push(DomEdit::SetAttribute {
+                path: path.to_vec(),
+                name: attribute.name.clone(),
+                value: attribute.value.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    #[test]
+    fn edit_script() {
+        let old = parse_document(
+            "<ul class=\"list\" hidden><li>one</li><li>two</li><li>three</li></ul><p>end</p>",
+        )
+        .unwrap();
+        let new = parse_document(
+            "<ul class=\"items\"><li>one</li><li>2</li><li>new</li><li>three</li></ul>",
+        )
+        .unwrap();
+        assert_eq!(
+            diff(&old, &new),
+            [
+                DomEdit::RemoveAttribute {
+                    path: vec![0],
+                    name: "hidden".into(),
+                },
+                DomEdit::SetAttribute {
+                    path: vec![0],
+                    name: "class".into(),
+                    value: Some("items".into()),
+                },
+                DomEdit::SetText {
+                    path: vec![0, 1, 0],
+                    text: "2".into(),
+                },
+                DomEdit::Insert {
+                    path: vec![0, 2],
+                    node: HtmlElement::new("li").text("new").into(),
+                },
+                DomEdit::Remove { path: vec![1] },
+            ]
+        );
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn patches() {
+        let old = parse_document("<div id=\"a\"><p>x</p>text<br></div><!-- c -->").unwrap();
+        let new = parse_document("<div><p class=\"y\">x</p>changed<i>!</i></div>").unwrap();
+        let mut patched = old.clone();
+        apply_patch(&mut patched, &diff(&old, &new)).unwrap();
+        assert_eq!(patched, new);
+
+        let conflicts = [
+            (DomEdit::Remove { path: vec![5] }, PatchConflict::NoSuchNode),
+            (
+                DomEdit::SetText {
+                    path: vec![0, 0],
+                    text: "y".into(),
+                },
+                PatchConflict::NotText,
+            ),
+            (
+                DomEdit::RemoveAttribute {
+                    path: vec![0],
+                    name: "class".into(),
+                },
+                PatchConflict::NoSuchAttribute,
+            ),
+        ];
+        for (edit, conflict) in conflicts {
+            let mut document = old.clone();
+            let edits = [DomEdit::Remove { path: vec![1] }, edit];
+            assert!(matches!(
+                apply_patch(&mut document, &edits),
+                Err(HtmlError::InvalidPatch { edit: 1, conflict: found }) if found == conflict
+            ));
+        }
+    }
+}
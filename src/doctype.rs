@@ -0,0 +1,266 @@
+use alloc::borrow::Cow;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HtmlDoctype<'a> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    name: Option<Cow<'a, str>>,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    public_id: Option<Cow<'a, str>>,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    system_id: Option<Cow<'a, str>>,
+    force_quirks: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QuirksMode {
+    NoQuirks,
+    LimitedQuirks,
+    Quirks,
+}
+
+const QUIRKS_PUBLIC_IDS: &[&str] = &[
+    "-//W3O//DTD W3 HTML Strict 3.0//EN//",
+    "-/W3C/DTD HTML 4.0 Transitional/EN",
+    "HTML",
+];
+
+const QUIRKS_SYSTEM_IDS: &[&str] = &["http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd"];
+
+const QUIRKS_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "+//Silmaril//dtd html Pro v0r11 19970101//",
+    "-//AS//DTD HTML 3.0 asWedit + extensions//",
+    "-//AdvaSoft Ltd//DTD HTML 3.0 asWedit + extensions//",
+    "-//IETF//DTD HTML 2.0 Level 1//",
+    "-//IETF//DTD HTML 2.0 Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict Level 1//",
+    "-//IETF//DTD HTML 2.0 Strict Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict//",
+    "-//IETF//DTD HTML 2.0//",
+    "-//IETF//DTD HTML 2.1E//",
+    "-//IETF//DTD HTML 3.0//",
+    "-//IETF//DTD HTML 3.2 Final//",
+    "-//IETF//DTD HTML 3.2//",
+    "-//IETF//DTD HTML 3//",
+    "-//IETF//DTD HTML Level 0//",
+    "-//IETF//DTD HTML Level 1//",
+    "-//IETF//DTD HTML Level 2//",
+    "-//IETF//DTD HTML Level 3//",
+    "-//IETF//DTD HTML Strict Level 0//",
+    "-//IETF//DTD HTML Strict Level 1//",
+    "-//IETF//DTD HTML Strict Level 2//",
+    "-//IETF//DTD HTML Strict Level 3//",
+    "-//IETF//DTD HTML Strict//",
+    "-//IETF//DTD HTML//",
+    "-//Metrius//DTD Metrius Presentational//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 2.0 Tables//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 3.0 Tables//",
+    "-//Netscape Comm. Corp.//DTD HTML//",
+    "-//Netscape Comm. Corp.//DTD Strict HTML//",
+    "-//O'Reilly and Associates//DTD HTML 2.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended 1.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended Relaxed 1.0//",
+    "-//SQ//DTD HTML 2.0 HoTMetaL + extensions//",
+    "-//SoftQuad Software//DTD HoTMetaL PRO 6.0::19990601::extensions to HTML 4.0//",
+    "-//SoftQuad//DTD HoTMetaL PRO 4.0::19971010::extensions to HTML 4.0//",
+    "-//Spyglass//DTD HTML 2.0 Extended//",
+    "-//Sun Microsystems Corp.//DTD HotJava HTML//",
+    "-//Sun Microsystems Corp.//DTD HotJava Strict HTML//",
+    "-//W3C//DTD HTML 3 1995-03-24//",
+    "-//W3C//DTD HTML 3.2 Draft//",
+    "-//W3C//DTD HTML 3.2 Final//",
+    "-//W3C//DTD HTML 3.2//",
+    "-//W3C//DTD HTML 3.2S Draft//",
+    "-//W3C//DTD HTML 4.0 Frameset//",
+    "-//W3C//DTD HTML 4.0 Transitional//",
+    "-//W3C//DTD HTML Experimental 19960712//",
+    "-//W3C//DTD HTML Experimental 970421//",
+    "-//W3C//DTD W3 HTML//",
+    "-//W3O//DTD W3 HTML 3.0//",
+    "-//WebTechs//DTD Mozilla HTML 2.0//",
+    "-//WebTechs//DTD Mozilla HTML//",
+];
+
+// Quirks without a system identifier, limited quirks with one
+const HTML4_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "-//W3C//DTD HTML 4.01 Frameset//",
+    "-//W3C//DTD HTML 4.01 Transitional//",
+];
+
+const LIMITED_QUIRKS_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "-//W3C//DTD XHTML 1.0 Frameset//",
+    "-//W3C//DTD XHTML 1.0 Transitional//",
+];
+
+impl<'a> HtmlDoctype<'a> {
+    pub fn new(name: &'a str) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn from_parts(
+        name: Option<&'a str>,
+        public_id: Option<&'a str>,
+        system_id: Option<&'a str>,
+        force_quirks: bool,
+    ) -> Self {
+        Self {
+            name: name.map(Cow::Borrowed),
+            public_id: public_id.map(Cow::Borrowed),
+            system_id: system_id.map(Cow::Borrowed),
+            force_quirks,
+        }
+    }
+
+    // Parses everything between `<!DOCTYPE` and the closing `>`
+    pub(crate) fn from_source(source: &'a str) -> Self {
+        let mut doctype = Self::default();
+
+        let rest = source.trim_start();
+        let name_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if name_len == 0 {
+            doctype.force_quirks = true;
+            return doctype;
+        }
+        doctype.name = Some(rest[..name_len].into());
+
+        let rest = rest[name_len..].trim_start();
+        let keyword_len = rest
+            .find(|ch: char| ch.is_whitespace() || ch == '"' || ch == '\'')
+            .unwrap_or(rest.len());
+        let (keyword, rest) = rest.split_at(keyword_len);
+
+        if keyword.eq_ignore_ascii_case("public") {
+            match read_quoted(rest) {
+                Some((public_id, rest)) => {
+                    doctype.public_id = Some(public_id.into());
+                    doctype.system_id = read_quoted(rest).map(|(system_id, _)| system_id.into());
+                }
+                None => doctype.force_quirks = true,
+            }
+        } else if keyword.eq_ignore_ascii_case("system") {
+            match read_quoted(rest) {
+                Some((system_id, _)) => doctype.system_id = Some(system_id.into()),
+                None => doctype.force_quirks = true,
+            }
+        } else if !keyword.is_empty() {
+            doctype.force_quirks = true;
+        }
+
+        doctype
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn public_id(&self) -> Option<&str> {
+        self.public_id.as_deref()
+    }
+
+    pub fn system_id(&self) -> Option<&str> {
+        self.system_id.as_deref()
+    }
+
+    pub fn force_quirks(&self) -> bool {
+        self.force_quirks
+    }
+
+    /// Determines the document mode per the "initial" insertion mode rules.
+    pub fn quirks_mode(&self) -> QuirksMode {
+        let public_id = self.public_id().unwrap_or_default();
+        let system_id = self.system_id().unwrap_or_default();
+
+        let is_html = self
+            .name()
+            .is_some_and(|name| name.eq_ignore_ascii_case("html"));
+
+        if self.force_quirks
+            || !is_html
+            || QUIRKS_PUBLIC_IDS
+                .iter()
+                .any(|id| id.eq_ignore_ascii_case(public_id))
+            || QUIRKS_SYSTEM_IDS
+                .iter()
+                .any(|id| id.eq_ignore_ascii_case(system_id))
+            || starts_with_any(public_id, QUIRKS_PUBLIC_ID_PREFIXES)
+            || (self.system_id.is_none() && starts_with_any(public_id, HTML4_PUBLIC_ID_PREFIXES))
+        {
+            QuirksMode::Quirks
+        } else if starts_with_any(public_id, LIMITED_QUIRKS_PUBLIC_ID_PREFIXES)
+            || (self.system_id.is_some() && starts_with_any(public_id, HTML4_PUBLIC_ID_PREFIXES))
+        {
+            QuirksMode::LimitedQuirks
+        } else {
+            QuirksMode::NoQuirks
+        }
+    }
+}
+
+fn read_quoted(data: &str) -> Option<(&str, &str)> {
+    let data = data.trim_start();
+    let quote = data.chars().next().filter(|ch| *ch == '"' || *ch == '\'')?;
+    let end = data[1..].find(quote)?;
+    Some((&data[1..end + 1], &data[end + 2..]))
+}
+
+fn starts_with_any(id: &str, prefixes: &[&str]) -> bool {
+    prefixes.iter().any(|prefix| {
+        id.len() >= prefix.len()
+            && id.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_doctype() {
+        let doctype = HtmlDoctype::from_source(
+            r#" html PUBLIC "-//W3C//DTD HTML 4.01//EN" 'http://www.w3.org/TR/html4/strict.dtd'"#,
+        );
+        assert_eq!(doctype.name(), Some("html"));
+        assert_eq!(doctype.public_id(), Some("-//W3C//DTD HTML 4.01//EN"));
+        assert_eq!(
+            doctype.system_id(),
+            Some("http://www.w3.org/TR/html4/strict.dtd")
+        );
+    }
+
+    #[test]
+    fn doctype_quirks_mode() {
+        let mode = |source| HtmlDoctype::from_source(source).quirks_mode();
+
+        assert_eq!(mode(" html"), QuirksMode::NoQuirks);
+        assert_eq!(mode(" HTML"), QuirksMode::NoQuirks);
+        assert_eq!(mode(""), QuirksMode::Quirks);
+        assert_eq!(mode(" svg"), QuirksMode::Quirks);
+        assert_eq!(mode(" html bogus"), QuirksMode::Quirks);
+        assert_eq!(
+            mode(r#" html PUBLIC "-//W3C//DTD HTML 3.2 Final//EN""#),
+            QuirksMode::Quirks
+        );
+        assert_eq!(
+            mode(r#" html PUBLIC "-//W3C//DTD HTML 4.01 Transitional//EN""#),
+            QuirksMode::Quirks
+        );
+        assert_eq!(
+            mode(
+                r#" html PUBLIC "-//W3C//DTD HTML 4.01 Transitional//EN" "http://www.w3.org/TR/html4/loose.dtd""#
+            ),
+            QuirksMode::LimitedQuirks
+        );
+        assert_eq!(
+            mode(r#" html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" "x""#),
+            QuirksMode::LimitedQuirks
+        );
+    }
+}
@@ -0,0 +1,748 @@
+use crate::prelude::*;
+use crate::HashMap;
+use alloc::borrow::Cow;
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+// Without `std` the id index can't be built from several threads at once
+#[cfg(not(feature = "std"))]
+use core::cell::OnceCell as OnceLock;
+
+use crate::{
+    find_attribute, parse_document, Atom, HtmlAttribute, HtmlDoctype, HtmlDocument, HtmlElement,
+    HtmlError, HtmlNode, Namespace, Span,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeData<'a> {
+    Document,
+    Element {
+        tag: Cow<'a, str>,
+        attributes: Vec<HtmlAttribute<'a>>,
+        namespace: Namespace,
+    },
+    Text(Cow<'a, str>),
+    CData(Cow<'a, str>),
+    Comment(Cow<'a, str>),
+    Doctype(HtmlDoctype<'a>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Node<'a> {
+    data: NodeData<'a>,
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    prev_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+    span: Option<Span>,
+    // The tag's atom, for quick selector matching. Dropped by `data_mut`,
+    // which could change the tag.
+    tag_atom: Option<Atom>,
+}
+
+impl<'a> Node<'a> {
+    fn new(data: NodeData<'a>) -> Self {
+        let tag_atom = match &data {
+            NodeData::Element { tag, .. } => Atom::new(tag),
+            _ => None,
+        };
+        Self {
+            tag_atom,
+            data,
+            span: None,
+            parent: None,
+            first_child: None,
+            last_child: None,
+            prev_sibling: None,
+            next_sibling: None,
+        }
+    }
+
+    pub fn data(&self) -> &NodeData<'a> {
+        &self.data
+    }
+
+    /// Where in the input the node was parsed from, if it was.
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    pub(crate) fn set_span(&mut self, span: Option<Span>) {
+        self.span = span;
+    }
+
+    pub fn data_mut(&mut self) -> &mut NodeData<'a> {
+        self.tag_atom = None;
+        &mut self.data
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn prev_sibling(&self) -> Option<NodeId> {
+        self.prev_sibling
+    }
+
+    pub fn next_sibling(&self) -> Option<NodeId> {
+        self.next_sibling
+    }
+
+    pub fn first_child(&self) -> Option<NodeId> {
+        self.first_child
+    }
+
+    pub fn last_child(&self) -> Option<NodeId> {
+        self.last_child
+    }
+
+    pub fn is_element(&self) -> bool {
+        matches!(self.data, NodeData::Element { .. })
+    }
+
+    pub fn tag(&self) -> Option<&str> {
+        match &self.data {
+            NodeData::Element { tag, .. } => Some(tag),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn tag_atom(&self) -> Option<Atom> {
+        self.tag_atom
+    }
+
+    pub fn attributes(&self) -> &[HtmlAttribute<'a>] {
+        match &self.data {
+            NodeData::Element { attributes, .. } => attributes,
+            _ => &[],
+        }
+    }
+
+    pub fn get_attr(&self, name: &str) -> Option<&str> {
+        find_attribute(self.attributes(), name).map(|attr| attr.value().unwrap_or_default())
+    }
+
+    pub fn has_attr(&self, name: &str) -> bool {
+        find_attribute(self.attributes(), name).is_some()
+    }
+
+    /// Text content of text and CDATA nodes
+    pub fn text(&self) -> Option<&str> {
+        match &self.data {
+            NodeData::Text(text) => Some(text),
+            NodeData::CData(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// Arena-backed document tree addressed by [`NodeId`] handles.
+#[derive(Debug, Clone)]
+pub struct Document<'a> {
+    nodes: Vec<Node<'a>>,
+    // Built on the first id lookup and dropped whenever a node is mutated
+    ids: OnceLock<HashMap<String, NodeId>>,
+    // `<template>` elements and the fragments holding their contents
+    templates: HashMap<NodeId, NodeId>,
+}
+
+impl Default for Document<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Document<'a> {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node::new(NodeData::Document)],
+            ids: OnceLock::new(),
+            templates: HashMap::new(),
+        }
+    }
+
+    pub fn parse(data: &'a str) -> Result<Self, HtmlError> {
+        parse_document(data).map(Document::from)
+    }
+
+    pub fn from_nodes(nodes: Vec<HtmlNode<'a>>) -> Self {
+        let mut document = Self::new();
+        let root = document.root();
+
+        // Nodes are appended iteratively so deep trees don't recurse
+        let mut stack = vec![(root, nodes.into_iter())];
+        while let Some((parent, children)) = stack.last_mut() {
+            let parent = *parent;
+            let Some(node) = children.next() else {
+                stack.pop();
+                continue;
+            };
+
+            let mut span = None;
+            let (data, children, content) = match node {
+                HtmlNode::Element(element) => {
+                    let is_template = element.tag.eq_ignore_ascii_case("template");
+                    span = element.span;
+                    (
+                        NodeData::Element {
+                            tag: element.tag,
+                            attributes: element.attributes.into_iter().collect(),
+                            namespace: element.namespace,
+                        },
+                        Some(element.children),
+                        is_template.then_some(element.content),
+                    )
+                }
+                HtmlNode::Text(text) => (NodeData::Text(text), None, None),
+                HtmlNode::CData(text) => (NodeData::CData(text), None, None),
+                HtmlNode::Comment(comment) => (NodeData::Comment(comment), None, None),
+                HtmlNode::Doctype(doctype) => (NodeData::Doctype(doctype), None, None),
+            };
+
+            let id = document.append(parent, data);
+            document.get_mut(id).span = span;
+            if let Some(content) = content {
+                let fragment = document.template_content_or_insert(id);
+                stack.push((fragment, content.into_iter()));
+            }
+            if let Some(children) = children {
+                stack.push((id, children.into_iter()));
+            }
+        }
+
+        document
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() == 1
+    }
+
+    pub fn get(&self, id: NodeId) -> &Node<'a> {
+        &self.nodes[id.0]
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> &mut Node<'a> {
+        self.ids.take();
+        &mut self.nodes[id.0]
+    }
+
+    /// The first element in document order whose `id` attribute matches.
+    pub fn get_element_by_id(&self, id: &str) -> Option<NodeId> {
+        self.ids
+            .get_or_init(|| {
+                let mut ids = HashMap::new();
+                for node in self.descendants(self.root()) {
+                    if let Some(id) = self.get(node).get_attr("id") {
+                        ids.entry(id.to_string()).or_insert(node);
+                    }
+                }
+                ids
+            })
+            .get(id)
+            .copied()
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.get(id).parent
+    }
+
+    /// Parents of `id` up to and including the document root.
+    pub fn ancestors(&self, id: NodeId) -> Ancestors<'_, 'a> {
+        Ancestors {
+            document: self,
+            next: self.get(id).parent,
+        }
+    }
+
+    /// The nearest element at or above `id` with the given tag.
+    pub fn closest(&self, id: NodeId, tag: &str) -> Option<NodeId> {
+        core::iter::once(id).chain(self.ancestors(id)).find(|id| {
+            self.get(*id)
+                .tag()
+                .is_some_and(|name| name.eq_ignore_ascii_case(tag))
+        })
+    }
+
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.get(id).next_sibling
+    }
+
+    pub fn prev_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.get(id).prev_sibling
+    }
+
+    /// The next sibling that is an element, skipping text and comments.
+    pub fn next_element_sibling(&self, id: NodeId) -> Option<NodeId> {
+        core::iter::successors(self.next_sibling(id), |id| self.next_sibling(*id))
+            .find(|id| self.get(*id).is_element())
+    }
+
+    /// The previous sibling that is an element, skipping text and comments.
+    pub fn prev_element_sibling(&self, id: NodeId) -> Option<NodeId> {
+        core::iter::successors(self.prev_sibling(id), |id| self.prev_sibling(*id))
+            .find(|id| self.get(*id).is_element())
+    }
+
+    pub fn children(&self, id: NodeId) -> Children<'_, 'a> {
+        Children {
+            document: self,
+            next: self.get(id).first_child,
+        }
+    }
+
+    /// Every node below `id` in document order, excluding `id` itself.
+    pub fn descendants(&self, id: NodeId) -> Descendants<'_, 'a> {
+        Descendants {
+            document: self,
+            root: id,
+            next: self.get(id).first_child,
+        }
+    }
+
+    /// The fragment holding the contents of a `<template>` element. Its
+    /// children are walked like those of any other node.
+    pub fn template_content(&self, id: NodeId) -> Option<NodeId> {
+        self.templates.get(&id).copied()
+    }
+
+    pub(crate) fn template_content_or_insert(&mut self, id: NodeId) -> NodeId {
+        if let Some(fragment) = self.template_content(id) {
+            return fragment;
+        }
+        let fragment = self.create_node(NodeData::Document);
+        self.templates.insert(id, fragment);
+        fragment
+    }
+
+    /// Creates a detached node that can later be appended anywhere in the tree.
+    pub fn create_node(&mut self, data: NodeData<'a>) -> NodeId {
+        self.nodes.push(Node::new(data));
+        NodeId(self.nodes.len() - 1)
+    }
+
+    pub fn append(&mut self, parent: NodeId, data: NodeData<'a>) -> NodeId {
+        let id = self.create_node(data);
+        self.append_child(parent, id);
+        id
+    }
+
+    /// Moves `child` to the end of `parent`'s children. Returns false, leaving
+    /// the tree as it was, if `child` is `parent` or one of its ancestors.
+    pub fn append_child(&mut self, parent: NodeId, child: NodeId) -> bool {
+        if self.contains(child, parent) {
+            return false;
+        }
+        self.detach(child);
+        let prev = self.get(parent).last_child;
+        self.link(parent, prev, None, child);
+        true
+    }
+
+    /// Like [`append_child`](Self::append_child), but as the first child.
+    pub fn prepend_child(&mut self, parent: NodeId, child: NodeId) -> bool {
+        if self.contains(child, parent) {
+            return false;
+        }
+        self.detach(child);
+        let next = self.get(parent).first_child;
+        self.link(parent, None, next, child);
+        true
+    }
+
+    /// Inserts `new` as the sibling directly before `reference`. Returns
+    /// false, leaving the tree as it was, if `reference` is detached or `new`
+    /// is one of its ancestors.
+    pub fn insert_before(&mut self, reference: NodeId, new: NodeId) -> bool {
+        let Some(parent) = self.sibling_parent(reference, new) else {
+            return false;
+        };
+        if new != reference {
+            self.detach(new);
+            let prev = self.get(reference).prev_sibling;
+            self.link(parent, prev, Some(reference), new);
+        }
+        true
+    }
+
+    /// Inserts `new` as the sibling directly after `reference`, with the same
+    /// checks as [`insert_before`](Self::insert_before).
+    pub fn insert_after(&mut self, reference: NodeId, new: NodeId) -> bool {
+        let Some(parent) = self.sibling_parent(reference, new) else {
+            return false;
+        };
+        if new != reference {
+            self.detach(new);
+            let next = self.get(reference).next_sibling;
+            self.link(parent, Some(reference), next, new);
+        }
+        true
+    }
+
+    /// Puts `new` in the place of `old`, leaving `old` detached. Returns
+    /// false under the same conditions as [`insert_before`](Self::insert_before).
+    pub fn replace_with(&mut self, old: NodeId, new: NodeId) -> bool {
+        if old == new {
+            return self.get(old).parent.is_some();
+        }
+        let inserted = self.insert_before(old, new);
+        if inserted {
+            self.detach(old);
+        }
+        inserted
+    }
+
+    // Whether `id` is `ancestor` or somewhere below it
+    fn contains(&self, ancestor: NodeId, id: NodeId) -> bool {
+        core::iter::successors(Some(id), |id| self.parent(*id)).any(|id| id == ancestor)
+    }
+
+    // The parent `new` would share with `reference`, if `new` can go there
+    fn sibling_parent(&self, reference: NodeId, new: NodeId) -> Option<NodeId> {
+        let parent = self.get(reference).parent?;
+        (!self.contains(new, parent)).then_some(parent)
+    }
+
+    /// Unlinks `id` (and its subtree) from its parent and siblings. The node
+    /// stays in the arena and can be reinserted.
+    pub fn detach(&mut self, id: NodeId) {
+        let node = self.get_mut(id);
+        let parent = node.parent.take();
+        let prev = node.prev_sibling.take();
+        let next = node.next_sibling.take();
+
+        match prev {
+            Some(prev) => self.get_mut(prev).next_sibling = next,
+            None => {
+                if let Some(parent) = parent {
+                    self.get_mut(parent).first_child = next;
+                }
+            }
+        }
+        match next {
+            Some(next) => self.get_mut(next).prev_sibling = prev,
+            None => {
+                if let Some(parent) = parent {
+                    self.get_mut(parent).last_child = prev;
+                }
+            }
+        }
+    }
+
+    // Links a detached node between two adjacent children of `parent`
+    fn link(&mut self, parent: NodeId, prev: Option<NodeId>, next: Option<NodeId>, id: NodeId) {
+        let node = self.get_mut(id);
+        node.parent = Some(parent);
+        node.prev_sibling = prev;
+        node.next_sibling = next;
+
+        match prev {
+            Some(prev) => self.get_mut(prev).next_sibling = Some(id),
+            None => self.get_mut(parent).first_child = Some(id),
+        }
+        match next {
+            Some(next) => self.get_mut(next).prev_sibling = Some(id),
+            None => self.get_mut(parent).last_child = Some(id),
+        }
+    }
+
+    /// Converts the subtree below `id` back into a recursive tree.
+    pub fn to_nodes(&self, id: NodeId) -> Vec<HtmlNode<'a>> {
+        self.children(id).map(|child| self.to_node(child)).collect()
+    }
+
+    /// Converts the node `id` and its subtree into a recursive tree.
+    pub fn to_node(&self, id: NodeId) -> HtmlNode<'a> {
+        match &self.get(id).data {
+            NodeData::Document => HtmlNode::Element(HtmlElement {
+                children: self.to_nodes(id),
+                ..Default::default()
+            }),
+            NodeData::Element {
+                tag,
+                attributes,
+                namespace,
+            } => HtmlNode::Element(HtmlElement {
+                tag: tag.clone(),
+                attributes: attributes.as_slice().into(),
+                namespace: *namespace,
+                content: self
+                    .template_content(id)
+                    .map(|fragment| self.to_nodes(fragment))
+                    .unwrap_or_default(),
+                children: self.to_nodes(id),
+                span: self.get(id).span,
+                ..Default::default()
+            }),
+            NodeData::Text(text) => HtmlNode::Text(text.clone()),
+            NodeData::CData(text) => HtmlNode::CData(text.clone()),
+            NodeData::Comment(comment) => HtmlNode::Comment(comment.clone()),
+            NodeData::Doctype(doctype) => HtmlNode::Doctype(doctype.clone()),
+        }
+    }
+}
+
+impl<'a> From<HtmlDocument<'a>> for Document<'a> {
+    fn from(document: HtmlDocument<'a>) -> Self {
+        Document::from_nodes(document.nodes)
+    }
+}
+
+pub struct Children<'d, 'a> {
+    document: &'d Document<'a>,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Children<'_, '_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        self.next = self.document.get(id).next_sibling;
+        Some(id)
+    }
+}
+
+pub struct Ancestors<'d, 'a> {
+    document: &'d Document<'a>,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Ancestors<'_, '_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        self.next = self.document.get(id).parent;
+        Some(id)
+    }
+}
+
+pub struct Descendants<'d, 'a> {
+    document: &'d Document<'a>,
+    root: NodeId,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Descendants<'_, '_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        let node = self.document.get(id);
+
+        self.next = node.first_child.or_else(|| {
+            // Climb until a node with a following sibling is found
+            let mut current = id;
+            loop {
+                if current == self.root {
+                    return None;
+                }
+                let node = self.document.get(current);
+                if let Some(sibling) = node.next_sibling {
+                    return Some(sibling);
+                }
+                current = node.parent?;
+            }
+        });
+
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document_to_string;
+
+    #[test]
+    fn build_document() {
+        let html = "<!DOCTYPE html><div id=\"a\"><p>one</p><p>two</p></div><!-- end -->";
+        let document = Document::parse(html).unwrap();
+        let root = document.root();
+
+        let top: Vec<_> = document.children(root).collect();
+        assert_eq!(top.len(), 3);
+        assert_eq!(document.get(top[1]).tag(), Some("div"));
+        assert_eq!(document.get(top[1]).attributes()[0].value(), Some("a"));
+
+        let texts: Vec<_> = document
+            .descendants(top[1])
+            .filter_map(|id| document.get(id).text())
+            .collect();
+        assert_eq!(texts, vec!["one", "two"]);
+
+        let nodes = document.to_nodes(root);
+        assert_eq!(
+            document_to_string(HtmlDocument {
+                nodes,
+                ..Default::default()
+            }),
+            html
+        );
+    }
+
+    #[test]
+    fn ancestors() {
+        let html = "<div class=\"card\"><ul><li><a href=\"#\">link</a></li></ul></div>";
+        let document = Document::parse(html).unwrap();
+        let text = document
+            .descendants(document.root())
+            .find(|id| document.get(*id).text().is_some())
+            .unwrap();
+
+        let tags: Vec<_> = document
+            .ancestors(text)
+            .filter_map(|id| document.get(id).tag())
+            .collect();
+        assert_eq!(tags, vec!["a", "li", "ul", "div"]);
+        assert_eq!(document.ancestors(text).last(), Some(document.root()));
+
+        let link = document.parent(text).unwrap();
+        assert_eq!(document.closest(link, "a"), Some(link));
+        let card = document.closest(text, "DIV").unwrap();
+        assert_eq!(document.get(card).get_attr("class"), Some("card"));
+        assert!(document.get(link).has_attr("href"));
+        assert_eq!(document.closest(text, "table"), None);
+    }
+
+    #[test]
+    fn siblings() {
+        let html = "<tr><td>a</td> <!-- x --> <td>b</td><td>c</td></tr>";
+        let document = Document::parse(html).unwrap();
+        let row = document.children(document.root()).next().unwrap();
+        let first = document.get(row).first_child().unwrap();
+
+        let cells: Vec<_> =
+            core::iter::successors(Some(first), |id| document.next_element_sibling(*id)).collect();
+        assert_eq!(cells.len(), 3);
+        assert_eq!(
+            document
+                .next_sibling(first)
+                .map(|id| document.get(id).is_element()),
+            Some(false)
+        );
+        assert_eq!(document.prev_element_sibling(cells[1]), Some(first));
+        assert_eq!(document.prev_element_sibling(first), None);
+        assert_eq!(document.prev_sibling(first), None);
+        assert_eq!(document.next_element_sibling(cells[2]), None);
+    }
+
+    #[test]
+    fn mutate_document() {
+        let mut document = Document::parse("<ul><li>a</li><li>b</li><li>c</li></ul>").unwrap();
+        let list = document.children(document.root()).next().unwrap();
+        let items: Vec<_> = document.children(list).collect();
+        let render = |document: &Document| {
+            document_to_string(HtmlDocument {
+                nodes: document.to_nodes(document.root()),
+                ..Default::default()
+            })
+        };
+
+        document.insert_before(items[0], items[2]);
+        assert_eq!(render(&document), "<ul><li>c</li><li>a</li><li>b</li></ul>");
+
+        document.insert_after(items[1], items[0]);
+        assert_eq!(render(&document), "<ul><li>c</li><li>b</li><li>a</li></ul>");
+
+        let item = document.create_node(NodeData::Element {
+            tag: "li".into(),
+            attributes: vec![],
+            namespace: Namespace::Html,
+        });
+        document.append(item, NodeData::Text("new".into()));
+        document.replace_with(items[1], item);
+        assert_eq!(document.parent(items[1]), None);
+        assert_eq!(
+            render(&document),
+            "<ul><li>c</li><li>new</li><li>a</li></ul>"
+        );
+
+        document.detach(items[2]);
+        document.prepend_child(list, items[1]);
+        assert_eq!(
+            render(&document),
+            "<ul><li>b</li><li>new</li><li>a</li></ul>"
+        );
+        assert_eq!(document.get(list).last_child(), Some(items[0]));
+
+        let before = render(&document);
+        let root = document.root();
+        assert!(!document.append_child(items[0], list));
+        assert!(!document.prepend_child(list, list));
+        assert!(!document.insert_before(items[0], list));
+        assert!(!document.insert_after(items[0], root));
+        assert!(!document.insert_before(items[2], items[0]));
+        assert!(!document.replace_with(items[0], list));
+        assert!(document.insert_before(items[0], items[0]));
+        assert_eq!(render(&document), before);
+    }
+
+    #[test]
+    fn append_nodes() {
+        let mut document = Document::new();
+        let root = document.root();
+        let list = document.append(
+            root,
+            NodeData::Element {
+                tag: "ul".into(),
+                attributes: vec![],
+                namespace: Namespace::Html,
+            },
+        );
+        for text in ["a", "b"] {
+            let item = document.append(
+                list,
+                NodeData::Element {
+                    tag: "li".into(),
+                    attributes: vec![],
+                    namespace: Namespace::Html,
+                },
+            );
+            document.append(item, NodeData::Text(text.into()));
+        }
+
+        assert_eq!(document.len(), 6);
+        assert_eq!(document.descendants(root).count(), 5);
+        assert_eq!(document.children(list).count(), 2);
+    }
+
+    #[test]
+    fn element_by_id() {
+        let html = "<div id=\"main\"><p id=\"a\">one</p><p id=\"a\">two</p></div>";
+        let mut document = Document::parse(html).unwrap();
+        let main = document.get_element_by_id("main").unwrap();
+        assert_eq!(document.get(main).tag(), Some("div"));
+
+        let first = document.get_element_by_id("a").unwrap();
+        assert_eq!(document.prev_sibling(first), None);
+        assert_eq!(document.get_element_by_id("missing"), None);
+
+        document.detach(first);
+        let second = document.get_element_by_id("a").unwrap();
+        assert_ne!(first, second);
+
+        if let NodeData::Element { attributes, .. } = document.get_mut(second).data_mut() {
+            attributes[0].set_value("b");
+        }
+        assert_eq!(document.get_element_by_id("a"), None);
+        assert_eq!(document.get_element_by_id("b"), Some(second));
+    }
+}
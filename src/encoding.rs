@@ -0,0 +1,242 @@
+use alloc::borrow::Cow;
+
+use encoding_rs::{Decoder, Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252, X_USER_DEFINED};
+
+use crate::{
+    parse_html, ChunkTokenizer, HtmlAst, HtmlElementOwned, HtmlError, StreamParser, Tokens,
+};
+
+// How far into the document a `<meta charset>` is looked for
+const PRESCAN_LEN: usize = 1024;
+
+/// Works out the encoding of a document the way browsers do before parsing:
+/// a byte order mark wins, then a `<meta charset>` (or `http-equiv`
+/// Content-Type) in the first 1024 bytes. Undeclared documents are UTF-8 if
+/// they decode as such and windows-1252 otherwise.
+pub fn sniff_encoding(bytes: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+    if let Some(encoding) = prescan(&bytes[..bytes.len().min(PRESCAN_LEN)]) {
+        return encoding;
+    }
+    match core::str::from_utf8(bytes) {
+        Ok(_) => UTF_8,
+        Err(_) => WINDOWS_1252,
+    }
+}
+
+/// Decodes a document to UTF-8 using [`sniff_encoding`]. Malformed
+/// sequences become U+FFFD.
+pub fn decode_html_bytes(bytes: &[u8]) -> Cow<'_, str> {
+    let (text, _) = sniff_encoding(bytes).decode_with_bom_removal(bytes);
+    text
+}
+
+/// Decodes in a known encoding, e.g. one from a Content-Type header. As in
+/// browsers, a byte order mark still takes precedence.
+pub fn decode_html_bytes_with<'b>(bytes: &'b [u8], encoding: &'static Encoding) -> Cow<'b, str> {
+    let (text, _, _) = encoding.decode(bytes);
+    text
+}
+
+/// Decodes with [`decode_html_bytes`] and parses the result. The tree is
+/// owned since the decoded text doesn't outlive the call.
+pub fn parse_html_bytes(bytes: &[u8]) -> Result<Vec<HtmlElementOwned>, HtmlError> {
+    parse_decoded(&decode_html_bytes(bytes))
+}
+
+pub fn parse_html_bytes_with(
+    bytes: &[u8],
+    encoding: &'static Encoding,
+) -> Result<Vec<HtmlElementOwned>, HtmlError> {
+    parse_decoded(&decode_html_bytes_with(bytes, encoding))
+}
+
+fn parse_decoded(text: &str) -> Result<Vec<HtmlElementOwned>, HtmlError> {
+    let elements = parse_html(text)?;
+    Ok(elements
+        .into_iter()
+        .map(|element| element.into_owned())
+        .collect())
+}
+
+impl ChunkTokenizer {
+    /// A tokenizer for input in `encoding`, transcoded to UTF-8 as it
+    /// arrives. Multi-byte sequences may be split across chunks.
+    pub fn with_encoding(encoding: &'static Encoding) -> Self {
+        let mut tokenizer = Self::new();
+        tokenizer.transcoder = Some(encoding.new_decoder());
+        tokenizer
+    }
+}
+
+impl<R: std::io::Read> StreamParser<R> {
+    pub fn with_encoding(reader: R, encoding: &'static Encoding) -> Self {
+        let mut parser = Self::new(reader);
+        parser.decoder = ChunkTokenizer::with_encoding(encoding);
+        parser
+    }
+}
+
+pub(crate) fn transcode(decoder: &mut Decoder, buffer: &mut String, bytes: &[u8], last: bool) {
+    if let Some(len) = decoder.max_utf8_buffer_length(bytes.len()) {
+        buffer.reserve(len);
+    }
+    let (_, read, _) = decoder.decode_to_string(bytes, buffer, last);
+    debug_assert_eq!(read, bytes.len());
+}
+
+fn prescan(bytes: &[u8]) -> Option<&'static Encoding> {
+    let mut rest = bytes;
+    while let Some(start) = find_meta(rest) {
+        rest = &rest[start..];
+        // A tag cut off by the prescan limit is still worth a look
+        let end = rest
+            .iter()
+            .position(|&b| b == b'>')
+            .unwrap_or(rest.len() - 1);
+        let tag = String::from_utf8_lossy(&rest[..=end]).into_owned();
+        rest = &rest[end + 1..];
+
+        let tag = if tag.ends_with('>') { tag } else { tag + ">" };
+        if let Some(encoding) = meta_encoding(&tag) {
+            // UTF-16 can't be declared from inside the document itself
+            return Some(match encoding {
+                e if e == UTF_16BE || e == UTF_16LE => UTF_8,
+                e if e == X_USER_DEFINED => WINDOWS_1252,
+                e => e,
+            });
+        }
+    }
+    None
+}
+
+fn find_meta(bytes: &[u8]) -> Option<usize> {
+    bytes.windows(6).position(|window| {
+        window[..5].eq_ignore_ascii_case(b"<meta")
+            && (window[5].is_ascii_whitespace() || window[5] == b'/')
+    })
+}
+
+fn meta_encoding(tag: &str) -> Option<&'static Encoding> {
+    let mut charset = None;
+    let mut content = None;
+    let mut content_type = false;
+
+    for token in Tokens::new(tag) {
+        let Ok(HtmlAst::Attribute(name, Some(value))) = token else {
+            continue;
+        };
+        if name.eq_ignore_ascii_case("charset") {
+            charset = Some(value);
+        } else if name.eq_ignore_ascii_case("content") {
+            content = Some(value);
+        } else if name.eq_ignore_ascii_case("http-equiv") {
+            content_type = value.trim().eq_ignore_ascii_case("content-type");
+        }
+    }
+
+    let label = match (charset, content) {
+        (Some(charset), _) => charset,
+        (None, Some(content)) if content_type => charset_from_content(content)?,
+        _ => return None,
+    };
+    Encoding::for_label(label.trim().as_bytes())
+}
+
+// Pulls the charset out of e.g. `text/html; charset="shift_jis"`
+fn charset_from_content(content: &str) -> Option<&str> {
+    let index = content.to_ascii_lowercase().find("charset")?;
+    let value = content[index + "charset".len()..]
+        .trim_start()
+        .strip_prefix('=')?
+        .trim_start();
+    let value = match value.strip_prefix(['"', '\'']) {
+        Some(quoted) => quoted.split(['"', '\'']).next()?,
+        None => value.split([';', ' ']).next()?,
+    };
+    (!value.is_empty()).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HtmlEvent;
+
+    #[test]
+    fn sniffing() {
+        assert_eq!(sniff_encoding(b"\xEF\xBB\xBF<p>x</p>"), UTF_8);
+        assert_eq!(sniff_encoding(b"\xFF\xFE<\0p\0>\0"), UTF_16LE);
+        assert_eq!(
+            sniff_encoding(b"<html><head><META CHARSET='iso-8859-2'>"),
+            encoding_rs::ISO_8859_2
+        );
+        assert_eq!(
+            sniff_encoding(
+                b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1251\">"
+            ),
+            encoding_rs::WINDOWS_1251
+        );
+        assert_eq!(sniff_encoding(b"<meta charset=\"utf-16\">"), UTF_8);
+        assert_eq!(sniff_encoding("<p>caf\u{e9}</p>".as_bytes()), UTF_8);
+        assert_eq!(sniff_encoding(b"<p>caf\xE9</p>"), WINDOWS_1252);
+
+        // Declarations past the prescan window are ignored
+        let mut late = vec![b' '; PRESCAN_LEN];
+        late.extend_from_slice(b"<meta charset=koi8-r>");
+        assert_eq!(sniff_encoding(&late), UTF_8);
+    }
+
+    #[test]
+    fn legacy_encodings() {
+        let elements =
+            parse_html_bytes_with(b"<p>\x93\xfa\x96\x7b</p>", encoding_rs::SHIFT_JIS).unwrap();
+        assert_eq!(
+            elements[0].children()[0],
+            crate::HtmlNodeOwned::Text("\u{65e5}\u{672c}".into())
+        );
+        let greek = decode_html_bytes_with(b"\xe1\xe2\xe3", encoding_rs::ISO_8859_7);
+        assert_eq!(greek, "\u{3b1}\u{3b2}\u{3b3}");
+
+        // A sequence split between chunks is put back together
+        let mut tokenizer = ChunkTokenizer::with_encoding(encoding_rs::SHIFT_JIS);
+        let mut events = tokenizer.feed(b"<p title=\"\x93").unwrap();
+        events.extend(tokenizer.feed(b"\xfa\">\x96").unwrap());
+        events.extend(tokenizer.feed(b"\x7b</p>").unwrap());
+        events.extend(tokenizer.finish().unwrap());
+        assert_eq!(
+            events,
+            vec![
+                HtmlEvent::StartTag {
+                    name: "p".into(),
+                    attributes: vec![crate::HtmlAttributeOwned::new(
+                        "title",
+                        Some("\u{65e5}".into())
+                    )],
+                },
+                HtmlEvent::Text("\u{672c}".into()),
+                HtmlEvent::EndTag("p".into()),
+            ]
+        );
+
+        let stream = StreamParser::with_encoding(&b"<b>\x80</b>"[..], encoding_rs::WINDOWS_1252);
+        let events: Vec<_> = stream.collect::<Result<_, _>>().unwrap();
+        assert_eq!(events[1], HtmlEvent::Text("\u{20ac}".into()));
+    }
+
+    #[test]
+    fn parse_bytes() {
+        let html = b"<html><head><meta charset=\"windows-1252\"></head><body><p title=\"\x93q\x94\">caf\xE9</p></body></html>";
+        let elements = parse_html_bytes(html).unwrap();
+        let body = &elements[0].children()[1];
+        let p = body.as_element().unwrap().children()[0]
+            .as_element()
+            .unwrap();
+        assert_eq!(p.attributes()[0].value(), Some("\u{201c}q\u{201d}"));
+        assert_eq!(
+            p.children()[0],
+            crate::HtmlNodeOwned::Text("caf\u{e9}".into())
+        );
+    }
+}
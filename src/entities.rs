@@ -0,0 +1,282 @@
+use alloc::borrow::Cow;
+
+use crate::prelude::*;
+
+const NAMED_ENTITIES: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{00A0}'),
+    ("copy", '\u{00A9}'),
+    ("reg", '\u{00AE}'),
+    ("trade", '\u{2122}'),
+    ("hellip", '\u{2026}'),
+    ("mdash", '\u{2014}'),
+    ("ndash", '\u{2013}'),
+    ("lsquo", '\u{2018}'),
+    ("rsquo", '\u{2019}'),
+    ("ldquo", '\u{201C}'),
+    ("rdquo", '\u{201D}'),
+    ("laquo", '\u{00AB}'),
+    ("raquo", '\u{00BB}'),
+    ("bull", '\u{2022}'),
+    ("middot", '\u{00B7}'),
+    ("deg", '\u{00B0}'),
+    ("plusmn", '\u{00B1}'),
+    ("times", '\u{00D7}'),
+    ("divide", '\u{00F7}'),
+    ("euro", '\u{20AC}'),
+    ("cent", '\u{00A2}'),
+    ("pound", '\u{00A3}'),
+    ("yen", '\u{00A5}'),
+    ("sect", '\u{00A7}'),
+    ("para", '\u{00B6}'),
+];
+
+// Replacements for numeric references in the C1 control range, see
+// https://html.spec.whatwg.org/#numeric-character-reference-end-state
+const WINDOWS_1252: [Option<char>; 32] = [
+    Some('\u{20AC}'),
+    None,
+    Some('\u{201A}'),
+    Some('\u{0192}'),
+    Some('\u{201E}'),
+    Some('\u{2026}'),
+    Some('\u{2020}'),
+    Some('\u{2021}'),
+    Some('\u{02C6}'),
+    Some('\u{2030}'),
+    Some('\u{0160}'),
+    Some('\u{2039}'),
+    Some('\u{0152}'),
+    None,
+    Some('\u{017D}'),
+    None,
+    None,
+    Some('\u{2018}'),
+    Some('\u{2019}'),
+    Some('\u{201C}'),
+    Some('\u{201D}'),
+    Some('\u{2022}'),
+    Some('\u{2013}'),
+    Some('\u{2014}'),
+    Some('\u{02DC}'),
+    Some('\u{2122}'),
+    Some('\u{0161}'),
+    Some('\u{203A}'),
+    Some('\u{0153}'),
+    None,
+    Some('\u{017E}'),
+    Some('\u{0178}'),
+];
+
+/// Decodes named and numeric character references, borrowing the input when
+/// it contains none.
+pub fn decode_entities(data: &str) -> Cow<'_, str> {
+    if !data.contains('&') {
+        return Cow::Borrowed(data);
+    }
+
+    let mut decoded = String::with_capacity(data.len());
+    decode_into(data, &mut decoded);
+    Cow::Owned(decoded)
+}
+
+// Writes `data` with its character references decoded, for callers that
+// decode into their own buffer
+pub(crate) fn decode_into(data: &str, decoded: &mut impl core::fmt::Write) {
+    let mut rest = data;
+    // Writing to a string can't fail
+    while let Some(i) = rest.find('&') {
+        let _ = decoded.write_str(&rest[..i]);
+        rest = &rest[i..];
+
+        match decode_reference(&rest[1..]) {
+            Some((ch, len)) => {
+                let _ = decoded.write_char(ch);
+                rest = &rest[1 + len..];
+            }
+            None => {
+                let _ = decoded.write_char('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    let _ = decoded.write_str(rest);
+}
+
+/// Escapes `&`, `<` and `>` so text can be embedded in markup.
+pub fn escape_text(text: &str) -> Cow<'_, str> {
+    escape(text, text_entity)
+}
+
+/// Escapes `&` and `"` for use inside a double-quoted attribute value.
+pub fn escape_attribute(value: &str) -> Cow<'_, str> {
+    escape(value, attribute_entity)
+}
+
+pub(crate) fn text_entity(byte: u8) -> Option<&'static str> {
+    match byte {
+        b'&' => Some("&amp;"),
+        b'<' => Some("&lt;"),
+        b'>' => Some("&gt;"),
+        _ => None,
+    }
+}
+
+pub(crate) fn attribute_entity(byte: u8) -> Option<&'static str> {
+    match byte {
+        b'&' => Some("&amp;"),
+        b'"' => Some("&quot;"),
+        _ => None,
+    }
+}
+
+fn escape(data: &str, replacement: impl Fn(u8) -> Option<&'static str>) -> Cow<'_, str> {
+    if !data.bytes().any(|byte| replacement(byte).is_some()) {
+        return Cow::Borrowed(data);
+    }
+    let mut escaped = String::with_capacity(data.len() + 8);
+    let _ = write_escaped(&mut escaped, data, replacement);
+    Cow::Owned(escaped)
+}
+
+// Writes `data` with every byte that has a replacement replaced, in runs
+// rather than a character at a time. Replaced bytes are ASCII, so they
+// always fall on character boundaries.
+pub(crate) fn write_escaped<W: core::fmt::Write>(
+    out: &mut W,
+    data: &str,
+    replacement: impl Fn(u8) -> Option<&'static str>,
+) -> core::fmt::Result {
+    let mut rest = data;
+    while let Some((index, entity)) = rest
+        .bytes()
+        .enumerate()
+        .find_map(|(index, byte)| replacement(byte).map(|entity| (index, entity)))
+    {
+        out.write_str(&rest[..index])?;
+        out.write_str(entity)?;
+        rest = &rest[index + 1..];
+    }
+    out.write_str(rest)
+}
+
+// Decodes the reference following an `&`, returning the character and the
+// number of bytes consumed.
+fn decode_reference(data: &str) -> Option<(char, usize)> {
+    if let Some(numeric) = data.strip_prefix('#') {
+        return decode_numeric(numeric).map(|(ch, len)| (ch, len + 1));
+    }
+
+    let name_len = data
+        .find(|ch: char| !ch.is_ascii_alphanumeric())
+        .unwrap_or(data.len());
+    if data[name_len..].starts_with(';') {
+        let name = &data[..name_len];
+        NAMED_ENTITIES
+            .iter()
+            .find(|(entity, _)| *entity == name)
+            .map(|(_, ch)| (*ch, name_len + 1))
+    } else {
+        None
+    }
+}
+
+fn decode_numeric(data: &str) -> Option<(char, usize)> {
+    let (digits, radix, prefix_len) = match data.strip_prefix(['x', 'X']) {
+        Some(hex) => (hex, 16, 1),
+        None => (data, 10, 0),
+    };
+
+    let digits_len = digits
+        .find(|ch: char| !ch.is_digit(radix))
+        .unwrap_or(digits.len());
+    if digits_len == 0 {
+        return None;
+    }
+
+    let value = digits[..digits_len]
+        .chars()
+        .filter_map(|ch| ch.to_digit(radix))
+        .fold(0u32, |acc, digit| {
+            acc.saturating_mul(radix).saturating_add(digit)
+        });
+
+    // The terminating semicolon is optional, but consumed when present.
+    let mut len = prefix_len + digits_len;
+    if digits[digits_len..].starts_with(';') {
+        len += 1;
+    }
+
+    Some((numeric_reference_char(value), len))
+}
+
+fn numeric_reference_char(value: u32) -> char {
+    match value {
+        0 => char::REPLACEMENT_CHARACTER,
+        0x80..=0x9F => WINDOWS_1252[(value - 0x80) as usize]
+            .or_else(|| char::from_u32(value))
+            .unwrap_or(char::REPLACEMENT_CHARACTER),
+        _ => char::from_u32(value).unwrap_or(char::REPLACEMENT_CHARACTER),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape() {
+        assert!(matches!(escape_text("plain"), Cow::Borrowed("plain")));
+        assert_eq!(
+            escape_text("a < b && c > \"d\""),
+            "a &lt; b &amp;&amp; c &gt; \"d\""
+        );
+        assert_eq!(
+            escape_attribute("say \"<hi>\" & go"),
+            "say &quot;<hi>&quot; &amp; go"
+        );
+        assert_eq!(decode_entities(&escape_text("&lt; <")), "&lt; <");
+    }
+
+    #[test]
+    fn decode_without_references() {
+        let decoded = decode_entities("Hello World");
+        assert!(matches!(decoded, Cow::Borrowed("Hello World")));
+    }
+
+    #[test]
+    fn decode_named() {
+        assert_eq!(decode_entities("a &amp; b &lt;c&gt;"), "a & b <c>");
+        assert_eq!(decode_entities("&unknown; &amp"), "&unknown; &amp");
+    }
+
+    #[test]
+    fn decode_numeric_references() {
+        assert_eq!(decode_entities("&#65;&#x42;&#X43;"), "ABC");
+        assert_eq!(decode_entities("&#x1F600;"), "\u{1F600}");
+        assert_eq!(decode_entities("&#65 B"), "A B");
+        assert_eq!(decode_entities("&#; &#x;"), "&#; &#x;");
+    }
+
+    #[test]
+    fn decode_invalid_numeric_references() {
+        assert_eq!(decode_entities("&#0;"), "\u{FFFD}");
+        assert_eq!(decode_entities("&#xD800;"), "\u{FFFD}");
+        assert_eq!(decode_entities("&#x110000;"), "\u{FFFD}");
+        assert_eq!(decode_entities("&#99999999999999;"), "\u{FFFD}");
+    }
+
+    #[test]
+    fn decode_windows_1252_quirks() {
+        assert_eq!(decode_entities("&#x80;"), "\u{20AC}");
+        assert_eq!(
+            decode_entities("&#147;quoted&#148;"),
+            "\u{201C}quoted\u{201D}"
+        );
+        assert_eq!(decode_entities("&#x81;"), "\u{81}");
+    }
+}
@@ -0,0 +1,148 @@
+use crate::prelude::*;
+use crate::{parse_document, HtmlDocument, HtmlElement, HtmlError};
+
+/// Types that can be read out of an element, usually through
+/// `#[derive(FromHtml)]` with the `derive` feature.
+pub trait FromHtml: Sized {
+    fn from_element(element: &HtmlElement<'_>) -> Result<Self, HtmlError>;
+
+    fn from_document(document: &HtmlDocument<'_>) -> Result<Self, HtmlError> {
+        let mut root = HtmlElement::new("");
+        *root.children_vec_mut() = document.children().to_vec();
+        Self::from_element(&root)
+    }
+
+    fn from_html(html: &str) -> Result<Self, HtmlError> {
+        Self::from_document(&parse_document(html)?)
+    }
+}
+
+/// A single value read from text or an attribute.
+pub trait HtmlValue: Sized {
+    fn parse_value(value: &str) -> Option<Self>;
+}
+
+impl HtmlValue for String {
+    fn parse_value(value: &str) -> Option<Self> {
+        Some(value.to_string())
+    }
+}
+
+macro_rules! from_str_values {
+    ($($ty:ty),*) => {
+        $(impl HtmlValue for $ty {
+            fn parse_value(value: &str) -> Option<Self> {
+                value.trim().parse().ok()
+            }
+        })*
+    };
+}
+
+from_str_values!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, bool, char
+);
+
+/// How a field is built from the values of every match.
+pub trait HtmlField: Sized {
+    fn from_values(field: &str, values: Vec<String>) -> Result<Self, HtmlError>;
+}
+
+impl<T: HtmlValue> HtmlField for T {
+    fn from_values(field: &str, values: Vec<String>) -> Result<Self, HtmlError> {
+        match values.into_iter().next() {
+            Some(value) => parse(field, value),
+            None => Err(HtmlError::MissingField(field.to_string())),
+        }
+    }
+}
+
+impl<T: HtmlValue> HtmlField for Option<T> {
+    fn from_values(field: &str, values: Vec<String>) -> Result<Self, HtmlError> {
+        values
+            .into_iter()
+            .next()
+            .map(|value| parse(field, value))
+            .transpose()
+    }
+}
+
+impl<T: HtmlValue> HtmlField for Vec<T> {
+    fn from_values(field: &str, values: Vec<String>) -> Result<Self, HtmlError> {
+        values
+            .into_iter()
+            .map(|value| parse(field, value))
+            .collect()
+    }
+}
+
+fn parse<T: HtmlValue>(field: &str, value: String) -> Result<T, HtmlError> {
+    T::parse_value(&value).ok_or_else(|| HtmlError::InvalidField {
+        field: field.to_string(),
+        value,
+    })
+}
+
+// Called by the code `#[derive(FromHtml)]` generates
+#[doc(hidden)]
+pub fn __extract_field<T: HtmlField>(
+    element: &HtmlElement<'_>,
+    field: &str,
+    select: Option<&str>,
+    attr: Option<&str>,
+) -> Result<T, HtmlError> {
+    let elements = match select {
+        Some(selector) => element.query_selector_all(selector)?,
+        None => vec![element],
+    };
+    let values = elements
+        .into_iter()
+        .filter_map(|element| match attr {
+            Some(attr) => element.get_attr(attr).map(str::to_string),
+            None => Some(element.inner_text()),
+        })
+        .collect();
+    T::from_values(field, values)
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use crate::{FromHtml, HtmlError};
+
+    #[derive(Debug, PartialEq, FromHtml)]
+    struct Product {
+        #[html(select = "h2")]
+        name: String,
+        #[html(select = ".price")]
+        price: f64,
+        #[html(select = "article", attr = "data-id")]
+        id: u32,
+        #[html(select = ".tag")]
+        tags: Vec<String>,
+        #[html(select = ".sale")]
+        sale: Option<String>,
+    }
+
+    #[test]
+    fn derive_from_html() {
+        let html = r#"<article data-id="7"><h2>Lamp</h2><span class="price"> 19.5 </span>
+            <ul><li class="tag">home</li><li class="tag">light</li></ul></article>"#;
+        assert_eq!(
+            Product::from_html(html).unwrap(),
+            Product {
+                name: "Lamp".to_string(),
+                price: 19.5,
+                id: 7,
+                tags: vec!["home".to_string(), "light".to_string()],
+                sale: None,
+            }
+        );
+
+        let error = Product::from_html("<article data-id=\"x\"><h2>Lamp</h2></article>");
+        assert!(matches!(error, Err(HtmlError::MissingField(field)) if field == "price"));
+        let html = "<article data-id=\"x\"><h2>Lamp</h2><b class=\"price\">1</b></article>";
+        assert!(matches!(
+            Product::from_html(html),
+            Err(HtmlError::InvalidField { field, value }) if field == "id" && value == "x"
+        ));
+    }
+}
@@ -0,0 +1,311 @@
+use crate::prelude::*;
+use crate::{HtmlDocument, HtmlElement, HtmlNode, Span};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FormMethod {
+    #[default]
+    Get,
+    Post,
+    Dialog,
+}
+
+/// A `<form>` and the controls inside it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Form<'d> {
+    pub action: Option<&'d str>,
+    pub method: FormMethod,
+    pub enctype: Option<&'d str>,
+    pub controls: Vec<FormControl<'d>>,
+    pub span: Option<Span>,
+}
+
+/// An `input`, `select`, `textarea` or `button`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormControl<'d> {
+    pub tag: &'d str,
+    pub name: Option<&'d str>,
+    /// The control's type as the DOM reports it: an input's `type` (`text`
+    /// when missing), a button's (`submit` when missing), `select-one`,
+    /// `select-multiple` or `textarea`.
+    pub control_type: String,
+    /// The `value` attribute, or a textarea's text.
+    pub value: Option<String>,
+    pub checked: bool,
+    pub disabled: bool,
+    pub required: bool,
+    /// The options of a `select`, including those in an `optgroup`.
+    pub options: Vec<SelectOption>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectOption {
+    /// The `value` attribute, or the option's text without one.
+    pub value: String,
+    pub label: String,
+    pub selected: bool,
+    pub disabled: bool,
+}
+
+impl Form<'_> {
+    /// The name and value pairs a browser would submit by default: enabled,
+    /// named controls, checked checkboxes and radios, selected options, and
+    /// no buttons.
+    pub fn values(&self) -> Vec<(&str, String)> {
+        let mut values = Vec::new();
+        for control in &self.controls {
+            let Some(name) = control.name.filter(|_| !control.disabled) else {
+                continue;
+            };
+            match control.control_type.as_str() {
+                "submit" | "reset" | "button" | "image" | "file" => {}
+                "checkbox" | "radio" if !control.checked => {}
+                "checkbox" | "radio" => {
+                    values.push((name, control.value.clone().unwrap_or("on".to_string())))
+                }
+                "select-one" | "select-multiple" => {
+                    let enabled = control.options.iter().filter(|option| !option.disabled);
+                    let mut selected: Vec<_> =
+                        enabled.clone().filter(|option| option.selected).collect();
+                    // A single select shows its first option when none is
+                    // selected
+                    if selected.is_empty() && control.control_type == "select-one" {
+                        selected.extend(enabled.take(1));
+                    }
+                    if control.control_type == "select-one" {
+                        selected.truncate(1);
+                    }
+                    values.extend(
+                        selected
+                            .into_iter()
+                            .map(|option| (name, option.value.clone())),
+                    );
+                }
+                _ => values.push((name, control.value.clone().unwrap_or_default())),
+            }
+        }
+        values
+    }
+}
+
+/// Every `<form>` in the document, in order.
+pub fn extract_forms<'d>(document: &'d HtmlDocument<'_>) -> Vec<Form<'d>> {
+    let mut forms = Vec::new();
+    collect_forms(document.children(), &mut forms);
+    forms
+}
+
+fn collect_forms<'d>(nodes: &'d [HtmlNode<'_>], forms: &mut Vec<Form<'d>>) {
+    for element in nodes.iter().filter_map(HtmlNode::as_element) {
+        if !element.tag().eq_ignore_ascii_case("form") {
+            collect_forms(element.children(), forms);
+            continue;
+        }
+        let method = match element.get_attr("method") {
+            Some(method) if method.eq_ignore_ascii_case("post") => FormMethod::Post,
+            Some(method) if method.eq_ignore_ascii_case("dialog") => FormMethod::Dialog,
+            _ => FormMethod::Get,
+        };
+        let mut controls = Vec::new();
+        collect_controls(element.children(), false, &mut controls);
+        forms.push(Form {
+            action: element.get_attr("action"),
+            method,
+            enctype: element.get_attr("enctype"),
+            controls,
+            span: element.span(),
+        });
+    }
+}
+
+// `disabled` is whether a fieldset around `nodes` is disabled
+fn collect_controls<'d>(
+    nodes: &'d [HtmlNode<'_>],
+    disabled: bool,
+    controls: &mut Vec<FormControl<'d>>,
+) {
+    for element in nodes.iter().filter_map(HtmlNode::as_element) {
+        let tag = element.tag().to_ascii_lowercase();
+        if tag == "fieldset" {
+            // Controls in a disabled fieldset are too, except in its first
+            // legend
+            let fieldset_disabled = disabled || element.has_attr("disabled");
+            let legend = element
+                .child_elements()
+                .find(|child| child.tag().eq_ignore_ascii_case("legend"));
+            for child in element.children() {
+                let in_legend = child
+                    .as_element()
+                    .zip(legend)
+                    .is_some_and(|(child, legend)| core::ptr::eq(child, legend));
+                let disabled = if in_legend {
+                    disabled
+                } else {
+                    fieldset_disabled
+                };
+                collect_controls(core::slice::from_ref(child), disabled, controls);
+            }
+            continue;
+        }
+        let control_type = match tag.as_str() {
+            "input" => attr_or(element, "type", "text"),
+            "button" => attr_or(element, "type", "submit"),
+            "select" if element.has_attr("multiple") => "select-multiple".to_string(),
+            "select" => "select-one".to_string(),
+            "textarea" => "textarea".to_string(),
+            _ => {
+                collect_controls(element.children(), disabled, controls);
+                continue;
+            }
+        };
+        let value = match tag.as_str() {
+            // As in browsers, a newline right after the start tag isn't
+            // part of the value
+            "textarea" => {
+                let text = text_of(element);
+                let value = text.strip_prefix("\r\n").or(text.strip_prefix('\n'));
+                Some(value.unwrap_or(&text).to_string())
+            }
+            _ => element.get_attr("value").map(str::to_string),
+        };
+        let mut options = Vec::new();
+        if tag == "select" {
+            collect_options(element.children(), false, &mut options);
+        }
+        controls.push(FormControl {
+            tag: element.tag(),
+            name: element.get_attr("name"),
+            control_type,
+            value,
+            checked: element.has_attr("checked"),
+            disabled: disabled || element.has_attr("disabled"),
+            required: element.has_attr("required"),
+            options,
+        });
+    }
+}
+
+fn collect_options(nodes: &[HtmlNode<'_>], disabled: bool, options: &mut Vec<SelectOption>) {
+    for element in nodes.iter().filter_map(HtmlNode::as_element) {
+        let disabled = disabled || element.has_attr("disabled");
+        if element.tag().eq_ignore_ascii_case("optgroup") {
+            collect_options(element.children(), disabled, options);
+        } else if element.tag().eq_ignore_ascii_case("option") {
+            let label = text_of(element).trim().to_string();
+            options.push(SelectOption {
+                value: element
+                    .get_attr("value")
+                    .map_or(label.clone(), str::to_string),
+                label,
+                selected: element.has_attr("selected"),
+                disabled,
+            });
+        }
+    }
+}
+
+fn attr_or(element: &HtmlElement<'_>, name: &str, default: &str) -> String {
+    element
+        .get_attr(name)
+        .unwrap_or(default)
+        .to_ascii_lowercase()
+}
+
+// Text content as written, without the whitespace collapsing of `inner_text`
+fn text_of(element: &HtmlElement<'_>) -> String {
+    element
+        .children()
+        .iter()
+        .filter_map(HtmlNode::as_text)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    #[test]
+    fn form_model() {
+        let html = r#"<form action="/login" method="POST">
+            <input name="user" value="ann" required>
+            <input type="password" name="pass">
+            <input type="checkbox" name="remember" checked>
+            <input type="checkbox" name="spam" value="yes">
+            <input name="id" value="7" disabled>
+            <select name="lang"><option>en</option><optgroup label="more"><option value="de" selected>German</option></optgroup></select>
+            <textarea name="bio">Hi
+there</textarea>
+            <button>Log in</button>
+        </form>"#;
+        let document = parse_document(html).unwrap();
+        let forms = extract_forms(&document);
+        assert_eq!(forms.len(), 1);
+        let form = &forms[0];
+        assert_eq!(form.action, Some("/login"));
+        assert_eq!(form.method, FormMethod::Post);
+        assert_eq!(form.controls.len(), 8);
+        assert!(form.controls[0].required);
+        assert_eq!(form.controls[1].control_type, "password");
+        assert_eq!(form.controls[7].control_type, "submit");
+        assert_eq!(
+            form.controls[5].options[1],
+            SelectOption {
+                value: "de".to_string(),
+                label: "German".to_string(),
+                selected: true,
+                disabled: false,
+            }
+        );
+        assert_eq!(
+            form.values(),
+            [
+                ("user", "ann".to_string()),
+                ("pass", String::new()),
+                ("remember", "on".to_string()),
+                ("lang", "de".to_string()),
+                ("bio", "Hi\nthere".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn disabled_fieldsets() {
+        let html = r#"<form>
+            <fieldset disabled>
+                <legend><input name="a"></legend>
+                <legend><input name="b"></legend>
+                <div><input name="c"></div>
+                <fieldset><legend><input name="d"></legend></fieldset>
+            </fieldset>
+            <fieldset><input name="e"></fieldset>
+            <textarea name="f">
+second line</textarea>
+        </form>"#;
+        let document = parse_document(html).unwrap();
+        let form = &extract_forms(&document)[0];
+        let disabled: Vec<_> = form
+            .controls
+            .iter()
+            .map(|control| (control.name.unwrap(), control.disabled))
+            .collect();
+        assert_eq!(
+            disabled,
+            [
+                ("a", false),
+                ("b", true),
+                ("c", true),
+                ("d", true),
+                ("e", false),
+                ("f", false)
+            ]
+        );
+        assert_eq!(
+            form.values(),
+            [
+                ("a", String::new()),
+                ("e", String::new()),
+                ("f", "second line".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use alloc::borrow::Cow;
+
+use crate::prelude::*;
+use crate::{
+    decode_entities,
+    parser::{is_raw_text_element, HtmlAst, Tokens},
+    HtmlAttribute, HtmlError,
+};
+
+/// Returned from [`HtmlHandler`] callbacks to keep going or stop parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandlerAction {
+    Continue,
+    Stop,
+}
+
+/// Callbacks for [`parse_with_handler`]. Every callback defaults to
+/// [`HandlerAction::Continue`].
+pub trait HtmlHandler<'a> {
+    fn start_tag(&mut self, name: &'a str, attributes: &[HtmlAttribute<'a>]) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn end_tag(&mut self, name: &'a str) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    /// Text with character references decoded, except inside script and
+    /// style. CDATA sections are reported as text too.
+    fn text(&mut self, text: Cow<'a, str>) -> HandlerAction {
+        HandlerAction::Continue
+    }
+
+    fn comment(&mut self, comment: &'a str) -> HandlerAction {
+        HandlerAction::Continue
+    }
+}
+
+/// Streams parse events to `handler` without building a tree. Returns early
+/// with `Ok(())` once a callback asks to stop.
+pub fn parse_with_handler<'a, H>(data: &'a str, handler: &mut H) -> Result<(), HtmlError>
+where
+    H: HtmlHandler<'a> + ?Sized,
+{
+    let mut tokens = Tokens::new(data).peekable();
+    let mut attributes = Vec::new();
+    let mut raw_text = false;
+
+    while let Some(token) = tokens.next() {
+        let action = match token? {
+            HtmlAst::StartTag(name) => {
+                attributes.clear();
+                while let Some(Ok(HtmlAst::Attribute(name, value))) =
+                    tokens.next_if(|token| matches!(token, Ok(HtmlAst::Attribute(..))))
+                {
+                    attributes.push(HtmlAttribute {
+                        name: Cow::Borrowed(name),
+                        value: value.map(decode_entities),
+                    });
+                }
+                raw_text = is_raw_text_element(name);
+                handler.start_tag(name, &attributes)
+            }
+            HtmlAst::EndTag(name) => {
+                raw_text = false;
+                handler.end_tag(name)
+            }
+            HtmlAst::Text(text) if raw_text => handler.text(Cow::Borrowed(text)),
+            HtmlAst::Text(text) => handler.text(decode_entities(text)),
+            HtmlAst::CData(text) => handler.text(Cow::Borrowed(text)),
+            HtmlAst::Comment(comment) => handler.comment(comment),
+            HtmlAst::Attribute(..) | HtmlAst::SelfClosing | HtmlAst::Doctype(_) => {
+                HandlerAction::Continue
+            }
+        };
+        if action == HandlerAction::Stop {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct TitleFinder {
+        in_title: bool,
+        title: String,
+        events: usize,
+    }
+
+    impl<'a> HtmlHandler<'a> for TitleFinder {
+        fn start_tag(&mut self, name: &'a str, _: &[HtmlAttribute<'a>]) -> HandlerAction {
+            self.events += 1;
+            self.in_title = name == "title";
+            HandlerAction::Continue
+        }
+
+        fn text(&mut self, text: Cow<'a, str>) -> HandlerAction {
+            self.events += 1;
+            if self.in_title {
+                self.title.push_str(&text);
+            }
+            HandlerAction::Continue
+        }
+
+        fn end_tag(&mut self, name: &'a str) -> HandlerAction {
+            self.events += 1;
+            match name {
+                "title" => HandlerAction::Stop,
+                _ => HandlerAction::Continue,
+            }
+        }
+    }
+
+    #[test]
+    fn stops_after_title() {
+        let html =
+            "<html><head><title>Fish &amp; Chips</title></head><body><p>ignored</p></body></html>";
+        let mut finder = TitleFinder::default();
+        parse_with_handler(html, &mut finder).unwrap();
+        assert_eq!(finder.title, "Fish & Chips");
+        assert_eq!(finder.events, 5);
+    }
+
+    #[test]
+    fn attributes_and_comments() {
+        struct Collect(Vec<String>);
+
+        impl<'a> HtmlHandler<'a> for Collect {
+            fn start_tag(&mut self, name: &'a str, attrs: &[HtmlAttribute<'a>]) -> HandlerAction {
+                let attrs: Vec<_> = attrs
+                    .iter()
+                    .map(|attr| format!("{}={}", attr.name, attr.value().unwrap_or("")))
+                    .collect();
+                self.0.push(format!("<{} {}>", name, attrs.join(" ")));
+                HandlerAction::Continue
+            }
+
+            fn comment(&mut self, comment: &'a str) -> HandlerAction {
+                self.0.push(format!("comment:{}", comment));
+                HandlerAction::Continue
+            }
+        }
+
+        let mut collect = Collect(Vec::new());
+        parse_with_handler("<a href=\"?a=1&amp;b=2\" hidden><!--x--></a>", &mut collect).unwrap();
+        assert_eq!(collect.0, vec!["<a href=?a=1&b=2 hidden=>", "comment:x"]);
+    }
+}
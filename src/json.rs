@@ -0,0 +1,216 @@
+//! A plain JSON shape for trees, for consumers outside Rust:
+//!
+//! - elements are `{"tag": .., "attrs": {name: value | null}, "children": [..]}`,
+//!   with a `"content": [..]` list too for a template's contents
+//! - text is a JSON string
+//! - comments, CDATA and doctypes are `{"comment": ..}`, `{"cdata": ..}` and
+//!   `{"doctype": name, "public_id": .., "system_id": ..}`
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    HtmlDoctypeOwned, HtmlDocument, HtmlElement, HtmlElementOwned, HtmlError, HtmlNode,
+    HtmlNodeOwned,
+};
+
+impl HtmlNode<'_> {
+    pub fn to_json(&self) -> Value {
+        match self {
+            HtmlNode::Element(element) => element.to_json(),
+            HtmlNode::Text(text) => Value::String(text.to_string()),
+            HtmlNode::CData(text) => json!({ "cdata": text }),
+            HtmlNode::Comment(comment) => json!({ "comment": comment }),
+            HtmlNode::Doctype(doctype) => json!({
+                "doctype": doctype.name(),
+                "public_id": doctype.public_id(),
+                "system_id": doctype.system_id(),
+            }),
+        }
+    }
+}
+
+impl HtmlElement<'_> {
+    pub fn to_json(&self) -> Value {
+        let attrs: Map<_, _> = self
+            .attributes
+            .iter()
+            .map(|attr| (attr.name.to_string(), json!(attr.value())))
+            .collect();
+        let mut value = json!({
+            "tag": self.tag,
+            "attrs": attrs,
+            "children": self.children.iter().map(HtmlNode::to_json).collect::<Vec<_>>(),
+        });
+        if !self.template_content().is_empty() {
+            let content = self.template_content().iter().map(HtmlNode::to_json);
+            value["content"] = Value::Array(content.collect());
+        }
+        value
+    }
+}
+
+impl HtmlDocument<'_> {
+    /// The top-level nodes as a JSON array.
+    pub fn to_json(&self) -> Value {
+        Value::Array(self.nodes.iter().map(HtmlNode::to_json).collect())
+    }
+}
+
+/// Reads nodes written by `to_json`, from either an array or a single node.
+pub fn from_json(value: &Value) -> Result<Vec<HtmlNodeOwned>, HtmlError> {
+    match value {
+        Value::Array(nodes) => nodes.iter().map(HtmlNodeOwned::from_json).collect(),
+        node => Ok(vec![HtmlNodeOwned::from_json(node)?]),
+    }
+}
+
+fn invalid(message: &str) -> HtmlError {
+    HtmlError::InvalidJson(message.to_string())
+}
+
+fn optional_string(object: &Map<String, Value>, key: &str) -> Result<Option<String>, HtmlError> {
+    match object.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value.clone())),
+        Some(_) => Err(invalid(&format!("`{}` must be a string or null", key))),
+    }
+}
+
+impl HtmlNodeOwned {
+    pub fn from_json(value: &Value) -> Result<Self, HtmlError> {
+        let object = match value {
+            Value::String(text) => return Ok(HtmlNodeOwned::Text(text.clone())),
+            Value::Object(object) => object,
+            _ => return Err(invalid("a node must be a string or an object")),
+        };
+
+        if object.contains_key("tag") {
+            HtmlElementOwned::from_json(value).map(HtmlNodeOwned::Element)
+        } else if let Some(comment) = optional_string(object, "comment")? {
+            Ok(HtmlNodeOwned::Comment(comment))
+        } else if let Some(text) = optional_string(object, "cdata")? {
+            Ok(HtmlNodeOwned::CData(text))
+        } else if object.contains_key("doctype") {
+            Ok(HtmlNodeOwned::Doctype(HtmlDoctypeOwned {
+                name: optional_string(object, "doctype")?,
+                public_id: optional_string(object, "public_id")?,
+                system_id: optional_string(object, "system_id")?,
+                force_quirks: false,
+            }))
+        } else {
+            Err(invalid("unknown node object"))
+        }
+    }
+}
+
+impl HtmlElementOwned {
+    pub fn from_json(value: &Value) -> Result<Self, HtmlError> {
+        let Some(Value::String(tag)) = value.get("tag") else {
+            return Err(invalid("an element needs a string `tag`"));
+        };
+        let mut element = HtmlElementOwned::new(tag.as_str());
+
+        match value.get("attrs") {
+            None | Some(Value::Null) => {}
+            Some(Value::Object(attrs)) => {
+                for (name, value) in attrs {
+                    let value = match value {
+                        Value::Null => None,
+                        Value::String(value) => Some(value.clone()),
+                        _ => return Err(invalid("attribute values must be strings or null")),
+                    };
+                    element.add_attribute(name.as_str(), value);
+                }
+            }
+            Some(_) => return Err(invalid("`attrs` must be an object")),
+        }
+
+        match value.get("children") {
+            None | Some(Value::Null) => {}
+            Some(Value::Array(children)) => {
+                for child in children {
+                    element.add_child(HtmlNodeOwned::from_json(child)?);
+                }
+            }
+            Some(_) => return Err(invalid("`children` must be an array")),
+        }
+
+        match value.get("content") {
+            None | Some(Value::Null) => {}
+            Some(Value::Array(content)) => {
+                for node in content {
+                    element.add_template_content(HtmlNodeOwned::from_json(node)?);
+                }
+            }
+            Some(_) => return Err(invalid("`content` must be an array")),
+        }
+
+        Ok(element)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{document_to_string, parse_document};
+
+    #[test]
+    fn json_round_trip() {
+        let html = "<!DOCTYPE html><div id=\"main\" hidden class=\"a\"><p>x &amp; y</p><!-- note --></div>";
+        let document = parse_document(html).unwrap();
+        let value = document.to_json();
+
+        assert_eq!(
+            value[1],
+            json!({
+                "tag": "div",
+                "attrs": { "id": "main", "hidden": null, "class": "a" },
+                "children": [
+                    { "tag": "p", "attrs": {}, "children": ["x & y"] },
+                    { "comment": " note " },
+                ],
+            })
+        );
+        // Attribute order survives the trip
+        let keys: Vec<_> = value[1]["attrs"].as_object().unwrap().keys().collect();
+        assert_eq!(keys, vec!["id", "hidden", "class"]);
+
+        let nodes = from_json(&value).unwrap();
+        let document = HtmlDocument {
+            nodes: nodes.iter().map(HtmlNodeOwned::to_borrowed).collect(),
+            ..Default::default()
+        };
+        assert_eq!(document_to_string(document), html);
+
+        let html = "<div><template><p>x</p></template></div>";
+        let value = parse_document(html).unwrap().to_json();
+        assert_eq!(
+            value[0]["children"][0],
+            json!({
+                "tag": "template",
+                "attrs": {},
+                "children": [],
+                "content": [{ "tag": "p", "attrs": {}, "children": ["x"] }],
+            })
+        );
+        let nodes = from_json(&value).unwrap();
+        let document = HtmlDocument {
+            nodes: nodes.iter().map(HtmlNodeOwned::to_borrowed).collect(),
+            ..Default::default()
+        };
+        assert_eq!(document_to_string(document), html);
+    }
+
+    #[test]
+    fn invalid_json() {
+        for value in [
+            json!(1),
+            json!({ "tag": 1 }),
+            json!({ "tag": "p", "attrs": [] }),
+            json!({ "tag": "p", "children": [{ "other": true }] }),
+            json!({ "tag": "template", "content": "x" }),
+        ] {
+            assert!(from_json(&value).is_err(), "{}", value);
+        }
+    }
+}
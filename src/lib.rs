@@ -1,50 +1,806 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![allow(unused)]
 
+extern crate alloc;
+
+use alloc::borrow::Cow;
+
+use prelude::*;
+
+// Lets derived code name the crate from inside it too
+extern crate self as html_parser;
+
+#[macro_use]
+mod macros;
+
+// What the standard prelude brings in, for modules to import so they build
+// without `std` too
+mod prelude {
+    pub(crate) use alloc::borrow::ToOwned;
+    pub(crate) use alloc::boxed::Box;
+    pub(crate) use alloc::string::{String, ToString};
+    pub(crate) use alloc::vec::Vec;
+    pub(crate) use alloc::{format, vec};
+}
+
+#[cfg(feature = "std")]
+pub(crate) use std::collections::{HashMap, HashSet};
+// Without `std` there's no hasher, so ordered collections stand in
+#[cfg(not(feature = "std"))]
+pub(crate) use alloc::collections::{BTreeMap as HashMap, BTreeSet as HashSet};
+
+mod arena;
+mod article;
+mod atom;
+#[cfg(feature = "smallvec")]
+mod attributes;
+#[cfg(feature = "rayon")]
+mod batch;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod class_list;
+mod cursor;
+mod diagnostic;
+mod diff;
+mod doctype;
+mod dom;
+#[cfg(feature = "encoding")]
+mod encoding;
+mod entities;
+mod extract;
+mod forms;
+mod handler;
+#[cfg(feature = "json")]
+mod json;
+mod links;
+mod lint;
+mod lookup;
+mod markdown;
+mod meta;
+pub mod minify;
+mod namespace;
+mod normalize;
+mod owned;
 mod parser;
 mod reader;
+#[cfg(feature = "std")]
+mod rewriter;
+pub mod sanitize;
+mod selection;
+mod selector;
+mod serializer;
+mod span;
+#[cfg(feature = "spec")]
+mod spec;
+mod start_tags;
+mod stream;
+mod structured;
+mod tables;
+mod text;
+mod traverse;
+mod urls;
+mod validate;
+mod visitor;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod xpath;
+
+#[cfg(feature = "bumpalo")]
+pub use arena::parse_document_in;
+pub use article::{extract_article, Article};
+pub use atom::Atom;
+#[cfg(feature = "rayon")]
+pub use batch::{parse_many, parse_many_with};
+#[cfg(feature = "bumpalo")]
+pub use bumpalo::Bump;
+pub use class_list::ClassList;
+pub use cursor::TreeCursor;
+pub use diagnostic::{parse_with_diagnostics, Diagnostic};
+pub use diff::{apply_patch, diff, DomEdit, PatchConflict};
+pub use doctype::{HtmlDoctype, QuirksMode};
+pub use dom::{Document, Node, NodeData, NodeId};
+#[cfg(feature = "encoding")]
+pub use encoding::{
+    decode_html_bytes, decode_html_bytes_with, parse_html_bytes, parse_html_bytes_with,
+    sniff_encoding,
+};
+#[cfg(feature = "encoding")]
+pub use encoding_rs::Encoding;
+pub use entities::{decode_entities, escape_attribute, escape_text};
+#[doc(hidden)]
+pub use extract::__extract_field;
+pub use extract::{FromHtml, HtmlField, HtmlValue};
+pub use forms::{extract_forms, Form, FormControl, FormMethod, SelectOption};
+pub use handler::{parse_with_handler, HandlerAction, HtmlHandler};
+#[cfg(feature = "derive")]
+pub use html_parser_derive::FromHtml;
+#[cfg(feature = "json")]
+pub use json::from_json;
+pub use links::{extract_links, Link};
+pub use lint::{ImgAlt, Lint, Linter, NoInlineStyle};
+pub use meta::{extract_metadata, Alternate, Metadata};
+pub use namespace::Namespace;
+pub use normalize::NormalizeOptions;
+pub use owned::{HtmlAttributeOwned, HtmlDoctypeOwned, HtmlElementOwned, HtmlNodeOwned};
+pub use parser::{
+    parse_document, parse_document_with, parse_html, parse_html_with, tokenize_html,
+    tokenize_html_spanned, tokenize_html_with, HtmlAst, Tokens,
+};
+pub use reader::ReadError;
+#[cfg(feature = "std")]
+pub use rewriter::{HtmlRewriter, RewriteElement};
+pub use selection::{has_class, Selection};
+pub use selector::{Select, Selector, SelectorError};
+#[cfg(feature = "std")]
+pub use serializer::write_html_io;
+pub use serializer::{
+    document_to_string, document_to_string_pretty, html_to_string, html_to_string_pretty,
+    html_to_string_with, write_html, write_html_with, PrettyOptions, SerializeOptions,
+};
+pub use span::Span;
+#[cfg(feature = "spec")]
+pub use spec::{parse_fragment, parse_fragment_with};
+pub use start_tags::{start_tags, start_tags_with, StartTag, StartTags};
+#[cfg(feature = "async")]
+pub use stream::AsyncStreamParser;
+#[cfg(feature = "std")]
+pub use stream::StreamParser;
+pub use stream::{ChunkTokenizer, HtmlEvent};
+#[cfg(feature = "json")]
+pub use structured::extract_json_ld;
+pub use structured::{extract_microdata, MicrodataItem, MicrodataValue};
+pub use tables::{extract_tables, Cell, Table};
+pub use traverse::{DescendantsBfs, DescendantsDfs};
+pub use urls::resolve_url;
+pub use validate::validate;
+pub use visitor::{walk_nodes, HtmlVisitor, VisitAction};
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HtmlAttribute<'a> {
-    name: &'a str,
-    value: Option<&'a str>,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    name: Cow<'a, str>,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    value: Option<Cow<'a, str>>,
 }
 
 impl<'a> HtmlAttribute<'a> {
-    pub fn new(name: &'a str, value: Option<&'a str>) -> Self {
-        Self { name, value }
+    pub fn new(name: impl Into<Cow<'a, str>>, value: Option<&'a str>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.map(Cow::Borrowed),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// Replaces the value, only allocating if `value` is owned.
+    pub fn set_value(&mut self, value: impl Into<Cow<'a, str>>) {
+        self.value = Some(value.into());
+    }
+}
+
+pub(crate) fn find_attribute<'e, 'a>(
+    attributes: &'e [HtmlAttribute<'a>],
+    name: &str,
+) -> Option<&'e HtmlAttribute<'a>> {
+    attributes
+        .iter()
+        .find(|attr| attr.name.eq_ignore_ascii_case(name))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+// Inline attributes make elements bigger, which is the trade being made
+#[cfg_attr(feature = "smallvec", allow(clippy::large_enum_variant))]
+pub enum HtmlNode<'a> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    Element(HtmlElement<'a>),
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    Text(Cow<'a, str>),
+    /// Contents of a `<![CDATA[ ... ]]>` section
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    CData(Cow<'a, str>),
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    Comment(Cow<'a, str>),
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    Doctype(HtmlDoctype<'a>),
+}
+
+impl<'a> HtmlNode<'a> {
+    pub fn as_element(&self) -> Option<&HtmlElement<'a>> {
+        match self {
+            HtmlNode::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    pub fn as_element_mut(&mut self) -> Option<&mut HtmlElement<'a>> {
+        match self {
+            HtmlNode::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    /// Text content of text and CDATA nodes
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            HtmlNode::Text(text) => Some(text),
+            HtmlNode::CData(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> From<HtmlElement<'a>> for HtmlNode<'a> {
+    fn from(element: HtmlElement<'a>) -> Self {
+        HtmlNode::Element(element)
     }
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HtmlElement<'a> {
-    tag: &'a str,
-    attributes: Vec<HtmlAttribute<'a>>,
-    children: Vec<HtmlElement<'a>>,
-    inner_text: Option<&'a str>,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    tag: Cow<'a, str>,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    attributes: Attributes<'a>,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    children: Vec<HtmlNode<'a>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    namespace: Namespace,
+    /// Template contents, kept apart from the children of a `<template>`
+    #[cfg_attr(feature = "serde", serde(borrow, default))]
+    content: Vec<HtmlNode<'a>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    source: ElementSource<'a>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    span: Option<Span>,
+}
+
+// With the `smallvec` feature a few attributes are stored inline. Children
+// can't be, since an element holding nodes inline would hold itself.
+#[cfg(feature = "smallvec")]
+pub(crate) use attributes::Attributes;
+#[cfg(not(feature = "smallvec"))]
+pub(crate) type Attributes<'a> = Vec<HtmlAttribute<'a>>;
+
+/// Original markup recorded by [`ParseOptions::preserve_source`]. Mutating
+/// an element drops the parts that no longer describe it.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct ElementSource<'a> {
+    /// The whole element, from its start tag through its end tag
+    pub(crate) outer: Option<&'a str>,
+    pub(crate) start_tag: Option<&'a str>,
+    pub(crate) end_tag: Option<&'a str>,
 }
 
+// Recorded source is formatting only and doesn't affect equality
+impl PartialEq for HtmlElement<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.tag == other.tag
+            && self.attributes == other.attributes
+            && self.children == other.children
+            && self.namespace == other.namespace
+            && self.content == other.content
+    }
+}
+
+impl Eq for HtmlElement<'_> {}
+
 impl<'a> HtmlElement<'a> {
-    pub fn new(tag: &'a str) -> Self {
+    pub fn new(tag: impl Into<Cow<'a, str>>) -> Self {
         Self {
-            tag,
+            tag: tag.into(),
             ..Default::default()
         }
     }
 
+    /// Sets an attribute, for building elements in one expression:
+    /// `HtmlElement::new("a").attr("href", url).text("click")`.
+    pub fn attr(mut self, name: &'a str, value: impl Into<Cow<'a, str>>) -> Self {
+        self.set_attr(name, value);
+        self
+    }
+
+    /// Appends a text child.
+    pub fn text(mut self, text: impl Into<Cow<'a, str>>) -> Self {
+        self.add_text(text);
+        self
+    }
+
+    /// Appends a child node.
+    pub fn child(mut self, child: impl Into<HtmlNode<'a>>) -> Self {
+        self.add_child(child);
+        self
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Where the element was parsed from: its start tag through its end tag
+    /// or, when that was left out, through its last content.
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    pub fn attributes(&self) -> &[HtmlAttribute<'a>] {
+        &self.attributes
+    }
+
+    pub fn children(&self) -> &[HtmlNode<'a>] {
+        &self.children
+    }
+
+    pub fn namespace(&self) -> Namespace {
+        self.namespace
+    }
+
+    /// The nodes inside a `<template>`. These are parsed into a separate
+    /// fragment rather than becoming children, as in browsers, so they
+    /// don't show up in traversals or text extraction.
+    pub fn template_content(&self) -> &[HtmlNode<'a>] {
+        &self.content
+    }
+
+    pub fn template_content_mut(&mut self) -> &mut Vec<HtmlNode<'a>> {
+        self.source.outer = None;
+        &mut self.content
+    }
+
+    /// Child nodes that are elements, skipping text and comments.
+    pub fn child_elements(&self) -> impl Iterator<Item = &HtmlElement<'a>> {
+        self.children.iter().filter_map(HtmlNode::as_element)
+    }
+
     pub fn add_attribute(&mut self, name: &'a str, value: Option<&'a str>) {
-        self.attributes.push(HtmlAttribute::new(name, value));
+        self.attributes_vec_mut()
+            .push(HtmlAttribute::new(name, value));
     }
 
-    pub fn add_child(&mut self, child: HtmlElement<'a>) {
-        self.children.push(child);
+    /// Looks up an attribute by its ASCII case-insensitive name. Attributes
+    /// without a value, like `disabled`, return an empty string.
+    pub fn get_attr(&self, name: &str) -> Option<&str> {
+        find_attribute(&self.attributes, name).map(|attr| attr.value().unwrap_or_default())
+    }
+
+    pub fn has_attr(&self, name: &str) -> bool {
+        find_attribute(&self.attributes, name).is_some()
+    }
+
+    /// Updates the attribute if present, otherwise appends it.
+    pub fn set_attr(&mut self, name: &'a str, value: impl Into<Cow<'a, str>>) {
+        let attributes = self.attributes_vec_mut();
+        match attributes
+            .iter_mut()
+            .find(|attr| attr.name.eq_ignore_ascii_case(name))
+        {
+            Some(attr) => attr.set_value(value),
+            None => attributes.push(HtmlAttribute {
+                name: name.into(),
+                value: Some(value.into()),
+            }),
+        }
+    }
+
+    pub fn remove_attr(&mut self, name: &str) -> Option<HtmlAttribute<'a>> {
+        let index = self
+            .attributes
+            .iter()
+            .position(|attr| attr.name.eq_ignore_ascii_case(name))?;
+        Some(self.attributes_vec_mut().remove(index))
+    }
+
+    pub fn add_child(&mut self, child: impl Into<HtmlNode<'a>>) {
+        self.children_vec_mut().push(child.into());
+    }
+
+    pub fn add_text(&mut self, text: impl Into<Cow<'a, str>>) {
+        self.children_vec_mut().push(HtmlNode::Text(text.into()));
+    }
+
+    /// Removes and returns the child at `index`, shifting later children left.
+    pub fn remove_child(&mut self, index: usize) -> HtmlNode<'a> {
+        self.children_vec_mut().remove(index)
+    }
+
+    pub fn insert_child_at(&mut self, index: usize, child: impl Into<HtmlNode<'a>>) {
+        self.children_vec_mut().insert(index, child.into());
+    }
+
+    /// Replaces the child at `index`, returning the previous node.
+    pub fn replace_child(&mut self, index: usize, child: impl Into<HtmlNode<'a>>) -> HtmlNode<'a> {
+        core::mem::replace(&mut self.children_vec_mut()[index], child.into())
+    }
+
+    /// Moves the child at `from` so that it ends up at index `to`.
+    pub fn move_child(&mut self, from: usize, to: usize) {
+        let children = self.children_vec_mut();
+        let child = children.remove(from);
+        children.insert(to, child);
+    }
+
+    pub fn attributes_mut(&mut self) -> &mut [HtmlAttribute<'a>] {
+        self.attributes_vec_mut()
+    }
+
+    pub fn children_mut(&mut self) -> &mut [HtmlNode<'a>] {
+        self.children_vec_mut()
+    }
+
+    // All in-crate mutation goes through these so recorded source is dropped
+    // once it stops matching the tree
+    pub(crate) fn attributes_vec_mut(&mut self) -> &mut Attributes<'a> {
+        self.source.outer = None;
+        self.source.start_tag = None;
+        &mut self.attributes
+    }
+
+    pub(crate) fn children_vec_mut(&mut self) -> &mut Vec<HtmlNode<'a>> {
+        self.source.outer = None;
+        &mut self.children
     }
 }
 
+#[derive(Debug, Default, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HtmlDocument<'a> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    nodes: Vec<HtmlNode<'a>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    source: Option<&'a str>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) diagnostics: Vec<Diagnostic<'a>>,
+}
+
+impl PartialEq for HtmlDocument<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.nodes == other.nodes
+    }
+}
+
+impl<'a> HtmlDocument<'a> {
+    pub fn doctype(&self) -> Option<&HtmlDoctype<'a>> {
+        self.nodes.iter().find_map(|node| match node {
+            HtmlNode::Doctype(doctype) => Some(doctype),
+            _ => None,
+        })
+    }
+
+    /// Problems the parser recovered from, such as elements it had to close
+    /// itself at the end of the input.
+    pub fn diagnostics(&self) -> &[Diagnostic<'a>] {
+        &self.diagnostics
+    }
+
+    /// Top-level nodes, including comments and the doctype
+    pub fn children(&self) -> &[HtmlNode<'a>] {
+        &self.nodes
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &HtmlElement<'a>> {
+        self.nodes.iter().filter_map(HtmlNode::as_element)
+    }
+
+    pub(crate) fn nodes_mut(&mut self) -> &mut Vec<HtmlNode<'a>> {
+        self.source = None;
+        &mut self.nodes
+    }
+
+    pub fn into_elements(self) -> Vec<HtmlElement<'a>> {
+        self.nodes
+            .into_iter()
+            .filter_map(|node| match node {
+                HtmlNode::Element(element) => Some(element),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A document without a doctype is rendered in quirks mode.
+    pub fn quirks_mode(&self) -> QuirksMode {
+        self.doctype()
+            .map_or(QuirksMode::Quirks, HtmlDoctype::quirks_mode)
+    }
+}
+
+/// Settings shared by `parse_html_with`, `parse_document_with`,
+/// `tokenize_html_with` and the other `_with` entry points. The default
+/// parses strictly and keeps everything in the input.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Report unquoted attribute values containing characters that require quoting.
+    pub warn_unquoted_attribute_values: bool,
+    /// Keep whitespace-only text and record the original markup of every
+    /// element, so an unchanged tree serializes back to the exact input.
+    pub preserve_source: bool,
+    /// Keep tag and attribute names as written instead of lowercasing them.
+    pub preserve_case: bool,
+    /// Leave comments out of the tree.
+    pub skip_comments: bool,
+    /// Keep character references such as `&amp;` as written in text and
+    /// attribute values instead of decoding them.
+    pub keep_entities: bool,
+    pub duplicate_attributes: DuplicateAttributes,
+    pub mode: ParseMode,
+    /// How deeply elements may nest, so hostile input can't exhaust the
+    /// stack of code that walks the tree recursively, such as `Clone` and
+    /// `Drop`. 512 by default, the depth at which browsers stop nesting.
+    pub max_depth: Option<usize>,
+    /// How many tokens the input may produce.
+    pub max_tokens: Option<usize>,
+    pub max_attributes_per_tag: Option<usize>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            warn_unquoted_attribute_values: false,
+            preserve_source: false,
+            preserve_case: false,
+            skip_comments: false,
+            keep_entities: false,
+            duplicate_attributes: DuplicateAttributes::default(),
+            mode: ParseMode::default(),
+            max_depth: Some(512),
+            max_tokens: None,
+            max_attributes_per_tag: None,
+        }
+    }
+}
+
+/// The `ParseOptions` limit that input went over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Depth,
+    Tokens,
+    Attributes,
+}
+
+/// What happens to an attribute repeated within one start tag, like the
+/// second `href` in `<a href="a" href="b">`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateAttributes {
+    /// Keep the first occurrence and drop the rest, as browsers do.
+    #[default]
+    KeepFirst,
+    /// Keep every occurrence, in source order.
+    KeepAll,
+    /// Keep the first occurrence and report each dropped one as a warning.
+    Warn,
+}
+
+/// How the tree builder deals with markup that doesn't nest properly.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Unclosed elements and stray end tags are errors.
+    #[default]
+    Strict,
+    /// Always builds a tree: end tags close the nearest matching open
+    /// element and anything it contains, unmatched end tags are ignored,
+    /// and elements still open when the input ends (or becomes unreadable)
+    /// are closed.
+    ///
+    /// Misplaced table content isn't relocated: the `stray` text and `<b>`
+    /// in `<table>stray<b>bold</b><tr>..` stay inside the table, where
+    /// browsers would move them before it. `ParseMode::Spec`, with the
+    /// `spec` feature, does that foster parenting.
+    Lenient,
+    /// Follows the WHATWG tree-construction rules, the way browsers recover
+    /// from misnested formatting, tables and missing `<html>`, `<head>` and
+    /// `<body>` tags. Only fails when a limit in `ParseOptions` is exceeded.
+    #[cfg(feature = "spec")]
+    Spec,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HtmlWarning<'a> {
+    UnquotedAttributeValue {
+        name: &'a str,
+        value: &'a str,
+    },
+    DuplicateAttribute {
+        name: &'a str,
+    },
+    /// Lenient parsing reached the end of the input with this element still
+    /// open and closed it, though its end tag is required.
+    UnclosedElement {
+        tag: Cow<'a, str>,
+    },
+    /// Lenient parsing ignored an end tag with no open element to close.
+    UnexpectedEndTag {
+        tag: &'a str,
+    },
+    /// An element whose end tag is required was closed by the end tag of an
+    /// element containing it, like the `<b>` in `<p><b>bold</p>`.
+    MisnestedElement {
+        tag: Cow<'a, str>,
+        closed_by: &'a str,
+    },
+    /// Lenient parsing dropped the rest of the input from here, as it ended
+    /// inside a tag, comment or quoted value.
+    UnreadableInput,
+    /// An element where its parent's content model doesn't allow it, like
+    /// `<li>` outside a list. Reported by `validate`, with no parent for
+    /// top-level elements.
+    MisplacedElement {
+        tag: Cow<'a, str>,
+        parent: Option<Cow<'a, str>>,
+    },
+    /// Reported by a `Linter` rule.
+    Lint {
+        rule: String,
+        message: String,
+    },
+}
+
+/// Everything that can go wrong, for every feature. Which variants can come
+/// up depends on the features enabled, and `Io` only exists with `std`, so
+/// matches need a catch-all arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum HtmlError {
-    ReaderError(reader::ReadError),
-    InvalidAst,
+    /// The input ended inside the construct starting at the span, such as an
+    /// unterminated quoted value.
+    ReaderError(reader::ReadError, Span),
+    /// Tokens that can't form a tree, like an attribute outside a start tag.
+    InvalidAst(Span),
+    /// An end tag with no open element for it to close.
+    UnexpectedEndTag {
+        tag: String,
+        span: Span,
+    },
+    /// The input ended with this element still open; the span starts at its
+    /// start tag.
+    UnclosedElement {
+        tag: String,
+        span: Span,
+    },
+    /// An end tag for an element around this one came while it was still
+    /// open, like the `</div>` in `<div><span>x</div>`; the span starts at
+    /// its start tag.
+    MisnestedElement {
+        tag: String,
+        closed_by: String,
+        span: Span,
+    },
     DecodeFailed,
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    InvalidSelector(SelectorError),
+    InvalidXPath(String),
+    LimitExceeded(Limit, Span),
+    /// `FromHtml` found nothing to read a required field from.
+    MissingField(String),
+    /// `FromHtml` couldn't convert the value read for a field.
+    InvalidField {
+        field: String,
+        value: String,
+    },
+    /// An edit that doesn't fit the tree, by its index in the patch.
+    InvalidPatch {
+        edit: usize,
+        conflict: PatchConflict,
+    },
+    /// JSON that isn't valid, or doesn't describe a tree.
+    InvalidJson(String),
+}
+
+impl HtmlError {
+    /// Where in the input the error was found, for errors about the markup
+    /// itself.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            HtmlError::ReaderError(_, span)
+            | HtmlError::InvalidAst(span)
+            | HtmlError::UnexpectedEndTag { span, .. }
+            | HtmlError::UnclosedElement { span, .. }
+            | HtmlError::MisnestedElement { span, .. }
+            | HtmlError::LimitExceeded(_, span) => Some(*span),
+            _ => None,
+        }
+    }
+
+    // Moves the span along, for input tokenized from a later offset
+    pub(crate) fn shifted(self, by: usize) -> Self {
+        match self {
+            HtmlError::ReaderError(error, span) => HtmlError::ReaderError(error, span.shifted(by)),
+            HtmlError::InvalidAst(span) => HtmlError::InvalidAst(span.shifted(by)),
+            HtmlError::UnexpectedEndTag { tag, span } => HtmlError::UnexpectedEndTag {
+                tag,
+                span: span.shifted(by),
+            },
+            HtmlError::UnclosedElement { tag, span } => HtmlError::UnclosedElement {
+                tag,
+                span: span.shifted(by),
+            },
+            HtmlError::MisnestedElement {
+                tag,
+                closed_by,
+                span,
+            } => HtmlError::MisnestedElement {
+                tag,
+                closed_by,
+                span: span.shifted(by),
+            },
+            HtmlError::LimitExceeded(limit, span) => {
+                HtmlError::LimitExceeded(limit, span.shifted(by))
+            }
+            error => error,
+        }
+    }
+}
+
+impl core::fmt::Display for HtmlError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            HtmlError::ReaderError(error, span) => {
+                write!(f, "{error} in markup starting at byte {}", span.start)
+            }
+            HtmlError::InvalidAst(span) => write!(f, "unexpected token at byte {}", span.start),
+            HtmlError::UnexpectedEndTag { tag, span } => {
+                write!(
+                    f,
+                    "end tag `</{tag}>` at byte {} closes nothing",
+                    span.start
+                )
+            }
+            HtmlError::UnclosedElement { tag, span } => {
+                write!(f, "`<{tag}>` at byte {} is never closed", span.start)
+            }
+            HtmlError::MisnestedElement {
+                tag,
+                closed_by,
+                span,
+            } => {
+                write!(
+                    f,
+                    "`<{tag}>` at byte {} is still open at `</{closed_by}>`",
+                    span.start
+                )
+            }
+            HtmlError::DecodeFailed => f.write_str("input isn't valid in its encoding"),
+            #[cfg(feature = "std")]
+            HtmlError::Io(error) => write!(f, "reading input failed: {error}"),
+            HtmlError::InvalidSelector(error) => write!(f, "invalid selector: {error}"),
+            HtmlError::InvalidXPath(message) => write!(f, "invalid XPath: {message}"),
+            HtmlError::LimitExceeded(limit, span) => {
+                let limit = match limit {
+                    Limit::Depth => "nesting depth",
+                    Limit::Tokens => "token count",
+                    Limit::Attributes => "attributes per tag",
+                };
+                write!(f, "{limit} limit exceeded at byte {}", span.start)
+            }
+            HtmlError::MissingField(field) => write!(f, "nothing to read field `{field}` from"),
+            HtmlError::InvalidField { field, value } => {
+                write!(f, "invalid value `{value}` for field `{field}`")
+            }
+            HtmlError::InvalidPatch { edit, conflict } => {
+                write!(f, "can't apply edit {edit} of the patch: {conflict}")
+            }
+            HtmlError::InvalidJson(message) => write!(f, "invalid JSON: {message}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for HtmlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HtmlError::ReaderError(error, _) => Some(error),
+            HtmlError::Io(error) => Some(error),
+            HtmlError::InvalidSelector(error) => Some(error),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -60,16 +816,99 @@ mod tests {
         assert_eq!(
             root,
             HtmlElement {
-                tag: "html",
-                attributes: vec![],
-                children: vec![HtmlElement {
-                    tag: "p",
-                    attributes: vec![],
+                tag: "html".into(),
+                attributes: Default::default(),
+                children: vec![HtmlNode::Element(HtmlElement {
+                    tag: "p".into(),
+                    attributes: Default::default(),
                     children: vec![],
-                    inner_text: None
-                }],
-                inner_text: None,
+                    ..Default::default()
+                })],
+                ..Default::default()
             }
         )
     }
+
+    #[test]
+    fn fluent_builder() {
+        let url = String::from("/home");
+        let link = HtmlElement::new("a")
+            .attr("href", url)
+            .text("go ")
+            .child(HtmlElement::new("b").text("home"));
+        assert_eq!(
+            html_to_string(vec![link]),
+            "<a href=\"/home\">go <b>home</b></a>"
+        );
+    }
+
+    #[test]
+    fn covariant_trees() {
+        // Whatever the features, a tree borrowing `'static` strings passes
+        // for one borrowing something shorter
+        fn shorten<'s>(node: HtmlNode<'static>, _: &'s str) -> HtmlNode<'s> {
+            node
+        }
+        let text = String::from("home");
+        let mut link = shorten(HtmlElement::new("a").attr("href", "/").into(), &text);
+        if let HtmlNode::Element(link) = &mut link {
+            link.add_attribute("title", Some(&text));
+        }
+        assert_eq!(
+            html_to_string(link.as_element().cloned().into_iter().collect()),
+            "<a href=\"/\" title=\"home\"></a>"
+        );
+    }
+
+    #[test]
+    fn html_element_attributes() {
+        let mut button = HtmlElement::new("button");
+        button.add_attribute("Class", Some("btn"));
+        button.add_attribute("disabled", None);
+
+        assert_eq!(button.get_attr("class"), Some("btn"));
+        assert_eq!(button.get_attr("DISABLED"), Some(""));
+        assert_eq!(button.get_attr("id"), None);
+        assert!(button.has_attr("disabled"));
+
+        button.set_attr("class", "btn primary");
+        button.set_attr("type", String::from("submit"));
+        assert_eq!(button.get_attr("class"), Some("btn primary"));
+        assert_eq!(button.attributes().len(), 3);
+
+        let removed = button.remove_attr("disabled").unwrap();
+        assert_eq!(removed.name(), "disabled");
+        assert!(!button.has_attr("disabled"));
+
+        let names: Vec<_> = button
+            .attributes()
+            .iter()
+            .map(HtmlAttribute::name)
+            .collect();
+        assert_eq!(names, vec!["Class", "type"]);
+    }
+
+    #[test]
+    fn html_element_mutation() {
+        let mut list = HtmlElement::new("ul");
+        for tag in ["a", "b", "c"] {
+            list.add_child(HtmlElement::new(tag));
+        }
+        fn tags<'l>(list: &'l HtmlElement) -> Vec<&'l str> {
+            list.children
+                .iter()
+                .filter_map(|child| child.as_element().map(HtmlElement::tag))
+                .collect()
+        }
+
+        list.move_child(0, 2);
+        assert_eq!(tags(&list), vec!["b", "c", "a"]);
+
+        let removed = list.remove_child(1);
+        assert_eq!(removed, HtmlNode::Element(HtmlElement::new("c")));
+
+        list.insert_child_at(0, HtmlElement::new("d"));
+        list.replace_child(1, HtmlElement::new("e"));
+        assert_eq!(tags(&list), vec!["d", "e", "a"]);
+    }
 }
@@ -0,0 +1,93 @@
+use crate::prelude::*;
+use crate::{HtmlDocument, HtmlElement, HtmlNode, Span};
+
+/// A hyperlink found by [`extract_links`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link<'d> {
+    /// The URL as written; see `resolve_url` to make it absolute.
+    pub url: &'d str,
+    /// The element it came from: `a`, `area`, `link` or `form`.
+    pub tag: &'d str,
+    /// The rendered text of an `a`, or the `alt` of an `area`.
+    pub text: String,
+    pub rel: Option<&'d str>,
+    /// Whether `rel` asks crawlers not to follow the link.
+    pub nofollow: bool,
+    pub span: Option<Span>,
+}
+
+/// Every `a`, `area` and `link` with an `href` and every `form` with an
+/// `action`, in document order.
+pub fn extract_links<'d>(document: &'d HtmlDocument<'_>) -> Vec<Link<'d>> {
+    let mut links = Vec::new();
+    collect_links(document.children(), &mut links);
+    links
+}
+
+fn collect_links<'d>(nodes: &'d [HtmlNode<'_>], links: &mut Vec<Link<'d>>) {
+    for element in nodes.iter().filter_map(HtmlNode::as_element) {
+        if let Some(link) = to_link(element) {
+            links.push(link);
+        }
+        collect_links(element.children(), links);
+    }
+}
+
+fn to_link<'d>(element: &'d HtmlElement<'_>) -> Option<Link<'d>> {
+    let tag = element.tag();
+    let url = match tag.to_ascii_lowercase().as_str() {
+        "a" | "area" | "link" => element.get_attr("href")?,
+        "form" => element.get_attr("action")?,
+        _ => return None,
+    };
+    let text = if tag.eq_ignore_ascii_case("a") {
+        element.inner_text()
+    } else if tag.eq_ignore_ascii_case("area") {
+        element.get_attr("alt").unwrap_or_default().to_string()
+    } else {
+        String::new()
+    };
+    let rel = element.get_attr("rel");
+    let nofollow = rel.is_some_and(|rel| {
+        rel.split_ascii_whitespace()
+            .any(|value| value.eq_ignore_ascii_case("nofollow"))
+    });
+    Some(Link {
+        url,
+        tag,
+        text,
+        rel,
+        nofollow,
+        span: element.span(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    #[test]
+    fn collects_links() {
+        let html = "<link rel=\"stylesheet\" href=\"/s.css\">\
+            <p><a href=\"/a\" rel=\"ugc NoFollow\">the <b>A</b> page</a><a name=\"x\">no</a></p>\
+            <map><area href=\"/b\" alt=\"B\"></map><form action=\"/search\"></form>";
+        let document = parse_document(html).unwrap();
+        let links = extract_links(&document);
+        let summary: Vec<_> = links
+            .iter()
+            .map(|link| (link.tag, link.url, link.text.as_str(), link.nofollow))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("link", "/s.css", "", false),
+                ("a", "/a", "the A page", true),
+                ("area", "/b", "B", false),
+                ("form", "/search", "", false),
+            ]
+        );
+        assert_eq!(links[1].rel, Some("ugc NoFollow"));
+        assert_eq!(links[1].span, Some(Span::new(40, 93)));
+    }
+}
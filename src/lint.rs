@@ -0,0 +1,132 @@
+use crate::prelude::*;
+use crate::{Diagnostic, HtmlDocument, HtmlElement, HtmlNode, HtmlWarning};
+
+/// A rule run over every element by a [`Linter`].
+pub trait Lint {
+    /// Identifies the rule in the diagnostics it reports, like `img-alt`.
+    fn name(&self) -> &str;
+
+    /// What's wrong with `element`, if anything.
+    fn check(&self, element: &HtmlElement) -> Option<String>;
+}
+
+/// Every `<img>` needs an `alt` attribute, even if empty.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ImgAlt;
+
+impl Lint for ImgAlt {
+    fn name(&self) -> &str {
+        "img-alt"
+    }
+
+    fn check(&self, element: &HtmlElement) -> Option<String> {
+        (element.tag().eq_ignore_ascii_case("img") && !element.has_attr("alt"))
+            .then(|| "`<img>` has no `alt` attribute".to_string())
+    }
+}
+
+/// Styling belongs in stylesheets rather than `style` attributes.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoInlineStyle;
+
+impl Lint for NoInlineStyle {
+    fn name(&self) -> &str {
+        "no-inline-style"
+    }
+
+    fn check(&self, element: &HtmlElement) -> Option<String> {
+        element
+            .has_attr("style")
+            .then(|| format!("`<{}>` has an inline `style`", element.tag()))
+    }
+}
+
+/// The set of rules to check a document against.
+#[derive(Default)]
+pub struct Linter<'l> {
+    rules: Vec<Box<dyn Lint + 'l>>,
+}
+
+impl<'l> Linter<'l> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, rule: impl Lint + 'l) -> &mut Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|rule| rule.name())
+    }
+
+    /// Runs every rule over every element, in document order.
+    pub fn check<'a>(&self, document: &HtmlDocument<'a>) -> Vec<Diagnostic<'a>> {
+        let mut diagnostics = Vec::new();
+        self.check_nodes(document.children(), &mut diagnostics);
+        diagnostics
+    }
+
+    fn check_nodes<'a>(&self, nodes: &[HtmlNode<'a>], diagnostics: &mut Vec<Diagnostic<'a>>) {
+        for element in nodes.iter().filter_map(HtmlNode::as_element) {
+            for rule in &self.rules {
+                if let Some(message) = rule.check(element) {
+                    let warning = HtmlWarning::Lint {
+                        rule: rule.name().to_string(),
+                        message,
+                    };
+                    diagnostics.push(Diagnostic::new(warning, element.span().unwrap_or_default()));
+                }
+            }
+            self.check_nodes(element.children(), diagnostics);
+            self.check_nodes(element.template_content(), diagnostics);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_document, Span};
+
+    struct NoTag(&'static str);
+
+    impl Lint for NoTag {
+        fn name(&self) -> &str {
+            "no-tag"
+        }
+
+        fn check(&self, element: &HtmlElement) -> Option<String> {
+            (element.tag() == self.0).then(|| format!("`<{}>` is banned", self.0))
+        }
+    }
+
+    #[test]
+    fn custom_rules() {
+        let html =
+            "<div style=\"color: red\"><img src=\"a.png\"><img alt=\"\"><marquee></marquee></div>";
+        let document = parse_document(html).unwrap();
+        let mut linter = Linter::new();
+        linter.add(ImgAlt).add(NoInlineStyle).add(NoTag("marquee"));
+        assert_eq!(
+            linter.rules().collect::<Vec<_>>(),
+            ["img-alt", "no-inline-style", "no-tag"]
+        );
+
+        let diagnostics = linter.check(&document);
+        let rules: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| match &diagnostic.warning {
+                HtmlWarning::Lint { rule, .. } => rule.as_str(),
+                warning => panic!("unexpected {warning:?}"),
+            })
+            .collect();
+        assert_eq!(rules, ["no-inline-style", "img-alt", "no-tag"]);
+        assert_eq!(diagnostics[1].span, Span::new(24, 41));
+        assert_eq!(
+            diagnostics[2].to_string(),
+            "`<marquee>` is banned (no-tag) at byte 53"
+        );
+    }
+}
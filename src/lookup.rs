@@ -0,0 +1,100 @@
+use crate::{Document, HtmlElement, NodeId};
+
+impl<'a> HtmlElement<'a> {
+    /// Descendant elements with the given tag (`*` matches any), in document
+    /// order.
+    pub fn get_elements_by_tag_name<'e>(
+        &'e self,
+        tag: &'e str,
+    ) -> impl Iterator<Item = &'e HtmlElement<'a>> {
+        self.descendants()
+            .map(|(element, _)| element)
+            .filter(move |element| tag == "*" || element.tag.eq_ignore_ascii_case(tag))
+    }
+
+    /// Descendant elements carrying every class in the whitespace-separated
+    /// `classes`.
+    pub fn get_elements_by_class_name<'e>(
+        &'e self,
+        classes: &'e str,
+    ) -> impl Iterator<Item = &'e HtmlElement<'a>> {
+        self.descendants()
+            .map(|(element, _)| element)
+            .filter(move |element| {
+                classes
+                    .split_ascii_whitespace()
+                    .all(|class| element.has_class(class))
+            })
+    }
+}
+
+impl Document<'_> {
+    pub fn get_elements_by_tag_name<'d>(
+        &'d self,
+        id: NodeId,
+        tag: &'d str,
+    ) -> impl Iterator<Item = NodeId> + 'd {
+        self.descendants(id).filter(move |id| {
+            self.get(*id)
+                .tag()
+                .is_some_and(|name| tag == "*" || name.eq_ignore_ascii_case(tag))
+        })
+    }
+
+    pub fn get_elements_by_class_name<'d>(
+        &'d self,
+        id: NodeId,
+        classes: &'d str,
+    ) -> impl Iterator<Item = NodeId> + 'd {
+        self.descendants(id).filter(move |id| {
+            let node = self.get(*id);
+            node.is_element() && {
+                let class = node.get_attr("class").unwrap_or_default();
+                classes
+                    .split_ascii_whitespace()
+                    .all(|name| class.split_ascii_whitespace().any(|class| class == name))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_html;
+    use crate::prelude::*;
+
+    const HTML: &str = "<div><p class=\"note big\">a</p><section><P class=\"note\">b</P></section><span class=\"big\">c</span></div>";
+
+    #[test]
+    fn element_lookup() {
+        let elements = parse_html(HTML).unwrap();
+        let root = &elements[0];
+
+        let paragraphs: Vec<_> = root.get_elements_by_tag_name("p").collect();
+        assert_eq!(paragraphs.len(), 2);
+        assert_eq!(paragraphs[1].tag(), "p");
+        assert_eq!(root.get_elements_by_tag_name("*").count(), 4);
+
+        let notes: Vec<_> = root
+            .get_elements_by_class_name("note")
+            .map(HtmlElement::tag)
+            .collect();
+        assert_eq!(notes, vec!["p", "p"]);
+        assert_eq!(root.get_elements_by_class_name("big note").count(), 1);
+        assert_eq!(root.get_elements_by_class_name("missing").count(), 0);
+    }
+
+    #[test]
+    fn document_lookup() {
+        let document = Document::parse(HTML).unwrap();
+        let root = document.root();
+
+        assert_eq!(document.get_elements_by_tag_name(root, "P").count(), 2);
+        let big: Vec<_> = document
+            .get_elements_by_class_name(root, "big")
+            .filter_map(|id| document.get(id).tag())
+            .collect();
+        assert_eq!(big, vec!["p", "span"]);
+    }
+}
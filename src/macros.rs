@@ -0,0 +1,92 @@
+/// Builds an [`HtmlElement`](crate::HtmlElement) from a tree written in
+/// Rust code.
+///
+/// Inside an element's braces, `name: value` adds an attribute, a string
+/// literal or a parenthesized expression adds text, and `tag { ... }` adds a
+/// child element. Names that aren't identifiers, like `data-id` or custom
+/// element tags, can be written as string literals. Attributes are
+/// followed by a comma unless they come last; elsewhere commas are optional.
+///
+/// ```
+/// use html_parser::{html, html_to_string};
+///
+/// let name = "world";
+/// let card = html! {
+///     div {
+///         class: "card",
+///         "data-id": 7.to_string(),
+///         p { "Hello, " (name) }
+///     }
+/// };
+/// assert_eq!(
+///     html_to_string(vec![card]),
+///     r#"<div class="card" data-id="7"><p>Hello, world</p></div>"#
+/// );
+/// ```
+#[macro_export]
+macro_rules! html {
+    ($tag:ident { $($body:tt)* }) => {{
+        let mut element = $crate::HtmlElement::new(stringify!($tag));
+        $crate::__html_items!(element; $($body)*);
+        element
+    }};
+    ($tag:literal { $($body:tt)* }) => {{
+        let mut element = $crate::HtmlElement::new($tag);
+        $crate::__html_items!(element; $($body)*);
+        element
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __html_items {
+    ($element:ident;) => {};
+    ($element:ident; , $($rest:tt)*) => {
+        $crate::__html_items!($element; $($rest)*);
+    };
+    ($element:ident; $name:ident : $value:expr $(, $($rest:tt)*)?) => {
+        $element.set_attr(stringify!($name), $value);
+        $crate::__html_items!($element; $($($rest)*)?);
+    };
+    ($element:ident; $name:literal : $value:expr $(, $($rest:tt)*)?) => {
+        $element.set_attr($name, $value);
+        $crate::__html_items!($element; $($($rest)*)?);
+    };
+    ($element:ident; $tag:ident { $($body:tt)* } $($rest:tt)*) => {
+        $element.add_child($crate::html!($tag { $($body)* }));
+        $crate::__html_items!($element; $($rest)*);
+    };
+    ($element:ident; $tag:literal { $($body:tt)* } $($rest:tt)*) => {
+        $element.add_child($crate::html!($tag { $($body)* }));
+        $crate::__html_items!($element; $($rest)*);
+    };
+    ($element:ident; $text:literal $($rest:tt)*) => {
+        $element.add_text($text);
+        $crate::__html_items!($element; $($rest)*);
+    };
+    ($element:ident; ($text:expr) $($rest:tt)*) => {
+        $element.add_text($text);
+        $crate::__html_items!($element; $($rest)*);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_html, HtmlElement};
+
+    #[test]
+    fn builds_elements() {
+        let items = ["one", "two"];
+        let list = html! {
+            ul {
+                id: "list",
+                li { (items[0]) }
+                li { class: "last", (items[1].to_uppercase()) }
+                "my-item" { hidden: "" }
+            }
+        };
+        let expected = "<ul id=\"list\"><li>one</li><li class=\"last\">TWO</li><my-item hidden=\"\"></my-item></ul>";
+        assert_eq!(list, parse_html(expected).unwrap()[0]);
+        assert_eq!(html! { br {} }, HtmlElement::new("br"));
+    }
+}
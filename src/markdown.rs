@@ -0,0 +1,462 @@
+use crate::prelude::*;
+use crate::{HtmlDocument, HtmlElement, HtmlNode};
+
+// Elements that start a new Markdown block rather than flowing inline
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "li",
+    "main",
+    "nav",
+    "noscript",
+    "ol",
+    "p",
+    "pre",
+    "script",
+    "section",
+    "style",
+    "table",
+    "template",
+    "title",
+    "ul",
+];
+
+const SKIPPED_ELEMENTS: &[&str] = &["head", "script", "style", "template", "title"];
+
+impl HtmlElement<'_> {
+    /// Converts the element to CommonMark, using GFM pipe tables for
+    /// `<table>`.
+    pub fn to_markdown(&self) -> String {
+        if is(self, BLOCK_ELEMENTS) {
+            return finish(block(self).into_iter().collect());
+        }
+        let mut blocks = Vec::new();
+        let mut inline_run = String::new();
+        push_inline_element(&mut inline_run, self);
+        flush(&mut blocks, &mut inline_run);
+        finish(blocks)
+    }
+}
+
+impl HtmlDocument<'_> {
+    pub fn to_markdown(&self) -> String {
+        finish(blocks(&self.nodes))
+    }
+}
+
+fn finish(blocks: Vec<String>) -> String {
+    let mut markdown = blocks.join("\n\n");
+    if !markdown.is_empty() {
+        markdown.push('\n');
+    }
+    markdown
+}
+
+fn is(element: &HtmlElement<'_>, tags: &[&str]) -> bool {
+    tags.iter().any(|tag| element.tag.eq_ignore_ascii_case(tag))
+}
+
+fn blocks(nodes: &[HtmlNode<'_>]) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut inline_run = String::new();
+
+    for node in nodes {
+        match node {
+            HtmlNode::Element(element) if is(element, BLOCK_ELEMENTS) => {
+                flush(&mut blocks, &mut inline_run);
+                blocks.extend(block(element));
+            }
+            node => push_inline(&mut inline_run, node),
+        }
+    }
+    flush(&mut blocks, &mut inline_run);
+    blocks
+}
+
+fn flush(blocks: &mut Vec<String>, inline_run: &mut String) {
+    let paragraph = escape_line_starts(&trim_lines(inline_run));
+    if !paragraph.is_empty() {
+        blocks.push(paragraph);
+    }
+    inline_run.clear();
+}
+
+// Strips the spaces left around line breaks by whitespace collapsing,
+// keeping the two trailing spaces of a hard break
+fn trim_lines(text: &str) -> String {
+    let lines: Vec<_> = text.trim().split('\n').collect();
+    let last = lines.len() - 1;
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let line = line.trim_start();
+            if i < last {
+                format!("{}  ", line.trim_end())
+            } else {
+                line.trim_end().to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn block(element: &HtmlElement<'_>) -> Option<String> {
+    let tag = element.tag.to_ascii_lowercase();
+    let text = match tag.as_str() {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = usize::from(tag.as_bytes()[1] - b'0');
+            format!("{} {}", "#".repeat(level), inline(&element.children))
+        }
+        "p" => escape_line_starts(&inline(&element.children)),
+        "hr" => String::from("---"),
+        "ul" => list(element, false),
+        "ol" => list(element, true),
+        "pre" => code_block(element),
+        "table" => table(element),
+        "blockquote" => blocks(&element.children)
+            .join("\n\n")
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    String::from(">")
+                } else {
+                    format!("> {}", line)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ if is(element, SKIPPED_ELEMENTS) => return None,
+        _ => blocks(&element.children).join("\n\n"),
+    };
+    (!text.trim().is_empty()).then_some(text)
+}
+
+fn inline(nodes: &[HtmlNode<'_>]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        push_inline(&mut text, node);
+    }
+    trim_lines(&text)
+}
+
+fn push_inline(out: &mut String, node: &HtmlNode<'_>) {
+    match node {
+        HtmlNode::Text(text) => push_text(out, text),
+        HtmlNode::CData(text) => push_text(out, text),
+        HtmlNode::Element(element) => push_inline_element(out, element),
+        _ => {}
+    }
+}
+
+fn push_inline_element(out: &mut String, element: &HtmlElement<'_>) {
+    let wrap = |out: &mut String, marker: &str| {
+        let content = inline(&element.children);
+        if !content.is_empty() {
+            out.push_str(marker);
+            out.push_str(&content);
+            out.push_str(marker);
+        }
+    };
+
+    match element.tag.to_ascii_lowercase().as_str() {
+        "strong" | "b" => wrap(out, "**"),
+        "em" | "i" => wrap(out, "*"),
+        "del" | "s" => wrap(out, "~~"),
+        "code" => {
+            let code = raw_text(&element.children);
+            let fence = fence(&code, 1);
+            // A space keeps backticks at either end from joining the fence
+            let padding = if code.starts_with('`') || code.ends_with('`') {
+                " "
+            } else {
+                ""
+            };
+            out.push_str(&fence);
+            out.push_str(padding);
+            out.push_str(&code);
+            out.push_str(padding);
+            out.push_str(&fence);
+        }
+        "br" => out.push('\n'),
+        "a" => {
+            out.push('[');
+            out.push_str(&inline(&element.children));
+            out.push_str("](");
+            out.push_str(element.get_attr("href").unwrap_or_default());
+            if let Some(title) = element.get_attr("title") {
+                out.push_str(&format!(" \"{}\"", title.replace('"', "\\\"")));
+            }
+            out.push(')');
+        }
+        "img" => out.push_str(&format!(
+            "![{}]({})",
+            escape(element.get_attr("alt").unwrap_or_default()),
+            element.get_attr("src").unwrap_or_default()
+        )),
+        _ if is(element, SKIPPED_ELEMENTS) => {}
+        _ => {
+            for child in &element.children {
+                push_inline(out, child);
+            }
+        }
+    }
+}
+
+// Appends text with whitespace collapsed to single spaces
+fn push_text(out: &mut String, text: &str) {
+    let mut space = out.ends_with([' ', '\n']) || out.is_empty();
+    for ch in escape(text).chars() {
+        if ch.is_whitespace() {
+            if !space {
+                out.push(' ');
+                space = true;
+            }
+        } else {
+            out.push(ch);
+            space = false;
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '\\' | '*' | '_' | '`' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+// Escapes what would make a line of paragraph text a heading, quote or
+// list item instead
+fn escape_line_starts(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            escaped.push('\n');
+        }
+        let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+        if line.starts_with(['#', '>', '-', '+']) {
+            escaped.push('\\');
+        } else if digits > 0 && line[digits..].starts_with(['.', ')']) {
+            escaped.push_str(&line[..digits]);
+            escaped.push('\\');
+            escaped.push_str(&line[digits..]);
+            continue;
+        }
+        escaped.push_str(line);
+    }
+    escaped
+}
+
+fn raw_text(nodes: &[HtmlNode<'_>]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        match node {
+            HtmlNode::Text(content) => text.push_str(content),
+            HtmlNode::CData(content) => text.push_str(content),
+            HtmlNode::Element(element) => text.push_str(&raw_text(&element.children)),
+            _ => {}
+        }
+    }
+    text
+}
+
+fn list(element: &HtmlElement<'_>, ordered: bool) -> String {
+    let start: usize = element
+        .get_attr("start")
+        .and_then(|start| start.parse().ok())
+        .unwrap_or(1);
+
+    element
+        .child_elements()
+        .filter(|item| item.tag.eq_ignore_ascii_case("li"))
+        .enumerate()
+        .map(|(i, item)| {
+            let marker = if ordered {
+                format!("{}. ", start + i)
+            } else {
+                String::from("- ")
+            };
+            // Continuation lines line up with the text after the marker
+            let indent = " ".repeat(marker.len());
+            let content = blocks(&item.children).join("\n");
+            let mut lines = content.lines();
+            let mut text = format!("{}{}", marker, lines.next().unwrap_or_default());
+            for line in lines {
+                text.push('\n');
+                if !line.is_empty() {
+                    text.push_str(&indent);
+                    text.push_str(line);
+                }
+            }
+            text
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn code_block(element: &HtmlElement<'_>) -> String {
+    let language = element
+        .child_elements()
+        .find(|child| child.tag.eq_ignore_ascii_case("code"))
+        .and_then(|code| {
+            code.classes()
+                .find_map(|class| class.strip_prefix("language-"))
+        })
+        .unwrap_or_default();
+    let code = raw_text(&element.children);
+    let fence = fence(&code, 3);
+    format!(
+        "{fence}{}\n{}\n{fence}",
+        language,
+        code.trim_end_matches('\n')
+    )
+}
+
+// Backticks one more than the longest run in `code`, so none of them end
+// the code early
+fn fence(code: &str, min: usize) -> String {
+    let longest = code
+        .split(|ch| ch != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    "`".repeat(min.max(longest + 1))
+}
+
+fn table(element: &HtmlElement<'_>) -> String {
+    let mut rows = Vec::new();
+    collect_rows(element, &mut rows);
+
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+
+    let format_row = |cells: &[String]| {
+        let mut line = String::from("|");
+        for i in 0..columns {
+            line.push(' ');
+            line.push_str(cells.get(i).map(String::as_str).unwrap_or_default());
+            line.push_str(" |");
+        }
+        line
+    };
+
+    let mut lines = vec![
+        format_row(&rows[0]),
+        format!("|{}", " --- |".repeat(columns)),
+    ];
+    lines.extend(rows[1..].iter().map(|row| format_row(row)));
+    lines.join("\n")
+}
+
+fn collect_rows(element: &HtmlElement<'_>, rows: &mut Vec<Vec<String>>) {
+    for child in element.child_elements() {
+        if child.tag.eq_ignore_ascii_case("tr") {
+            rows.push(
+                child
+                    .child_elements()
+                    .filter(|cell| is(cell, &["td", "th"]))
+                    .map(|cell| {
+                        inline(&cell.children)
+                            .replace('|', "\\|")
+                            .replace('\n', " ")
+                    })
+                    .collect(),
+            );
+        } else if is(child, &["thead", "tbody", "tfoot"]) {
+            collect_rows(child, rows);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_document;
+
+    #[test]
+    fn markdown_blocks() {
+        let html = r#"<html><head><title>x</title></head><body>
+            <h1>Title</h1>
+            <p>Some <b>bold</b>, <em>emphasis</em> and <a href="/x" title="X">a link</a>.<br>Next line with <code>a_b</code></p>
+            <ul><li>one</li><li>two<ol start="3"><li>three</li></ol></li></ul>
+            <blockquote><p>quoted *text*</p></blockquote>
+            <pre><code class="language-rust">fn main() {}
+</code></pre>
+            <hr>
+            <script>ignored()</script>
+        </body></html>"#;
+        let document = parse_document(html).unwrap();
+        assert_eq!(
+            document.to_markdown(),
+            "# Title\n\n\
+             Some **bold**, *emphasis* and [a link](/x \"X\").  \nNext line with `a_b`\n\n\
+             - one\n- two\n  3. three\n\n\
+             > quoted \\*text\\*\n\n\
+             ```rust\nfn main() {}\n```\n\n\
+             ---\n"
+        );
+    }
+
+    #[test]
+    fn markdown_line_starts() {
+        let html = "<p># not heading</p><p>- x<br>+ y<br>&gt; z</p><ul><li>1. x</li><li>2) y</li></ul><p>a - b 3. c</p>";
+        let document = parse_document(html).unwrap();
+        assert_eq!(
+            document.to_markdown(),
+            "\\# not heading\n\n\\- x  \n\\+ y  \n\\> z\n\n- 1\\. x\n- 2\\) y\n\na - b 3. c\n"
+        );
+    }
+
+    #[test]
+    fn markdown_code_fences() {
+        let html = "<pre>a\n```\nb</pre><p><code>x `` y</code> <code>`z</code></p>";
+        let document = parse_document(html).unwrap();
+        assert_eq!(
+            document.to_markdown(),
+            "````\na\n```\nb\n````\n\n```x `` y``` `` `z ``\n"
+        );
+    }
+
+    #[test]
+    fn markdown_table() {
+        let html = "<table><thead><tr><th>Name</th><th>Qty</th></tr></thead><tbody><tr><td>a|b</td><td>1</td></tr><tr><td><img src=\"i.png\" alt=\"pic\"></td></tr></tbody></table>";
+        let document = parse_document(html).unwrap();
+        assert_eq!(
+            document.to_markdown(),
+            "| Name | Qty |\n| --- | --- |\n| a\\|b | 1 |\n| ![pic](i.png) |  |\n"
+        );
+        assert_eq!(
+            document.elements().next().unwrap().to_markdown(),
+            document.to_markdown()
+        );
+    }
+}
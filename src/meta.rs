@@ -0,0 +1,173 @@
+use crate::prelude::*;
+use crate::{HtmlDocument, HtmlElement, HtmlNode};
+
+/// What a link preview needs to know about a page, from [`extract_metadata`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Metadata<'d> {
+    /// The first `<title>`, with whitespace collapsed.
+    pub title: Option<String>,
+    pub description: Option<&'d str>,
+    /// The `href` of `<link rel="canonical">`.
+    pub canonical: Option<&'d str>,
+    /// From `<meta charset>` or a `Content-Type` `http-equiv`.
+    pub charset: Option<&'d str>,
+    pub viewport: Option<&'d str>,
+    /// Every `og:*` property and its content, in document order.
+    pub open_graph: Vec<(&'d str, &'d str)>,
+    /// Every `twitter:*` property and its content, in document order.
+    pub twitter: Vec<(&'d str, &'d str)>,
+    /// `<link rel="alternate">` entries, such as translations and feeds.
+    pub alternates: Vec<Alternate<'d>>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Alternate<'d> {
+    pub href: &'d str,
+    pub hreflang: Option<&'d str>,
+    /// The `type` attribute, like `application/rss+xml`.
+    pub media_type: Option<&'d str>,
+    pub title: Option<&'d str>,
+}
+
+impl<'d> Metadata<'d> {
+    /// The first value of an `og:` or `twitter:` property, like
+    /// `og:image`.
+    pub fn property(&self, name: &str) -> Option<&'d str> {
+        self.open_graph
+            .iter()
+            .chain(&self.twitter)
+            .find(|(property, _)| property.eq_ignore_ascii_case(name))
+            .map(|&(_, content)| content)
+    }
+}
+
+pub fn extract_metadata<'d>(document: &'d HtmlDocument<'_>) -> Metadata<'d> {
+    let mut metadata = Metadata::default();
+    collect(document.children(), &mut metadata);
+    metadata
+}
+
+fn collect<'d>(nodes: &'d [HtmlNode<'_>], metadata: &mut Metadata<'d>) {
+    for element in nodes.iter().filter_map(HtmlNode::as_element) {
+        match element.tag().to_ascii_lowercase().as_str() {
+            "title" if metadata.title.is_none() => {
+                let text: String = element
+                    .children()
+                    .iter()
+                    .filter_map(HtmlNode::as_text)
+                    .collect();
+                metadata.title = Some(text.split_whitespace().collect::<Vec<_>>().join(" "));
+            }
+            "meta" => collect_meta(element, metadata),
+            "link" => collect_link(element, metadata),
+            _ => collect(element.children(), metadata),
+        }
+    }
+}
+
+fn collect_meta<'d>(element: &'d HtmlElement<'_>, metadata: &mut Metadata<'d>) {
+    if let Some(charset) = element.get_attr("charset") {
+        metadata.charset.get_or_insert(charset.trim());
+    }
+    let Some(content) = element.get_attr("content") else {
+        return;
+    };
+    if element
+        .get_attr("http-equiv")
+        .is_some_and(|name| name.eq_ignore_ascii_case("content-type"))
+    {
+        if let Some(charset) = charset_parameter(content) {
+            metadata.charset.get_or_insert(charset);
+        }
+        return;
+    }
+    // Open Graph uses `property` and Twitter `name`, but pages mix them up
+    let Some(name) = element
+        .get_attr("property")
+        .or_else(|| element.get_attr("name"))
+    else {
+        return;
+    };
+    let prefix = |prefix: &str| {
+        name.get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    };
+    if prefix("og:") {
+        metadata.open_graph.push((name, content));
+    } else if prefix("twitter:") {
+        metadata.twitter.push((name, content));
+    } else if name.eq_ignore_ascii_case("description") {
+        metadata.description.get_or_insert(content);
+    } else if name.eq_ignore_ascii_case("viewport") {
+        metadata.viewport.get_or_insert(content);
+    }
+}
+
+fn collect_link<'d>(element: &'d HtmlElement<'_>, metadata: &mut Metadata<'d>) {
+    let (Some(rel), Some(href)) = (element.get_attr("rel"), element.get_attr("href")) else {
+        return;
+    };
+    let has_rel = |value: &str| {
+        rel.split_ascii_whitespace()
+            .any(|rel| rel.eq_ignore_ascii_case(value))
+    };
+    if has_rel("canonical") {
+        metadata.canonical.get_or_insert(href);
+    }
+    if has_rel("alternate") {
+        metadata.alternates.push(Alternate {
+            href,
+            hreflang: element.get_attr("hreflang"),
+            media_type: element.get_attr("type"),
+            title: element.get_attr("title"),
+        });
+    }
+}
+
+// The `charset` of a `text/html; charset=utf-8` style value
+fn charset_parameter(content: &str) -> Option<&str> {
+    content.split(';').find_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches(['"', '\'']))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    #[test]
+    fn page_metadata() {
+        let html = r#"<html><head>
+            <meta http-equiv="Content-Type" content="text/html; charset=ISO-8859-1">
+            <title>  My
+              page </title>
+            <meta name="description" content="About things">
+            <meta name="viewport" content="width=device-width">
+            <meta property="og:title" content="Things">
+            <meta property="og:image" content="/a.png">
+            <meta name="twitter:card" content="summary">
+            <link rel="canonical" href="https://x.org/page">
+            <link rel="alternate" hreflang="de" href="/de/page">
+            <link rel="alternate" type="application/rss+xml" title="Feed" href="/feed">
+        </head></html>"#;
+        let document = parse_document(html).unwrap();
+        let metadata = extract_metadata(&document);
+        assert_eq!(metadata.title.as_deref(), Some("My page"));
+        assert_eq!(metadata.description, Some("About things"));
+        assert_eq!(metadata.charset, Some("ISO-8859-1"));
+        assert_eq!(metadata.viewport, Some("width=device-width"));
+        assert_eq!(metadata.canonical, Some("https://x.org/page"));
+        assert_eq!(metadata.open_graph.len(), 2);
+        assert_eq!(metadata.property("og:image"), Some("/a.png"));
+        assert_eq!(metadata.property("twitter:card"), Some("summary"));
+        assert_eq!(metadata.alternates[0].hreflang, Some("de"));
+        assert_eq!(
+            metadata.alternates[1].media_type,
+            Some("application/rss+xml")
+        );
+    }
+}
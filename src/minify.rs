@@ -0,0 +1,209 @@
+//! Smallest-equivalent serialization: whitespace between elements is
+//! collapsed, comments are dropped, attribute quotes are omitted where HTML
+//! allows it and boolean attributes lose their redundant values.
+
+use crate::prelude::*;
+use crate::{
+    escape_attribute, escape_text,
+    parser::{is_inline, is_preformatted_element, is_raw_text_element, is_void_element},
+    serializer::{raw_text, write_cdata, write_doctype},
+    HtmlAttribute, HtmlDocument, HtmlElement, HtmlNode,
+};
+
+const BOOLEAN_ATTRIBUTES: &[&str] = &[
+    "allowfullscreen",
+    "async",
+    "autofocus",
+    "autoplay",
+    "checked",
+    "controls",
+    "default",
+    "defer",
+    "disabled",
+    "formnovalidate",
+    "hidden",
+    "inert",
+    "ismap",
+    "itemscope",
+    "loop",
+    "multiple",
+    "muted",
+    "nomodule",
+    "novalidate",
+    "open",
+    "playsinline",
+    "readonly",
+    "required",
+    "reversed",
+    "selected",
+];
+
+pub fn minify(elements: &[HtmlElement<'_>]) -> String {
+    let mut html = String::new();
+    for element in elements {
+        push_element(&mut html, element);
+    }
+    html
+}
+
+pub fn minify_document(document: &HtmlDocument<'_>) -> String {
+    let mut html = String::new();
+    push_nodes(&mut html, &document.nodes, false, None);
+    html
+}
+
+fn contains(list: &[&str], tag: &str) -> bool {
+    list.iter().any(|name| name.eq_ignore_ascii_case(tag))
+}
+
+// `raw` is the raw text element the nodes are in, if any
+fn push_nodes(html: &mut String, nodes: &[HtmlNode<'_>], preserve: bool, raw: Option<&str>) {
+    // Comments are dropped up front so they don't separate text from inline
+    // neighbours
+    let nodes: Vec<_> = nodes
+        .iter()
+        .filter(|node| !matches!(node, HtmlNode::Comment(_)))
+        .collect();
+
+    for (i, node) in nodes.iter().enumerate() {
+        match (node, raw) {
+            (HtmlNode::Element(element), _) => push_element(html, element),
+            (HtmlNode::Text(text), Some(tag)) => html.push_str(&raw_text(text, tag)),
+            (HtmlNode::Text(text), None) if preserve => html.push_str(&escape_text(text)),
+            (HtmlNode::Text(text), None) => {
+                // Whitespace between inline neighbours is kept, collapsed
+                if text.trim().is_empty() {
+                    let prev = i.checked_sub(1).and_then(|i| nodes.get(i)).copied();
+                    if is_inline(prev) && is_inline(nodes.get(i + 1).copied()) {
+                        html.push(' ');
+                    }
+                    continue;
+                }
+                let mut collapsed = String::new();
+                push_collapsed(&mut collapsed, text);
+                html.push_str(&escape_text(&collapsed));
+            }
+            (HtmlNode::CData(text), _) => write_cdata(html, text).unwrap(),
+            (HtmlNode::Comment(_), _) => {}
+            (HtmlNode::Doctype(doctype), _) => write_doctype(html, doctype).unwrap(),
+        }
+    }
+}
+
+fn push_collapsed(html: &mut String, text: &str) {
+    let mut space = false;
+    for ch in text.chars() {
+        if ch.is_ascii_whitespace() {
+            space = true;
+            continue;
+        }
+        if space {
+            html.push(' ');
+            space = false;
+        }
+        html.push(ch);
+    }
+    if space {
+        html.push(' ');
+    }
+}
+
+fn push_element(html: &mut String, element: &HtmlElement<'_>) {
+    html.push('<');
+    html.push_str(&element.tag);
+    for attr in &element.attributes {
+        html.push(' ');
+        push_attribute(html, attr);
+    }
+    html.push('>');
+    if is_void_element(&element.tag) {
+        return;
+    }
+    push_nodes(html, element.template_content(), false, None);
+    push_nodes(
+        html,
+        &element.children,
+        is_preformatted_element(&element.tag),
+        Some(&*element.tag).filter(|tag| is_raw_text_element(tag)),
+    );
+    html.push_str("</");
+    html.push_str(&element.tag);
+    html.push('>');
+}
+
+fn push_attribute(html: &mut String, attr: &HtmlAttribute<'_>) {
+    html.push_str(&attr.name);
+    let value = match attr.value() {
+        Some(value) if !contains(BOOLEAN_ATTRIBUTES, &attr.name) => value,
+        _ => return,
+    };
+
+    html.push('=');
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|ch| ch.is_ascii_whitespace() || matches!(ch, '"' | '\'' | '=' | '<' | '>' | '`'));
+    if !needs_quotes {
+        html.push_str(&escape_text(value));
+    } else if value.contains('"') && !value.contains('\'') {
+        html.push('\'');
+        html.push_str(&value.replace('&', "&amp;"));
+        html.push('\'');
+    } else {
+        html.push('"');
+        html.push_str(&escape_attribute(value));
+        html.push('"');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_document, parse_html};
+
+    #[test]
+    fn minify_html() {
+        let html = r#"
+            <div class="a b" id="main">
+                <!-- drop me -->
+                <p>  Hello,
+                    <b>big</b> <i>world</i>  </p>
+                <input type="checkbox" checked="checked" disabled value="">
+                <pre>  keep   this </pre>
+            </div>
+        "#;
+        let elements = parse_html(html).unwrap();
+        assert_eq!(
+            minify(&elements),
+            "<div class=\"a b\" id=main><p> Hello, <b>big</b> <i>world</i></p><input type=checkbox checked disabled value=\"\"><pre>  keep   this </pre></div>"
+        );
+        let elements = parse_html("<listing>  keep   <b>this</b>\n</listing>").unwrap();
+        assert_eq!(
+            minify(&elements),
+            "<listing>  keep   <b>this</b>\n</listing>"
+        );
+
+        // Whitespace between inline elements renders, so one space stays
+        let elements = parse_html("<p><span>a</span>\n    <span>b</span>\n</p>\n<p>c</p>").unwrap();
+        assert_eq!(
+            minify(&elements),
+            "<p><span>a</span> <span>b</span></p><p>c</p>"
+        );
+
+        let elements = parse_html("<div><template>\n  <p>x</p>\n</template></div>").unwrap();
+        assert_eq!(
+            minify(&elements),
+            "<div><template><p>x</p></template></div>"
+        );
+    }
+
+    #[test]
+    fn minify_document_quotes() {
+        let document =
+            parse_document("<!DOCTYPE html><a title='say \"hi\"' href=\"/x?a=b\">x</a>").unwrap();
+        assert_eq!(
+            minify_document(&document),
+            "<!DOCTYPE html><a title='say \"hi\"' href=\"/x?a=b\">x</a>"
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use alloc::borrow::Cow;
+
+/// The namespace an element belongs to. Everything is HTML except the
+/// contents of inline `<svg>` and `<math>`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Namespace {
+    #[default]
+    Html,
+    Svg,
+    MathMl,
+}
+
+impl Namespace {
+    pub fn url(self) -> &'static str {
+        match self {
+            Namespace::Html => "http://www.w3.org/1999/xhtml",
+            Namespace::Svg => "http://www.w3.org/2000/svg",
+            Namespace::MathMl => "http://www.w3.org/1998/Math/MathML",
+        }
+    }
+
+    /// The namespace of a `tag` element opened inside an element of this
+    /// namespace named `parent`.
+    pub(crate) fn for_child(self, parent: &str, tag: &str) -> Namespace {
+        if tag.eq_ignore_ascii_case("svg") {
+            return Namespace::Svg;
+        }
+        if tag.eq_ignore_ascii_case("math") {
+            return Namespace::MathMl;
+        }
+        match self {
+            Namespace::Html => Namespace::Html,
+            _ if is_integration_point(self, parent) => Namespace::Html,
+            namespace => namespace,
+        }
+    }
+}
+
+/// Foreign elements whose children are HTML again.
+pub(crate) fn is_integration_point(namespace: Namespace, tag: &str) -> bool {
+    let tags: &[&str] = match namespace {
+        Namespace::Html => &[],
+        Namespace::Svg => &["desc", "foreignObject", "title"],
+        Namespace::MathMl => &["annotation-xml", "mi", "mn", "mo", "ms", "mtext"],
+    };
+    tags.iter()
+        .any(|candidate| candidate.eq_ignore_ascii_case(tag))
+}
+
+// SVG names that are mixed case, which HTML would otherwise lowercase
+const SVG_TAGS: &[&str] = &[
+    "altGlyph",
+    "altGlyphDef",
+    "altGlyphItem",
+    "animateColor",
+    "animateMotion",
+    "animateTransform",
+    "clipPath",
+    "feBlend",
+    "feColorMatrix",
+    "feComponentTransfer",
+    "feComposite",
+    "feConvolveMatrix",
+    "feDiffuseLighting",
+    "feDisplacementMap",
+    "feDistantLight",
+    "feDropShadow",
+    "feFlood",
+    "feFuncA",
+    "feFuncB",
+    "feFuncG",
+    "feFuncR",
+    "feGaussianBlur",
+    "feImage",
+    "feMerge",
+    "feMergeNode",
+    "feMorphology",
+    "feOffset",
+    "fePointLight",
+    "feSpecularLighting",
+    "feSpotLight",
+    "feTile",
+    "feTurbulence",
+    "foreignObject",
+    "glyphRef",
+    "linearGradient",
+    "radialGradient",
+    "textPath",
+];
+
+const SVG_ATTRIBUTES: &[&str] = &[
+    "attributeName",
+    "attributeType",
+    "baseFrequency",
+    "baseProfile",
+    "calcMode",
+    "clipPathUnits",
+    "diffuseConstant",
+    "edgeMode",
+    "filterUnits",
+    "glyphRef",
+    "gradientTransform",
+    "gradientUnits",
+    "kernelMatrix",
+    "kernelUnitLength",
+    "keyPoints",
+    "keySplines",
+    "keyTimes",
+    "lengthAdjust",
+    "limitingConeAngle",
+    "markerHeight",
+    "markerUnits",
+    "markerWidth",
+    "maskContentUnits",
+    "maskUnits",
+    "numOctaves",
+    "pathLength",
+    "patternContentUnits",
+    "patternTransform",
+    "patternUnits",
+    "pointsAtX",
+    "pointsAtY",
+    "pointsAtZ",
+    "preserveAlpha",
+    "preserveAspectRatio",
+    "primitiveUnits",
+    "refX",
+    "refY",
+    "repeatCount",
+    "repeatDur",
+    "requiredExtensions",
+    "requiredFeatures",
+    "specularConstant",
+    "specularExponent",
+    "spreadMethod",
+    "startOffset",
+    "stdDeviation",
+    "stitchTiles",
+    "surfaceScale",
+    "systemLanguage",
+    "tableValues",
+    "targetX",
+    "targetY",
+    "textLength",
+    "viewBox",
+    "viewTarget",
+    "xChannelSelector",
+    "yChannelSelector",
+    "zoomAndPan",
+];
+
+fn lookup(names: &'static [&'static str], name: &str) -> Option<&'static str> {
+    names
+        .iter()
+        .find(|candidate| candidate.eq_ignore_ascii_case(name))
+        .copied()
+}
+
+/// The correctly cased name of a foreign element, e.g. `foreignObject` for
+/// `<foreignobject>`.
+pub(crate) fn adjust_tag(namespace: Namespace, tag: Cow<'_, str>) -> Cow<'_, str> {
+    match namespace {
+        Namespace::Svg => lookup(SVG_TAGS, &tag).map_or(tag, Cow::Borrowed),
+        _ => tag,
+    }
+}
+
+pub(crate) fn adjust_attribute(namespace: Namespace, name: Cow<'_, str>) -> Cow<'_, str> {
+    match namespace {
+        Namespace::Svg => lookup(SVG_ATTRIBUTES, &name).map_or(name, Cow::Borrowed),
+        Namespace::MathMl if name.eq_ignore_ascii_case("definitionURL") => {
+            Cow::Borrowed("definitionURL")
+        }
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn child_namespaces() {
+        assert_eq!(Namespace::Html.for_child("div", "svg"), Namespace::Svg);
+        assert_eq!(Namespace::Svg.for_child("svg", "circle"), Namespace::Svg);
+        assert_eq!(
+            Namespace::Svg.for_child("foreignObject", "p"),
+            Namespace::Html
+        );
+        assert_eq!(Namespace::MathMl.for_child("mi", "b"), Namespace::Html);
+        assert_eq!(
+            adjust_tag(Namespace::Svg, "lineargradient".into()),
+            "linearGradient"
+        );
+        assert_eq!(
+            adjust_tag(Namespace::Html, "foreignobject".into()),
+            "foreignobject"
+        );
+        assert_eq!(
+            adjust_attribute(Namespace::Svg, "VIEWBOX".into()),
+            "viewBox"
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use alloc::borrow::Cow;
+
+use crate::prelude::*;
+use crate::{HtmlDocument, HtmlElement, HtmlNode};
+
+// Elements around which whitespace doesn't render
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "base",
+    "blockquote",
+    "body",
+    "caption",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "li",
+    "link",
+    "main",
+    "meta",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "ul",
+];
+
+// Elements whose whitespace is content
+const PRESERVED: &[&str] = &["pre", "textarea", "script", "style"];
+
+/// What [`HtmlDocument::normalize_with`] does. Everything is on by default.
+#[derive(Debug, Clone)]
+pub struct NormalizeOptions {
+    /// Join adjacent text nodes into one.
+    pub merge_text: bool,
+    /// Replace runs of whitespace in text with a single space.
+    pub collapse_whitespace: bool,
+    /// Drop whitespace-only text next to block elements, like the
+    /// indentation between `<li>`s.
+    pub remove_block_whitespace: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self {
+            merge_text: true,
+            collapse_whitespace: true,
+            remove_block_whitespace: true,
+        }
+    }
+}
+
+impl HtmlDocument<'_> {
+    /// Tidies text nodes with the default [`NormalizeOptions`]. Nothing
+    /// inside `pre`, `textarea`, `script` or `style` is touched.
+    pub fn normalize(&mut self) {
+        self.normalize_with(&NormalizeOptions::default());
+    }
+
+    pub fn normalize_with(&mut self, options: &NormalizeOptions) {
+        normalize_nodes(self.nodes_mut(), true, options);
+    }
+}
+
+impl HtmlElement<'_> {
+    /// Like [`HtmlDocument::normalize`], for the element's contents.
+    pub fn normalize(&mut self) {
+        self.normalize_with(&NormalizeOptions::default());
+    }
+
+    pub fn normalize_with(&mut self, options: &NormalizeOptions) {
+        if is_one_of(self, PRESERVED) {
+            return;
+        }
+        let block = is_one_of(self, BLOCK_ELEMENTS);
+        normalize_nodes(self.template_content_mut(), true, options);
+        normalize_nodes(self.children_vec_mut(), block, options);
+    }
+}
+
+// `block` says whether the nodes sit directly in a block, so that
+// whitespace at either end of the list doesn't render
+fn normalize_nodes(nodes: &mut Vec<HtmlNode<'_>>, block: bool, options: &NormalizeOptions) {
+    if options.merge_text {
+        merge_text(nodes);
+    }
+    for node in nodes.iter_mut() {
+        match node {
+            HtmlNode::Element(element) => element.normalize_with(options),
+            HtmlNode::Text(text) if options.collapse_whitespace => collapse(text),
+            _ => {}
+        }
+    }
+    if options.remove_block_whitespace {
+        let is_block = |node: Option<&HtmlNode<'_>>| match node {
+            None => block,
+            Some(HtmlNode::Element(element)) => is_one_of(element, BLOCK_ELEMENTS),
+            Some(HtmlNode::Comment(_) | HtmlNode::Doctype(_)) => true,
+            Some(_) => false,
+        };
+        let mut index = 0;
+        while index < nodes.len() {
+            let blank =
+                matches!(&nodes[index], HtmlNode::Text(text) if text.trim_ascii().is_empty());
+            let removable = blank
+                && (is_block(index.checked_sub(1).map(|before| &nodes[before]))
+                    || is_block(nodes.get(index + 1)));
+            if removable {
+                nodes.remove(index);
+            } else {
+                index += 1;
+            }
+        }
+    }
+}
+
+fn merge_text(nodes: &mut Vec<HtmlNode<'_>>) {
+    let mut index = 1;
+    while index < nodes.len() {
+        if let (HtmlNode::Text(before), HtmlNode::Text(after)) = (&nodes[index - 1], &nodes[index])
+        {
+            let merged = format!("{before}{after}");
+            nodes[index - 1] = HtmlNode::Text(Cow::Owned(merged));
+            nodes.remove(index);
+        } else {
+            index += 1;
+        }
+    }
+}
+
+fn collapse(text: &mut Cow<'_, str>) {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_whitespace = false;
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            if !in_whitespace {
+                collapsed.push(' ');
+            }
+            in_whitespace = true;
+        } else {
+            collapsed.push(c);
+            in_whitespace = false;
+        }
+    }
+    if collapsed != **text {
+        *text = Cow::Owned(collapsed);
+    }
+}
+
+fn is_one_of(element: &HtmlElement<'_>, tags: &[&str]) -> bool {
+    tags.iter()
+        .any(|tag| element.tag().eq_ignore_ascii_case(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{document_to_string, parse_document_with, HtmlNode, ParseOptions};
+
+    #[test]
+    fn normalizes_text() {
+        let html = "<ul>\n  <li>one  \n two</li>\n  <li><b>a</b> <i>b</i></li>\n</ul>\n<pre>  x\n  y</pre>";
+        let options = ParseOptions {
+            preserve_source: true,
+            ..Default::default()
+        };
+        let mut document = parse_document_with(html, &options).unwrap();
+        document.normalize();
+        assert_eq!(
+            document_to_string(document.clone()),
+            "<ul><li>one two</li><li><b>a</b> <i>b</i></li></ul><pre>  x\n  y</pre>"
+        );
+
+        let mut list = document.elements().next().unwrap().clone();
+        list.add_text("c");
+        list.add_text("d");
+        list.normalize();
+        assert_eq!(list.children()[2], HtmlNode::Text("cd".into()));
+    }
+}
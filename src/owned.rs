@@ -0,0 +1,329 @@
+use alloc::borrow::Cow;
+
+use crate::atom::Name;
+use crate::prelude::*;
+use crate::{
+    Document, HtmlAttribute, HtmlDoctype, HtmlDocument, HtmlElement, HtmlError, HtmlNode, Namespace,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HtmlAttributeOwned {
+    name: Name,
+    value: Option<String>,
+}
+
+impl HtmlAttributeOwned {
+    pub fn new(name: impl Into<String>, value: Option<String>) -> Self {
+        Self {
+            name: Name::from(name.into()),
+            value,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    pub fn to_borrowed(&self) -> HtmlAttribute<'_> {
+        HtmlAttribute::new(&*self.name, self.value.as_deref())
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HtmlElementOwned {
+    tag: Name,
+    attributes: Vec<HtmlAttributeOwned>,
+    children: Vec<HtmlNodeOwned>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    namespace: Namespace,
+    #[cfg_attr(feature = "serde", serde(default))]
+    content: Vec<HtmlNodeOwned>,
+}
+
+impl HtmlElementOwned {
+    pub fn new(tag: impl Into<String>) -> Self {
+        Self {
+            tag: Name::from(tag.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn namespace(&self) -> Namespace {
+        self.namespace
+    }
+
+    pub fn template_content(&self) -> &[HtmlNodeOwned] {
+        &self.content
+    }
+
+    pub fn attributes(&self) -> &[HtmlAttributeOwned] {
+        &self.attributes
+    }
+
+    pub fn children(&self) -> &[HtmlNodeOwned] {
+        &self.children
+    }
+
+    pub fn add_attribute(&mut self, name: impl Into<String>, value: Option<String>) {
+        self.attributes.push(HtmlAttributeOwned::new(name, value));
+    }
+
+    pub fn add_child(&mut self, child: impl Into<HtmlNodeOwned>) {
+        self.children.push(child.into());
+    }
+
+    /// Adds to a `<template>`'s contents, which are kept apart from its
+    /// children.
+    pub fn add_template_content(&mut self, node: impl Into<HtmlNodeOwned>) {
+        self.content.push(node.into());
+    }
+
+    /// Borrows the owned tree, e.g. to serialize it.
+    pub fn to_borrowed(&self) -> HtmlElement<'_> {
+        HtmlElement {
+            tag: Cow::Borrowed(&self.tag),
+            attributes: self
+                .attributes
+                .iter()
+                .map(HtmlAttributeOwned::to_borrowed)
+                .collect(),
+            children: self
+                .children
+                .iter()
+                .map(HtmlNodeOwned::to_borrowed)
+                .collect(),
+            namespace: self.namespace,
+            content: self
+                .content
+                .iter()
+                .map(HtmlNodeOwned::to_borrowed)
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HtmlDoctypeOwned {
+    pub name: Option<String>,
+    pub public_id: Option<String>,
+    pub system_id: Option<String>,
+    pub force_quirks: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HtmlNodeOwned {
+    Element(HtmlElementOwned),
+    Text(String),
+    CData(String),
+    Comment(String),
+    Doctype(HtmlDoctypeOwned),
+}
+
+impl HtmlNodeOwned {
+    pub fn as_element(&self) -> Option<&HtmlElementOwned> {
+        match self {
+            HtmlNodeOwned::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    pub fn to_borrowed(&self) -> HtmlNode<'_> {
+        match self {
+            HtmlNodeOwned::Element(element) => HtmlNode::Element(element.to_borrowed()),
+            HtmlNodeOwned::Text(text) => HtmlNode::Text(text.into()),
+            HtmlNodeOwned::CData(text) => HtmlNode::CData(text.as_str().into()),
+            HtmlNodeOwned::Comment(comment) => HtmlNode::Comment(comment.as_str().into()),
+            HtmlNodeOwned::Doctype(doctype) => HtmlNode::Doctype(HtmlDoctype::from_parts(
+                doctype.name.as_deref(),
+                doctype.public_id.as_deref(),
+                doctype.system_id.as_deref(),
+                doctype.force_quirks,
+            )),
+        }
+    }
+}
+
+impl From<HtmlElementOwned> for HtmlNodeOwned {
+    fn from(element: HtmlElementOwned) -> Self {
+        HtmlNodeOwned::Element(element)
+    }
+}
+
+impl HtmlAttribute<'_> {
+    pub fn to_owned(&self) -> HtmlAttributeOwned {
+        HtmlAttributeOwned {
+            name: Name::new(&self.name),
+            value: self.value().map(String::from),
+        }
+    }
+
+    pub fn into_owned(self) -> HtmlAttributeOwned {
+        HtmlAttributeOwned {
+            name: self.name.into(),
+            value: self.value.map(Cow::into_owned),
+        }
+    }
+}
+
+impl HtmlElement<'_> {
+    pub fn to_owned(&self) -> HtmlElementOwned {
+        HtmlElementOwned {
+            tag: Name::new(&self.tag),
+            attributes: self
+                .attributes
+                .iter()
+                .map(HtmlAttribute::to_owned)
+                .collect(),
+            children: self.children.iter().map(HtmlNode::to_owned).collect(),
+            namespace: self.namespace,
+            content: self.content.iter().map(HtmlNode::to_owned).collect(),
+        }
+    }
+
+    pub fn into_owned(self) -> HtmlElementOwned {
+        HtmlElementOwned {
+            tag: self.tag.into(),
+            attributes: self
+                .attributes
+                .into_iter()
+                .map(HtmlAttribute::into_owned)
+                .collect(),
+            children: self
+                .children
+                .into_iter()
+                .map(HtmlNode::into_owned)
+                .collect(),
+            namespace: self.namespace,
+            content: self.content.into_iter().map(HtmlNode::into_owned).collect(),
+        }
+    }
+}
+
+impl HtmlDoctype<'_> {
+    pub fn to_owned(&self) -> HtmlDoctypeOwned {
+        HtmlDoctypeOwned {
+            name: self.name().map(String::from),
+            public_id: self.public_id().map(String::from),
+            system_id: self.system_id().map(String::from),
+            force_quirks: self.force_quirks(),
+        }
+    }
+}
+
+impl HtmlNode<'_> {
+    pub fn to_owned(&self) -> HtmlNodeOwned {
+        match self {
+            HtmlNode::Element(element) => HtmlNodeOwned::Element(element.to_owned()),
+            HtmlNode::Text(text) => HtmlNodeOwned::Text(text.to_string()),
+            HtmlNode::CData(text) => HtmlNodeOwned::CData(text.to_string()),
+            HtmlNode::Comment(comment) => HtmlNodeOwned::Comment(comment.to_string()),
+            HtmlNode::Doctype(doctype) => HtmlNodeOwned::Doctype(doctype.to_owned()),
+        }
+    }
+
+    pub fn into_owned(self) -> HtmlNodeOwned {
+        match self {
+            HtmlNode::Element(element) => HtmlNodeOwned::Element(element.into_owned()),
+            HtmlNode::Text(text) => HtmlNodeOwned::Text(text.into_owned()),
+            node => node.to_owned(),
+        }
+    }
+}
+
+// What the language bindings keep: a tree that doesn't borrow the input,
+// borrowed back whenever it's queried or serialized
+pub(crate) fn borrow_nodes(nodes: &[HtmlNodeOwned]) -> HtmlDocument<'_> {
+    HtmlDocument {
+        nodes: nodes.iter().map(HtmlNodeOwned::to_borrowed).collect(),
+        ..Default::default()
+    }
+}
+
+pub(crate) fn select_owned(
+    nodes: &[HtmlNodeOwned],
+    selector: &str,
+) -> Result<Vec<HtmlElementOwned>, HtmlError> {
+    let document = Document::from_nodes(borrow_nodes(nodes).nodes);
+    let matches = document.select(selector)?;
+    Ok(matches
+        .filter_map(|id| match document.to_node(id).into_owned() {
+            HtmlNodeOwned::Element(element) => Some(element),
+            _ => None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{html_to_string, parse_html};
+
+    fn parse_owned(html: &str) -> Vec<HtmlElementOwned> {
+        let source = html.to_string();
+        parse_html(&source)
+            .unwrap()
+            .into_iter()
+            .map(HtmlElement::into_owned)
+            .collect()
+    }
+
+    #[test]
+    fn owned_outlives_source() {
+        let elements = parse_owned("<div id=\"main\"><p>Hello</p><!-- note --></div>");
+        let root = &elements[0];
+        assert_eq!(root.tag(), "div");
+        assert_eq!(root.attributes()[0].value(), Some("main"));
+        assert_eq!(root.children().len(), 2);
+        assert_eq!(root.children()[1], HtmlNodeOwned::Comment(" note ".into()));
+    }
+
+    #[test]
+    fn owned_round_trip() {
+        let html = "<div id=\"main\"><p>Hello <b>world</b></p></div>";
+        let elements = parse_html(html).unwrap();
+        let owned = elements[0].to_owned();
+        assert_eq!(owned.to_borrowed(), elements[0]);
+        assert_eq!(html_to_string(vec![owned.to_borrowed()]), html);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let html = "<!DOCTYPE html><div id=\"main\" hidden><p>Hello</p><!-- note --></div>";
+        let document = crate::parse_document(html).unwrap();
+
+        let json = serde_json::to_string(&document).unwrap();
+        let borrowed: crate::HtmlDocument = serde_json::from_str(&json).unwrap();
+        assert_eq!(borrowed, document);
+
+        let elements = parse_owned(html);
+        let json = serde_json::to_string(&elements).unwrap();
+        let owned: Vec<HtmlElementOwned> = serde_json::from_str(&json).unwrap();
+        assert_eq!(owned, elements);
+
+        // Quotes and backslashes come back as JSON escapes, which can't be
+        // borrowed from the JSON text
+        let html = "<!DOCTYPE html SYSTEM \"C:\\dtd\"><p title='\"a\"'>\"b\"<!-- \"c\" --></p>";
+        let document = crate::parse_document(html).unwrap();
+        let json = serde_json::to_string(&document).unwrap();
+        let borrowed: crate::HtmlDocument = serde_json::from_str(&json).unwrap();
+        assert_eq!(borrowed, document);
+        assert_eq!(
+            borrowed.doctype().and_then(|doctype| doctype.system_id()),
+            Some("C:\\dtd")
+        );
+    }
+}
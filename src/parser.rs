@@ -1,149 +1,1151 @@
-use crate::{reader::StrReader, HtmlElement, HtmlError};
+use alloc::borrow::Cow;
+use alloc::collections::VecDeque;
+use core::ops::Range;
+
+use crate::prelude::*;
+use crate::{
+    arena::Arena,
+    decode_entities,
+    namespace::{adjust_attribute, adjust_tag},
+    reader::{ReadError, Reader, StrReader},
+    Diagnostic, DuplicateAttributes, HtmlAttribute, HtmlDoctype, HtmlDocument, HtmlElement,
+    HtmlError, HtmlNode, HtmlWarning, Limit, Namespace, ParseMode, ParseOptions, Span,
+};
 
 #[derive(Debug, PartialEq)]
 pub enum HtmlAst<'a> {
     StartTag(&'a str),
     Attribute(&'a str, Option<&'a str>),
-    EndTag,
+    EndTag(&'a str),
     Text(&'a str),
+    Doctype(HtmlDoctype<'a>),
+    CData(&'a str),
+    Comment(&'a str),
+    /// The preceding start tag ended with `/>`
+    SelfClosing,
 }
 
 pub fn tokenize_html<'a>(data: &'a str) -> Result<Vec<HtmlAst<'a>>, HtmlError> {
-    let mut reader = StrReader::new(data);
-    let mut ast = Vec::new();
+    Tokens::new(data).collect()
+}
+
+pub fn tokenize_html_with<'a>(
+    data: &'a str,
+    options: &ParseOptions,
+) -> Result<(Vec<HtmlAst<'a>>, Vec<HtmlWarning<'a>>), HtmlError> {
+    let mut tokens = Tokens::with_options(data, options);
+    let ast = tokens.by_ref().collect::<Result<_, _>>()?;
+    Ok((ast, tokens.tokenizer.warnings))
+}
+
+pub fn tokenize_html_spanned(data: &str) -> Result<Vec<(HtmlAst<'_>, Span)>, HtmlError> {
+    let mut tokens = Tokens::new(data);
+    core::iter::from_fn(|| tokens.next_spanned()).collect()
+}
+
+/// Lazily tokenizes its input, so callers that only need the start of a
+/// document can stop early. Iteration ends after the first error.
+pub struct Tokens<'a> {
+    tokenizer: Tokenizer<'a>,
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(data: &'a str) -> Self {
+        Self::with_options(data, &ParseOptions::default())
+    }
+
+    pub fn with_options(data: &'a str, options: &ParseOptions) -> Self {
+        Self {
+            tokenizer: Tokenizer::new(data, options),
+        }
+    }
+
+    /// Warnings for the input consumed so far.
+    pub fn warnings(&self) -> &[HtmlWarning<'a>] {
+        &self.tokenizer.warnings
+    }
 
-    loop {
-        // Skip whitespace
-        reader.skip_while(|ch| ch.is_whitespace());
+    /// The next token together with the part of the input it was read from.
+    /// A start tag's span covers its attributes too.
+    pub fn next_spanned(&mut self) -> Option<Result<(HtmlAst<'a>, Span), HtmlError>> {
+        self.tokenizer
+            .next_token()
+            .map(|token| token.map(|(token, span)| (token, span.into())))
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<HtmlAst<'a>, HtmlError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.tokenizer
+            .next_token()
+            .map(|token| token.map(|(token, _)| token))
+    }
+}
 
-        match reader.seek() {
-            Some('<') => {
-                reader.skip(1);
+/// What the tokenizer knows about the elements open where it stopped, for
+/// a tokenizer picking up the rest of the input.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct TokenizerState {
+    // How many elements that keep their whitespace are open
+    pub(crate) preformatted: usize,
+    // How many `svg` and `math` elements are open, inside which `style` or
+    // `title` are ordinary elements
+    foreign: usize,
+    // The raw text or RCDATA element whose contents come next
+    raw_text: Option<&'static str>,
+}
 
-                if reader.seek() == Some('/') {
-                    ast.push(HtmlAst::EndTag);
+impl TokenizerState {
+    fn open(&mut self, tag: &str, self_closing: bool) {
+        if is_preformatted_element(tag) {
+            self.preformatted += 1;
+        }
+        if tag.eq_ignore_ascii_case("svg") || tag.eq_ignore_ascii_case("math") {
+            self.foreign += usize::from(!self_closing);
+        } else if self.foreign == 0 {
+            self.raw_text = RAW_TEXT_ELEMENTS
+                .iter()
+                .chain(RCDATA_ELEMENTS)
+                .find(|raw| raw.eq_ignore_ascii_case(tag))
+                .copied();
+        }
+    }
+
+    fn close(&mut self, tag: &str) {
+        if is_preformatted_element(tag) {
+            self.preformatted = self.preformatted.saturating_sub(1);
+        }
+        if tag.eq_ignore_ascii_case("svg") || tag.eq_ignore_ascii_case("math") {
+            self.foreign = self.foreign.saturating_sub(1);
+        }
+    }
+}
+
+type SpannedToken<'a> = (HtmlAst<'a>, Range<usize>);
+type Construct<'a> = (Range<usize>, Vec<HtmlAst<'a>>);
+
+// Reads through the `Reader` trait, though tokens borrow from the input, so
+// the reader has to be over a `str`
+pub(crate) struct Tokenizer<'a, R = StrReader<'a>> {
+    source: &'a str,
+    reader: R,
+    options: ParseOptions,
+    // Tokens read but not yet handed out, with their byte range in `source`
+    pending: VecDeque<SpannedToken<'a>>,
+    pub(crate) warnings: Vec<HtmlWarning<'a>>,
+    // Where in `source` each warning was found
+    warning_spans: Vec<Span>,
+    token_count: usize,
+    // Where the construct being read starts
+    construct_start: usize,
+    pub(crate) state: TokenizerState,
+    // How much of the text `source` starts with was already searched for
+    // its end, by the tokenizer of an earlier chunk
+    pub(crate) text_scanned: usize,
+    // Step over attributes without queueing them, for `StartTags`
+    pub(crate) lazy_attributes: bool,
+    done: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub(crate) fn new(data: &'a str, options: &ParseOptions) -> Self {
+        Tokenizer::with_reader(StrReader::new(data), options)
+    }
+}
+
+impl<'a, R: Reader<'a, Item = char, Slice = str>> Tokenizer<'a, R> {
+    // `reader` is expected at the start of its input, where offsets count from
+    pub(crate) fn with_reader(reader: R, options: &ParseOptions) -> Self {
+        Self {
+            source: reader.rest(),
+            reader,
+            options: options.clone(),
+            pending: VecDeque::new(),
+            warnings: Vec::new(),
+            warning_spans: Vec::new(),
+            token_count: 0,
+            construct_start: 0,
+            state: TokenizerState::default(),
+            text_scanned: 0,
+            lazy_attributes: false,
+            done: false,
+        }
+    }
+
+    pub(crate) fn next_token(&mut self) -> Option<Result<SpannedToken<'a>, HtmlError>> {
+        while self.pending.is_empty() && !self.done {
+            match self.step() {
+                Ok(more) => self.done = !more,
+                Err(error) => {
+                    // Half-read constructs aren't handed out
+                    self.pending.clear();
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+
+    /// Reads the constructs that are known to be complete, returning their
+    /// tokens and the number of bytes they span. Unless `eof` is set, a
+    /// construct reaching the end of the input (or failing to parse there)
+    /// may still grow and is left for a later call with more data.
+    pub(crate) fn read_complete(
+        &mut self,
+        eof: bool,
+    ) -> Result<(Vec<HtmlAst<'a>>, usize), HtmlError> {
+        let mut tokens = Vec::new();
+        let mut consumed = 0;
+        while let Some((span, construct)) = self.next_complete(eof)? {
+            consumed = span.end;
+            tokens.extend(construct);
+        }
+        if eof {
+            consumed = self.source.len();
+        }
+        Ok((tokens, consumed))
+    }
+
+    /// Reads one complete construct, as described for `read_complete`,
+    /// together with the range of input it covers.
+    pub(crate) fn next_complete(&mut self, eof: bool) -> Result<Option<Construct<'a>>, HtmlError> {
+        let start = self.offset();
+        let state = self.state;
+        match self.step() {
+            Ok(true) if eof || self.offset() < self.source.len() => {
+                let tokens = self.pending.drain(..).map(|(token, _)| token).collect();
+                Ok(Some((start..self.offset(), tokens)))
+            }
+            Err(error) if eof => Err(error),
+            _ => {
+                // The construct is read again along with the next chunk, but
+                // text only grows, so most of it needn't be searched again
+                self.state = state;
+                self.text_scanned = match (self.pending.front(), state.raw_text) {
+                    (Some((HtmlAst::Text(text), _)), raw_text) => scanned_len(text, raw_text),
+                    _ => 0,
+                };
+                Ok(None)
+            }
+        }
+    }
+
+    // Queues a token spanning the construct read so far
+    fn emit(&mut self, token: HtmlAst<'a>) {
+        self.emit_spanned(token, self.construct_start..self.offset());
+    }
+
+    fn emit_spanned(&mut self, token: HtmlAst<'a>, span: Range<usize>) {
+        self.pending.push_back((token, span));
+    }
+
+    fn warn(&mut self, warning: HtmlWarning<'a>, span: Span) {
+        self.warnings.push(warning);
+        self.warning_spans.push(span);
+    }
+
+    pub(crate) fn take_diagnostics(&mut self) -> Vec<Diagnostic<'a>> {
+        let spans = core::mem::take(&mut self.warning_spans);
+        core::mem::take(&mut self.warnings)
+            .into_iter()
+            .zip(spans)
+            .map(|(warning, span)| Diagnostic::new(warning, span))
+            .collect()
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.reader.offset()
+    }
+
+    // The next byte, which may be part of a longer character
+    fn peek_byte(&self) -> Option<u8> {
+        self.reader.rest().as_bytes().first().copied()
+    }
+
+    fn starts_with_ignore_case(&self, prefix: &str) -> bool {
+        self.reader
+            .rest()
+            .as_bytes()
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix.as_bytes()))
+    }
+
+    fn skip_ascii_whitespace(&mut self) {
+        let rest = self.reader.rest().as_bytes();
+        let len = rest
+            .iter()
+            .position(|byte| !byte.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        self.reader.skip(len);
+    }
+
+    // The current construct runs off the end of the input
+    fn unterminated(&self, error: ReadError) -> HtmlError {
+        HtmlError::ReaderError(error, Span::new(self.construct_start, self.source.len()))
+    }
+
+    // Reads the next construct from the input, returning false at the end
+    fn step(&mut self) -> Result<bool, HtmlError> {
+        let pending = self.pending.len();
+        let more = self.read_construct()?;
+        self.token_count += self.pending.len() - pending;
+        if exceeds(self.options.max_tokens, self.token_count) {
+            let span = Span::new(self.construct_start, self.offset());
+            return Err(HtmlError::LimitExceeded(Limit::Tokens, span));
+        }
+        Ok(more)
+    }
+
+    fn read_construct(&mut self) -> Result<bool, HtmlError> {
+        // The contents of a `<script>` or `<textarea>` are text up to its
+        // end tag, whatever markup they seem to hold
+        let scanned = core::mem::take(&mut self.text_scanned);
+        if let Some(tag) = self.state.raw_text.take() {
+            self.construct_start = self.offset();
+            let rest = self.reader.rest();
+            let len = if tag == "plaintext" {
+                // Anything fed later is text too
+                self.state.raw_text = Some(tag);
+                rest.len()
+            } else {
+                find_end_tag(&rest[scanned..], tag).map_or(rest.len(), |len| scanned + len)
+            };
+            if len > 0 {
+                self.reader.skip(len);
+                self.emit(HtmlAst::Text(&rest[..len]));
+                return Ok(true);
+            }
+        }
+
+        let start = self.offset();
+        self.construct_start = start;
+        match self.peek_byte() {
+            Some(b'<') if opens_markup(self.reader.rest()) => {
+                self.reader.skip(1);
+
+                if self.peek_byte() == Some(b'/') {
+                    self.reader.skip(1);
+                    let tag = self.read_name();
                     // Skip until closing bracket
-                    reader.read_until('>').map_err(HtmlError::ReaderError)?;
-                    reader.skip(1);
-                } else if reader.seek() == Some('!') {
-                    // Skip comment
-                    reader.skip(1);
-                    reader.skip_while(|ch| ch != '>');
-                    reader.skip(1);
+                    self.reader
+                        .read_until('>')
+                        .map_err(|error| self.unterminated(error))?;
+                    self.reader.skip(1);
+                    self.state.close(tag);
+                    self.emit(HtmlAst::EndTag(tag));
+                } else if self.reader.starts_with("!--") {
+                    self.comment();
+                } else if self.reader.starts_with("![CDATA[") {
+                    self.cdata()?;
+                } else if self.starts_with_ignore_case("!doctype") {
+                    self.doctype()?;
+                } else if matches!(self.peek_byte(), Some(b'!' | b'?')) {
+                    self.bogus_comment();
                 } else {
-                    let tag = reader.read_until('>').map_err(HtmlError::ReaderError)?;
-                    reader.skip(1);
+                    let index = self.pending.len();
+                    self.start_tag()?;
+                    self.pending[index].1 = start..self.offset();
+                    let self_closing =
+                        matches!(self.pending.back(), Some((HtmlAst::SelfClosing, _)));
+                    if let HtmlAst::StartTag(tag) = self.pending[index].0 {
+                        self.state.open(tag, self_closing);
+                    }
+                }
+            }
+            Some(_) => {
+                let rest = self.reader.rest();
+                let len = memchr::memchr_iter(b'<', &rest.as_bytes()[scanned..])
+                    .map(|index| scanned + index)
+                    .find(|&index| index > 0 && opens_markup(&rest[index..]))
+                    .unwrap_or(rest.len());
+                self.reader.skip(len);
+                self.emit(HtmlAst::Text(&rest[..len]));
+            }
+            None => return Ok(false),
+        }
+        Ok(true)
+    }
 
-                    let (tag, attrs) = match tag.find(' ') {
-                        Some(i) => {
-                            let (tag, attrs) = tag.split_at(i);
-                            (tag, Some(attrs))
-                        }
-                        None => (tag, None),
-                    };
+    fn comment(&mut self) {
+        self.reader.skip("!--".len());
+        let rest = self.reader.rest();
 
-                    ast.push(HtmlAst::StartTag(tag));
+        // `<!-->` and `<!--->` are complete, empty comments
+        for abrupt in [">", "->"] {
+            if rest.starts_with(abrupt) {
+                self.reader.skip(abrupt.len());
+                self.emit(HtmlAst::Comment(""));
+                return;
+            }
+        }
 
-                    if let Some(attrs) = attrs {
-                        for attr in attrs.trim().split(' ') {
-                            println!("attr: {:?}", attr);
-                            let (name, value) = match attr.find('=') {
-                                Some(i) => {
-                                    let (name, value) = attr.split_at(i);
-                                    (name, Some(&value[1..]))
-                                }
-                                None => (attr, None),
-                            };
+        match self.reader.read_until_slice("-->") {
+            Ok(comment) => {
+                self.reader.skip("-->".len());
+                self.emit(HtmlAst::Comment(comment));
+            }
+            Err(_) => {
+                // Unterminated comments run until the end of input
+                self.reader.skip(rest.len());
+                self.emit(HtmlAst::Comment(rest));
+            }
+        }
+    }
 
-                            let name = name.trim();
-                            let value = value.map(|v| v.trim().trim_matches('"'));
-                            ast.push(HtmlAst::Attribute(name, value));
+    // `<!...>` and `<?...>` constructs are treated as comments
+    fn bogus_comment(&mut self) {
+        let rest = self.reader.rest();
+        let start = if rest.starts_with('!') { 1 } else { 0 };
+        let len = rest.find('>').unwrap_or(rest.len());
+        self.reader.skip((len + 1).min(rest.len()));
+        self.emit(HtmlAst::Comment(&rest[start..len]));
+    }
+
+    fn doctype(&mut self) -> Result<(), HtmlError> {
+        self.reader.skip("!doctype".len());
+        let source = self
+            .reader
+            .read_until('>')
+            .map_err(|error| self.unterminated(error))?;
+        self.reader.skip(1);
+        self.emit(HtmlAst::Doctype(HtmlDoctype::from_source(source)));
+        Ok(())
+    }
+
+    fn cdata(&mut self) -> Result<(), HtmlError> {
+        self.reader.skip("![CDATA[".len());
+        let text = self
+            .reader
+            .read_until_slice("]]>")
+            .map_err(|error| self.unterminated(error))?;
+        self.reader.skip("]]>".len());
+        self.emit(HtmlAst::CData(text));
+        Ok(())
+    }
+
+    fn start_tag(&mut self) -> Result<(), HtmlError> {
+        let tag = self.read_name();
+        self.emit(HtmlAst::StartTag(tag));
+        if self.lazy_attributes {
+            return self.skip_attributes();
+        }
+        // The tag's attributes so far are queued after it, so duplicates are
+        // found there rather than in a list of their own
+        let first_attribute = self.pending.len();
+        let mut count = 0;
+
+        loop {
+            self.skip_ascii_whitespace();
+
+            match self.peek_byte() {
+                Some(b'>') => {
+                    self.reader.skip(1);
+                    return Ok(());
+                }
+                Some(b'/') => {
+                    self.reader.skip(1);
+                    if self.peek_byte() == Some(b'>') {
+                        let end = self.offset() + 1;
+                        self.emit_spanned(HtmlAst::SelfClosing, end - 2..end);
+                    }
+                }
+                Some(_) => {
+                    count += 1;
+                    if exceeds(self.options.max_attributes_per_tag, count) {
+                        let span = Span::new(self.construct_start, self.offset());
+                        return Err(HtmlError::LimitExceeded(Limit::Attributes, span));
+                    }
+                    let start = self.offset();
+                    let name = self.read_name();
+                    let mut end = self.offset();
+                    self.skip_ascii_whitespace();
+
+                    let value = if self.peek_byte() == Some(b'=') {
+                        self.reader.skip(1);
+                        self.skip_ascii_whitespace();
+                        let value = self.read_attribute_value(name)?;
+                        end = self.offset();
+                        Some(value)
+                    } else {
+                        None
+                    };
+                    let span = start..end;
+                    let duplicate = self.pending.range(first_attribute..).any(|(token, _)| {
+                        matches!(token, HtmlAst::Attribute(seen, _) if seen.eq_ignore_ascii_case(name))
+                    });
+                    match self.options.duplicate_attributes {
+                        _ if !duplicate => {
+                            self.emit_spanned(HtmlAst::Attribute(name, value), span);
+                        }
+                        DuplicateAttributes::KeepAll => {
+                            self.emit_spanned(HtmlAst::Attribute(name, value), span)
+                        }
+                        DuplicateAttributes::KeepFirst => {}
+                        DuplicateAttributes::Warn => {
+                            self.warn(HtmlWarning::DuplicateAttribute { name }, span.into())
                         }
                     }
                 }
+                None => return Err(self.unterminated(ReadError::DelimNotFound)),
             }
-            Some(_) => {
-                let text = reader.read_until('<').map_err(HtmlError::ReaderError)?;
-                ast.push(HtmlAst::Text(text));
+        }
+    }
+
+    // Reads past a start tag's attributes the way `start_tag` does, but
+    // without checking for duplicates or queueing them
+    fn skip_attributes(&mut self) -> Result<(), HtmlError> {
+        let mut count = 0;
+        loop {
+            self.skip_ascii_whitespace();
+            match self.peek_byte() {
+                Some(b'>') => {
+                    self.reader.skip(1);
+                    return Ok(());
+                }
+                Some(b'/') => {
+                    self.reader.skip(1);
+                    if self.peek_byte() == Some(b'>') {
+                        let end = self.offset() + 1;
+                        self.emit_spanned(HtmlAst::SelfClosing, end - 2..end);
+                    }
+                }
+                Some(_) => {
+                    count += 1;
+                    if exceeds(self.options.max_attributes_per_tag, count) {
+                        let span = Span::new(self.construct_start, self.offset());
+                        return Err(HtmlError::LimitExceeded(Limit::Attributes, span));
+                    }
+                    let name = self.read_name();
+                    self.skip_ascii_whitespace();
+                    if self.peek_byte() == Some(b'=') {
+                        self.reader.skip(1);
+                        self.skip_ascii_whitespace();
+                        self.read_attribute_value(name)?;
+                    }
+                }
+                None => return Err(self.unterminated(ReadError::DelimNotFound)),
             }
-            None => break,
         }
     }
 
-    Ok(ast)
+    // Reads a tag or attribute name, stopping at whitespace, `/`, `=` or `>`
+    fn read_name(&mut self) -> &'a str {
+        self.reader
+            .read_while(|ch| !(ch.is_ascii_whitespace() || matches!(ch, '/' | '=' | '>')))
+    }
+
+    fn read_attribute_value(&mut self, name: &'a str) -> Result<&'a str, HtmlError> {
+        if let Some(quote @ (b'"' | b'\'')) = self.peek_byte() {
+            self.reader.skip(1);
+            let value = self
+                .reader
+                .read_until(quote as char)
+                .map_err(|error| self.unterminated(error))?;
+            self.reader.skip(1);
+            return Ok(value);
+        }
+
+        // Unquoted values end at whitespace or the end of the tag
+        let start = self.offset();
+        let value = self
+            .reader
+            .read_while(|ch| !ch.is_ascii_whitespace() && ch != '>');
+        let len = value.len();
+
+        if self.options.warn_unquoted_attribute_values && value.contains(['"', '\'', '<', '=', '`'])
+        {
+            let span = Span::new(start, start + len);
+            self.warn(HtmlWarning::UnquotedAttributeValue { name, value }, span);
+        }
+        Ok(value)
+    }
+}
+
+pub(crate) fn decode_text<'a>(
+    text: &'a str,
+    options: &ParseOptions,
+    arena: Arena<'a>,
+) -> Cow<'a, str> {
+    if options.keep_entities {
+        Cow::Borrowed(text)
+    } else {
+        arena.decode(text)
+    }
+}
+
+pub(crate) fn exceeds(limit: Option<usize>, count: usize) -> bool {
+    limit.is_some_and(|limit| count > limit)
+}
+
+// How much of `text`, cut off by the end of the input, is sure not to hold
+// the start of whatever ends it: markup, or for the contents of a `raw_text`
+// element its end tag
+fn scanned_len(text: &str, raw_text: Option<&str>) -> usize {
+    let mut len = match raw_text {
+        Some(tag) => text.len().saturating_sub("</".len() + tag.len()),
+        None => text.len() - usize::from(text.ends_with('<')),
+    };
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+    len
+}
+
+// Whether `rest` starts with a tag, comment or doctype. Any other `<` is
+// just text, as in `5 < 6`.
+fn opens_markup(rest: &str) -> bool {
+    match rest.as_bytes() {
+        [b'<', next, ..] => next.is_ascii_alphabetic() || matches!(next, b'/' | b'!' | b'?'),
+        _ => false,
+    }
+}
+
+// HTML names are case-insensitive, so they're stored lowercase unless the
+// caller wants them as written
+pub(crate) fn normalize_name<'a>(
+    name: &'a str,
+    options: &ParseOptions,
+    arena: Arena<'a>,
+) -> Cow<'a, str> {
+    if options.preserve_case || !name.bytes().any(|byte| byte.is_ascii_uppercase()) {
+        Cow::Borrowed(name)
+    } else {
+        arena.lowercase(name)
+    }
+}
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input",
+    "keygen", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Void elements have no content and no end tag.
+pub fn is_void_element(tag: &str) -> bool {
+    VOID_ELEMENTS
+        .iter()
+        .any(|void| void.eq_ignore_ascii_case(tag))
+}
+
+// Whitespace between two of these can be visible
+const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "button", "cite", "code", "data", "dfn", "em", "i", "img",
+    "input", "kbd", "label", "mark", "q", "s", "samp", "select", "small", "span", "strong", "sub",
+    "sup", "textarea", "time", "u", "var",
+];
+
+pub(crate) fn is_inline_element(tag: &str) -> bool {
+    INLINE_ELEMENTS
+        .iter()
+        .any(|inline| inline.eq_ignore_ascii_case(tag))
 }
 
-pub fn parse_html(data: &str) -> Result<Vec<HtmlElement>, HtmlError> {
-    let mut reader = StrReader::new(data);
+// Text and inline elements, which whitespace next to is rendered
+pub(crate) fn is_inline(node: Option<&HtmlNode<'_>>) -> bool {
+    match node {
+        Some(HtmlNode::Element(element)) => is_inline_element(&element.tag),
+        Some(HtmlNode::Text(_)) => true,
+        _ => false,
+    }
+}
+
+// Elements whose whitespace is rendered as written
+const PREFORMATTED_ELEMENTS: &[&str] = &["listing", "pre", "textarea", "xmp"];
+
+pub(crate) fn is_preformatted_element(tag: &str) -> bool {
+    PREFORMATTED_ELEMENTS
+        .iter()
+        .any(|preformatted| preformatted.eq_ignore_ascii_case(tag))
+}
+
+// `plaintext` has no end tag: its contents run to the end of the input
+const RAW_TEXT_ELEMENTS: &[&str] = &[
+    "iframe",
+    "noembed",
+    "noframes",
+    "plaintext",
+    "script",
+    "style",
+    "xmp",
+];
+
+// Elements holding text without tags, but with character references
+const RCDATA_ELEMENTS: &[&str] = &["textarea", "title"];
+
+// Where `rest`, the contents of a raw text or RCDATA `tag`, reaches its end
+// tag: `</` and the name in any case, then whitespace, `/` or `>`
+pub(crate) fn find_end_tag(rest: &str, tag: &str) -> Option<usize> {
+    memchr::memmem::find_iter(rest.as_bytes(), b"</").find(|&index| {
+        let name = &rest.as_bytes()[index + 2..];
+        name.len() >= tag.len()
+            && name[..tag.len()].eq_ignore_ascii_case(tag.as_bytes())
+            && name
+                .get(tag.len())
+                .is_none_or(|&next| next.is_ascii_whitespace() || matches!(next, b'/' | b'>'))
+    })
+}
+
+/// Raw text elements hold their content verbatim, without tags, character
+/// references or escaping.
+pub fn is_raw_text_element(tag: &str) -> bool {
+    RAW_TEXT_ELEMENTS
+        .iter()
+        .any(|raw| raw.eq_ignore_ascii_case(tag))
+}
+
+pub fn parse_html(data: &str) -> Result<Vec<HtmlElement<'_>>, HtmlError> {
+    parse_document(data).map(HtmlDocument::into_elements)
+}
+
+pub fn parse_html_with<'a>(
+    data: &'a str,
+    options: &ParseOptions,
+) -> Result<Vec<HtmlElement<'a>>, HtmlError> {
+    parse_document_with(data, options).map(HtmlDocument::into_elements)
+}
+
+pub fn parse_document(data: &str) -> Result<HtmlDocument<'_>, HtmlError> {
+    parse_document_with(data, &ParseOptions::default())
+}
+
+pub fn parse_document_with<'a>(
+    data: &'a str,
+    options: &ParseOptions,
+) -> Result<HtmlDocument<'a>, HtmlError> {
+    parse_document_using(data, options, Arena::default())
+}
+
+pub(crate) fn parse_document_using<'a>(
+    data: &'a str,
+    options: &ParseOptions,
+    arena: Arena<'a>,
+) -> Result<HtmlDocument<'a>, HtmlError> {
+    #[cfg(feature = "spec")]
+    if options.mode == ParseMode::Spec {
+        return crate::spec::parse_document(data, options, arena);
+    }
+    let lenient = options.mode == ParseMode::Lenient;
+    let mut tokenizer = Tokenizer::new(data, options);
+    let mut token_stack: Vec<HtmlElement> = Vec::new();
+    let mut nodes = Vec::new();
+    let mut diagnostics = Vec::new();
+    // Whitespace-only text after a block-level node, which is only kept if
+    // inline content follows it
+    let mut blank = None;
+
+    while let Some(token) = tokenizer.next_token() {
+        diagnostics.append(&mut tokenizer.take_diagnostics());
+        let (token, span) = match token {
+            Ok(token) => token,
+            // Whatever can't be tokenized is dropped, as if input ended there
+            Err(error) if lenient && !matches!(error, HtmlError::LimitExceeded(..)) => {
+                let span = error.span().unwrap_or(Span::at(data.len()));
+                diagnostics.push(Diagnostic::new(HtmlWarning::UnreadableInput, span));
+                break;
+            }
+            Err(error) => return Err(error),
+        };
+        let range = Span::from(span.clone());
+        let span = Some(&data[span]).filter(|_| options.preserve_source);
 
-    let tokens = tokenize_html(data)?;
-    let mut token_stack = Vec::new();
-    let mut elements = Vec::new();
+        // A void element ends as soon as its attributes do
+        if !matches!(token, HtmlAst::Attribute(..)) {
+            close_void_element(&mut token_stack, &mut nodes);
+        }
+        if let Some((text, end)) = blank.take() {
+            // With no next sibling, the parent's end decides
+            let inline = match token {
+                HtmlAst::StartTag(tag) => is_inline_element(tag),
+                HtmlAst::Text(_) => true,
+                HtmlAst::EndTag(_) => token_stack
+                    .last()
+                    .is_some_and(|parent| is_inline_element(&parent.tag)),
+                _ => false,
+            };
+            if inline {
+                append_node(&mut token_stack, &mut nodes, text, end);
+            }
+        }
 
-    for token in tokens {
-        match token {
-            HtmlAst::StartTag(element) => {
-                token_stack.insert(0, HtmlElement::new(element));
+        let node = match token {
+            HtmlAst::StartTag(tag) => {
+                let namespace = match token_stack.last() {
+                    Some(parent) => parent.namespace.for_child(&parent.tag, tag),
+                    None => Namespace::Html.for_child("", tag),
+                };
+                if namespace == Namespace::Html {
+                    close_implied_elements(&mut token_stack, &mut nodes, tag);
+                }
+                if exceeds(options.max_depth, token_stack.len() + 1) {
+                    return Err(HtmlError::LimitExceeded(Limit::Depth, range));
+                }
+                let mut element =
+                    HtmlElement::new(adjust_tag(namespace, normalize_name(tag, options, arena)));
+                element.namespace = namespace;
+                element.span = Some(range);
+                element.source.start_tag = span;
+                if is_void(&element) {
+                    element.source.outer = span;
+                }
+                token_stack.push(element);
+                continue;
+            }
+            // Only foreign elements can be closed by their start tag
+            HtmlAst::SelfClosing => {
+                if let Some(element) = token_stack
+                    .last_mut()
+                    .filter(|element| element.namespace != Namespace::Html)
+                {
+                    element.source.outer = element.source.start_tag;
+                    close_element(&mut token_stack, &mut nodes);
+                }
+                continue;
             }
             HtmlAst::Attribute(name, value) => {
-                let element = token_stack.first_mut().ok_or(HtmlError::InvalidAst)?;
-                element.add_attribute(name, value);
+                let Some(element) = token_stack.last_mut() else {
+                    if lenient {
+                        continue;
+                    }
+                    return Err(HtmlError::InvalidAst(range));
+                };
+                element.attributes.push(HtmlAttribute {
+                    name: adjust_attribute(element.namespace, normalize_name(name, options, arena)),
+                    value: value.map(|value| decode_text(value, options, arena)),
+                });
+                continue;
             }
-            HtmlAst::EndTag => {
-                let element = token_stack.remove(0);
-                if let Some(parent) = token_stack.first_mut() {
-                    parent.add_child(element);
-                } else {
-                    elements.push(element);
+            HtmlAst::EndTag(name) if lenient => {
+                let Some(index) = token_stack
+                    .iter()
+                    .rposition(|element| element.tag.eq_ignore_ascii_case(name))
+                else {
+                    let warning = HtmlWarning::UnexpectedEndTag { tag: name };
+                    diagnostics.push(Diagnostic::new(warning, range));
+                    continue;
+                };
+                while token_stack.len() > index + 1 {
+                    let element = &token_stack[token_stack.len() - 1];
+                    if !has_optional_end_tag(&element.tag) {
+                        let warning = HtmlWarning::MisnestedElement {
+                            tag: element.tag.clone(),
+                            closed_by: name,
+                        };
+                        diagnostics.push(Diagnostic::new(warning, element.span.unwrap_or(range)));
+                    }
+                    close_element(&mut token_stack, &mut nodes);
+                }
+                let mut element = token_stack.pop().unwrap();
+                extend_span(&mut element, range.end);
+                if let (Some(start_tag), Some(end_tag)) = (element.source.start_tag, span) {
+                    element.source.end_tag = Some(end_tag);
+                    element.source.outer = Some(source_between(data, start_tag, end_tag));
                 }
+                HtmlNode::Element(element)
             }
-            HtmlAst::Text(text) => {
-                let element = token_stack.first_mut().ok_or(HtmlError::InvalidAst)?;
-                element.inner_text = Some(text);
+            HtmlAst::EndTag(name) => {
+                let Some(index) = token_stack
+                    .iter()
+                    .rposition(|element| element.tag.eq_ignore_ascii_case(name))
+                else {
+                    return Err(HtmlError::UnexpectedEndTag {
+                        tag: name.to_string(),
+                        span: range,
+                    });
+                };
+                // `</ul>` also ends an open `<li>` whose end tag was left out
+                while token_stack.len() > index + 1 {
+                    let element = &token_stack[token_stack.len() - 1];
+                    if !has_optional_end_tag(&element.tag) {
+                        return Err(HtmlError::MisnestedElement {
+                            tag: element.tag.to_string(),
+                            closed_by: name.to_string(),
+                            span: element.span.unwrap_or(range),
+                        });
+                    }
+                    close_element(&mut token_stack, &mut nodes);
+                }
+                let mut element = token_stack.pop().unwrap();
+                extend_span(&mut element, range.end);
+                if let (Some(start_tag), Some(end_tag)) = (element.source.start_tag, span) {
+                    element.source.end_tag = Some(end_tag);
+                    element.source.outer = Some(source_between(data, start_tag, end_tag));
+                }
+                HtmlNode::Element(element)
             }
+            HtmlAst::Text(text) => match token_stack.last() {
+                Some(parent) if is_raw_text_element(&parent.tag) => HtmlNode::Text(text.into()),
+                parent => {
+                    let node = HtmlNode::Text(decode_text(text, options, arena));
+                    let keep = options.preserve_source || tokenizer.state.preformatted > 0;
+                    if !keep && text.bytes().all(|byte| byte.is_ascii_whitespace()) {
+                        let inline = match parent {
+                            Some(parent) => {
+                                match parent.content.last().or(parent.children.last()) {
+                                    None => is_inline_element(&parent.tag),
+                                    previous => is_inline(previous),
+                                }
+                            }
+                            None => is_inline(nodes.last()),
+                        };
+                        if !inline {
+                            blank = Some((node, range.end));
+                            continue;
+                        }
+                    }
+                    node
+                }
+            },
+            HtmlAst::CData(text) => HtmlNode::CData(text.into()),
+            HtmlAst::Comment(_) if options.skip_comments => continue,
+            HtmlAst::Comment(comment) => HtmlNode::Comment(comment.into()),
+            HtmlAst::Doctype(doctype) => {
+                // Only a doctype preceding all elements is meaningful
+                let is_first = nodes
+                    .iter()
+                    .all(|node| matches!(node, HtmlNode::Comment(_)));
+                if !token_stack.is_empty() || !is_first {
+                    continue;
+                }
+                HtmlNode::Doctype(doctype)
+            }
+        };
+
+        append_node(&mut token_stack, &mut nodes, node, range.end);
+    }
+    close_void_element(&mut token_stack, &mut nodes);
+    let all_optional = token_stack
+        .iter()
+        .all(|element| has_optional_end_tag(&element.tag));
+    diagnostics.append(&mut tokenizer.take_diagnostics());
+    if lenient || all_optional {
+        while let Some(element) = token_stack.last() {
+            if !has_optional_end_tag(&element.tag) {
+                let warning = HtmlWarning::UnclosedElement {
+                    tag: element.tag.clone(),
+                };
+                let span = element.span.unwrap_or(Span::at(data.len()));
+                diagnostics.push(Diagnostic::new(warning, span));
+            }
+            close_element(&mut token_stack, &mut nodes);
         }
     }
+
     if token_stack.is_empty() {
-        Ok(elements)
+        let source = options.preserve_source.then_some(data);
+        Ok(HtmlDocument {
+            nodes,
+            source,
+            diagnostics,
+        })
     } else {
-        Err(HtmlError::InvalidAst)
+        // Report the innermost element that needed an end tag
+        let element = token_stack
+            .iter()
+            .rev()
+            .find(|element| !has_optional_end_tag(&element.tag))
+            .unwrap_or(&token_stack[token_stack.len() - 1]);
+        Err(HtmlError::UnclosedElement {
+            tag: element.tag.to_string(),
+            span: element.span.unwrap_or(Span::at(data.len())),
+        })
     }
 }
 
-pub fn html_to_string(elements: Vec<HtmlElement<'_>>) -> String {
-    let mut html = String::new();
-    for element in elements {
-        if element.attributes.len() > 0 {
-            html.push_str("<");
-            html.push_str(element.tag);
-            for attr in element.attributes {
-                match attr.value {
-                    Some(value) => html.push_str(&format!(" {}=\"{}\"", attr.name, value)),
-                    None => html.push_str(&format!(" {}", attr.name)),
-                }
-            }
-            html.push_str(">");
-            if let Some(text) = element.inner_text {
-                html.push_str(text);
-            } else {
-                html.push_str(&html_to_string(element.children));
-            }
-            html.push_str(&format!("</{}>", element.tag));
-        } else {
-            html.push_str(&format!("<{}>", element.tag));
-            if let Some(text) = element.inner_text {
-                html.push_str(text);
-            } else {
-                html.push_str(&html_to_string(element.children));
-            }
-            html.push_str(&format!("</{}>", element.tag));
+// The slice of `data` spanning from the start of `first` to the end of `last`
+fn source_between<'a>(data: &'a str, first: &str, last: &str) -> &'a str {
+    let start = first.as_ptr() as usize - data.as_ptr() as usize;
+    let end = last.as_ptr() as usize - data.as_ptr() as usize + last.len();
+    &data[start..end]
+}
+
+// Adds `node`, which ends at `end` in the input, to the innermost open
+// element
+fn append_node<'a>(
+    token_stack: &mut [HtmlElement<'a>],
+    nodes: &mut Vec<HtmlNode<'a>>,
+    node: HtmlNode<'a>,
+    end: usize,
+) {
+    if let Some(parent) = token_stack.last_mut() {
+        extend_span(parent, end);
+    }
+    match token_stack.last_mut() {
+        Some(parent) if parent.tag.eq_ignore_ascii_case("template") => parent.content.push(node),
+        Some(parent) => parent.children.push(node),
+        None => nodes.push(node),
+    }
+}
+
+fn close_void_element<'a>(token_stack: &mut Vec<HtmlElement<'a>>, nodes: &mut Vec<HtmlNode<'a>>) {
+    if token_stack.last().is_some_and(is_void) {
+        close_element(token_stack, nodes);
+    }
+}
+
+fn is_void(element: &HtmlElement) -> bool {
+    element.namespace == Namespace::Html && is_void_element(&element.tag)
+}
+
+const OPTIONAL_END_TAGS: &[&str] = &[
+    "body",
+    "colgroup",
+    "dd",
+    "dt",
+    "head",
+    "html",
+    "li",
+    "optgroup",
+    "option",
+    "p",
+    "plaintext",
+    "rp",
+    "rt",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+];
+
+/// Elements whose end tag may be left out, being implied by what follows.
+pub fn has_optional_end_tag(tag: &str) -> bool {
+    OPTIONAL_END_TAGS
+        .iter()
+        .any(|optional| optional.eq_ignore_ascii_case(tag))
+}
+
+// Elements whose start tag ends an open `<p>`
+const CLOSES_PARAGRAPH: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "li",
+    "main",
+    "menu",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "search",
+    "section",
+    "summary",
+    "table",
+    "ul",
+];
+
+// For a start tag, the open elements it implicitly ends and the elements
+// that stop the search, e.g. a nested list shields its outer `<li>`
+fn implied_end_rules(tag: &str) -> Vec<(&'static [&'static str], &'static [&'static str])> {
+    const PARAGRAPH: (&[&str], &[&str]) = (
+        &["p"],
+        &[
+            "button", "caption", "html", "object", "table", "td", "template", "th",
+        ],
+    );
+
+    let mut rules = Vec::new();
+    match tag.to_ascii_lowercase().as_str() {
+        "li" => rules.push((&["li"][..], &["ol", "ul", "menu"][..])),
+        "dt" | "dd" => rules.push((&["dt", "dd"][..], &["dl"][..])),
+        "option" => rules.push((&["option"][..], &["select", "datalist", "optgroup"][..])),
+        "optgroup" => rules.push((&["option", "optgroup"][..], &["select"][..])),
+        "tr" => rules.push((&["tr"][..], &["table", "tbody", "thead", "tfoot"][..])),
+        "td" | "th" => rules.push((&["td", "th"][..], &["tr", "table"][..])),
+        "thead" | "tbody" | "tfoot" => rules.push((
+            &["thead", "tbody", "tfoot", "caption", "colgroup"][..],
+            &["table"][..],
+        )),
+        "rt" | "rp" => rules.push((&["rt", "rp"][..], &["ruby"][..])),
+        _ => {}
+    }
+    if CLOSES_PARAGRAPH
+        .iter()
+        .any(|closer| closer.eq_ignore_ascii_case(tag))
+    {
+        rules.push(PARAGRAPH);
+    }
+    rules
+}
+
+// Closes the elements that a `tag` start tag ends, like the open `<li>`
+// when another `<li>` starts
+fn close_implied_elements<'a>(
+    token_stack: &mut Vec<HtmlElement<'a>>,
+    nodes: &mut Vec<HtmlNode<'a>>,
+    tag: &str,
+) {
+    let open = token_stack.iter().rev().map(|element| &*element.tag);
+    for _ in 0..implied_end_count(open, tag) {
+        close_element(token_stack, nodes);
+    }
+}
+
+// How many of the `open` elements, innermost first, a `tag` start tag ends
+pub(crate) fn implied_end_count<'t>(
+    open: impl Iterator<Item = &'t str> + Clone,
+    tag: &str,
+) -> usize {
+    let is = |name: &str, tags: &[&str]| tags.iter().any(|tag| name.eq_ignore_ascii_case(tag));
+
+    let mut closed = 0;
+    for (targets, boundaries) in implied_end_rules(tag) {
+        // The outermost target before a boundary, so that everything opened
+        // inside it is closed along with it
+        let depth = open
+            .clone()
+            .skip(closed)
+            .take_while(|name| !is(name, boundaries))
+            .enumerate()
+            .filter(|(_, name)| is(name, targets))
+            .map(|(depth, _)| depth)
+            .last();
+        if let Some(depth) = depth {
+            closed += depth + 1;
         }
     }
-    html
+    closed
+}
+
+// Ends the innermost open element without an end tag of its own
+fn close_element<'a>(token_stack: &mut Vec<HtmlElement<'a>>, nodes: &mut Vec<HtmlNode<'a>>) {
+    let element = token_stack.pop().unwrap();
+    let end = element.span.map_or(0, |span| span.end);
+    append_node(token_stack, nodes, HtmlNode::Element(element), end);
+}
+
+fn extend_span(element: &mut HtmlElement, end: usize) {
+    if let Some(span) = &mut element.span {
+        span.end = span.end.max(end);
+    }
 }
+
 #[cfg(test)]
 mod tests {
-    use crate::HtmlAttribute;
+    use crate::{document_to_string, html_to_string, HtmlAttribute, HtmlNode, QuirksMode};
 
     use super::*;
 
@@ -156,7 +1158,7 @@ mod tests {
             vec![
                 HtmlAst::StartTag("button"),
                 HtmlAst::Text("Hello"),
-                HtmlAst::EndTag
+                HtmlAst::EndTag("button")
             ]
         );
     }
@@ -171,12 +1173,28 @@ mod tests {
                 HtmlAst::StartTag("div"),
                 HtmlAst::StartTag("button"),
                 HtmlAst::Text("Hello"),
-                HtmlAst::EndTag,
-                HtmlAst::EndTag
+                HtmlAst::EndTag("button"),
+                HtmlAst::EndTag("div")
             ]
         );
     }
 
+    #[test]
+    fn tokenize_bare_less_than() {
+        let tokens = tokenize_html("<p>5 < 6 and 7 > 3</p>< <3").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlAst::StartTag("p"),
+                HtmlAst::Text("5 < 6 and 7 > 3"),
+                HtmlAst::EndTag("p"),
+                HtmlAst::Text("< <3"),
+            ]
+        );
+        let elements = parse_html("<p>a <= b</p>").unwrap();
+        assert_eq!(html_to_string(elements), "<p>a &lt;= b</p>");
+    }
+
     #[test]
     fn tokenize_attr() {
         let html = "<button class=\"btn\">Hello</button>";
@@ -187,7 +1205,7 @@ mod tests {
                 HtmlAst::StartTag("button"),
                 HtmlAst::Attribute("class", "btn".into()),
                 HtmlAst::Text("Hello"),
-                HtmlAst::EndTag
+                HtmlAst::EndTag("button")
             ]
         );
     }
@@ -203,11 +1221,125 @@ mod tests {
                 HtmlAst::Attribute("class", "btn".into()),
                 HtmlAst::Attribute("disabled", None),
                 HtmlAst::Text("Hello"),
-                HtmlAst::EndTag
+                HtmlAst::EndTag("button")
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_attr_with_spaces() {
+        let html = "<div class=\"a b c\" title=\"x = y > z\">Hello</div>";
+        let tokens = tokenize_html(html).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlAst::StartTag("div"),
+                HtmlAst::Attribute("class", Some("a b c")),
+                HtmlAst::Attribute("title", Some("x = y > z")),
+                HtmlAst::Text("Hello"),
+                HtmlAst::EndTag("div")
             ]
         );
     }
 
+    #[test]
+    fn tokenize_attr_spacing() {
+        let html = "<input  type = \"checkbox\"\n checked/>";
+        let tokens = tokenize_html(html).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlAst::StartTag("input"),
+                HtmlAst::Attribute("type", Some("checkbox")),
+                HtmlAst::Attribute("checked", None),
+                HtmlAst::SelfClosing,
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_single_quoted_attr() {
+        let html = "<div id='main' title='say \"hi\"' data-x=\"it's\"></div>";
+        let tokens = tokenize_html(html).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlAst::StartTag("div"),
+                HtmlAst::Attribute("id", Some("main")),
+                HtmlAst::Attribute("title", Some("say \"hi\"")),
+                HtmlAst::Attribute("data-x", Some("it's")),
+                HtmlAst::EndTag("div")
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_unquoted_attr() {
+        let html = "<input type=text width=100/><a href=/path?a=b>link</a>";
+        let tokens = tokenize_html(html).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlAst::StartTag("input"),
+                HtmlAst::Attribute("type", Some("text")),
+                HtmlAst::Attribute("width", Some("100/")),
+                HtmlAst::StartTag("a"),
+                HtmlAst::Attribute("href", Some("/path?a=b")),
+                HtmlAst::Text("link"),
+                HtmlAst::EndTag("a")
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_unquoted_attr_warnings() {
+        let html = "<a href=/path?a=b title=ok>link</a>";
+        let options = ParseOptions {
+            warn_unquoted_attribute_values: true,
+            ..Default::default()
+        };
+        let (_, warnings) = tokenize_html_with(html, &options).unwrap();
+        assert_eq!(
+            warnings,
+            vec![HtmlWarning::UnquotedAttributeValue {
+                name: "href",
+                value: "/path?a=b"
+            }]
+        );
+
+        let (_, warnings) = tokenize_html_with(html, &ParseOptions::default()).unwrap();
+        assert_eq!(warnings, vec![]);
+    }
+
+    #[test]
+    fn duplicate_attributes() {
+        let html = "<a href=\"a\" HREF=\"b\" title=\"t\">";
+        let hrefs = |options: &ParseOptions| {
+            let (tokens, warnings) = tokenize_html_with(html, options).unwrap();
+            let count = tokens
+                .iter()
+                .filter(|token| matches!(token, HtmlAst::Attribute(name, _) if name.eq_ignore_ascii_case("href")))
+                .count();
+            (count, warnings)
+        };
+        assert_eq!(hrefs(&ParseOptions::default()), (1, vec![]));
+
+        let mut options = ParseOptions {
+            duplicate_attributes: DuplicateAttributes::KeepAll,
+            ..Default::default()
+        };
+        assert_eq!(hrefs(&options), (2, vec![]));
+
+        options.duplicate_attributes = DuplicateAttributes::Warn;
+        assert_eq!(
+            hrefs(&options),
+            (1, vec![HtmlWarning::DuplicateAttribute { name: "HREF" }])
+        );
+
+        let elements = parse_html("<a href=\"a\" href=\"b\"></a>").unwrap();
+        assert_eq!(elements[0].get_attr("href"), Some("a"));
+    }
+
     #[test]
     fn decode_html() {
         let html = "<div><button class=\"btn\">Hello</button></div>";
@@ -215,15 +1347,17 @@ mod tests {
         assert_eq!(
             element,
             vec![HtmlElement {
-                tag: "div",
-                attributes: vec![],
-                children: vec![HtmlElement {
-                    tag: "button",
-                    attributes: vec![HtmlAttribute::new("class", "btn".into())],
-                    children: vec![],
-                    inner_text: Some("Hello")
-                }],
-                inner_text: None
+                tag: "div".into(),
+                attributes: Default::default(),
+                children: vec![HtmlNode::Element(HtmlElement {
+                    tag: "button".into(),
+                    attributes: [HtmlAttribute::new("class", "btn".into())]
+                        .into_iter()
+                        .collect(),
+                    children: vec![HtmlNode::Text("Hello".into())],
+                    ..Default::default()
+                })],
+                ..Default::default()
             }]
         );
     }
@@ -235,13 +1369,15 @@ mod tests {
         assert_eq!(
             element,
             vec![HtmlElement {
-                tag: "button",
-                attributes: vec![
+                tag: "button".into(),
+                attributes: [
                     HtmlAttribute::new("class", Some("btn")),
                     HtmlAttribute::new("disabled", None)
-                ],
-                children: vec![],
-                inner_text: Some("Hello")
+                ]
+                .into_iter()
+                .collect(),
+                children: vec![HtmlNode::Text("Hello".into())],
+                ..Default::default()
             }]
         );
     }
@@ -251,20 +1387,170 @@ mod tests {
         let html = "<!-- comment -->";
         let element = parse_html(html).unwrap();
         assert_eq!(element, vec![]);
+
+        let document = parse_document(html).unwrap();
+        assert_eq!(
+            document.children(),
+            &[HtmlNode::Comment(" comment ".into())]
+        );
+    }
+
+    #[test]
+    fn tokenize_comments() {
+        let html = "<!-- a > b --><!--><!---><!bogus><?xml version=\"1.0\"?><!-- open";
+        let tokens = tokenize_html(html).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlAst::Comment(" a > b "),
+                HtmlAst::Comment(""),
+                HtmlAst::Comment(""),
+                HtmlAst::Comment("bogus"),
+                HtmlAst::Comment("?xml version=\"1.0\"?"),
+                HtmlAst::Comment(" open"),
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_nodes() {
+        let html = "<!DOCTYPE html><div><!-- note -->Hi<![CDATA[x]]></div>";
+        let document = parse_document(html).unwrap();
+        assert_eq!(
+            document.children(),
+            &[
+                HtmlNode::Doctype(HtmlDoctype::new("html")),
+                HtmlNode::Element(HtmlElement {
+                    tag: "div".into(),
+                    attributes: Default::default(),
+                    children: vec![
+                        HtmlNode::Comment(" note ".into()),
+                        HtmlNode::Text("Hi".into()),
+                        HtmlNode::CData("x".into()),
+                    ],
+                    ..Default::default()
+                })
+            ]
+        );
+        assert_eq!(document_to_string(document), html);
+    }
+
+    #[test]
+    fn tokenize_doctype() {
+        let html = "<!DOCTYPE html><!-- comment --><html></html>";
+        let tokens = tokenize_html(html).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                HtmlAst::Doctype(HtmlDoctype::new("html")),
+                HtmlAst::Comment(" comment "),
+                HtmlAst::StartTag("html"),
+                HtmlAst::EndTag("html")
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_document() {
+        let html = "<!doctype html>\n<html><body></body></html>";
+        let document = parse_document(html).unwrap();
+        assert_eq!(document.doctype(), Some(&HtmlDoctype::new("html")));
+        assert_eq!(document.quirks_mode(), QuirksMode::NoQuirks);
+        assert_eq!(document.elements().count(), 1);
+
+        let document = parse_document("<html></html>").unwrap();
+        assert_eq!(document.doctype(), None);
+        assert_eq!(document.quirks_mode(), QuirksMode::Quirks);
+    }
+
+    #[test]
+    fn decode_cdata() {
+        let html = "<svg><style><![CDATA[ a > b { fill: red } <!-- ]]></style></svg>";
+        let tokens = tokenize_html(html).unwrap();
+        assert_eq!(tokens[2], HtmlAst::CData(" a > b { fill: red } <!-- "));
+
+        let elements = parse_html(html).unwrap();
+        assert_eq!(html_to_string(elements), html);
+    }
+
+    #[test]
+    fn decode_mixed_content() {
+        let html = "<p>Hello <b>world</b>!</p><p>one <i>two</i> three</p>";
+        let elements = parse_html(html).unwrap();
+        assert_eq!(
+            elements[0].children,
+            vec![
+                HtmlNode::Text("Hello ".into()),
+                HtmlNode::Element(HtmlElement {
+                    tag: "b".into(),
+                    attributes: Default::default(),
+                    children: vec![HtmlNode::Text("world".into())],
+                    ..Default::default()
+                }),
+                HtmlNode::Text("!".into()),
+            ]
+        );
+        assert_eq!(elements[1].children[2], HtmlNode::Text(" three".into()));
+        assert_eq!(html_to_string(elements), html);
+    }
+
+    #[test]
+    fn decode_void_elements() {
+        let html = "<p>line<br>next <img src=\"a.png\"/> end</p>";
+        let elements = parse_html(html).unwrap();
+        assert_eq!(elements.len(), 1);
+        assert_eq!(elements[0].children.len(), 5);
+        assert_eq!(
+            html_to_string(elements),
+            "<p>line<br>next <img src=\"a.png\"> end</p>"
+        );
+
+        let html = "<object><param name=\"a\"><param name=\"b\"></object>\
+            <frameset><frame src=\"a.html\"><frame src=\"b.html\"></frameset>";
+        let elements = parse_html(html).unwrap();
+        assert_eq!(elements[0].children.len(), 2);
+        assert_eq!(elements[1].children.len(), 2);
+        assert_eq!(
+            html_to_string(elements),
+            "<object><param name=\"a\"><param name=\"b\"></object>\
+            <frameset><frame src=\"a.html\"><frame src=\"b.html\"></frameset>"
+        );
+    }
+
+    #[test]
+    fn edit_and_encode() {
+        let html = "<a href=\"/old\" title=\"keep\">old <b>text</b></a>";
+        let mut elements = parse_html(html).unwrap();
+        let link = &mut elements[0];
+
+        let href = &mut link.attributes_mut()[0];
+        href.set_value(format!("https://example.com{}", href.value().unwrap()));
+        link.children_mut()[0] = HtmlNode::Text("new ".into());
+
+        assert!(matches!(
+            link.attributes[1].value,
+            Some(alloc::borrow::Cow::Borrowed("keep"))
+        ));
+        assert_eq!(
+            html_to_string(elements),
+            "<a href=\"https://example.com/old\" title=\"keep\">new <b>text</b></a>"
+        );
     }
 
     #[test]
     fn encode_html() {
         let elements = vec![HtmlElement {
-            tag: "div",
-            attributes: vec![],
-            children: vec![HtmlElement {
-                tag: "button",
-                attributes: vec![HtmlAttribute::new("class", Some("btn"))],
-                children: vec![],
-                inner_text: Some("Hello"),
-            }],
-            inner_text: None,
+            tag: "div".into(),
+            attributes: Default::default(),
+            children: vec![HtmlNode::Element(HtmlElement {
+                tag: "button".into(),
+                attributes: [HtmlAttribute::new("class", Some("btn"))]
+                    .into_iter()
+                    .collect(),
+                children: vec![HtmlNode::Text("Hello".into())],
+                ..Default::default()
+            })],
+            ..Default::default()
         }];
         let html = html_to_string(elements);
         assert_eq!(html, "<div><button class=\"btn\">Hello</button></div>");
@@ -273,15 +1559,463 @@ mod tests {
     #[test]
     fn encode_html_attr() {
         let elements = vec![HtmlElement {
-            tag: "button",
-            attributes: vec![
+            tag: "button".into(),
+            attributes: [
                 HtmlAttribute::new("class", Some("btn")),
                 HtmlAttribute::new("disabled", None),
-            ],
-            children: vec![],
-            inner_text: Some("Hello"),
+            ]
+            .into_iter()
+            .collect(),
+            children: vec![HtmlNode::Text("Hello".into())],
+            ..Default::default()
         }];
         let html = html_to_string(elements);
         assert_eq!(html, "<button class=\"btn\" disabled>Hello</button>");
     }
+
+    #[test]
+    fn preserve_source() {
+        let html = "<!doctype html>\n<HTML lang=en>\n  <Body class='a  b'>\n    <p id=\"x\" >1 &lt; 2 &amp; more</P>\n    <br/>\n  </Body>\n</HTML>\n";
+        let options = ParseOptions {
+            preserve_source: true,
+            ..Default::default()
+        };
+        let document = parse_document_with(html, &options).unwrap();
+        assert_eq!(document_to_string(document.clone()), html);
+
+        // Only what was edited is rebuilt, the rest keeps its original markup
+        let mut elements = document.into_elements();
+        let body = elements[0].children_mut()[1].as_element_mut().unwrap();
+        body.children_mut()[1]
+            .as_element_mut()
+            .unwrap()
+            .set_attr("id", "y");
+        assert_eq!(
+            html_to_string(elements),
+            "<HTML lang=en>\n  <Body class='a  b'>\n    <p id=\"y\">1 &lt; 2 &amp; more</P>\n    <br/>\n  </Body>\n</HTML>"
+        );
+    }
+
+    #[test]
+    fn preformatted_whitespace() {
+        let html =
+            "<div>\n  <pre>\n  <b>bold</b>\n    <i>x</i>\n</pre>\n  <textarea>  </textarea></div>";
+        let elements = parse_html(html).unwrap();
+        assert_eq!(
+            html_to_string(elements),
+            "<div><pre>\n  <b>bold</b>\n    <i>x</i>\n</pre>\n  <textarea>  </textarea></div>"
+        );
+
+        // A textarea holds only text, kept as written
+        let elements = parse_html("<textarea>  <p> </textarea>").unwrap();
+        assert_eq!(elements[0].children(), [HtmlNode::Text("  <p> ".into())]);
+        assert_eq!(
+            html_to_string(elements),
+            "<textarea>  &lt;p&gt; </textarea>"
+        );
+    }
+
+    #[test]
+    fn whitespace_between_tags() {
+        // Kept next to inline content, dropped between blocks
+        let html = "<div>\n  <p>Hello <b>big</b> <i>world</i></p>\n  <span>a</span>\n<span>b</span>\n</div>\n";
+        let document = parse_document(html).unwrap();
+        assert_eq!(
+            document_to_string(document),
+            "<div><p>Hello <b>big</b> <i>world</i></p>\n  <span>a</span>\n<span>b</span>\n</div>"
+        );
+        let html = "<p><b>a</b> <i>b</i></p> <span>c</span>";
+        assert_eq!(document_to_string(parse_document(html).unwrap()), html);
+
+        // Or as the only child of an inline element
+        for html in [
+            "<p>x<b> </b>y</p>",
+            "<p>a<span> </span>b</p>",
+            "<p><b><br> </b></p>",
+        ] {
+            assert_eq!(document_to_string(parse_document(html).unwrap()), html);
+        }
+        assert_eq!(
+            parse_document("<p>x<b> </b>y</p>").unwrap().to_text(),
+            "x y"
+        );
+    }
+
+    #[test]
+    fn raw_text_and_rcdata() {
+        let html = "<script>if (a<b && c>d) x()</scriptx></SCRIPT ><style>p<b{}</style><title>a<b>&amp;</title><svg><title><tspan>t</tspan></title></svg>";
+        let tokens = tokenize_html(html).unwrap();
+        assert_eq!(tokens[1], HtmlAst::Text("if (a<b && c>d) x()</scriptx>"));
+        assert_eq!(tokens[2], HtmlAst::EndTag("SCRIPT"));
+        assert_eq!(tokens[4], HtmlAst::Text("p<b{}"));
+        assert_eq!(tokens[7], HtmlAst::Text("a<b>&amp;"));
+        assert_eq!(tokens[11], HtmlAst::StartTag("tspan"));
+
+        // Nothing ends a `<plaintext>`, so it holds the rest of the input
+        let tokens = tokenize_html("<plaintext>a</plaintext><b>").unwrap();
+        assert_eq!(tokens[1..], [HtmlAst::Text("a</plaintext><b>")]);
+        let document = parse_document("<plaintext>&amp;</plaintext>").unwrap();
+        let plaintext = document.elements().next().unwrap();
+        assert_eq!(
+            plaintext.children(),
+            [HtmlNode::Text("&amp;</plaintext>".into())]
+        );
+
+        for mode in [ParseMode::Strict, ParseMode::Lenient] {
+            let options = ParseOptions {
+                mode,
+                ..Default::default()
+            };
+            let document = parse_document_with(html, &options).unwrap();
+            let title = document.elements().nth(2).unwrap();
+            assert_eq!(title.children(), [HtmlNode::Text("a<b>&".into())]);
+            assert_eq!(
+                document_to_string(document),
+                "<script>if (a<b && c>d) x()</scriptx></script><style>p<b{}</style><title>a&lt;b&gt;&amp;</title><svg><title><tspan>t</tspan></title></svg>"
+            );
+        }
+
+        #[cfg(feature = "spec")]
+        {
+            let options = ParseOptions {
+                mode: ParseMode::Spec,
+                ..Default::default()
+            };
+            let document = parse_document_with("<title>a<b>c</title><p>", &options).unwrap();
+            assert_eq!(
+                document_to_string(document),
+                "<html><head><title>a&lt;b&gt;c</title></head><body><p></p></body></html>"
+            );
+        }
+    }
+
+    #[test]
+    fn tokens_stop_early() {
+        let html = "<html><head><title>Page</title></head><body><p>unclosed=\"</body></html>";
+        let title = Tokens::new(html)
+            .skip_while(|token| !matches!(token, Ok(HtmlAst::StartTag("title"))))
+            .find_map(|token| match token {
+                Ok(HtmlAst::Text(text)) => Some(text),
+                _ => None,
+            });
+        assert_eq!(title, Some("Page"));
+
+        // Errors end the stream
+        let mut tokens = Tokens::new("<p a=\"x>");
+        assert!(matches!(tokens.next(), Some(Err(_))));
+        assert!(tokens.next().is_none());
+    }
+
+    #[test]
+    fn lenient_mode() {
+        let options = ParseOptions {
+            mode: ParseMode::Lenient,
+            ..Default::default()
+        };
+        let html = "<div><p>one<b>bold</p></span><p>two</div><ul><li>three";
+        let elements = parse_html_with(html, &options).unwrap();
+        assert_eq!(
+            html_to_string(elements),
+            "<div><p>one<b>bold</b></p><p>two</p></div><ul><li>three</li></ul>"
+        );
+        assert!(parse_html(html).is_err());
+
+        // Unreadable input ends the document
+        let elements = parse_html_with("<p>text</p><a href=\"x", &options).unwrap();
+        assert_eq!(html_to_string(elements), "<p>text</p>");
+    }
+
+    #[test]
+    fn comment_and_entity_options() {
+        let html = "<!-- note --><p title=\"&lt;\">a &amp; b<!-- x --></p>";
+        let document = parse_document(html).unwrap();
+        assert_eq!(document.children().len(), 2);
+        assert_eq!(
+            document.elements().next().unwrap().get_attr("title"),
+            Some("<")
+        );
+
+        let options = ParseOptions {
+            skip_comments: true,
+            keep_entities: true,
+            ..Default::default()
+        };
+        let document = parse_document_with(html, &options).unwrap();
+        assert_eq!(document.children().len(), 1);
+        let p = document.elements().next().unwrap();
+        assert_eq!(p.get_attr("title"), Some("&lt;"));
+        assert_eq!(p.children, vec![HtmlNode::Text("a &amp; b".into())]);
+    }
+
+    #[test]
+    fn token_spans() {
+        let html = "<a href=\"x\" hidden/>text<!-- c --></a>";
+        let spans: Vec<_> = tokenize_html_spanned(html)
+            .unwrap()
+            .into_iter()
+            .map(|(_, span)| &html[span.range()])
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                "<a href=\"x\" hidden/>",
+                "href=\"x\"",
+                "hidden",
+                "/>",
+                "text",
+                "<!-- c -->",
+                "</a>"
+            ]
+        );
+    }
+
+    #[test]
+    fn element_spans() {
+        let html = "<ul>\n<li>one<li>two <b>x</b>\n</ul><br><p>end";
+        let document = parse_document(html).unwrap();
+        let source = |element: &HtmlElement| &html[element.span().unwrap().range()];
+        let elements: Vec<_> = document.elements().collect();
+        assert_eq!(source(elements[0]), "<ul>\n<li>one<li>two <b>x</b>\n</ul>");
+        let items: Vec<_> = elements[0]
+            .children
+            .iter()
+            .filter_map(HtmlNode::as_element)
+            .collect();
+        assert_eq!(source(items[0]), "<li>one");
+        assert_eq!(source(items[1]), "<li>two <b>x</b>\n");
+        assert_eq!(source(elements[1]), "<br>");
+        assert_eq!(source(elements[2]), "<p>end");
+        assert_eq!(HtmlElement::new("div").span(), None);
+    }
+
+    #[test]
+    fn error_spans() {
+        let html = "<p>\n  <a href=\"x>link</a>";
+        let error = parse_html(html).unwrap_err();
+        assert!(matches!(
+            error,
+            HtmlError::ReaderError(ReadError::DelimNotFound, _)
+        ));
+        let span = error.span().unwrap();
+        assert_eq!(span, Span::new(6, html.len()));
+        assert_eq!(span.line_col(html), (2, 3));
+
+        let error = parse_html("<p></p></div>").unwrap_err();
+        assert!(matches!(
+            &error,
+            HtmlError::UnexpectedEndTag { tag, span } if tag == "div" && *span == Span::new(7, 13)
+        ));
+        assert_eq!(
+            error.to_string(),
+            "end tag `</div>` at byte 7 closes nothing"
+        );
+
+        let error = parse_html("<div><span>x</div></span>").unwrap_err();
+        assert!(matches!(
+            &error,
+            HtmlError::MisnestedElement { tag, closed_by, .. } if tag == "span" && closed_by == "div"
+        ));
+        assert_eq!(error.span().map(|span| span.start), Some(5));
+        assert_eq!(
+            error.to_string(),
+            "`<span>` at byte 5 is still open at `</div>`"
+        );
+        assert!(matches!(
+            parse_html("<div><span>x</div>").unwrap_err(),
+            HtmlError::MisnestedElement { tag, .. } if tag == "span"
+        ));
+        assert!(matches!(
+            parse_html("<div>x</span></div>").unwrap_err(),
+            HtmlError::UnexpectedEndTag { tag, .. } if tag == "span"
+        ));
+
+        let error = parse_html("<div><p>text").unwrap_err();
+        assert!(matches!(&error, HtmlError::UnclosedElement { tag, .. } if tag == "div"));
+        assert_eq!(error.span(), Some(Span::new(0, 5)));
+        assert_eq!(error.to_string(), "`<div>` at byte 0 is never closed");
+
+        #[cfg(feature = "std")]
+        {
+            let error: Box<dyn std::error::Error> = parse_html("<a href=\"x").unwrap_err().into();
+            assert_eq!(
+                error.to_string(),
+                "unexpected end of input in markup starting at byte 0"
+            );
+            assert_eq!(
+                error.source().unwrap().to_string(),
+                "unexpected end of input"
+            );
+        }
+    }
+
+    #[test]
+    fn limits() {
+        // The default limit applies unless it's lifted
+        let deep = "<div>".repeat(100_000);
+        let options = ParseOptions {
+            mode: ParseMode::Lenient,
+            ..Default::default()
+        };
+        assert!(matches!(
+            parse_document_with(&deep, &options),
+            Err(HtmlError::LimitExceeded(Limit::Depth, _))
+        ));
+        let options = ParseOptions {
+            mode: ParseMode::Lenient,
+            max_depth: None,
+            ..Default::default()
+        };
+        assert!(parse_document_with(&"<div>".repeat(1000), &options).is_ok());
+
+        let html = "<p a b c>one</p><p>two</p>";
+        let options = ParseOptions {
+            max_tokens: Some(6),
+            ..Default::default()
+        };
+        assert!(matches!(
+            parse_document_with(html, &options),
+            Err(HtmlError::LimitExceeded(Limit::Tokens, _))
+        ));
+        let options = ParseOptions {
+            max_attributes_per_tag: Some(2),
+            ..Default::default()
+        };
+        assert!(matches!(
+            parse_document_with(html, &options),
+            Err(HtmlError::LimitExceeded(Limit::Attributes, _))
+        ));
+        let options = ParseOptions {
+            max_depth: Some(1),
+            max_tokens: Some(9),
+            max_attributes_per_tag: Some(3),
+            ..Default::default()
+        };
+        assert!(parse_document_with(html, &options).is_ok());
+    }
+
+    #[test]
+    fn unclosed_at_eof() {
+        let html = "<div><p>hello";
+        assert!(parse_document(html).is_err());
+
+        let options = ParseOptions {
+            mode: ParseMode::Lenient,
+            ..Default::default()
+        };
+        let document = parse_document_with(html, &options).unwrap();
+        assert_eq!(
+            document_to_string(document.clone()),
+            "<div><p>hello</p></div>"
+        );
+        assert_eq!(
+            document.diagnostics(),
+            [Diagnostic::new(
+                HtmlWarning::UnclosedElement { tag: "div".into() },
+                Span::new(0, 13)
+            )]
+        );
+
+        // Leaving out an optional end tag isn't worth a warning
+        let document = parse_document_with("<p>one<p>two", &options).unwrap();
+        assert!(document.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn name_case() {
+        let html = "<DIV Class=\"a\"><svg viewbox=\"0 0 1 1\"><ForeignObject/></svg></DIV>";
+        let elements = parse_html(html).unwrap();
+        assert_eq!(
+            html_to_string(elements),
+            "<div class=\"a\"><svg viewBox=\"0 0 1 1\"><foreignObject></foreignObject></svg></div>"
+        );
+
+        let options = ParseOptions {
+            preserve_case: true,
+            ..Default::default()
+        };
+        let elements = parse_html_with(html, &options).unwrap();
+        assert_eq!(elements[0].tag(), "DIV");
+        assert_eq!(elements[0].attributes[0].name(), "Class");
+    }
+
+    #[test]
+    fn implied_end_tags() {
+        let cases = [
+            ("<ul><li>one<li>two</ul>", "<ul><li>one</li><li>two</li></ul>"),
+            (
+                "<ul><li>a<ul><li>b<li>c</ul><li>d</ul>",
+                "<ul><li>a<ul><li>b</li><li>c</li></ul></li><li>d</li></ul>",
+            ),
+            ("<p>one<p>two<div>three</div>", "<p>one</p><p>two</p><div>three</div>"),
+            (
+                "<dl><dt>term<dd>definition<dt>next</dl>",
+                "<dl><dt>term</dt><dd>definition</dd><dt>next</dt></dl>",
+            ),
+            (
+                "<table><tr><td>a<td>b<tr><th>c</table>",
+                "<table><tr><td>a</td><td>b</td></tr><tr><th>c</th></tr></table>",
+            ),
+            (
+                "<select><option>a<option>b<optgroup><option>c</select>",
+                "<select><option>a</option><option>b</option><optgroup><option>c</option></optgroup></select>",
+            ),
+            // Elements with optional end tags can stay open at the end
+            ("<p>left open", "<p>left open</p>"),
+        ];
+        for (html, expected) in cases {
+            assert_eq!(
+                html_to_string(parse_html(html).unwrap()),
+                expected,
+                "{}",
+                html
+            );
+        }
+        assert!(parse_html("<div>left open").is_err());
+    }
+
+    #[test]
+    fn template_content() {
+        let html = "<ul><template id=item><li>a</li> </template></ul>";
+        let elements = parse_html(html).unwrap();
+        let template = elements[0].children()[0].as_element().unwrap();
+        assert!(template.children().is_empty());
+        assert_eq!(template.template_content().len(), 1);
+        assert_eq!(
+            html_to_string(elements),
+            "<ul><template id=\"item\"><li>a</li></template></ul>"
+        );
+    }
+
+    #[test]
+    fn foreign_elements() {
+        let html = "<div/><svg VIEWBOX=\"0 0 1 1\"><lineargradient id=\"g\"/><circle r=1 /><foreignObject><p>x</p></foreignObject></svg><math><mi>y</mi></math></div>";
+        let elements = parse_html(html).unwrap();
+        let div = &elements[0];
+        let svg = div.children()[0].as_element().unwrap();
+        assert_eq!(svg.namespace(), Namespace::Svg);
+        assert_eq!(svg.attributes()[0].name(), "viewBox");
+
+        let children: Vec<_> = svg
+            .child_elements()
+            .map(|child| (child.tag(), child.namespace(), child.children().len()))
+            .collect();
+        assert_eq!(
+            children,
+            vec![
+                ("linearGradient", Namespace::Svg, 0),
+                ("circle", Namespace::Svg, 0),
+                ("foreignObject", Namespace::Svg, 1),
+            ]
+        );
+        let p = svg.children()[2].as_element().unwrap().children()[0]
+            .as_element()
+            .unwrap();
+        assert_eq!(p.namespace(), Namespace::Html);
+
+        let mi = div.children()[1].as_element().unwrap().children()[0]
+            .as_element()
+            .unwrap();
+        assert_eq!(mi.namespace(), Namespace::MathMl);
+        assert_eq!(div.namespace(), Namespace::Html);
+    }
 }
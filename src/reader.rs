@@ -1,3 +1,104 @@
+use core::cell::Cell;
+#[cfg(feature = "std")]
+use std::io::BufRead;
+
+use crate::prelude::*;
+use crate::{HtmlError, Span};
+
+/// Items a [`SliceReader`] can search for. Bytes use `memchr`.
+pub trait Delimiter: PartialEq + Copy {
+    fn position(haystack: &[Self], needle: Self) -> Option<usize> {
+        haystack.iter().position(|item| *item == needle)
+    }
+
+    /// Where `needle` first occurs in `haystack` as a run of items.
+    fn find(haystack: &[Self], needle: &[Self]) -> Option<usize> {
+        if needle.is_empty() {
+            return Some(0);
+        }
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    }
+}
+
+impl Delimiter for u8 {
+    fn position(haystack: &[u8], needle: u8) -> Option<usize> {
+        memchr::memchr(needle, haystack)
+    }
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        memchr::memmem::find(haystack, needle)
+    }
+}
+
+impl Delimiter for char {}
+
+// `str::find`, but with `memchr` for the ASCII delimiters markup is made of
+fn find_char(haystack: &str, needle: char) -> Option<usize> {
+    if needle.is_ascii() {
+        memchr::memchr(needle as u8, haystack.as_bytes())
+    } else {
+        haystack.find(needle)
+    }
+}
+
+// The first character boundary at or after `pos`, or the end of `data`
+fn next_boundary(data: &str, pos: usize) -> usize {
+    let mut pos = pos.min(data.len());
+    while !data.is_char_boundary(pos) {
+        pos += 1;
+    }
+    pos
+}
+
+/// A position to return a reader to, from its `checkpoint`. For scanning
+/// ahead speculatively, such as trying to read a tag and treating it as text
+/// when that fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Mark(usize);
+
+impl Mark {
+    pub fn offset(self) -> usize {
+        self.0
+    }
+}
+
+/// What [`StrReader`] and [`SliceReader`] have in common, so code that
+/// scans input can be written once for `&str` and `&[u8]`.
+///
+/// Offsets count the input's items, which for both `&str` and `&[u8]` are
+/// bytes.
+pub trait Reader<'a> {
+    /// What the input is made of, `char` or the slice's element.
+    type Item: Copy;
+    /// The input type, `str` or a slice.
+    type Slice: ?Sized + 'a;
+
+    fn rest(&self) -> &'a Self::Slice;
+    fn offset(&self) -> usize;
+    fn is_eof(&self) -> bool;
+    fn seek(&self) -> Option<Self::Item>;
+    fn skip(&mut self, n: usize);
+    fn skip_while(&mut self, f: impl Fn(Self::Item) -> bool);
+    fn starts_with(&self, prefix: &Self::Slice) -> bool;
+    /// Reads items for as long as `f` holds.
+    fn read_while(&mut self, f: impl Fn(Self::Item) -> bool) -> &'a Self::Slice;
+    /// Reads exactly `n` items, or nothing when fewer are left.
+    fn take(&mut self, n: usize) -> Option<&'a Self::Slice>;
+    fn read_until(&mut self, delim: Self::Item) -> Result<&'a Self::Slice, ReadError>;
+    /// Like `read_until`, for a delimiter of several items, which is left
+    /// unread.
+    fn read_until_slice(&mut self, delim: &Self::Slice) -> Result<&'a Self::Slice, ReadError>;
+    fn checkpoint(&self) -> Mark;
+    fn rollback(&mut self, mark: Mark);
+
+    /// From `mark` up to where the reader is now.
+    fn span_since(&self, mark: Mark) -> Span {
+        Span::new(mark.offset(), self.offset())
+    }
+}
+
 pub struct SliceReader<'s, S> {
     pos: usize,
     data: &'s [S],
@@ -5,7 +106,7 @@ pub struct SliceReader<'s, S> {
 
 impl<'s, S> SliceReader<'s, S>
 where
-    S: PartialEq,
+    S: Delimiter,
 {
     pub fn new(data: &'s [S]) -> Self {
         Self { pos: 0, data }
@@ -23,37 +124,70 @@ where
         self.data.len()
     }
 
+    /// Items read so far.
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+
     pub fn seek(&self) -> Option<&S> {
-        self.rest().get(0)
+        self.rest().first()
     }
 
     pub fn seek_until(&self, delim: S) -> Option<&'s [S]> {
-        self.rest()
-            .iter()
-            .position(|ch| *ch == delim)
-            .map(|i| &self.data[self.pos..(self.pos + i)])
+        S::position(self.rest(), delim).map(|i| &self.data[self.pos..(self.pos + i)])
     }
 
     pub fn reset(&mut self) {
         self.pos = 0;
     }
 
+    pub fn checkpoint(&self) -> Mark {
+        Mark(self.pos)
+    }
+
+    /// Goes back (or forward) to where `checkpoint` was called.
+    pub fn rollback(&mut self, mark: Mark) {
+        self.pos = mark.0;
+    }
+
     pub fn skip(&mut self, n: usize) {
         self.pos += n;
     }
 
     pub fn skip_while(&mut self, f: impl Fn(&S) -> bool) {
-        while let Some(ch) = self.seek() {
-            if f(ch) {
-                self.skip(1);
-            } else {
-                break;
+        self.read_while(f);
+    }
+
+    /// Reads items for as long as `f` holds.
+    pub fn read_while(&mut self, f: impl Fn(&S) -> bool) -> &'s [S] {
+        let rest = self.rest();
+        let len = rest.iter().position(|ch| !f(ch)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Reads exactly `n` items, or nothing when fewer are left.
+    pub fn take(&mut self, n: usize) -> Option<&'s [S]> {
+        let taken = self.rest().get(..n)?;
+        self.pos += n;
+        Some(taken)
+    }
+
+    pub fn read_until(&mut self, delim: S) -> Result<&'s [S], ReadError> {
+        match S::position(self.rest(), delim) {
+            Some(i) => {
+                let search_str = &self.data[self.pos..(self.pos + i)];
+                self.pos += i;
+                Ok(search_str)
             }
+            None => Err(ReadError::DelimNotFound),
         }
     }
 
-    pub fn read_until(&mut self, delim: S) -> Result<&'s [S], ReadError> {
-        match self.rest().iter().position(|ch| *ch == delim) {
+    /// Like `read_until`, for a delimiter of several items, which is left
+    /// unread.
+    pub fn read_until_slice(&mut self, delim: &[S]) -> Result<&'s [S], ReadError> {
+        match S::find(self.rest(), delim) {
             Some(i) => {
                 let search_str = &self.data[self.pos..(self.pos + i)];
                 self.pos += i;
@@ -67,11 +201,34 @@ where
 pub struct StrReader<'a> {
     pos: usize,
     data: &'a str,
+    // Lines counted so far: up to which offset, how many, and where the
+    // last one starts. Counting picks up from there the next time a line
+    // is asked for, which keeps it off the paths that just move the reader.
+    lines: Cell<Lines>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Lines {
+    counted: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl Lines {
+    const START: Lines = Lines {
+        counted: 0,
+        line: 1,
+        line_start: 0,
+    };
 }
 
 impl<'a> StrReader<'a> {
     pub fn new(data: &'a str) -> Self {
-        Self { pos: 0, data }
+        Self {
+            pos: 0,
+            data,
+            lines: Cell::new(Lines::START),
+        }
     }
 
     pub fn rest(&self) -> &'a str {
@@ -86,36 +243,171 @@ impl<'a> StrReader<'a> {
         self.data.len()
     }
 
+    /// Bytes read so far.
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+
+    /// The 1-based line the reader is at.
+    pub fn line(&self) -> usize {
+        self.lines().line
+    }
+
+    /// The 1-based column the reader is at, counted in characters.
+    pub fn column(&self) -> usize {
+        let line_start = self.lines().line_start;
+        self.data[line_start..self.pos].chars().count() + 1
+    }
+
+    pub fn line_col(&self) -> (usize, usize) {
+        (self.line(), self.column())
+    }
+
+    // The lines up to `pos`, counting only what's been read since last time
+    fn lines(&self) -> Lines {
+        let mut lines = self.lines.get();
+        if lines.counted > self.pos {
+            // Rolled back: uncount the lines since
+            let unread = &self.data.as_bytes()[self.pos..lines.counted];
+            let newlines = memchr::memchr_iter(b'\n', unread).count();
+            if newlines > 0 {
+                lines.line -= newlines;
+                lines.line_start = memchr::memrchr(b'\n', &self.data.as_bytes()[..self.pos])
+                    .map_or(0, |last| last + 1);
+            }
+            lines.counted = self.pos;
+        }
+        let end = self.pos.min(self.data.len());
+        let passed = &self.data.as_bytes()[lines.counted..end];
+        if let Some(last) = memchr::memrchr(b'\n', passed) {
+            lines.line += memchr::memchr_iter(b'\n', passed).count();
+            lines.line_start = lines.counted + last + 1;
+        }
+        lines.counted = end;
+        self.lines.set(lines);
+        lines
+    }
+
+    /// Skips `n` bytes, or to the end of the input if there are fewer. A
+    /// character the skip would end inside of is skipped whole, so the
+    /// reader is always at a character.
     pub fn skip(&mut self, n: usize) {
-        self.pos += n;
+        let pos = self.pos + n;
+        // Just a bounds and a byte check when the skip is already fine
+        self.pos = match self.data.is_char_boundary(pos) {
+            true => pos,
+            false => next_boundary(self.data, pos),
+        };
+    }
+
+    /// Reads the next character.
+    pub fn next_char(&mut self) -> Option<char> {
+        let ch = self.seek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    pub fn skip_char(&mut self) {
+        self.next_char();
     }
 
     pub fn skip_while(&mut self, f: impl Fn(char) -> bool) {
-        while let Some(ch) = self.seek() {
-            if f(ch) {
-                self.skip(1);
-            } else {
-                break;
-            }
+        self.read_while(f);
+    }
+
+    /// Reads characters for as long as `f` holds.
+    pub fn read_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|ch| !f(ch)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Reads exactly `n` characters, or nothing when fewer are left.
+    pub fn take(&mut self, n: usize) -> Option<&'a str> {
+        let taken = self.peek_n(n);
+        if taken.chars().count() < n {
+            return None;
         }
+        self.pos += taken.len();
+        Some(taken)
     }
 
     pub fn reset(&mut self) {
         self.pos = 0;
+        self.lines.set(Lines::START);
+    }
+
+    pub fn checkpoint(&self) -> Mark {
+        Mark(self.pos)
+    }
+
+    /// Goes back (or forward) to where `checkpoint` was called.
+    pub fn rollback(&mut self, mark: Mark) {
+        self.pos = mark.0;
     }
 
+    /// The next character, decoding UTF-8 only when it isn't ASCII.
     pub fn seek(&self) -> Option<char> {
-        self.rest().chars().nth(0)
+        match self.peek_byte()? {
+            byte if byte.is_ascii() => Some(byte as char),
+            _ => self.rest().chars().next(),
+        }
     }
 
-    pub fn seek_until(&self, delim: char) -> Option<&'a str> {
+    /// The next byte, which may be part of a longer character.
+    pub fn peek_byte(&self) -> Option<u8> {
+        self.data.as_bytes().get(self.pos).copied()
+    }
+
+    /// Up to the next `n` characters, without reading them.
+    pub fn peek_n(&self, n: usize) -> &'a str {
+        let rest = self.rest();
+        match rest.char_indices().nth(n) {
+            Some((end, _)) => &rest[..end],
+            None => rest,
+        }
+    }
+
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        self.rest().starts_with(prefix)
+    }
+
+    /// Like `starts_with`, ignoring ASCII case, as for `<!DOCTYPE`.
+    pub fn starts_with_ignore_case(&self, prefix: &str) -> bool {
         self.rest()
-            .find(delim)
-            .map(|i| &self.data[self.pos..(self.pos + i)])
+            .as_bytes()
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix.as_bytes()))
+    }
+
+    /// Skips spaces, tabs, line feeds, form feeds and carriage returns.
+    pub fn skip_ascii_whitespace(&mut self) {
+        let rest = self.rest().as_bytes();
+        self.pos += rest
+            .iter()
+            .position(|byte| !byte.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+    }
+
+    pub fn seek_until(&self, delim: char) -> Option<&'a str> {
+        find_char(self.rest(), delim).map(|i| &self.data[self.pos..(self.pos + i)])
     }
 
     pub fn read_until(&mut self, delim: char) -> Result<&'a str, ReadError> {
-        match self.rest().find(delim) {
+        match find_char(self.rest(), delim) {
+            Some(i) => {
+                let search_str = &self.data[self.pos..(self.pos + i)];
+                self.pos += i;
+                Ok(search_str)
+            }
+            None => Err(ReadError::DelimNotFound),
+        }
+    }
+
+    /// Reads up to the first `delim`, such as `-->`, leaving it unread.
+    pub fn read_until_str(&mut self, delim: &str) -> Result<&'a str, ReadError> {
+        match memchr::memmem::find(self.rest().as_bytes(), delim.as_bytes()) {
             Some(i) => {
                 let search_str = &self.data[self.pos..(self.pos + i)];
                 self.pos += i;
@@ -126,11 +418,285 @@ impl<'a> StrReader<'a> {
     }
 }
 
+/// A reader that pulls UTF-8 input from a [`BufRead`] as it goes, so input
+/// doesn't have to be in memory all at once. What's been read is dropped
+/// the next time the buffer is refilled.
+///
+/// Reads that need more input than is buffered, like a `read_until` whose
+/// delimiter is in a later chunk, refill until they're satisfied or the
+/// input ends, so constructs split across chunks are read whole.
+#[cfg(feature = "std")]
+pub struct IoReader<R> {
+    inner: R,
+    buffer: String,
+    pos: usize,
+    // Bytes dropped from the front of `buffer`
+    dropped: usize,
+    // The start of a character whose other bytes haven't been read yet
+    partial: Vec<u8>,
+    eof: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> IoReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buffer: String::new(),
+            pos: 0,
+            dropped: 0,
+            partial: Vec::new(),
+            eof: false,
+        }
+    }
+
+    /// What's buffered and not yet read.
+    pub fn rest(&self) -> &str {
+        &self.buffer[self.pos..]
+    }
+
+    /// Bytes read so far.
+    pub fn offset(&self) -> usize {
+        self.dropped + self.pos
+    }
+
+    /// Reads another chunk into the buffer, returning false at the end of the
+    /// input.
+    pub fn fill(&mut self) -> Result<bool, HtmlError> {
+        if self.eof {
+            return Ok(false);
+        }
+        self.buffer.drain(..self.pos);
+        self.dropped += self.pos;
+        self.pos = 0;
+
+        let chunk = self.inner.fill_buf().map_err(HtmlError::Io)?;
+        if chunk.is_empty() {
+            self.eof = true;
+            if !self.partial.is_empty() {
+                return Err(HtmlError::DecodeFailed);
+            }
+            return Ok(false);
+        }
+        self.partial.extend_from_slice(chunk);
+        let len = chunk.len();
+        self.inner.consume(len);
+
+        let valid = match core::str::from_utf8(&self.partial) {
+            Ok(text) => text,
+            // A character cut off at the end of the chunk is finished by the next
+            Err(error) if error.error_len().is_none() => {
+                core::str::from_utf8(&self.partial[..error.valid_up_to()]).unwrap_or_default()
+            }
+            Err(_) => return Err(HtmlError::DecodeFailed),
+        };
+        self.buffer.push_str(valid);
+        let valid = valid.len();
+        self.partial.drain(..valid);
+        Ok(true)
+    }
+
+    // Refills until at least `len` bytes are buffered, or the input ends
+    fn fill_to(&mut self, len: usize) -> Result<(), HtmlError> {
+        while self.rest().len() < len && self.fill()? {}
+        Ok(())
+    }
+
+    /// The next character, or `None` at the end of the input.
+    pub fn seek(&mut self) -> Result<Option<char>, HtmlError> {
+        // A character is at most 4 bytes
+        self.fill_to(4)?;
+        Ok(self.rest().chars().next())
+    }
+
+    pub fn is_eof(&mut self) -> Result<bool, HtmlError> {
+        Ok(self.seek()?.is_none())
+    }
+
+    pub fn starts_with(&mut self, prefix: &str) -> Result<bool, HtmlError> {
+        self.fill_to(prefix.len())?;
+        Ok(self.rest().starts_with(prefix))
+    }
+
+    /// Skips `n` bytes, or to the end of the input if there are fewer, as
+    /// [`StrReader::skip`] does.
+    pub fn skip(&mut self, n: usize) -> Result<(), HtmlError> {
+        self.fill_to(n)?;
+        self.pos = next_boundary(&self.buffer, self.pos + n);
+        Ok(())
+    }
+
+    pub fn read_until(&mut self, delim: char) -> Result<&str, HtmlError> {
+        let mut searched = 0;
+        loop {
+            if let Some(i) = find_char(&self.rest()[searched..], delim) {
+                return Ok(self.advance(searched + i));
+            }
+            searched = self.rest().len();
+            if !self.fill()? {
+                return Err(self.unterminated());
+            }
+        }
+    }
+
+    /// Reads up to the first `delim`, such as `-->`, leaving it unread.
+    pub fn read_until_str(&mut self, delim: &str) -> Result<&str, HtmlError> {
+        let mut searched = 0;
+        loop {
+            let rest = self.rest().as_bytes();
+            if let Some(i) = memchr::memmem::find(&rest[searched..], delim.as_bytes()) {
+                return Ok(self.advance(searched + i));
+            }
+            // The delimiter may start in what's buffered and end in what isn't
+            searched = rest.len().saturating_sub(delim.len().saturating_sub(1));
+            if !self.fill()? {
+                return Err(self.unterminated());
+            }
+        }
+    }
+
+    fn advance(&mut self, len: usize) -> &str {
+        let start = self.pos;
+        self.pos += len;
+        &self.buffer[start..self.pos]
+    }
+
+    fn unterminated(&self) -> HtmlError {
+        let end = self.dropped + self.buffer.len();
+        HtmlError::ReaderError(ReadError::DelimNotFound, Span::new(self.offset(), end))
+    }
+}
+
+impl<'s, S: Delimiter> Reader<'s> for SliceReader<'s, S> {
+    type Item = S;
+    type Slice = [S];
+
+    fn rest(&self) -> &'s [S] {
+        SliceReader::rest(self)
+    }
+
+    fn offset(&self) -> usize {
+        SliceReader::offset(self)
+    }
+
+    fn is_eof(&self) -> bool {
+        SliceReader::is_eof(self)
+    }
+
+    fn seek(&self) -> Option<S> {
+        SliceReader::seek(self).copied()
+    }
+
+    fn skip(&mut self, n: usize) {
+        SliceReader::skip(self, n)
+    }
+
+    fn skip_while(&mut self, f: impl Fn(S) -> bool) {
+        SliceReader::skip_while(self, |item| f(*item))
+    }
+
+    fn starts_with(&self, prefix: &[S]) -> bool {
+        SliceReader::rest(self).starts_with(prefix)
+    }
+
+    fn read_while(&mut self, f: impl Fn(S) -> bool) -> &'s [S] {
+        SliceReader::read_while(self, |item| f(*item))
+    }
+
+    fn take(&mut self, n: usize) -> Option<&'s [S]> {
+        SliceReader::take(self, n)
+    }
+
+    fn read_until(&mut self, delim: S) -> Result<&'s [S], ReadError> {
+        SliceReader::read_until(self, delim)
+    }
+
+    fn read_until_slice(&mut self, delim: &[S]) -> Result<&'s [S], ReadError> {
+        SliceReader::read_until_slice(self, delim)
+    }
+
+    fn checkpoint(&self) -> Mark {
+        SliceReader::checkpoint(self)
+    }
+
+    fn rollback(&mut self, mark: Mark) {
+        SliceReader::rollback(self, mark)
+    }
+}
+
+impl<'a> Reader<'a> for StrReader<'a> {
+    type Item = char;
+    type Slice = str;
+
+    fn rest(&self) -> &'a str {
+        StrReader::rest(self)
+    }
+
+    fn offset(&self) -> usize {
+        StrReader::offset(self)
+    }
+
+    fn is_eof(&self) -> bool {
+        StrReader::is_eof(self)
+    }
+
+    fn seek(&self) -> Option<char> {
+        StrReader::seek(self)
+    }
+
+    fn skip(&mut self, n: usize) {
+        StrReader::skip(self, n)
+    }
+
+    fn skip_while(&mut self, f: impl Fn(char) -> bool) {
+        StrReader::skip_while(self, f)
+    }
+
+    fn starts_with(&self, prefix: &str) -> bool {
+        StrReader::starts_with(self, prefix)
+    }
+
+    fn read_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        StrReader::read_while(self, f)
+    }
+
+    fn take(&mut self, n: usize) -> Option<&'a str> {
+        StrReader::take(self, n)
+    }
+
+    fn read_until(&mut self, delim: char) -> Result<&'a str, ReadError> {
+        StrReader::read_until(self, delim)
+    }
+
+    fn read_until_slice(&mut self, delim: &str) -> Result<&'a str, ReadError> {
+        StrReader::read_until_str(self, delim)
+    }
+
+    fn checkpoint(&self) -> Mark {
+        StrReader::checkpoint(self)
+    }
+
+    fn rollback(&mut self, mark: Mark) {
+        StrReader::rollback(self, mark)
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ReadError {
     DelimNotFound,
 }
 
+impl core::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ReadError::DelimNotFound => f.write_str("unexpected end of input"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ReadError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,11 +712,177 @@ mod tests {
 
         let world = reader.rest();
         assert_eq!(world, "World");
+        assert_eq!(reader.offset(), 6);
+        assert_eq!(reader.line_col(), (1, 7));
+
+        let mut reader = StrReader::new("<p>\n  <a\n\u{e9}t\u{e9}>");
+        reader.read_until('a').unwrap();
+        assert_eq!((reader.offset(), reader.line(), reader.column()), (7, 2, 4));
+        reader.read_until('>').unwrap();
+        assert_eq!(reader.line_col(), (3, 4));
+        reader.reset();
+        assert_eq!(reader.line_col(), (1, 1));
     }
 
     #[test]
     pub fn read_until_delim_not_found() {
         let data = "Hello World";
         let mut slice_reader = SliceReader::new(data.as_bytes());
+        assert_eq!(slice_reader.read_until(b'!'), Err(ReadError::DelimNotFound));
+        assert_eq!(slice_reader.read_until(b'W'), Ok(&b"Hello "[..]));
+
+        let mut reader = StrReader::new("  \u{e9}t\u{e9} > x");
+        reader.skip_while(char::is_whitespace);
+        assert_eq!(reader.seek(), Some('\u{e9}'));
+        assert_eq!(reader.peek_byte(), Some(0xc3));
+        assert_eq!(reader.read_until('>'), Ok("\u{e9}t\u{e9} "));
+        assert_eq!(reader.seek_until('\u{e9}'), None);
+
+        // Skips never stop inside a character
+        let mut reader = StrReader::new("\u{e9}t\u{1f600}");
+        reader.skip(1);
+        assert_eq!(reader.rest(), "t\u{1f600}");
+        assert_eq!(reader.next_char(), Some('t'));
+        reader.skip(2);
+        assert!(reader.is_eof());
+        reader.skip(1);
+        assert_eq!((reader.offset(), reader.next_char()), (7, None));
+        reader.reset();
+        reader.skip_char();
+        assert_eq!(reader.seek(), Some('t'));
+
+        let reader = StrReader::new("<!DocType \u{e9}");
+        assert!(reader.starts_with_ignore_case("<!doctype"));
+        assert!(!reader.starts_with_ignore_case("<!doctype \u{c9}"));
+        assert_eq!(reader.peek_n(3), "<!D");
+        assert_eq!(StrReader::new("\u{e9}t\u{e9}").peek_n(2), "\u{e9}t");
+        assert_eq!(StrReader::new("ab").peek_n(5), "ab");
+    }
+
+    #[test]
+    pub fn read_until_str() {
+        let mut reader = StrReader::new("a > b -- c --> d");
+        assert_eq!(reader.read_until_str("-->"), Ok("a > b -- c "));
+        assert!(reader.rest().starts_with("-->"));
+        assert_eq!(reader.read_until_str("]]>"), Err(ReadError::DelimNotFound));
+        assert!(reader.starts_with("-->") && !reader.starts_with("--> d!"));
+        assert_eq!(reader.offset(), 11);
+
+        let mut bytes = SliceReader::new(&b"x]]y]]>z"[..]);
+        assert_eq!(bytes.read_until_slice(b"]]>"), Ok(&b"x]]y"[..]));
+        let chars: Vec<char> = "x]]y]]>z".chars().collect();
+        let mut chars = SliceReader::new(&chars[..]);
+        assert_eq!(
+            chars.read_until_slice(&[']', ']', '>']).map(<[char]>::len),
+            Ok(4)
+        );
+    }
+
+    #[test]
+    pub fn checkpoints() {
+        let mut reader = StrReader::new("<a\nhref=x\n<b>");
+        reader.skip(1);
+        let mark = reader.checkpoint();
+        assert_eq!(reader.read_until('<'), Ok("a\nhref=x\n"));
+        assert_eq!(reader.line_col(), (3, 1));
+        reader.rollback(mark);
+        assert_eq!(mark.offset(), reader.offset());
+        assert_eq!(
+            (reader.rest(), reader.line_col()),
+            ("a\nhref=x\n<b>", (1, 2))
+        );
+        reader.skip(3);
+        assert_eq!(reader.line_col(), (2, 2));
+
+        let mut bytes = SliceReader::new(&b"abc"[..]);
+        let mark = bytes.checkpoint();
+        bytes.skip_while(u8::is_ascii_alphabetic);
+        assert!(bytes.is_eof());
+        bytes.rollback(mark);
+        assert_eq!(bytes.rest(), b"abc");
+    }
+
+    #[test]
+    pub fn read_while_and_take() {
+        let mut reader = StrReader::new("h1\u{e9} class");
+        assert_eq!(reader.read_while(char::is_alphanumeric), "h1\u{e9}");
+        assert_eq!(reader.read_while(char::is_alphanumeric), "");
+        assert_eq!(reader.take(3), Some(" cl"));
+        assert_eq!(reader.take(4), None);
+        assert_eq!(reader.rest(), "ass");
+        assert_eq!(StrReader::new("\u{e9}t\u{e9}").take(2), Some("\u{e9}t"));
+
+        let mut bytes = SliceReader::new(&b"abc1"[..]);
+        assert_eq!(bytes.read_while(u8::is_ascii_alphabetic), b"abc");
+        assert_eq!(bytes.take(2), None);
+        assert_eq!(bytes.take(1), Some(&b"1"[..]));
+        assert!(bytes.is_eof());
+    }
+
+    // Reads `key=value`, generically over the reader
+    fn key<'a, R: Reader<'a>>(reader: &mut R, equals: R::Item) -> (&'a R::Slice, Span) {
+        let mark = reader.checkpoint();
+        let key = reader.read_until(equals).unwrap();
+        let span = reader.span_since(mark);
+        reader.skip(1);
+        (key, span)
+    }
+
+    #[test]
+    pub fn generic_readers() {
+        let mut reader = StrReader::new("width=10");
+        assert_eq!(key(&mut reader, '='), ("width", Span::new(0, 5)));
+        assert_eq!(Reader::rest(&reader), "10");
+
+        let mut bytes = SliceReader::new(&b"width=10"[..]);
+        assert_eq!(key(&mut bytes, b'='), (&b"width"[..], Span::new(0, 5)));
+        Reader::skip_while(&mut bytes, |byte| byte.is_ascii_digit());
+        assert!(Reader::is_eof(&bytes));
+
+        let mut reader = StrReader::new("<!-- a -->b");
+        assert!(Reader::starts_with(&reader, "<!--"));
+        assert_eq!(Reader::take(&mut reader, 4), Some("<!--"));
+        assert_eq!(Reader::read_while(&mut reader, char::is_whitespace), " ");
+        assert_eq!(Reader::read_until_slice(&mut reader, "-->"), Ok("a "));
+        let mut bytes = SliceReader::new(&b"<!-- a -->b"[..]);
+        assert!(Reader::starts_with(&bytes, &b"<!--"[..]));
+        assert_eq!(Reader::take(&mut bytes, 4), Some(&b"<!--"[..]));
+        assert_eq!(Reader::read_while(&mut bytes, |byte| byte == b' '), b" ");
+        assert_eq!(Reader::read_until_slice(&mut bytes, b"-->"), Ok(&b"a "[..]));
+        assert_eq!(Reader::take(&mut bytes, 5), None);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    pub fn io_reader() {
+        let html = "<p title=\"caf\u{e9}\">x</p><!-- a - b -->end";
+        // Three bytes at a time splits the `é` and the `-->`
+        let mut reader = IoReader::new(std::io::BufReader::with_capacity(3, html.as_bytes()));
+        assert!(reader.starts_with("<p title").unwrap());
+        assert_eq!(reader.read_until('"').unwrap(), "<p title=");
+        reader.skip(1).unwrap();
+        assert_eq!(reader.read_until('"').unwrap(), "caf\u{e9}");
+        assert_eq!(reader.read_until('<').unwrap(), "\">x");
+        assert_eq!(reader.read_until_str("-->").unwrap(), "</p><!-- a - b ");
+        assert_eq!(reader.offset(), html.len() - "-->end".len());
+        reader.skip(3).unwrap();
+        assert_eq!(reader.seek().unwrap(), Some('e'));
+        assert!(matches!(
+            reader.read_until('>'),
+            Err(HtmlError::ReaderError(ReadError::DelimNotFound, span)) if span == Span::new(36, 39)
+        ));
+        reader.skip(10).unwrap();
+        assert!(reader.is_eof().unwrap());
+
+        let mut reader = IoReader::new(&b"ab\xff"[..]);
+        assert!(matches!(
+            reader.read_until('>'),
+            Err(HtmlError::DecodeFailed)
+        ));
+        let mut reader = IoReader::new(&b"ab\xc3"[..]);
+        assert!(matches!(
+            reader.read_until('>'),
+            Err(HtmlError::DecodeFailed)
+        ));
     }
 }
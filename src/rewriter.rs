@@ -0,0 +1,418 @@
+use std::io::Write;
+
+use crate::prelude::*;
+use crate::{
+    decode_entities, escape_attribute,
+    parser::{implied_end_count, is_void_element, HtmlAst, Tokenizer, TokenizerState},
+    selector::Element,
+    stream::push_utf8,
+    HtmlAttributeOwned, HtmlError, Namespace, ParseOptions, Selector,
+};
+
+type Handler<'h> = Box<dyn FnMut(&mut RewriteElement) + 'h>;
+
+/// Rewrites a document as it streams through, lol_html style: handlers
+/// registered with [`on`](Self::on) run for each matching start tag, and
+/// everything they don't touch is copied to the output byte for byte.
+///
+/// Selectors are matched against the open elements only, so sibling
+/// combinators (`+`, `~`) never match.
+pub struct HtmlRewriter<'h, W> {
+    output: W,
+    handlers: Vec<(Selector, Handler<'h>)>,
+    options: ParseOptions,
+    buffer: String,
+    // Bytes of a UTF-8 sequence split across chunks
+    partial: Vec<u8>,
+    stack: Vec<OpenElement>,
+    // Where the tokenizer stopped, for the next one
+    state: TokenizerState,
+    // How much of the text at the start of `buffer` was searched already
+    text_scanned: usize,
+    // Depth of the element whose contents are being dropped, and whether
+    // its end tag is dropped too
+    skip: Option<(usize, bool)>,
+}
+
+struct OpenElement {
+    name: String,
+    attributes: Vec<HtmlAttributeOwned>,
+    namespace: Namespace,
+}
+
+/// A matched element, as seen by a rewriter handler.
+#[derive(Debug)]
+pub struct RewriteElement {
+    tag: String,
+    attributes: Vec<HtmlAttributeOwned>,
+    attributes_changed: bool,
+    action: RewriteAction,
+}
+
+#[derive(Debug)]
+enum RewriteAction {
+    Keep,
+    SetInnerContent(String),
+    Replace(String),
+    Remove,
+}
+
+impl RewriteElement {
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn attributes(&self) -> &[HtmlAttributeOwned] {
+        &self.attributes
+    }
+
+    pub fn get_attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attr| attr.name().eq_ignore_ascii_case(name))
+            .map(|attr| attr.value().unwrap_or_default())
+    }
+
+    pub fn set_attr(&mut self, name: &str, value: impl Into<String>) {
+        let attr = HtmlAttributeOwned::new(name, Some(value.into()));
+        match self
+            .attributes
+            .iter_mut()
+            .find(|attr| attr.name().eq_ignore_ascii_case(name))
+        {
+            Some(existing) => *existing = attr,
+            None => self.attributes.push(attr),
+        }
+        self.attributes_changed = true;
+    }
+
+    pub fn remove_attr(&mut self, name: &str) {
+        let len = self.attributes.len();
+        self.attributes
+            .retain(|attr| !attr.name().eq_ignore_ascii_case(name));
+        self.attributes_changed |= self.attributes.len() != len;
+    }
+
+    /// Replaces the element's contents with `html`, which is written as-is.
+    pub fn set_inner_content(&mut self, html: impl Into<String>) {
+        self.action = RewriteAction::SetInnerContent(html.into());
+    }
+
+    /// Replaces the whole element with `html`, which is written as-is.
+    pub fn replace(&mut self, html: impl Into<String>) {
+        self.action = RewriteAction::Replace(html.into());
+    }
+
+    /// Drops the element along with its contents.
+    pub fn remove(&mut self) {
+        self.action = RewriteAction::Remove;
+    }
+}
+
+impl<'h, W: Write> HtmlRewriter<'h, W> {
+    pub fn new(output: W) -> Self {
+        Self {
+            output,
+            handlers: Vec::new(),
+            options: ParseOptions {
+                preserve_source: true,
+                ..Default::default()
+            },
+            buffer: String::new(),
+            partial: Vec::new(),
+            stack: Vec::new(),
+            state: TokenizerState::default(),
+            text_scanned: 0,
+            skip: None,
+        }
+    }
+
+    pub fn on(
+        &mut self,
+        selector: &str,
+        handler: impl FnMut(&mut RewriteElement) + 'h,
+    ) -> Result<&mut Self, HtmlError> {
+        self.handlers
+            .push((Selector::parse(selector)?, Box::new(handler)));
+        Ok(self)
+    }
+
+    /// Feeds the next chunk of input, writing out whatever is complete.
+    pub fn write(&mut self, chunk: &[u8]) -> Result<(), HtmlError> {
+        push_utf8(&mut self.buffer, &mut self.partial, chunk)?;
+        self.rewrite(false)
+    }
+
+    /// Flushes the rest of the input and hands back the output.
+    pub fn end(mut self) -> Result<W, HtmlError> {
+        if !self.partial.is_empty() {
+            return Err(HtmlError::DecodeFailed);
+        }
+        self.rewrite(true)?;
+        Ok(self.output)
+    }
+
+    fn rewrite(&mut self, eof: bool) -> Result<(), HtmlError> {
+        let buffer = core::mem::take(&mut self.buffer);
+        let mut tokenizer = Tokenizer::new(&buffer, &self.options);
+        tokenizer.state = self.state;
+        tokenizer.text_scanned = self.text_scanned;
+        let mut consumed = 0;
+
+        while let Some((span, tokens)) = tokenizer.next_complete(eof)? {
+            consumed = span.end;
+            let source = &buffer[span];
+            match tokens.first() {
+                Some(HtmlAst::StartTag(name)) => self.start_tag(name, &tokens[1..], source)?,
+                Some(HtmlAst::EndTag(name)) => self.end_tag(name, source)?,
+                _ if self.skip.is_some() => {}
+                _ => self.emit(source)?,
+            }
+        }
+
+        self.state = tokenizer.state;
+        self.text_scanned = tokenizer.text_scanned;
+        self.buffer = buffer;
+        self.buffer.drain(..consumed);
+        Ok(())
+    }
+
+    fn emit(&mut self, html: &str) -> Result<(), HtmlError> {
+        self.output
+            .write_all(html.as_bytes())
+            .map_err(HtmlError::Io)
+    }
+
+    fn start_tag(
+        &mut self,
+        name: &str,
+        tokens: &[HtmlAst<'_>],
+        source: &str,
+    ) -> Result<(), HtmlError> {
+        let attributes = tokens
+            .iter()
+            .filter_map(|token| match token {
+                HtmlAst::Attribute(name, value) => Some(HtmlAttributeOwned::new(
+                    *name,
+                    value.map(|value| decode_entities(value).into_owned()),
+                )),
+                _ => None,
+            })
+            .collect();
+        let namespace = match self.stack.last() {
+            Some(parent) => parent.namespace.for_child(&parent.name, name),
+            None => Namespace::Html.for_child("", name),
+        };
+        // A `<li>` ends an open `<li>` and whatever is open inside it, even
+        // one being dropped
+        if namespace == Namespace::Html {
+            let open = self.stack.iter().rev().map(|open| open.name.as_str());
+            let len = self.stack.len() - implied_end_count(open, name);
+            self.stack.truncate(len);
+            if self.skip.is_some_and(|(depth, _)| len < depth) {
+                self.skip = None;
+            }
+        }
+        let self_closing = tokens.contains(&HtmlAst::SelfClosing);
+        // Foreign elements written as `<circle/>` have no end tag
+        let void = match namespace {
+            Namespace::Html => is_void_element(name),
+            _ => self_closing,
+        };
+
+        self.stack.push(OpenElement {
+            name: name.to_string(),
+            attributes,
+            namespace,
+        });
+        if self.skip.is_some() {
+            if void {
+                self.stack.pop();
+            }
+            return Ok(());
+        }
+
+        let current = self.stack.last().unwrap();
+        let mut element = RewriteElement {
+            tag: current.name.clone(),
+            attributes: current.attributes.clone(),
+            attributes_changed: false,
+            action: RewriteAction::Keep,
+        };
+        let open = StackElement(&self.stack);
+        for (selector, handler) in &mut self.handlers {
+            if selector.matches(&open) {
+                handler(&mut element);
+            }
+        }
+
+        let depth = self.stack.len();
+        if void {
+            self.stack.pop();
+        }
+        match element.action {
+            RewriteAction::Remove => {
+                self.skip = (!void).then_some((depth, true));
+                Ok(())
+            }
+            RewriteAction::Replace(html) => {
+                self.skip = (!void).then_some((depth, true));
+                self.emit(&html)
+            }
+            action => {
+                if element.attributes_changed {
+                    let tag = rebuild_start_tag(name, &element.attributes, self_closing);
+                    self.emit(&tag)?;
+                } else {
+                    self.emit(source)?;
+                }
+                if let RewriteAction::SetInnerContent(html) = action {
+                    self.skip = (!void).then_some((depth, false));
+                    self.emit(&html)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn end_tag(&mut self, name: &str, source: &str) -> Result<(), HtmlError> {
+        // Stray end tags are passed through untouched
+        let Some(index) = self
+            .stack
+            .iter()
+            .rposition(|open| open.name.eq_ignore_ascii_case(name))
+        else {
+            return match self.skip {
+                Some(_) => Ok(()),
+                None => self.emit(source),
+            };
+        };
+        self.stack.truncate(index);
+
+        match self.skip {
+            // Still inside the skipped element
+            Some((depth, _)) if index >= depth => Ok(()),
+            Some((depth, drop_end_tag)) => {
+                self.skip = None;
+                if index + 1 == depth && drop_end_tag {
+                    Ok(())
+                } else {
+                    self.emit(source)
+                }
+            }
+            None => self.emit(source),
+        }
+    }
+}
+
+fn rebuild_start_tag(name: &str, attributes: &[HtmlAttributeOwned], self_closing: bool) -> String {
+    let mut tag = format!("<{}", name);
+    for attr in attributes {
+        match attr.value() {
+            Some(value) => {
+                tag.push_str(&format!(" {}=\"{}\"", attr.name(), escape_attribute(value)))
+            }
+            None => tag.push_str(&format!(" {}", attr.name())),
+        }
+    }
+    tag.push_str(if self_closing { "/>" } else { ">" });
+    tag
+}
+
+// The innermost open element, with the rest of the stack as its ancestors
+#[derive(Clone, Copy)]
+struct StackElement<'s>(&'s [OpenElement]);
+
+impl Element for StackElement<'_> {
+    fn tag(&self) -> &str {
+        &self.0.last().unwrap().name
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.0
+            .last()
+            .unwrap()
+            .attributes
+            .iter()
+            .find(|attr| attr.name().eq_ignore_ascii_case(name))
+            .map(|attr| attr.value().unwrap_or_default())
+    }
+
+    fn parent_element(&self) -> Option<Self> {
+        match self.0 {
+            [] | [_] => None,
+            [ancestors @ .., _] => Some(Self(ancestors)),
+        }
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(html: &str, chunk_size: usize) -> String {
+        let mut rewriter = HtmlRewriter::new(Vec::new());
+        rewriter
+            .on("a[href^='http:']", |a| {
+                let href = a.get_attr("href").unwrap().replacen("http:", "https:", 1);
+                a.set_attr("href", href);
+            })
+            .unwrap()
+            .on("body .ad", RewriteElement::remove)
+            .unwrap()
+            .on("#title", |title| title.set_inner_content("<b>New</b>"))
+            .unwrap()
+            .on("img", |img| img.replace("<picture></picture>"))
+            .unwrap();
+
+        for chunk in html.as_bytes().chunks(chunk_size) {
+            rewriter.write(chunk).unwrap();
+        }
+        String::from_utf8(rewriter.end().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn implied_end_tags_and_self_closing() {
+        let rewrite = |html: &str| {
+            let mut rewriter = HtmlRewriter::new(Vec::new());
+            rewriter
+                .on(".ad", RewriteElement::remove)
+                .unwrap()
+                .on("path", |path| path.set_attr("fill", "red"))
+                .unwrap()
+                .on("circle", |circle| circle.remove())
+                .unwrap();
+            rewriter.write(html.as_bytes()).unwrap();
+            String::from_utf8(rewriter.end().unwrap()).unwrap()
+        };
+
+        assert_eq!(
+            rewrite("<ul><li class=ad>a<li>b<li>c</ul>"),
+            "<ul><li>b<li>c</ul>"
+        );
+        assert_eq!(rewrite("<p class=ad>buy<div>rest</div>"), "<div>rest</div>");
+        assert_eq!(
+            rewrite("<svg><path d=\"M0\"/><circle/><rect/></svg>"),
+            "<svg><path d=\"M0\" fill=\"red\"/><rect/></svg>"
+        );
+    }
+
+    #[test]
+    fn rewrite_stream() {
+        let html = "<!DOCTYPE html>\n<HTML><body class='x'>\n  <h1 id=title>Old <i>title</i></h1>\n  <a href=\"http://example.com/?a=1&amp;b=2\" rel=next>link</a>\n  <div class=\"ad\"><p>buy <img src=ad.png></p></div>\n  <a href='/local'>kept</a> <img src=\"x.png\">\n<script>s = '<img src=x.png>'</script></body></HTML>\n";
+        let expected = "<!DOCTYPE html>\n<HTML><body class='x'>\n  <h1 id=title><b>New</b></h1>\n  <a href=\"https://example.com/?a=1&amp;b=2\" rel=\"next\">link</a>\n  \n  <a href='/local'>kept</a> <picture></picture>\n<script>s = '<img src=x.png>'</script></body></HTML>\n";
+
+        for chunk_size in [1, 5, 64, html.len()] {
+            assert_eq!(
+                rewrite(html, chunk_size),
+                expected,
+                "chunk size {}",
+                chunk_size
+            );
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Allowlist-based cleaning of untrusted markup. Anything a [`Policy`]
+//! doesn't allow is removed: disallowed elements are replaced by their
+//! contents (or dropped along with them, for scripts and the like), and
+//! disallowed attributes and URLs are dropped.
+
+use crate::prelude::*;
+use crate::urls::URL_ATTRIBUTES;
+use crate::{
+    document_to_string, parse_document_with, HtmlAttribute, HtmlDocument, HtmlNode, ParseMode,
+    ParseOptions,
+};
+
+// Elements whose contents are never safe to keep as text
+const DROPPED_ELEMENTS: &[&str] = &[
+    "embed", "frame", "frameset", "iframe", "noembed", "noframes", "noscript", "object", "script",
+    "style", "template", "title", "xmp",
+];
+
+/// What survives sanitizing. `Policy::default()` allows common formatting,
+/// lists, tables, links and images; `Policy::new()` allows nothing and is
+/// built up with the `allow_` methods.
+#[derive(Debug, Clone)]
+pub struct Policy {
+    tags: Vec<String>,
+    // (tag, attribute) pairs, with `*` standing for every allowed tag
+    attributes: Vec<(String, String)>,
+    url_schemes: Vec<String>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::new()
+            .allow_tags(&[
+                "a",
+                "abbr",
+                "b",
+                "blockquote",
+                "br",
+                "code",
+                "dd",
+                "del",
+                "div",
+                "dl",
+                "dt",
+                "em",
+                "h1",
+                "h2",
+                "h3",
+                "h4",
+                "h5",
+                "h6",
+                "hr",
+                "i",
+                "img",
+                "ins",
+                "kbd",
+                "li",
+                "ol",
+                "p",
+                "pre",
+                "q",
+                "s",
+                "small",
+                "span",
+                "strong",
+                "sub",
+                "sup",
+                "table",
+                "tbody",
+                "td",
+                "tfoot",
+                "th",
+                "thead",
+                "tr",
+                "u",
+                "ul",
+            ])
+            .allow_attributes("a", &["href", "title"])
+            .allow_attributes("img", &["src", "alt", "title", "width", "height"])
+            .allow_attributes("td", &["colspan", "rowspan"])
+            .allow_attributes("th", &["colspan", "rowspan"])
+            .allow_url_schemes(&["http", "https", "mailto"])
+    }
+}
+
+impl Policy {
+    pub fn new() -> Self {
+        Self {
+            tags: Vec::new(),
+            attributes: Vec::new(),
+            url_schemes: Vec::new(),
+        }
+    }
+
+    pub fn allow_tags(mut self, tags: &[&str]) -> Self {
+        self.tags
+            .extend(tags.iter().map(|tag| tag.to_ascii_lowercase()));
+        self
+    }
+
+    /// Allows `attributes` on `tag`, or on every allowed tag if `tag` is
+    /// `*`. Event handlers like `onclick` are never allowed.
+    pub fn allow_attributes(mut self, tag: &str, attributes: &[&str]) -> Self {
+        let tag = tag.to_ascii_lowercase();
+        self.attributes.extend(
+            attributes
+                .iter()
+                .map(|attribute| (tag.clone(), attribute.to_ascii_lowercase())),
+        );
+        self
+    }
+
+    /// Schemes URL attributes may use, like `https`. Relative URLs are
+    /// always allowed.
+    pub fn allow_url_schemes(mut self, schemes: &[&str]) -> Self {
+        self.url_schemes
+            .extend(schemes.iter().map(|scheme| scheme.to_ascii_lowercase()));
+        self
+    }
+
+    /// Parses `html` and serializes what the policy allows of it.
+    pub fn sanitize(&self, html: &str) -> String {
+        let options = ParseOptions {
+            mode: ParseMode::Lenient,
+            ..Default::default()
+        };
+        let mut document = parse_document_with(html, &options).unwrap_or_default();
+        self.sanitize_document(&mut document);
+        document_to_string(document)
+    }
+
+    pub fn sanitize_document(&self, document: &mut HtmlDocument<'_>) {
+        self.sanitize_nodes(document.nodes_mut());
+    }
+
+    /// Cleans `nodes` in place. Comments, the doctype and CDATA are removed.
+    pub fn sanitize_nodes(&self, nodes: &mut Vec<HtmlNode<'_>>) {
+        let mut clean = Vec::with_capacity(nodes.len());
+        for node in nodes.drain(..) {
+            match node {
+                HtmlNode::Element(mut element) => {
+                    if contains(DROPPED_ELEMENTS, &element.tag) {
+                        continue;
+                    }
+                    let mut children = core::mem::take(element.children_vec_mut());
+                    self.sanitize_nodes(&mut children);
+                    if !self
+                        .tags
+                        .iter()
+                        .any(|tag| tag.eq_ignore_ascii_case(&element.tag))
+                    {
+                        clean.append(&mut children);
+                        continue;
+                    }
+                    *element.children_vec_mut() = children;
+                    let tag = element.tag.clone();
+                    element
+                        .attributes_vec_mut()
+                        .retain(|attribute| self.allows_attribute(&tag, attribute));
+                    clean.push(HtmlNode::Element(element));
+                }
+                HtmlNode::Text(_) => clean.push(node),
+                _ => {}
+            }
+        }
+        *nodes = clean;
+    }
+
+    fn allows_attribute(&self, tag: &str, attribute: &HtmlAttribute<'_>) -> bool {
+        let name = attribute.name();
+        if name.len() > 2 && name[..2].eq_ignore_ascii_case("on") {
+            return false;
+        }
+        let allowed = self.attributes.iter().any(|(allowed_tag, allowed)| {
+            (allowed_tag == "*" || allowed_tag.eq_ignore_ascii_case(tag))
+                && allowed.eq_ignore_ascii_case(name)
+        });
+        allowed
+            && (!(contains(URL_ATTRIBUTES, name) || name.eq_ignore_ascii_case("srcset"))
+                || attribute.value().is_none_or(|url| self.allows_url(url)))
+    }
+
+    fn allows_url(&self, url: &str) -> bool {
+        // Browsers ignore whitespace and control characters in schemes, as
+        // in `java\tscript:`
+        let url: String = url
+            .chars()
+            .filter(|ch| !ch.is_whitespace() && !ch.is_control())
+            .collect();
+        let Some(colon) = url.find(':') else {
+            return true;
+        };
+        // A colon after a path, query or fragment starts doesn't end a scheme
+        if url[..colon].contains(['/', '?', '#']) {
+            return true;
+        }
+        let scheme = &url[..colon];
+        self.url_schemes
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+    }
+}
+
+fn contains(list: &[&str], tag: &str) -> bool {
+    list.iter().any(|name| name.eq_ignore_ascii_case(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy() {
+        let policy = Policy::default();
+        assert_eq!(
+            policy.sanitize(
+                "<p onclick=\"steal()\">Hi <script>alert(1)</script><blink>there</blink></p>"
+            ),
+            "<p>Hi there</p>"
+        );
+        assert_eq!(
+            policy.sanitize("<a href=\"java&#9;script:alert(1)\" title=\"x\">a</a><a href=\"/ok:1\">b</a><a href=\"HTTPS://x.y\">c</a>"),
+            "<a title=\"x\">a</a><a href=\"/ok:1\">b</a><a href=\"HTTPS://x.y\">c</a>"
+        );
+        assert_eq!(
+            policy.sanitize("<!-- c --><img src=\"data:text/html,x\" alt=\"&lt;\" style=\"x\">"),
+            "<img alt=\"<\">"
+        );
+    }
+
+    #[test]
+    fn custom_policy() {
+        let policy = Policy::new()
+            .allow_tags(&["span"])
+            .allow_attributes("*", &["class", "onload"]);
+        assert_eq!(
+            policy.sanitize("<div class=\"a\" onload=\"x\"><span class=\"b\">t</span></div>"),
+            "<span class=\"b\">t</span>"
+        );
+
+        let policy = Policy::new()
+            .allow_tags(&["a"])
+            .allow_attributes("a", &["xlink:href"]);
+        assert_eq!(
+            policy.sanitize("<a xlink:href=\"javascript:x()\">a</a><a xlink:href=\"/b\">b</a>"),
+            "<a>a</a><a xlink:href=\"/b\">b</a>"
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use crate::prelude::*;
+use crate::HashSet;
+use alloc::borrow::Cow;
+use core::ops::{Deref, DerefMut};
+
+use crate::{Document, HtmlAttribute, HtmlError, Node, NodeData, NodeId, Selector};
+
+/// A set of nodes in a [`Document`] that operations apply to all at once,
+/// in the style of jQuery:
+///
+/// ```
+/// # use html_parser::{has_class, Document};
+/// let document = Document::parse(
+///     r#"<ul class="menu"><li data-id="1">a</li><li class="active" data-id="2">b</li></ul>"#,
+/// )
+/// .unwrap();
+/// let id = document
+///     .find("ul.menu")?
+///     .children("li")?
+///     .filter(has_class("active"))
+///     .attr("data-id");
+/// assert_eq!(id.as_deref(), Some("2"));
+/// # Ok::<(), html_parser::HtmlError>(())
+/// ```
+///
+/// Selections from [`Document::find`] only read; those from
+/// [`Document::find_mut`] can also change the nodes. Methods taking a
+/// selector fail with [`HtmlError::InvalidSelector`] when it doesn't parse.
+#[derive(Debug, Clone)]
+pub struct Selection<D> {
+    document: D,
+    nodes: Vec<NodeId>,
+}
+
+impl<'a> Document<'a> {
+    /// The elements matching `selector`.
+    pub fn find(&self, selector: &str) -> Result<Selection<&Self>, HtmlError> {
+        let root = self.root();
+        Selection::new(self, vec![root]).find(selector)
+    }
+
+    pub fn find_mut(&mut self, selector: &str) -> Result<Selection<&mut Self>, HtmlError> {
+        let root = self.root();
+        Selection::new(self, vec![root]).find(selector)
+    }
+}
+
+/// For [`Selection::filter`]: whether an element has the class.
+pub fn has_class(class: &str) -> impl Fn(&Node<'_>) -> bool + '_ {
+    move |node| {
+        node.get_attr("class")
+            .is_some_and(|classes| classes.split_ascii_whitespace().any(|name| name == class))
+    }
+}
+
+impl<'a, D: Deref<Target = Document<'a>>> Selection<D> {
+    pub fn new(document: D, nodes: Vec<NodeId>) -> Self {
+        Self { document, nodes }
+    }
+
+    pub fn nodes(&self) -> &[NodeId] {
+        &self.nodes
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Descendants of the selected nodes that match, in document order.
+    pub fn find(mut self, selector: &str) -> Result<Self, HtmlError> {
+        let selector = Selector::parse(selector)?;
+        let document = &*self.document;
+        let selected: HashSet<_> = self.nodes.iter().copied().collect();
+        let nodes = document
+            .descendants(document.root())
+            .filter(|&id| {
+                document.matches(id, &selector)
+                    && document.ancestors(id).any(|id| selected.contains(&id))
+            })
+            .collect();
+        Ok(self.with_nodes(nodes))
+    }
+
+    /// Child elements of the selected nodes that match.
+    pub fn children(self, selector: &str) -> Result<Self, HtmlError> {
+        let selector = Selector::parse(selector)?;
+        let document = &*self.document;
+        let nodes = self
+            .nodes
+            .iter()
+            .flat_map(|&id| document.children(id))
+            .filter(|&id| document.matches(id, &selector))
+            .collect();
+        Ok(self.with_nodes(nodes))
+    }
+
+    /// The parent element of each selected node, once each.
+    pub fn parent(self) -> Self {
+        let document = &*self.document;
+        let mut seen = HashSet::new();
+        let nodes = self
+            .nodes
+            .iter()
+            .filter_map(|&id| document.parent(id))
+            .filter(|&id| document.get(id).is_element() && seen.insert(id))
+            .collect();
+        self.with_nodes(nodes)
+    }
+
+    /// Keeps the nodes for which `predicate` holds.
+    pub fn filter(self, predicate: impl Fn(&Node<'a>) -> bool) -> Self {
+        let document = &*self.document;
+        let nodes = self
+            .nodes
+            .iter()
+            .copied()
+            .filter(|&id| predicate(document.get(id)))
+            .collect();
+        self.with_nodes(nodes)
+    }
+
+    pub fn first(mut self) -> Self {
+        self.nodes.truncate(1);
+        self
+    }
+
+    /// The attribute of the first selected node.
+    pub fn attr(&self, name: &str) -> Option<String> {
+        let id = *self.nodes.first()?;
+        self.document.get(id).get_attr(name).map(str::to_string)
+    }
+
+    /// The text of the selected nodes and their descendants, joined.
+    pub fn text(&self) -> String {
+        let document = &*self.document;
+        let mut text = String::new();
+        for &id in &self.nodes {
+            let nodes = core::iter::once(id).chain(document.descendants(id));
+            text.extend(nodes.filter_map(|id| document.get(id).text()));
+        }
+        text
+    }
+
+    fn with_nodes(mut self, nodes: Vec<NodeId>) -> Self {
+        self.nodes = nodes;
+        self
+    }
+}
+
+impl<'a, D: DerefMut<Target = Document<'a>>> Selection<D> {
+    /// Sets the attribute on every selected element.
+    pub fn set_attr(&mut self, name: &'a str, value: impl Into<Cow<'a, str>>) -> &mut Self {
+        let value = value.into();
+        for &id in &self.nodes {
+            if let NodeData::Element { attributes, .. } = self.document.get_mut(id).data_mut() {
+                match attributes
+                    .iter_mut()
+                    .find(|attr| attr.name.eq_ignore_ascii_case(name))
+                {
+                    Some(attr) => attr.set_value(value.clone()),
+                    None => attributes.push(HtmlAttribute {
+                        name: name.into(),
+                        value: Some(value.clone()),
+                    }),
+                }
+            }
+        }
+        self
+    }
+
+    pub fn remove_attr(&mut self, name: &str) -> &mut Self {
+        for &id in &self.nodes {
+            if let NodeData::Element { attributes, .. } = self.document.get_mut(id).data_mut() {
+                attributes.retain(|attr| !attr.name.eq_ignore_ascii_case(name));
+            }
+        }
+        self
+    }
+
+    pub fn add_class(&mut self, class: &str) -> &mut Self {
+        self.edit_classes(|classes| {
+            if !classes.iter().any(|name| name == class) {
+                classes.push(class.to_string());
+            }
+        })
+    }
+
+    pub fn remove_class(&mut self, class: &str) -> &mut Self {
+        self.edit_classes(|classes| classes.retain(|name| *name != class))
+    }
+
+    /// Detaches the selected nodes from the tree.
+    pub fn remove(self) {
+        let mut document = self.document;
+        for id in self.nodes {
+            document.detach(id);
+        }
+    }
+
+    fn edit_classes(&mut self, mut edit: impl FnMut(&mut Vec<String>)) -> &mut Self {
+        for index in 0..self.nodes.len() {
+            let node = self.document.get(self.nodes[index]);
+            if !node.is_element() {
+                continue;
+            }
+            let mut classes: Vec<_> = node
+                .get_attr("class")
+                .unwrap_or_default()
+                .split_ascii_whitespace()
+                .map(str::to_string)
+                .collect();
+            edit(&mut classes);
+            let classes = classes.join(" ");
+            let id = self.nodes[index];
+            if let NodeData::Element { attributes, .. } = self.document.get_mut(id).data_mut() {
+                match attributes
+                    .iter_mut()
+                    .find(|attr| attr.name.eq_ignore_ascii_case("class"))
+                {
+                    Some(attr) => attr.set_value(classes),
+                    None if classes.is_empty() => {}
+                    None => attributes.push(HtmlAttribute {
+                        name: "class".into(),
+                        value: Some(classes.into()),
+                    }),
+                }
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chained_edits() {
+        let mut document = Document::parse(
+            "<ul class=\"menu\"><li class=\"a\">one</li><li>two <b>2</b></li></ul><p>x</p>",
+        )
+        .unwrap();
+        assert_eq!(
+            document.find("ul").unwrap().children("li").unwrap().len(),
+            2
+        );
+        assert_eq!(document.find("li").unwrap().text(), "onetwo 2");
+        assert_eq!(
+            document
+                .find("b")
+                .unwrap()
+                .parent()
+                .parent()
+                .attr("class")
+                .as_deref(),
+            Some("menu")
+        );
+        assert!(matches!(
+            document.find("li["),
+            Err(HtmlError::InvalidSelector(_))
+        ));
+        assert!(document.find("ul").unwrap().children(">").is_err());
+
+        document
+            .find_mut("li")
+            .unwrap()
+            .add_class("item")
+            .remove_class("a")
+            .set_attr("role", "menuitem");
+        document.find_mut("p").unwrap().remove();
+        let html = crate::html_to_string(
+            document
+                .to_nodes(document.root())
+                .into_iter()
+                .filter_map(|node| node.as_element().cloned())
+                .collect(),
+        );
+        assert_eq!(
+            html,
+            "<ul class=\"menu\"><li class=\"item\" role=\"menuitem\">one</li>\
+            <li class=\"item\" role=\"menuitem\">two <b>2</b></li></ul>"
+        );
+    }
+}
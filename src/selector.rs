@@ -0,0 +1,800 @@
+use alloc::borrow::Cow;
+use core::fmt;
+use core::str::Chars;
+
+use crate::prelude::*;
+use crate::{dom::Descendants, Atom, Document, HtmlElement, HtmlError, HtmlNode, NodeId};
+
+/// A compiled CSS selector list, e.g. `div.card > a[href], #main p`.
+///
+/// Parsing once and matching many times is cheaper than the `&str`
+/// methods, which parse on every call. Selectors are `Send` and `Sync`, so
+/// one can be shared by threads working on different documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    alternatives: Vec<ComplexSelector>,
+}
+
+// Compound selectors ordered from the subject (rightmost) to the left, each
+// paired with the combinator linking it to the next one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ComplexSelector {
+    parts: Vec<(CompoundSelector, Option<Combinator>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+    NextSibling,
+    SubsequentSibling,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct CompoundSelector {
+    tag: Option<String>,
+    // The lowercased tag's atom, compared before falling back to the name
+    tag_atom: Option<Atom>,
+    ids: Vec<String>,
+    classes: Vec<String>,
+    attributes: Vec<AttributeSelector>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AttributeSelector {
+    name: String,
+    matcher: Option<(AttributeOperator, String)>,
+    case_insensitive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttributeOperator {
+    Equals,
+    Includes,
+    DashMatch,
+    Prefix,
+    Suffix,
+    Substring,
+}
+
+/// The view of an element the matcher needs, independent of the tree layout.
+pub(crate) trait Element: Sized {
+    fn tag(&self) -> &str;
+    /// The tag's atom, when the element has one at hand.
+    fn tag_atom(&self) -> Option<Atom> {
+        None
+    }
+    fn attr(&self, name: &str) -> Option<&str>;
+    fn parent_element(&self) -> Option<Self>;
+    fn prev_sibling_element(&self) -> Option<Self>;
+}
+
+/// A selector that couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorError {
+    selector: String,
+    position: usize,
+}
+
+impl SelectorError {
+    pub fn selector(&self) -> &str {
+        &self.selector
+    }
+
+    /// The byte offset in the selector where parsing failed.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.selector[self.position..].chars().next() {
+            Some(ch) => write!(
+                f,
+                "unexpected `{ch}` at byte {} of `{}`",
+                self.position, self.selector
+            ),
+            None => write!(f, "`{}` ends early", self.selector),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SelectorError {}
+
+impl From<SelectorError> for HtmlError {
+    fn from(error: SelectorError) -> Self {
+        HtmlError::InvalidSelector(error)
+    }
+}
+
+impl Selector {
+    pub fn parse(selector: &str) -> Result<Self, SelectorError> {
+        let mut parser = SelectorParser {
+            chars: selector.chars(),
+            peeked: None,
+        };
+        parser.selector_list().ok_or_else(|| SelectorError {
+            selector: selector.to_string(),
+            position: selector.len() - parser.remaining(),
+        })
+    }
+
+    /// Matching nodes of `document`, in document order.
+    pub fn select<'d, 'a>(&'d self, document: &'d Document<'a>) -> Select<'d, 'a> {
+        Select {
+            selector: Cow::Borrowed(self),
+            descendants: document.descendants(document.root()),
+            document,
+        }
+    }
+
+    /// Descendants of `element` that match, as for
+    /// [`HtmlElement::query_selector_all`].
+    pub fn query<'e, 'a>(&self, element: &'e HtmlElement<'a>) -> Vec<&'e HtmlElement<'a>> {
+        element.query(self, false)
+    }
+
+    /// The first descendant of `element` that matches.
+    pub fn query_first<'e, 'a>(&self, element: &'e HtmlElement<'a>) -> Option<&'e HtmlElement<'a>> {
+        element.query(self, true).into_iter().next()
+    }
+
+    pub(crate) fn matches<E: Element>(&self, element: &E) -> bool {
+        self.alternatives
+            .iter()
+            .any(|complex| complex.matches(element, 0))
+    }
+}
+
+impl ComplexSelector {
+    fn matches<E: Element>(&self, element: &E, index: usize) -> bool {
+        let (compound, combinator) = &self.parts[index];
+        if !compound.matches(element) {
+            return false;
+        }
+
+        let Some(combinator) = combinator else {
+            return true;
+        };
+        match combinator {
+            Combinator::Child => element
+                .parent_element()
+                .is_some_and(|parent| self.matches(&parent, index + 1)),
+            Combinator::Descendant => {
+                let mut ancestor = element.parent_element();
+                while let Some(element) = ancestor {
+                    if self.matches(&element, index + 1) {
+                        return true;
+                    }
+                    ancestor = element.parent_element();
+                }
+                false
+            }
+            Combinator::NextSibling => element
+                .prev_sibling_element()
+                .is_some_and(|sibling| self.matches(&sibling, index + 1)),
+            Combinator::SubsequentSibling => {
+                let mut sibling = element.prev_sibling_element();
+                while let Some(element) = sibling {
+                    if self.matches(&element, index + 1) {
+                        return true;
+                    }
+                    sibling = element.prev_sibling_element();
+                }
+                false
+            }
+        }
+    }
+}
+
+impl CompoundSelector {
+    fn is_empty(&self) -> bool {
+        self.tag.is_none()
+            && self.ids.is_empty()
+            && self.classes.is_empty()
+            && self.attributes.is_empty()
+    }
+
+    fn matches<E: Element>(&self, element: &E) -> bool {
+        if let Some(tag) = &self.tag {
+            // Atoms are of lowercase names, so equal atoms mean equal
+            // names ignoring case and different ones mean different names
+            let same = match (self.tag_atom, element.tag_atom()) {
+                (Some(expected), Some(atom)) => expected == atom,
+                _ => element.tag().eq_ignore_ascii_case(tag),
+            };
+            if !same {
+                return false;
+            }
+        }
+        if !self.ids.is_empty() {
+            let id = element.attr("id");
+            if !self.ids.iter().all(|expected| id == Some(expected)) {
+                return false;
+            }
+        }
+        if !self.classes.is_empty() {
+            let classes = element.attr("class").unwrap_or_default();
+            if !self
+                .classes
+                .iter()
+                .all(|class| classes.split_ascii_whitespace().any(|name| name == class))
+            {
+                return false;
+            }
+        }
+        self.attributes.iter().all(|attr| attr.matches(element))
+    }
+}
+
+impl AttributeSelector {
+    fn matches<E: Element>(&self, element: &E) -> bool {
+        let Some(value) = element.attr(&self.name) else {
+            return false;
+        };
+        let Some((operator, expected)) = &self.matcher else {
+            return true;
+        };
+
+        let (value, expected) = if self.case_insensitive {
+            (value.to_ascii_lowercase(), expected.to_ascii_lowercase())
+        } else {
+            (value.to_string(), expected.clone())
+        };
+
+        match operator {
+            AttributeOperator::Equals => value == expected,
+            AttributeOperator::Includes => {
+                value.split_ascii_whitespace().any(|word| word == expected)
+            }
+            AttributeOperator::DashMatch => {
+                value == expected || value.starts_with(&format!("{}-", expected))
+            }
+            AttributeOperator::Prefix => !expected.is_empty() && value.starts_with(&expected),
+            AttributeOperator::Suffix => !expected.is_empty() && value.ends_with(&expected),
+            AttributeOperator::Substring => !expected.is_empty() && value.contains(&expected),
+        }
+    }
+}
+
+struct SelectorParser<'s> {
+    chars: Chars<'s>,
+    peeked: Option<Option<char>>,
+}
+
+impl SelectorParser<'_> {
+    // Bytes not yet consumed, counting a peeked character as unconsumed
+    fn remaining(&self) -> usize {
+        let peeked = match self.peeked {
+            Some(Some(ch)) => ch.len_utf8(),
+            _ => 0,
+        };
+        self.chars.as_str().len() + peeked
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        let chars = &mut self.chars;
+        *self.peeked.get_or_insert_with(|| chars.next())
+    }
+
+    fn next(&mut self) -> Option<char> {
+        match self.peeked.take() {
+            Some(ch) => ch,
+            None => self.chars.next(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let mut skipped = false;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.next();
+            skipped = true;
+        }
+        skipped
+    }
+
+    fn selector_list(&mut self) -> Option<Selector> {
+        let mut alternatives = vec![self.complex()?];
+        while self.peek() == Some(',') {
+            self.next();
+            alternatives.push(self.complex()?);
+        }
+        if self.peek().is_some() {
+            return None;
+        }
+        Some(Selector { alternatives })
+    }
+
+    fn complex(&mut self) -> Option<ComplexSelector> {
+        self.skip_whitespace();
+        let mut parts = vec![(self.compound()?, None)];
+
+        loop {
+            let whitespace = self.skip_whitespace();
+            let combinator = match self.peek() {
+                Some('>') => Combinator::Child,
+                Some('+') => Combinator::NextSibling,
+                Some('~') => Combinator::SubsequentSibling,
+                None | Some(',') => break,
+                Some(_) if whitespace => Combinator::Descendant,
+                Some(_) => return None,
+            };
+            if combinator != Combinator::Descendant {
+                self.next();
+                self.skip_whitespace();
+            }
+            parts.push((self.compound()?, Some(combinator)));
+        }
+
+        // Matching starts from the subject, the rightmost compound
+        parts.reverse();
+        Some(ComplexSelector { parts })
+    }
+
+    fn compound(&mut self) -> Option<CompoundSelector> {
+        let mut compound = CompoundSelector::default();
+
+        let universal = match self.peek() {
+            Some('*') => {
+                self.next();
+                true
+            }
+            Some(ch) if is_ident_char(ch) => {
+                let tag = self.ident()?;
+                compound.tag_atom = Atom::new(&tag.to_ascii_lowercase());
+                compound.tag = Some(tag);
+                false
+            }
+            _ => false,
+        };
+
+        loop {
+            match self.peek() {
+                Some('#') => {
+                    self.next();
+                    compound.ids.push(self.ident()?);
+                }
+                Some('.') => {
+                    self.next();
+                    compound.classes.push(self.ident()?);
+                }
+                Some('[') => {
+                    self.next();
+                    compound.attributes.push(self.attribute()?);
+                }
+                _ => break,
+            }
+        }
+
+        (universal || !compound.is_empty()).then_some(compound)
+    }
+
+    fn attribute(&mut self) -> Option<AttributeSelector> {
+        self.skip_whitespace();
+        let name = self.ident()?;
+        self.skip_whitespace();
+
+        let operator = match self.next()? {
+            ']' => {
+                return Some(AttributeSelector {
+                    name,
+                    matcher: None,
+                    case_insensitive: false,
+                })
+            }
+            '=' => AttributeOperator::Equals,
+            ch => {
+                let operator = match ch {
+                    '~' => AttributeOperator::Includes,
+                    '|' => AttributeOperator::DashMatch,
+                    '^' => AttributeOperator::Prefix,
+                    '$' => AttributeOperator::Suffix,
+                    '*' => AttributeOperator::Substring,
+                    _ => return None,
+                };
+                if self.next()? != '=' {
+                    return None;
+                }
+                operator
+            }
+        };
+
+        self.skip_whitespace();
+        let value = match self.peek()? {
+            quote @ ('"' | '\'') => {
+                self.next();
+                self.string(quote)?
+            }
+            _ => self.ident()?,
+        };
+        self.skip_whitespace();
+
+        let case_insensitive = match self.peek()? {
+            'i' | 'I' => {
+                self.next();
+                self.skip_whitespace();
+                true
+            }
+            _ => false,
+        };
+        if self.next()? != ']' {
+            return None;
+        }
+
+        Some(AttributeSelector {
+            name,
+            matcher: Some((operator, value)),
+            case_insensitive,
+        })
+    }
+
+    fn ident(&mut self) -> Option<String> {
+        let mut ident = String::new();
+        while let Some(ch) = self.peek() {
+            if ch == '\\' {
+                self.next();
+                ident.push(self.next()?);
+            } else if is_ident_char(ch) {
+                self.next();
+                ident.push(ch);
+            } else {
+                break;
+            }
+        }
+        (!ident.is_empty()).then_some(ident)
+    }
+
+    fn string(&mut self, quote: char) -> Option<String> {
+        let mut value = String::new();
+        loop {
+            match self.next()? {
+                '\\' => value.push(self.next()?),
+                ch if ch == quote => return Some(value),
+                ch => value.push(ch),
+            }
+        }
+    }
+}
+
+fn is_ident_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '-' || ch == '_' || !ch.is_ascii()
+}
+
+#[derive(Clone, Copy)]
+struct DomElement<'d, 'a> {
+    document: &'d Document<'a>,
+    id: NodeId,
+}
+
+impl Element for DomElement<'_, '_> {
+    fn tag(&self) -> &str {
+        self.document.get(self.id).tag().unwrap_or_default()
+    }
+
+    fn tag_atom(&self) -> Option<Atom> {
+        self.document.get(self.id).tag_atom()
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.document.get(self.id).get_attr(name)
+    }
+
+    fn parent_element(&self) -> Option<Self> {
+        let parent = self.document.parent(self.id)?;
+        self.document.get(parent).is_element().then_some(Self {
+            document: self.document,
+            id: parent,
+        })
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        self.document.prev_element_sibling(self.id).map(|id| Self {
+            document: self.document,
+            id,
+        })
+    }
+}
+
+// An element outside of any tree, so it has no parent or siblings
+struct DetachedElement<'e, 'a>(&'e HtmlElement<'a>);
+
+impl Element for DetachedElement<'_, '_> {
+    fn tag(&self) -> &str {
+        self.0.tag()
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.0.get_attr(name)
+    }
+
+    fn parent_element(&self) -> Option<Self> {
+        None
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        None
+    }
+}
+
+// An element inside a recursive tree, linked to the path that led to it
+#[derive(Clone, Copy)]
+struct TreeElement<'p, 't, 'a> {
+    element: &'t HtmlElement<'a>,
+    parent: Option<&'p TreeElement<'p, 't, 'a>>,
+    siblings: &'t [HtmlNode<'a>],
+    index: usize,
+}
+
+impl Element for TreeElement<'_, '_, '_> {
+    fn tag(&self) -> &str {
+        self.element.tag()
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.element.get_attr(name)
+    }
+
+    fn parent_element(&self) -> Option<Self> {
+        self.parent.copied()
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        self.siblings[..self.index]
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, node)| {
+                node.as_element().map(|element| Self {
+                    element,
+                    index,
+                    ..*self
+                })
+            })
+    }
+}
+
+// Collects matching descendants of `parent` in document order, stopping
+// after the first match when `first` is set.
+fn collect_matches<'t, 'a>(
+    parent: &TreeElement<'_, 't, 'a>,
+    selector: &Selector,
+    first: bool,
+    matches: &mut Vec<&'t HtmlElement<'a>>,
+) {
+    let siblings = &parent.element.children;
+    for (index, node) in siblings.iter().enumerate() {
+        let HtmlNode::Element(element) = node else {
+            continue;
+        };
+        let child = TreeElement {
+            element,
+            parent: Some(parent),
+            siblings,
+            index,
+        };
+
+        if selector.matches(&child) {
+            matches.push(element);
+            if first {
+                return;
+            }
+        }
+        collect_matches(&child, selector, first, matches);
+        if first && !matches.is_empty() {
+            return;
+        }
+    }
+}
+
+impl<'a> HtmlElement<'a> {
+    /// Matches the element on its own; combinators never match since a lone
+    /// element has no parent or siblings.
+    pub fn matches(&self, selector: &Selector) -> bool {
+        selector.matches(&DetachedElement(self))
+    }
+
+    /// The first descendant matching `selector`. Combinators can only refer
+    /// to this element and its descendants.
+    pub fn query_selector(&self, selector: &str) -> Result<Option<&HtmlElement<'a>>, HtmlError> {
+        let selector = Selector::parse(selector)?;
+        Ok(self.query(&selector, true).into_iter().next())
+    }
+
+    /// All descendants matching `selector`, in document order.
+    pub fn query_selector_all(&self, selector: &str) -> Result<Vec<&HtmlElement<'a>>, HtmlError> {
+        let selector = Selector::parse(selector)?;
+        Ok(self.query(&selector, false))
+    }
+
+    fn query(&self, selector: &Selector, first: bool) -> Vec<&HtmlElement<'a>> {
+        let root = TreeElement {
+            element: self,
+            parent: None,
+            siblings: &[],
+            index: 0,
+        };
+        let mut matches = Vec::new();
+        collect_matches(&root, selector, first, &mut matches);
+        matches
+    }
+}
+
+impl<'a> Document<'a> {
+    pub fn matches(&self, id: NodeId, selector: &Selector) -> bool {
+        self.get(id).is_element() && selector.matches(&DomElement { document: self, id })
+    }
+
+    /// Matching elements in document order.
+    pub fn select(&self, selector: &str) -> Result<Select<'_, 'a>, HtmlError> {
+        Ok(Select {
+            selector: Cow::Owned(Selector::parse(selector)?),
+            descendants: self.descendants(self.root()),
+            document: self,
+        })
+    }
+}
+
+pub struct Select<'d, 'a> {
+    document: &'d Document<'a>,
+    selector: Cow<'d, Selector>,
+    descendants: Descendants<'d, 'a>,
+}
+
+impl Iterator for Select<'_, '_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let document = self.document;
+        let selector = &self.selector;
+        self.descendants.find(|id| document.matches(*id, selector))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select_tags<'a>(document: &'a Document, selector: &str) -> Vec<&'a str> {
+        document
+            .select(selector)
+            .unwrap()
+            .filter_map(|id| document.get(id).tag())
+            .collect()
+    }
+
+    fn select_ids<'d>(document: &'d Document<'_>, selector: &str) -> Vec<&'d str> {
+        document
+            .select(selector)
+            .unwrap()
+            .filter_map(|id| document.get(id).get_attr("id"))
+            .collect()
+    }
+
+    const HTML: &str = r#"
+        <div id="main" class="page">
+            <div id="card1" class="card featured">
+                <a id="a1" href="/one">One</a>
+                <span id="s1"><a id="a2">Two</a></span>
+            </div>
+            <div id="card2" class="card" data-kind="news-item">
+                <a id="a3" href="https://example.com/three" lang="en-US">Three</a>
+                <p id="p1"></p>
+                <p id="p2"></p>
+            </div>
+        </div>
+    "#;
+
+    #[test]
+    fn select_simple() {
+        let document = Document::parse(HTML).unwrap();
+        assert_eq!(select_ids(&document, "a"), vec!["a1", "a2", "a3"]);
+        assert_eq!(select_ids(&document, "A"), vec!["a1", "a2", "a3"]);
+        assert_eq!(select_ids(&document, "#card2"), vec!["card2"]);
+        assert_eq!(select_ids(&document, ".card.featured"), vec!["card1"]);
+        assert_eq!(select_ids(&document, "div.card"), vec!["card1", "card2"]);
+        assert_eq!(select_tags(&document, "*").len(), 9);
+        assert_eq!(select_ids(&document, "p, #s1"), vec!["s1", "p1", "p2"]);
+    }
+
+    #[test]
+    fn select_attributes() {
+        let document = Document::parse(HTML).unwrap();
+        assert_eq!(select_ids(&document, "a[href]"), vec!["a1", "a3"]);
+        assert_eq!(select_ids(&document, "[href='/one']"), vec!["a1"]);
+        assert_eq!(select_ids(&document, "[href^=https]"), vec!["a3"]);
+        assert_eq!(select_ids(&document, "[href$=\"three\"]"), vec!["a3"]);
+        assert_eq!(select_ids(&document, "[data-kind*=ws-it]"), vec!["card2"]);
+        assert_eq!(select_ids(&document, "[class~=featured]"), vec!["card1"]);
+        assert_eq!(select_ids(&document, "[lang|=en]"), vec!["a3"]);
+        assert_eq!(select_ids(&document, "[lang=EN-us i]"), vec!["a3"]);
+        assert_eq!(select_ids(&document, "[lang=EN-us]"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn select_combinators() {
+        let document = Document::parse(HTML).unwrap();
+        assert_eq!(select_ids(&document, "div.card > a"), vec!["a1", "a3"]);
+        assert_eq!(select_ids(&document, ".card a"), vec!["a1", "a2", "a3"]);
+        assert_eq!(select_ids(&document, "#main span > a"), vec!["a2"]);
+        assert_eq!(select_ids(&document, "a + p"), vec!["p1"]);
+        assert_eq!(select_ids(&document, "a ~ p"), vec!["p1", "p2"]);
+        assert_eq!(select_ids(&document, "#card1 + div > p + p"), vec!["p2"]);
+        assert_eq!(select_ids(&document, "span>a"), vec!["a2"]);
+    }
+
+    #[test]
+    fn invalid_selectors() {
+        for selector in ["", "div >", "a[href", "[=x]", ".", "a,", "a!", "[a==b]"] {
+            assert!(Selector::parse(selector).is_err(), "{:?}", selector);
+        }
+        let error = Selector::parse("div > a!").unwrap_err();
+        assert_eq!(error.position(), 7);
+        assert_eq!(error.to_string(), "unexpected `!` at byte 7 of `div > a!`");
+        assert_eq!(Selector::parse("a[").unwrap_err().position(), 2);
+    }
+
+    #[test]
+    fn compiled_selector() {
+        fn shareable<T: Send + Sync>(_: &T) {}
+        let selector = Selector::parse(".card > a").unwrap();
+        shareable(&selector);
+
+        let document = Document::parse(HTML).unwrap();
+        let ids: Vec<_> = selector
+            .select(&document)
+            .filter_map(|id| document.get(id).get_attr("id"))
+            .collect();
+        assert_eq!(ids, ["a1", "a3"]);
+
+        let elements = crate::parse_html(HTML).unwrap();
+        assert_eq!(selector.query(&elements[0]).len(), 2);
+        let first = selector.query_first(&elements[0]).unwrap();
+        assert_eq!(first.get_attr("id"), Some("a1"));
+    }
+
+    #[test]
+    fn query_selector() {
+        let elements = crate::parse_html(HTML).unwrap();
+        let main = &elements[0];
+        let ids = |found: Vec<&HtmlElement>| -> Vec<String> {
+            found
+                .iter()
+                .filter_map(|element| element.get_attr("id").map(String::from))
+                .collect()
+        };
+
+        assert_eq!(
+            ids(main.query_selector_all("a").unwrap()),
+            vec!["a1", "a2", "a3"]
+        );
+        assert_eq!(
+            ids(main.query_selector_all("#main > .card").unwrap()),
+            vec!["card1", "card2"]
+        );
+        assert_eq!(ids(main.query_selector_all("p + p").unwrap()), vec!["p2"]);
+        assert_eq!(
+            ids(main.query_selector_all(".card ~ div a").unwrap()),
+            vec!["a3"]
+        );
+        assert_eq!(
+            ids(main.query_selector_all("#main").unwrap()),
+            Vec::<String>::new()
+        );
+
+        let first = main.query_selector("span a, p").unwrap().unwrap();
+        assert_eq!(first.get_attr("id"), Some("a2"));
+        assert_eq!(main.query_selector("table").unwrap(), None);
+        assert!(main.query_selector("a[").is_err());
+    }
+
+    #[test]
+    fn element_matches() {
+        let mut element = HtmlElement::new("a");
+        element.add_attribute("class", Some("btn primary"));
+        assert!(element.matches(&Selector::parse("a.btn").unwrap()));
+        assert!(element.matches(&Selector::parse("p, .primary").unwrap()));
+        assert!(!element.matches(&Selector::parse("div > a").unwrap()));
+    }
+}
@@ -0,0 +1,845 @@
+use alloc::borrow::Cow;
+use core::fmt::{self, Write};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::prelude::*;
+use crate::{
+    decode_entities,
+    entities::{attribute_entity, text_entity, write_escaped},
+    escape_attribute, escape_text,
+    parser::{
+        find_end_tag, is_inline, is_inline_element, is_preformatted_element, is_raw_text_element,
+        is_void_element,
+    },
+    ElementSource, HtmlDoctype, HtmlDocument, HtmlElement, HtmlNode, Namespace,
+};
+
+#[derive(Debug, Default, Clone)]
+pub struct SerializeOptions {
+    /// Writes text and attribute values exactly as stored instead of
+    /// escaping `&`, `<`, `>` and `"`. Only safe for trusted trees.
+    pub raw: bool,
+    /// Polyglot output that XML tooling accepts: void elements are
+    /// self-closed, names are lowercased, attributes always get a quoted
+    /// value and script/style contents are escaped too.
+    pub xhtml: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrettyOptions {
+    /// Spaces per nesting level.
+    pub indent: usize,
+    /// Elements whose single-line form would exceed this are broken up.
+    pub max_line_width: usize,
+}
+
+impl Default for PrettyOptions {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            max_line_width: 80,
+        }
+    }
+}
+
+pub fn html_to_string(elements: Vec<HtmlElement<'_>>) -> String {
+    let mut html = String::new();
+    write_html(&elements, &mut html).unwrap();
+    html
+}
+
+pub fn document_to_string(document: HtmlDocument<'_>) -> String {
+    if let Some(source) = document.source {
+        return source.to_string();
+    }
+    let mut html = String::new();
+    write_nodes(
+        &mut html,
+        &document.nodes,
+        &SerializeOptions::default(),
+        false,
+    )
+    .unwrap();
+    html
+}
+
+pub fn html_to_string_with(elements: &[HtmlElement<'_>], options: &SerializeOptions) -> String {
+    let mut html = String::new();
+    write_html_with(elements, &mut html, options).unwrap();
+    html
+}
+
+/// Serializes `elements` straight into `out` without building the whole
+/// document in memory first.
+pub fn write_html<W: fmt::Write>(elements: &[HtmlElement<'_>], out: &mut W) -> fmt::Result {
+    write_html_with(elements, out, &SerializeOptions::default())
+}
+
+pub fn write_html_with<W: fmt::Write>(
+    elements: &[HtmlElement<'_>],
+    out: &mut W,
+    options: &SerializeOptions,
+) -> fmt::Result {
+    for element in elements {
+        write_element(out, element, options, options.raw)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+pub fn write_html_io<W: io::Write>(elements: &[HtmlElement<'_>], out: &mut W) -> io::Result<()> {
+    let mut adapter = IoAdapter {
+        inner: out,
+        error: None,
+    };
+    match write_html(elements, &mut adapter) {
+        Ok(()) => Ok(()),
+        Err(_) => Err(adapter
+            .error
+            .unwrap_or_else(|| io::Error::other("formatter error"))),
+    }
+}
+
+// Bridges fmt::Write to io::Write, keeping the underlying io error
+#[cfg(feature = "std")]
+struct IoAdapter<'w, W> {
+    inner: &'w mut W,
+    error: Option<io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> fmt::Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+/// Serializes with one node per line, indenting nested elements. Elements
+/// that fit within `max_line_width` stay on a single line.
+pub fn html_to_string_pretty(elements: &[HtmlElement<'_>], options: PrettyOptions) -> String {
+    let mut html = String::new();
+    for element in elements {
+        push_pretty_element(&mut html, element, 0, &options);
+    }
+    html
+}
+
+/// As [`html_to_string_pretty`], for a whole document with its doctype and
+/// top-level comments.
+pub fn document_to_string_pretty(document: &HtmlDocument<'_>, options: PrettyOptions) -> String {
+    let mut html = String::new();
+    for node in document.children() {
+        push_pretty_node(&mut html, node, 0, &options);
+    }
+    html
+}
+
+impl HtmlDocument<'_> {
+    /// A normalized serialization for checking that two documents have the
+    /// same structure and content, whatever their formatting.
+    ///
+    /// HTML tag and attribute names are lowercased, attributes are sorted
+    /// by name, and runs of whitespace in text become one space, dropped
+    /// next to block elements, except inside preformatted elements like
+    /// `pre` and `textarea` and raw text ones like `script` and `style`.
+    /// Recorded source isn't reused.
+    pub fn to_canonical_string(&self) -> String {
+        let mut html = String::new();
+        let parent = Parent {
+            preserve: false,
+            inline: false,
+        };
+        write_canonical(&mut html, Canonical::Nodes(&self.nodes, 0, parent));
+        html
+    }
+}
+
+impl HtmlElement<'_> {
+    /// Like [`HtmlDocument::to_canonical_string`], for one element.
+    pub fn to_canonical_string(&self) -> String {
+        let mut html = String::new();
+        let parent = Parent {
+            preserve: false,
+            inline: false,
+        };
+        write_canonical(&mut html, Canonical::Element(self, parent));
+        html
+    }
+}
+
+// Work left for the canonical writer, kept on a stack rather than the call
+// stack so deep trees can't overflow it
+enum Canonical<'n, 'a> {
+    // Siblings from the index on
+    Nodes(&'n [HtmlNode<'a>], usize, Parent),
+    Element(&'n HtmlElement<'a>, Parent),
+    EndTag(String),
+}
+
+// What the canonical writer needs to know of the element holding the nodes
+#[derive(Clone, Copy)]
+struct Parent {
+    preserve: bool,
+    inline: bool,
+}
+
+fn write_canonical(html: &mut String, start: Canonical<'_, '_>) {
+    let mut stack = vec![start];
+    while let Some(step) = stack.pop() {
+        match step {
+            Canonical::Nodes(nodes, index, parent) => {
+                // Adjacent text and CDATA nodes are written as one
+                let run = nodes[index..]
+                    .iter()
+                    .take_while(|node| matches!(node, HtmlNode::Text(_) | HtmlNode::CData(_)))
+                    .count();
+                let end = index + run;
+                if run > 0 {
+                    let text: String = nodes[index..end]
+                        .iter()
+                        .filter_map(HtmlNode::as_text)
+                        .collect();
+                    // With no sibling on a side, the parent's boundary counts
+                    let inline_before = match index.checked_sub(1) {
+                        Some(previous) => is_inline(nodes.get(previous)),
+                        None => parent.inline,
+                    };
+                    let inline_after = match nodes.get(end) {
+                        Some(next) => is_inline(Some(next)),
+                        None => parent.inline,
+                    };
+                    push_canonical_text(html, &text, parent.preserve, inline_before, inline_after);
+                }
+
+                let Some(node) = nodes.get(end) else {
+                    continue;
+                };
+                stack.push(Canonical::Nodes(nodes, end + 1, parent));
+                match node {
+                    HtmlNode::Element(element) => {
+                        stack.push(Canonical::Element(element, parent));
+                    }
+                    HtmlNode::Comment(comment) => write_comment(html, comment.trim()).unwrap(),
+                    HtmlNode::Doctype(doctype) => {
+                        html.push_str("<!DOCTYPE");
+                        if let Some(name) = doctype.name() {
+                            write!(html, " {}", name.to_ascii_lowercase()).unwrap();
+                        }
+                        html.push('>');
+                    }
+                    HtmlNode::Text(_) | HtmlNode::CData(_) => unreachable!(),
+                }
+            }
+            Canonical::Element(element, parent) => {
+                write_canonical_start_tag(html, element, parent.preserve, &mut stack);
+            }
+            Canonical::EndTag(tag) => write!(html, "</{}>", tag).unwrap(),
+        }
+    }
+}
+
+// Text is stored decoded, so only escaping is left
+fn push_canonical_text(
+    html: &mut String,
+    text: &str,
+    preserve: bool,
+    inline_before: bool,
+    inline_after: bool,
+) {
+    if preserve {
+        html.push_str(&escape_text(text));
+        return;
+    }
+    let mut collapsed = String::with_capacity(text.len());
+    for (index, word) in text.split_ascii_whitespace().enumerate() {
+        if index > 0 {
+            collapsed.push(' ');
+        }
+        collapsed.push_str(word);
+    }
+    let starts_blank = text.starts_with(|ch: char| ch.is_ascii_whitespace());
+    let ends_blank = text.ends_with(|ch: char| ch.is_ascii_whitespace());
+    if collapsed.is_empty() {
+        if starts_blank && inline_before && inline_after {
+            html.push(' ');
+        }
+        return;
+    }
+    if starts_blank && inline_before {
+        html.push(' ');
+    }
+    html.push_str(&escape_text(&collapsed));
+    if ends_blank && inline_after {
+        html.push(' ');
+    }
+}
+
+// Writes the start tag, and raw text contents whole, leaving anything else
+// on `stack`
+fn write_canonical_start_tag<'n, 'a>(
+    html: &mut String,
+    element: &'n HtmlElement<'a>,
+    preserve: bool,
+    stack: &mut Vec<Canonical<'n, 'a>>,
+) {
+    let canonical_name = |name: &str| match element.namespace {
+        Namespace::Html => name.to_ascii_lowercase(),
+        _ => name.to_string(),
+    };
+    let tag = canonical_name(&element.tag);
+    write!(html, "<{}", tag).unwrap();
+    let mut attributes: Vec<_> = element
+        .attributes
+        .iter()
+        .map(|attr| {
+            let value = attr.value().unwrap_or_default();
+            (
+                canonical_name(&attr.name),
+                escape_attribute(value).into_owned(),
+            )
+        })
+        .collect();
+    attributes.sort();
+    for (name, value) in attributes {
+        write!(html, " {}=\"{}\"", name, value).unwrap();
+    }
+    html.push('>');
+    if is_void_element(&tag) {
+        return;
+    }
+    if is_raw_text_element(&tag) {
+        for node in &element.children {
+            if let Some(text) = node.as_text() {
+                html.push_str(&raw_text(text, &tag));
+            }
+        }
+        write!(html, "</{}>", tag).unwrap();
+    } else {
+        let parent = Parent {
+            preserve: preserve || is_preformatted_element(&tag),
+            inline: element.namespace == Namespace::Html && is_inline_element(&tag),
+        };
+        stack.push(Canonical::EndTag(tag));
+        stack.push(Canonical::Nodes(&element.children, 0, parent));
+        stack.push(Canonical::Nodes(&element.content, 0, parent));
+    }
+}
+
+fn write_nodes<W: fmt::Write>(
+    out: &mut W,
+    nodes: &[HtmlNode<'_>],
+    options: &SerializeOptions,
+    raw: bool,
+) -> fmt::Result {
+    write_tree(out, Step::Nodes(nodes, TextMode::new(raw)), options)
+}
+
+fn write_node<W: fmt::Write>(
+    out: &mut W,
+    node: &HtmlNode<'_>,
+    options: &SerializeOptions,
+    raw: bool,
+) -> fmt::Result {
+    write_nodes(out, core::slice::from_ref(node), options, raw)
+}
+
+fn write_element<W: fmt::Write>(
+    out: &mut W,
+    element: &HtmlElement<'_>,
+    options: &SerializeOptions,
+    raw: bool,
+) -> fmt::Result {
+    write_tree(out, Step::Element(element, TextMode::new(raw)), options)
+}
+
+// What's left to write of a tree, and how its text is written
+enum Step<'n, 'a> {
+    Nodes(&'n [HtmlNode<'a>], TextMode<'n>),
+    Element(&'n HtmlElement<'a>, TextMode<'n>),
+    EndTag(&'n HtmlElement<'a>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TextMode<'n> {
+    Escaped,
+    // As stored, for `SerializeOptions::raw`
+    Verbatim,
+    // Inside a raw text element like `<script>`, which only its end tag
+    // can end
+    RawText(&'n str),
+}
+
+impl TextMode<'_> {
+    fn new(raw: bool) -> Self {
+        match raw {
+            true => TextMode::Verbatim,
+            false => TextMode::Escaped,
+        }
+    }
+}
+
+// Walks the tree with a stack of its own rather than recursing, so deeply
+// nested documents can't overflow the call stack. The stack holds a few
+// steps per open element.
+fn write_tree<W: fmt::Write>(
+    out: &mut W,
+    first: Step<'_, '_>,
+    options: &SerializeOptions,
+) -> fmt::Result {
+    let mut stack = vec![first];
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Nodes(nodes, mode) => {
+                let Some((node, rest)) = nodes.split_first() else {
+                    continue;
+                };
+                stack.push(Step::Nodes(rest, mode));
+                match (node, mode) {
+                    (HtmlNode::Element(element), _) => stack.push(Step::Element(element, mode)),
+                    (HtmlNode::Text(text), TextMode::Escaped) => {
+                        write_escaped(out, text, text_entity)?
+                    }
+                    (HtmlNode::Text(text), TextMode::Verbatim) => out.write_str(text)?,
+                    (HtmlNode::Text(text), TextMode::RawText(tag)) => {
+                        out.write_str(&raw_text(text, tag))?
+                    }
+                    (HtmlNode::CData(text), _) => write_cdata(out, text)?,
+                    (HtmlNode::Comment(comment), _) => write_comment(out, comment)?,
+                    (HtmlNode::Doctype(doctype), _) => write_doctype(out, doctype)?,
+                }
+            }
+            Step::Element(element, mode) => {
+                let source = recorded_source(element, options);
+                if let Some(outer) = source.outer {
+                    out.write_str(outer)?;
+                    continue;
+                }
+                match source.start_tag {
+                    Some(start_tag) => out.write_str(start_tag)?,
+                    None => write_start_tag(out, element, options, mode == TextMode::Verbatim)?,
+                }
+                if is_void_element(&element.tag) {
+                    continue;
+                }
+                // Script and style contents are only escaped for XML consumers
+                let mode = match mode {
+                    TextMode::Escaped if !options.xhtml && is_raw_text_element(&element.tag) => {
+                        TextMode::RawText(&element.tag)
+                    }
+                    mode => mode,
+                };
+                stack.push(Step::EndTag(element));
+                stack.push(Step::Nodes(&element.children, mode));
+                stack.push(Step::Nodes(&element.content, mode));
+            }
+            Step::EndTag(element) => match recorded_source(element, options).end_tag {
+                Some(end_tag) => out.write_str(end_tag)?,
+                None => {
+                    out.write_str("</")?;
+                    write_name(out, &element.tag, element.namespace, options)?;
+                    out.write_char('>')?;
+                }
+            },
+        }
+    }
+    Ok(())
+}
+
+// A comment containing `-->` or `--!>`, or starting with `>` or `->`,
+// would end early, so a space goes before the `>`
+pub(crate) fn write_comment<W: fmt::Write>(out: &mut W, comment: &str) -> fmt::Result {
+    out.write_str("<!--")?;
+    if !comment.contains('>') {
+        out.write_str(comment)?;
+        return out.write_str("-->");
+    }
+    // The opening `<!--` counts, for `<!-->`
+    let mut written = String::from("<!--");
+    for ch in comment.chars() {
+        if ch == '>' && (written.ends_with("--") || written.ends_with("--!")) {
+            written.push(' ');
+        }
+        written.push(ch);
+    }
+    out.write_str(&written["<!--".len()..])?;
+    out.write_str("-->")
+}
+
+// `]]>` ends a CDATA section, so one holding it is split in two there
+pub(crate) fn write_cdata<W: fmt::Write>(out: &mut W, text: &str) -> fmt::Result {
+    write!(
+        out,
+        "<![CDATA[{}]]>",
+        text.replace("]]>", "]]]]><![CDATA[>")
+    )
+}
+
+// The text of a raw text `tag`, with `</script` written as `<\/script` so
+// it can't end the element, which string literals and style sheets read
+// the same
+pub(crate) fn raw_text<'t>(text: &'t str, tag: &str) -> Cow<'t, str> {
+    let Some(mut index) = find_end_tag(text, tag) else {
+        return Cow::Borrowed(text);
+    };
+    let mut escaped = String::with_capacity(text.len() + 1);
+    let mut rest = text;
+    loop {
+        escaped.push_str(&rest[..index + 1]);
+        escaped.push('\\');
+        rest = &rest[index + 1..];
+        match find_end_tag(rest, tag) {
+            Some(next) => index = next,
+            None => break,
+        }
+    }
+    escaped.push_str(rest);
+    Cow::Owned(escaped)
+}
+
+// Markup recorded at parse time is reused verbatim, except for XHTML which
+// has to be normalized anyway
+fn recorded_source<'e>(element: &HtmlElement<'e>, options: &SerializeOptions) -> ElementSource<'e> {
+    match options.xhtml {
+        true => Default::default(),
+        false => element.source,
+    }
+}
+
+fn write_start_tag<W: fmt::Write>(
+    out: &mut W,
+    element: &HtmlElement<'_>,
+    options: &SerializeOptions,
+    raw: bool,
+) -> fmt::Result {
+    out.write_char('<')?;
+    write_name(out, &element.tag, element.namespace, options)?;
+    for attr in &element.attributes {
+        out.write_char(' ')?;
+        write_name(out, &attr.name, element.namespace, options)?;
+        let escape = match options.xhtml {
+            true => xml_attribute_entity,
+            false => attribute_entity,
+        };
+        match attr.value() {
+            Some(value) => {
+                out.write_str("=\"")?;
+                match raw {
+                    true => out.write_str(value)?,
+                    false => write_escaped(out, value, escape)?,
+                }
+                out.write_char('"')?;
+            }
+            // `<input disabled>` becomes `disabled="disabled"`
+            None if options.xhtml => {
+                out.write_str("=\"")?;
+                write_name(out, &attr.name, element.namespace, options)?;
+                out.write_char('"')?;
+            }
+            None => {}
+        }
+    }
+    if options.xhtml && is_void_element(&element.tag) {
+        out.write_str(" />")
+    } else {
+        out.write_char('>')
+    }
+}
+
+// XHTML names are lowercase, but SVG and MathML ones keep their case
+fn write_name<W: fmt::Write>(
+    out: &mut W,
+    name: &str,
+    namespace: Namespace,
+    options: &SerializeOptions,
+) -> fmt::Result {
+    if !options.xhtml
+        || namespace != Namespace::Html
+        || !name.bytes().any(|b| b.is_ascii_uppercase())
+    {
+        return out.write_str(name);
+    }
+    for ch in name.chars() {
+        out.write_char(ch.to_ascii_lowercase())?;
+    }
+    Ok(())
+}
+
+// Attribute values in XML can't contain a literal `<` either
+fn xml_attribute_entity(byte: u8) -> Option<&'static str> {
+    match byte {
+        b'<' => Some("&lt;"),
+        byte => attribute_entity(byte),
+    }
+}
+
+pub(crate) fn write_doctype<W: fmt::Write>(out: &mut W, doctype: &HtmlDoctype<'_>) -> fmt::Result {
+    out.write_str("<!DOCTYPE")?;
+    if let Some(name) = doctype.name() {
+        write!(out, " {}", name)?;
+    }
+    match (doctype.public_id(), doctype.system_id()) {
+        (Some(public_id), Some(system_id)) => {
+            write!(out, " PUBLIC \"{}\" \"{}\"", public_id, system_id)?
+        }
+        (Some(public_id), None) => write!(out, " PUBLIC \"{}\"", public_id)?,
+        (None, Some(system_id)) => write!(out, " SYSTEM \"{}\"", system_id)?,
+        (None, None) => {}
+    }
+    out.write_char('>')
+}
+
+fn push_line(html: &mut String, depth: usize, options: &PrettyOptions, line: &str) {
+    html.push_str(&" ".repeat(depth * options.indent));
+    html.push_str(line);
+    html.push('\n');
+}
+
+fn push_pretty_node(html: &mut String, node: &HtmlNode<'_>, depth: usize, options: &PrettyOptions) {
+    match node {
+        HtmlNode::Element(element) => push_pretty_element(html, element, depth, options),
+        HtmlNode::Text(text) => {
+            let text = text.trim();
+            if !text.is_empty() {
+                push_line(html, depth, options, &escape_text(text));
+            }
+        }
+        node => {
+            let mut line = String::new();
+            write_node(&mut line, node, &SerializeOptions::default(), false).unwrap();
+            push_line(html, depth, options, &line);
+        }
+    }
+}
+
+fn push_pretty_element(
+    html: &mut String,
+    element: &HtmlElement<'_>,
+    depth: usize,
+    options: &PrettyOptions,
+) {
+    let mut line = String::new();
+    write_element(&mut line, element, &SerializeOptions::default(), false).unwrap();
+
+    let fits =
+        !line.contains('\n') && depth * options.indent + line.len() <= options.max_line_width;
+    // Preformatted and raw text contents are written as they are
+    let sensitive = is_preformatted_element(&element.tag) || is_raw_text_element(&element.tag);
+    if fits || sensitive || (element.children.is_empty() && element.content.is_empty()) {
+        push_line(html, depth, options, &line);
+        return;
+    }
+
+    line.clear();
+    write_start_tag(&mut line, element, &SerializeOptions::default(), false).unwrap();
+    push_line(html, depth, options, &line);
+    for child in element.content.iter().chain(&element.children) {
+        push_pretty_node(html, child, depth + 1, options);
+    }
+    push_line(html, depth, options, &format!("</{}>", element.tag));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_html;
+
+    #[test]
+    fn escape_on_serialize() {
+        let mut element = HtmlElement::new("p");
+        element.set_attr("title", "say \"hi\" & leave");
+        element.add_text("1 < 2 & <b>");
+        let mut script = HtmlElement::new("script");
+        script.add_text("if (a < b && c) {}");
+        let elements = vec![element, script];
+
+        assert_eq!(
+            html_to_string(elements.clone()),
+            "<p title=\"say &quot;hi&quot; &amp; leave\">1 &lt; 2 &amp; &lt;b&gt;</p><script>if (a < b && c) {}</script>"
+        );
+        assert_eq!(
+            html_to_string_with(
+                &elements,
+                &SerializeOptions {
+                    raw: true,
+                    ..Default::default()
+                }
+            ),
+            "<p title=\"say \"hi\" & leave\">1 < 2 & <b></p><script>if (a < b && c) {}</script>"
+        );
+    }
+
+    #[test]
+    fn xhtml_output() {
+        let html = "<DIV Class=\"a<b\"><BR><input disabled><script>a && b</script></DIV>";
+        let elements = parse_html(html).unwrap();
+        let options = SerializeOptions {
+            xhtml: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            html_to_string_with(&elements, &options),
+            "<div class=\"a&lt;b\"><br /><input disabled=\"disabled\" /><script>a &amp;&amp; b</script></div>"
+        );
+
+        let html = "<P><svg viewBox=\"0 0 1 1\"><foreignObject></foreignObject></svg></P>";
+        let elements = parse_html(html).unwrap();
+        assert_eq!(
+            html_to_string_with(&elements, &options),
+            "<p><svg viewBox=\"0 0 1 1\"><foreignObject></foreignObject></svg></p>"
+        );
+    }
+
+    #[test]
+    fn entities_round_trip() {
+        let html = "<a href=\"?a=1&amp;b=2\" title=\"&quot;x&quot;\">fish &amp; chips &lt;3</a>";
+        let elements = parse_html(html).unwrap();
+        assert_eq!(elements[0].get_attr("href"), Some("?a=1&b=2"));
+        assert_eq!(elements[0].children()[0].as_text(), Some("fish & chips <3"));
+        assert_eq!(html_to_string(elements), html);
+    }
+
+    #[test]
+    fn write_to_sinks() {
+        let html = "<ul><li class=\"a\">one</li><li>two<br></li></ul>";
+        let elements = parse_html(html).unwrap();
+
+        let mut out = String::new();
+        write_html(&elements, &mut out).unwrap();
+        assert_eq!(out, html);
+
+        #[cfg(feature = "std")]
+        {
+            let mut bytes = Vec::new();
+            write_html_io(&elements, &mut bytes).unwrap();
+            assert_eq!(bytes, html.as_bytes());
+
+            let mut full = [0u8; 8];
+            let error = write_html_io(&elements, &mut &mut full[..]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::WriteZero);
+        }
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let depth = 100_000;
+        let mut root = HtmlElement::new("b").text("x");
+        for _ in 1..depth {
+            root = HtmlElement::new("b").child(root);
+        }
+        let html = html_to_string_with(core::slice::from_ref(&root), &SerializeOptions::default());
+        assert_eq!(html, "<b>".repeat(depth) + "x" + &"</b>".repeat(depth));
+        assert_eq!(root.to_canonical_string(), html);
+
+        // Dropping recurses too, so the tree is taken apart from the top
+        let mut element = root;
+        while let Some(HtmlNode::Element(child)) = element.children_vec_mut().pop() {
+            element = child;
+        }
+    }
+
+    #[test]
+    fn constructs_cant_be_escaped() {
+        let mut div = HtmlElement::new("div");
+        div.add_child(HtmlNode::Comment("a-->b--!>c".into()));
+        div.add_child(HtmlNode::Comment("->".into()));
+        div.add_child(HtmlNode::CData("x]]><b>".into()));
+        let mut script = HtmlElement::new("script");
+        script.add_text("s = '</SCRIPT ><b>'; t = '</scripts>'");
+        div.add_child(script);
+        let html = html_to_string(vec![div]);
+        assert_eq!(
+            html,
+            "<div><!--a-- >b--! >c--><!--- >--><![CDATA[x]]]]><![CDATA[><b>]]><script>s = '<\\/SCRIPT ><b>'; t = '</scripts>'</script></div>"
+        );
+
+        // Read back, everything is still inside the div, the CDATA as two
+        // sections
+        let elements = parse_html(&html).unwrap();
+        assert_eq!(elements.len(), 1);
+        let children = elements[0].children();
+        assert_eq!(children.len(), 5);
+        assert_eq!(children[4].as_element().unwrap().tag(), "script");
+        assert!(crate::minify::minify(&elements).contains("<\\/SCRIPT >"));
+    }
+
+    #[test]
+    fn pretty_print() {
+        let html = "<div id=\"main\"><ul>\n  <li>one</li><li>two</li></ul><pre>  keep\n   this</pre><p>short</p></div>";
+        let elements = parse_html(html).unwrap();
+        let options = PrettyOptions {
+            max_line_width: 20,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            html_to_string_pretty(&elements, options),
+            "<div id=\"main\">\n  <ul>\n    <li>one</li>\n    <li>two</li>\n  </ul>\n  <pre>  keep\n   this</pre>\n  <p>short</p>\n</div>\n"
+        );
+
+        let html = "<div><listing>  keep <b>this</b>\n   too</listing></div>";
+        let elements = parse_html(html).unwrap();
+        assert_eq!(
+            html_to_string_pretty(&elements, PrettyOptions::default()),
+            "<div>\n  <listing>  keep <b>this</b>\n   too</listing>\n</div>\n"
+        );
+    }
+
+    #[test]
+    fn pretty_print_fits_on_line() {
+        let elements = parse_html("<p>Hello <b>world</b></p><br>").unwrap();
+        assert_eq!(
+            html_to_string_pretty(&elements, PrettyOptions::default()),
+            "<p>Hello <b>world</b></p>\n<br>\n"
+        );
+
+        let options = PrettyOptions {
+            indent: 4,
+            max_line_width: 16,
+        };
+        assert_eq!(
+            html_to_string_pretty(&elements, options),
+            "<p>\n    Hello\n    <b>world</b>\n</p>\n<br>\n"
+        );
+
+        let document = crate::parse_document("<!DOCTYPE html><!-- a --> <p>b</p>").unwrap();
+        assert_eq!(
+            document_to_string_pretty(&document, PrettyOptions::default()),
+            "<!DOCTYPE html>\n<!-- a -->\n<p>b</p>\n"
+        );
+    }
+
+    #[test]
+    fn canonical_form() {
+        let a = crate::parse_document(
+            "<DIV Class=\"x\" id=x>\n  Caf&#233;   <b>au</b> lait\n  <pre> a  b</pre></DIV>",
+        )
+        .unwrap();
+        let b = crate::parse_document(
+            "<div id=\"x\" class='x'>Café <b>au</b> lait<pre> a  b</pre></div>",
+        )
+        .unwrap();
+        assert_eq!(a.to_canonical_string(), b.to_canonical_string());
+        assert_eq!(
+            b.to_canonical_string(),
+            "<div class=\"x\" id=\"x\">Café <b>au</b> lait<pre> a  b</pre></div>"
+        );
+
+        // Whitespace next to inline content is kept, as one space
+        let canonical = |html| crate::parse_document(html).unwrap().to_canonical_string();
+        assert_ne!(canonical("foo <b>bar</b>"), canonical("foo<b>bar</b>"));
+        assert_eq!(canonical("foo \n <b>bar</b>"), "foo <b>bar</b>");
+        assert_eq!(canonical("<p>x<b> </b>y</p>"), "<p>x<b> </b>y</p>");
+
+        // Text and values are compared as parsed, not decoded again
+        assert_ne!(canonical("<p>&amp;lt;</p>"), canonical("<p>&lt;</p>"));
+        assert_eq!(canonical("<p>&amp;lt;</p>"), "<p>&amp;lt;</p>");
+        assert_eq!(
+            canonical("<p title=\"&amp;amp;\"></p>"),
+            "<p title=\"&amp;amp;\"></p>"
+        );
+    }
+}
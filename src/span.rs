@@ -0,0 +1,59 @@
+use core::ops::Range;
+
+/// A byte range into the parsed input.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// An empty span at `offset`.
+    pub fn at(offset: usize) -> Self {
+        Self::new(offset, offset)
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// The 1-based line and column of the start of the span in `source`,
+    /// counting columns in characters.
+    pub fn line_col(&self, source: &str) -> (usize, usize) {
+        line_col(source, self.start)
+    }
+
+    pub(crate) fn shifted(self, by: usize) -> Self {
+        Self::new(self.start + by, self.end + by)
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Self::new(range.start, range.end)
+    }
+}
+
+pub(crate) fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let line = before.matches('\n').count() + 1;
+    (line, before[line_start..].chars().count() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_and_column() {
+        let source = "<p>\n  <a href=\"x\n";
+        assert_eq!(Span::at(0).line_col(source), (1, 1));
+        assert_eq!(Span::at(6).line_col(source), (2, 3));
+        assert_eq!(Span::at(source.len()).line_col(source), (3, 1));
+    }
+}
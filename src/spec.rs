@@ -0,0 +1,2577 @@
+//! The WHATWG tree-construction stage: insertion modes, the list of active
+//! formatting elements, the adoption agency algorithm and foster parenting,
+//! so misnested markup ends up in the same tree a browser builds.
+//!
+//! Tokenization is still done by the crate's own tokenizer, which doesn't
+//! switch into RCDATA or raw text states, so markup inside `<title>` or
+//! `<textarea>` is parsed as elements.
+
+use alloc::borrow::Cow;
+
+use crate::prelude::*;
+use crate::{
+    arena::Arena,
+    namespace::{adjust_attribute, adjust_tag, is_integration_point},
+    parser::{decode_text, exceeds, is_raw_text_element, normalize_name, HtmlAst, Tokenizer},
+    Diagnostic, Document, HtmlAttribute, HtmlDoctype, HtmlDocument, HtmlError, Limit, Namespace,
+    NodeData, NodeId, ParseOptions, Span,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Initial,
+    BeforeHtml,
+    BeforeHead,
+    InHead,
+    AfterHead,
+    InBody,
+    Text,
+    InTable,
+    InTableText,
+    InCaption,
+    InColumnGroup,
+    InTableBody,
+    InRow,
+    InCell,
+    InSelect,
+    InSelectInTable,
+    AfterBody,
+    InFrameset,
+    AfterFrameset,
+    AfterAfterBody,
+    InTemplate,
+}
+
+#[derive(Debug)]
+enum Token<'a> {
+    StartTag {
+        name: &'a str,
+        attributes: Vec<HtmlAttribute<'a>>,
+    },
+    EndTag(&'a str),
+    Text(Cow<'a, str>),
+    Comment(&'a str),
+    CData(&'a str),
+    Doctype(HtmlDoctype<'a>),
+    Eof,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Formatting {
+    Marker,
+    Element(NodeId),
+}
+
+const SPECIAL: &[&str] = &[
+    "address",
+    "applet",
+    "area",
+    "article",
+    "aside",
+    "base",
+    "basefont",
+    "bgsound",
+    "blockquote",
+    "body",
+    "br",
+    "button",
+    "caption",
+    "center",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "embed",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "frame",
+    "frameset",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hgroup",
+    "hr",
+    "html",
+    "iframe",
+    "img",
+    "input",
+    "keygen",
+    "li",
+    "link",
+    "listing",
+    "main",
+    "marquee",
+    "menu",
+    "meta",
+    "nav",
+    "noembed",
+    "noframes",
+    "noscript",
+    "object",
+    "ol",
+    "p",
+    "param",
+    "plaintext",
+    "pre",
+    "script",
+    "search",
+    "section",
+    "select",
+    "source",
+    "style",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "template",
+    "textarea",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "track",
+    "ul",
+    "wbr",
+    "xmp",
+];
+
+const SCOPE: &[&str] = &[
+    "applet", "caption", "html", "marquee", "object", "table", "td", "template", "th",
+];
+const TABLE_SCOPE: &[&str] = &["html", "table", "template"];
+
+const FORMATTING: &[&str] = &[
+    "a", "b", "big", "code", "em", "font", "i", "nobr", "s", "small", "strike", "strong", "tt", "u",
+];
+
+const IMPLIED_END: &[&str] = &[
+    "dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc",
+];
+
+const HEADINGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+
+// Start tags in body that close an open `<p>` before opening themselves
+const BLOCK_STARTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "center",
+    "details",
+    "dialog",
+    "dir",
+    "div",
+    "dl",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "header",
+    "hgroup",
+    "main",
+    "menu",
+    "nav",
+    "ol",
+    "p",
+    "search",
+    "section",
+    "summary",
+    "ul",
+];
+
+const BLOCK_ENDS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "button",
+    "center",
+    "details",
+    "dialog",
+    "dir",
+    "div",
+    "dl",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "header",
+    "hgroup",
+    "listing",
+    "main",
+    "menu",
+    "nav",
+    "ol",
+    "pre",
+    "search",
+    "section",
+    "summary",
+    "ul",
+];
+
+const HEAD_ELEMENTS: &[&str] = &[
+    "base", "basefont", "bgsound", "link", "meta", "noframes", "script", "style", "template",
+    "title",
+];
+
+// Elements whose children get foster parented
+const FOSTER_TARGETS: &[&str] = &["table", "tbody", "tfoot", "thead", "tr"];
+
+const TABLE_SECTIONS: &[&str] = &["tbody", "tfoot", "thead"];
+
+// HTML start tags that end foreign content they appear in
+fn breaks_out(name: &str, attributes: &[HtmlAttribute<'_>]) -> bool {
+    const BREAKOUT: &[&str] = &[
+        "b",
+        "big",
+        "blockquote",
+        "body",
+        "br",
+        "center",
+        "code",
+        "dd",
+        "div",
+        "dl",
+        "dt",
+        "em",
+        "embed",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "head",
+        "hr",
+        "i",
+        "img",
+        "li",
+        "listing",
+        "menu",
+        "meta",
+        "nobr",
+        "ol",
+        "p",
+        "pre",
+        "ruby",
+        "s",
+        "small",
+        "span",
+        "strong",
+        "strike",
+        "sub",
+        "sup",
+        "table",
+        "tt",
+        "u",
+        "ul",
+        "var",
+    ];
+    is_one_of(name, BREAKOUT)
+        || name.eq_ignore_ascii_case("font")
+            && attributes
+                .iter()
+                .any(|attr| is_one_of(&attr.name, &["color", "face", "size"]))
+}
+
+fn is_one_of(tag: &str, tags: &[&str]) -> bool {
+    tags.iter()
+        .any(|candidate| candidate.eq_ignore_ascii_case(tag))
+}
+
+fn is_whitespace(ch: char) -> bool {
+    matches!(ch, '\t' | '\n' | '\x0c' | '\r' | ' ')
+}
+
+// Splits off leading whitespace, which several modes treat differently
+// from the text after it
+fn split_whitespace(text: Cow<'_, str>) -> (Option<Cow<'_, str>>, Option<Cow<'_, str>>) {
+    fn non_empty(text: Cow<'_, str>) -> Option<Cow<'_, str>> {
+        (!text.is_empty()).then_some(text)
+    }
+
+    let len = text.len() - text.trim_start_matches(is_whitespace).len();
+    match text {
+        Cow::Borrowed(text) => (
+            non_empty(Cow::Borrowed(&text[..len])),
+            non_empty(Cow::Borrowed(&text[len..])),
+        ),
+        Cow::Owned(text) => (
+            non_empty(Cow::Owned(text[..len].to_string())),
+            non_empty(Cow::Owned(text[len..].to_string())),
+        ),
+    }
+}
+
+pub(crate) fn parse_document<'a>(
+    data: &'a str,
+    options: &ParseOptions,
+    arena: Arena<'a>,
+) -> Result<HtmlDocument<'a>, HtmlError> {
+    let mut builder = TreeBuilder::new(options);
+    builder.arena = arena;
+    let (document, diagnostics) = build(data, options, builder)?;
+    Ok(HtmlDocument {
+        nodes: document.to_nodes(document.root()),
+        source: options.preserve_source.then_some(data),
+        diagnostics,
+    })
+}
+
+/// Parses `data` as the contents of a `context` element, the way browsers
+/// parse `innerHTML`, so `<td>x</td>` in a `tr` gives a cell rather than
+/// being dropped. The fragment's top-level nodes are the document's.
+pub fn parse_fragment<'a>(data: &'a str, context: &str) -> Result<HtmlDocument<'a>, HtmlError> {
+    parse_fragment_with(data, context, &ParseOptions::default())
+}
+
+/// Like `parse_fragment`, with the tree always built by the spec rules
+/// whatever `options.mode` says.
+pub fn parse_fragment_with<'a>(
+    data: &'a str,
+    context: &str,
+    options: &ParseOptions,
+) -> Result<HtmlDocument<'a>, HtmlError> {
+    let mut builder = TreeBuilder::new(options);
+    builder.context = Some(context.to_ascii_lowercase());
+    let html = builder.insert_element("html", Vec::new());
+    if context.eq_ignore_ascii_case("template") {
+        builder.template_modes.push(Mode::InTemplate);
+    }
+    builder.reset_mode();
+    let (document, diagnostics) = build(data, options, builder)?;
+    Ok(HtmlDocument {
+        nodes: document.to_nodes(html),
+        source: options.preserve_source.then_some(data),
+        diagnostics,
+    })
+}
+
+fn build<'a>(
+    data: &'a str,
+    options: &ParseOptions,
+    mut builder: TreeBuilder<'a>,
+) -> Result<(Document<'a>, Vec<Diagnostic<'a>>), HtmlError> {
+    // Whitespace between tags is significant to tree construction
+    let tokenizer_options = ParseOptions {
+        preserve_source: true,
+        ..options.clone()
+    };
+    let mut tokenizer = Tokenizer::new(data, &tokenizer_options);
+    let mut lookahead = None;
+
+    loop {
+        let token = match lookahead.take().or_else(|| tokenizer.next_token()) {
+            Some(Err(error @ HtmlError::LimitExceeded(..))) => return Err(error),
+            // Input that can't be tokenized ends the document
+            None | Some(Err(_)) => Token::Eof,
+            Some(Ok((token, span))) => match token {
+                HtmlAst::StartTag(name) => {
+                    builder.token_span = Some(span.into());
+                    builder.token_tag = Some(name);
+                    let mut attributes = Vec::new();
+                    builder.self_closing = false;
+                    loop {
+                        match tokenizer.next_token() {
+                            Some(Ok((HtmlAst::Attribute(name, value), _))) => {
+                                attributes.push(HtmlAttribute {
+                                    name: normalize_name(name, options, builder.arena),
+                                    value: value
+                                        .map(|value| decode_text(value, options, builder.arena)),
+                                })
+                            }
+                            Some(Ok((HtmlAst::SelfClosing, _))) => builder.self_closing = true,
+                            next => {
+                                lookahead = next;
+                                break;
+                            }
+                        }
+                    }
+                    Token::StartTag { name, attributes }
+                }
+                HtmlAst::EndTag(name) => {
+                    builder.token_span = Some(span.into());
+                    Token::EndTag(name)
+                }
+                HtmlAst::Comment(_) if options.skip_comments => continue,
+                token => {
+                    builder.token_span = Some(span.into());
+                    match token {
+                        HtmlAst::Text(text) if builder.in_raw_text() => Token::Text(text.into()),
+                        HtmlAst::Text(text) => {
+                            Token::Text(decode_text(text, options, builder.arena))
+                        }
+                        HtmlAst::Comment(comment) => Token::Comment(comment),
+                        HtmlAst::CData(text) => Token::CData(text),
+                        HtmlAst::Doctype(doctype) => Token::Doctype(doctype),
+                        _ => continue,
+                    }
+                }
+            },
+        };
+
+        let eof = matches!(token, Token::Eof);
+        // Elements an end tag closes, named or implied, end with it
+        let open = matches!(token, Token::EndTag(_)).then(|| builder.open.clone());
+        builder.process(token);
+        if let (Some(open), Some(span)) = (open, builder.token_span.take()) {
+            let kept = open
+                .iter()
+                .zip(&builder.open)
+                .take_while(|(before, after)| before == after)
+                .count();
+            for &id in &open[kept..] {
+                if !builder.open.contains(&id) {
+                    extend_span(&mut builder.document, id, span.end);
+                }
+            }
+        }
+        builder.token_span = None;
+        builder.token_tag = None;
+        if exceeds(options.max_depth, builder.open.len()) {
+            let span = Span::at(tokenizer.offset());
+            return Err(HtmlError::LimitExceeded(Limit::Depth, span));
+        }
+        if eof {
+            break;
+        }
+    }
+
+    let mut document = builder.document;
+    // Elements whose end was implied reach to the end of their content
+    let nodes: Vec<_> = document.descendants(document.root()).collect();
+    for &id in nodes.iter().rev() {
+        let end = document.get(id).span().map(|span| span.end);
+        if let (Some(parent), Some(end)) = (document.parent(id), end) {
+            extend_span(&mut document, parent, end);
+        }
+    }
+    Ok((document, tokenizer.take_diagnostics()))
+}
+
+fn extend_span(document: &mut Document, id: NodeId, end: usize) {
+    let node = document.get_mut(id);
+    if let Some(span) = node.span() {
+        node.set_span(Some(Span::new(span.start, span.end.max(end))));
+    }
+}
+
+struct TreeBuilder<'a> {
+    options: ParseOptions,
+    arena: Arena<'a>,
+    document: Document<'a>,
+    mode: Mode,
+    original_mode: Mode,
+    // Open elements, outermost first
+    open: Vec<NodeId>,
+    formatting: Vec<Formatting>,
+    head: Option<NodeId>,
+    form: Option<NodeId>,
+    // The element whose text content `Mode::Text` is collecting
+    text_element: Option<NodeId>,
+    pending_table_text: Vec<Cow<'a, str>>,
+    frameset_ok: bool,
+    foster_parenting: bool,
+    // Insertion modes for the open `<template>` elements, innermost last
+    template_modes: Vec<Mode>,
+    // Whether the start tag being processed ended with `/>`
+    self_closing: bool,
+    // Where the token being processed was read from. Elements the builder
+    // implies itself have no span.
+    token_span: Option<Span>,
+    token_tag: Option<&'a str>,
+    // A newline directly after `<pre>`, `<listing>` or `<textarea>` is dropped
+    skip_newline: bool,
+    // The element a fragment is parsed into, which stands in for the root
+    // when resetting the insertion mode
+    context: Option<String>,
+}
+
+impl<'a> TreeBuilder<'a> {
+    fn new(options: &ParseOptions) -> Self {
+        Self {
+            options: options.clone(),
+            arena: Arena::default(),
+            document: Document::new(),
+            mode: Mode::Initial,
+            original_mode: Mode::Initial,
+            open: Vec::new(),
+            formatting: Vec::new(),
+            head: None,
+            form: None,
+            text_element: None,
+            pending_table_text: Vec::new(),
+            frameset_ok: true,
+            foster_parenting: false,
+            template_modes: Vec::new(),
+            self_closing: false,
+            token_span: None,
+            token_tag: None,
+            skip_newline: false,
+            context: None,
+        }
+    }
+
+    fn process(&mut self, token: Token<'a>) {
+        let token = match token {
+            Token::Text(text) if core::mem::take(&mut self.skip_newline) => {
+                match text.strip_prefix('\n') {
+                    Some("") => return,
+                    Some(rest) => Token::Text(Cow::Owned(rest.to_string())),
+                    None => Token::Text(text),
+                }
+            }
+            token => {
+                self.skip_newline = false;
+                token
+            }
+        };
+
+        let mut token = Some(token);
+        while let Some(current) = token.take() {
+            token = self.step(current);
+        }
+    }
+
+    // Handles a token in the current mode, returning it when a mode switch
+    // means it has to be processed again
+    fn step(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        if self.in_foreign_content(&token) {
+            return self.foreign_content(token);
+        }
+        self.step_mode(token)
+    }
+
+    fn step_mode(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match self.mode {
+            Mode::Initial => self.initial(token),
+            Mode::BeforeHtml => self.before_html(token),
+            Mode::BeforeHead => self.before_head(token),
+            Mode::InHead => self.in_head(token),
+            Mode::AfterHead => self.after_head(token),
+            Mode::InBody => self.in_body(token),
+            Mode::Text => self.text(token),
+            Mode::InTable => self.in_table(token),
+            Mode::InTableText => self.in_table_text(token),
+            Mode::InCaption => self.in_caption(token),
+            Mode::InColumnGroup => self.in_column_group(token),
+            Mode::InTableBody => self.in_table_body(token),
+            Mode::InRow => self.in_row(token),
+            Mode::InCell => self.in_cell(token),
+            Mode::InSelect => self.in_select(token),
+            Mode::InSelectInTable => self.in_select_in_table(token),
+            Mode::AfterBody => self.after_body(token),
+            Mode::InFrameset => self.in_frameset(token),
+            Mode::AfterFrameset => self.after_frameset(token),
+            Mode::AfterAfterBody => self.after_after_body(token),
+            Mode::InTemplate => self.in_template(token),
+        }
+    }
+
+    fn in_raw_text(&self) -> bool {
+        self.current()
+            .is_some_and(|current| is_raw_text_element(self.tag(current)))
+    }
+
+    // Tree helpers
+
+    fn tag(&self, id: NodeId) -> &str {
+        self.document.get(id).tag().unwrap_or_default()
+    }
+
+    fn namespace(&self, id: NodeId) -> Namespace {
+        match self.document.get(id).data() {
+            NodeData::Element { namespace, .. } => *namespace,
+            _ => Namespace::Html,
+        }
+    }
+
+    // Only HTML elements match, so e.g. an SVG `<title>` isn't mistaken
+    // for the document title
+    fn is(&self, id: NodeId, tags: &[&str]) -> bool {
+        self.namespace(id) == Namespace::Html && is_one_of(self.tag(id), tags)
+    }
+
+    fn is_integration_point(&self, id: NodeId) -> bool {
+        is_integration_point(self.namespace(id), self.tag(id))
+    }
+
+    fn current(&self) -> Option<NodeId> {
+        self.open.last().copied()
+    }
+
+    fn current_is(&self, tags: &[&str]) -> bool {
+        self.current().is_some_and(|current| self.is(current, tags))
+    }
+
+    // Where a node inserted into `target` ends up: appended to a parent, or
+    // when foster parenting, placed before the table it would land inside
+    fn insertion_location(&self, target: NodeId) -> (NodeId, Option<NodeId>) {
+        let (parent, before) = if self.foster_parenting && self.is(target, FOSTER_TARGETS) {
+            let table = self.open.iter().rposition(|&id| self.is(id, &["table"]));
+            let template = self.open.iter().rposition(|&id| self.is(id, &["template"]));
+            match (table, template) {
+                (Some(table), Some(template)) if template > table => (self.open[template], None),
+                (Some(table), _) => {
+                    let table_id = self.open[table];
+                    match self.document.parent(table_id) {
+                        Some(parent) => (parent, Some(table_id)),
+                        None => (self.open[table.saturating_sub(1)], None),
+                    }
+                }
+                (None, Some(template)) => (self.open[template], None),
+                (None, None) => (self.open[0], None),
+            }
+        } else {
+            (target, None)
+        };
+        // Children of a `<template>` go into its contents instead
+        match self.document.template_content(parent) {
+            Some(fragment) if before.is_none() => (fragment, None),
+            _ => (parent, before),
+        }
+    }
+
+    fn insert_into(&mut self, target: NodeId, id: NodeId) {
+        match self.insertion_location(target) {
+            (_, Some(table)) => self.document.insert_before(table, id),
+            (parent, None) => self.document.append_child(parent, id),
+        };
+    }
+
+    fn insert_node(&mut self, id: NodeId) {
+        let target = self.current().unwrap_or(self.document.root());
+        self.insert_into(target, id);
+    }
+
+    fn create_element(&mut self, name: &'a str, attributes: Vec<HtmlAttribute<'a>>) -> NodeId {
+        let id = self.document.create_node(NodeData::Element {
+            tag: normalize_name(name, &self.options, self.arena),
+            attributes,
+            namespace: Namespace::Html,
+        });
+        self.set_span(id, name);
+        id
+    }
+
+    // Gives an element created for the current start tag the tag's span
+    fn set_span(&mut self, id: NodeId, name: &str) {
+        if self
+            .token_tag
+            .is_some_and(|tag| tag.eq_ignore_ascii_case(name))
+        {
+            self.document.get_mut(id).set_span(self.token_span);
+        }
+    }
+
+    fn insert_element(&mut self, name: &'a str, attributes: Vec<HtmlAttribute<'a>>) -> NodeId {
+        let id = self.create_element(name, attributes);
+        if name.eq_ignore_ascii_case("template") {
+            self.document.template_content_or_insert(id);
+        }
+        self.insert_node(id);
+        self.open.push(id);
+        id
+    }
+
+    fn insert_foreign(
+        &mut self,
+        name: &'a str,
+        mut attributes: Vec<HtmlAttribute<'a>>,
+        namespace: Namespace,
+    ) {
+        for attr in &mut attributes {
+            let name = core::mem::take(&mut attr.name);
+            attr.name = adjust_attribute(namespace, name);
+        }
+        let id = self.document.create_node(NodeData::Element {
+            tag: adjust_tag(namespace, normalize_name(name, &self.options, self.arena)),
+            attributes,
+            namespace,
+        });
+        self.set_span(id, name);
+        self.insert_node(id);
+        self.open.push(id);
+        if self.self_closing {
+            self.open.pop();
+        }
+    }
+
+    fn insert_void(&mut self, name: &'a str, attributes: Vec<HtmlAttribute<'a>>) {
+        self.insert_element(name, attributes);
+        self.open.pop();
+    }
+
+    fn insert_text(&mut self, text: Cow<'a, str>) {
+        let target = self.current().unwrap_or(self.document.root());
+        let previous = match self.insertion_location(target) {
+            (_, Some(table)) => self.document.prev_sibling(table),
+            (parent, None) => self.document.get(parent).last_child(),
+        };
+        if let Some(previous) = previous {
+            if let NodeData::Text(existing) = self.document.get_mut(previous).data_mut() {
+                existing.to_mut().push_str(&text);
+                if let Some(span) = self.token_span {
+                    extend_span(&mut self.document, previous, span.end);
+                }
+                return;
+            }
+        }
+        let id = self.document.create_node(NodeData::Text(text));
+        self.document.get_mut(id).set_span(self.token_span);
+        self.insert_node(id);
+    }
+
+    fn insert_comment(&mut self, comment: &'a str) {
+        let id = self.document.create_node(NodeData::Comment(comment.into()));
+        self.document.get_mut(id).set_span(self.token_span);
+        self.insert_node(id);
+    }
+
+    fn insert_cdata(&mut self, text: &'a str) {
+        let id = self.document.create_node(NodeData::CData(text.into()));
+        self.document.get_mut(id).set_span(self.token_span);
+        self.insert_node(id);
+    }
+
+    // Adds attributes the element doesn't have yet, for repeated `<html>`
+    // and `<body>` start tags
+    fn merge_attributes(&mut self, id: NodeId, attributes: Vec<HtmlAttribute<'a>>) {
+        if let NodeData::Element {
+            attributes: existing,
+            ..
+        } = self.document.get_mut(id).data_mut()
+        {
+            for attr in attributes {
+                if !existing
+                    .iter()
+                    .any(|other| other.name.eq_ignore_ascii_case(&attr.name))
+                {
+                    existing.push(attr);
+                }
+            }
+        }
+    }
+
+    fn in_scope_with(&self, tags: &[&str], boundaries: &[&str]) -> bool {
+        for &id in self.open.iter().rev() {
+            if self.is(id, tags) {
+                return true;
+            }
+            if self.is(id, SCOPE) || self.is(id, boundaries) || self.is_integration_point(id) {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn in_scope(&self, tags: &[&str]) -> bool {
+        self.in_scope_with(tags, &[])
+    }
+
+    fn in_button_scope(&self, tags: &[&str]) -> bool {
+        self.in_scope_with(tags, &["button"])
+    }
+
+    fn in_list_item_scope(&self, tags: &[&str]) -> bool {
+        self.in_scope_with(tags, &["ol", "ul"])
+    }
+
+    fn in_table_scope(&self, tags: &[&str]) -> bool {
+        for &id in self.open.iter().rev() {
+            if self.is(id, tags) {
+                return true;
+            }
+            if self.is(id, TABLE_SCOPE) {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn in_select_scope(&self, tags: &[&str]) -> bool {
+        for &id in self.open.iter().rev() {
+            if self.is(id, tags) {
+                return true;
+            }
+            if !self.is(id, &["optgroup", "option"]) {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn element_in_scope(&self, target: NodeId) -> bool {
+        for &id in self.open.iter().rev() {
+            if id == target {
+                return true;
+            }
+            if self.is(id, SCOPE) || self.is_integration_point(id) {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn generate_implied_end_tags(&mut self, except: Option<&str>) {
+        while let Some(current) = self.current() {
+            let tag = self.tag(current);
+            if !is_one_of(tag, IMPLIED_END)
+                || except.is_some_and(|except| tag.eq_ignore_ascii_case(except))
+            {
+                break;
+            }
+            self.open.pop();
+        }
+    }
+
+    fn pop_until(&mut self, tags: &[&str]) {
+        while let Some(id) = self.open.pop() {
+            if self.is(id, tags) {
+                break;
+            }
+        }
+    }
+
+    fn close_p(&mut self) {
+        self.generate_implied_end_tags(Some("p"));
+        self.pop_until(&["p"]);
+    }
+
+    fn close_p_in_button_scope(&mut self) {
+        if self.in_button_scope(&["p"]) {
+            self.close_p();
+        }
+    }
+
+    fn clear_stack_back_to(&mut self, tags: &[&str]) {
+        while self
+            .current()
+            .is_some_and(|current| !self.is(current, tags))
+        {
+            self.open.pop();
+        }
+    }
+
+    fn reset_mode(&mut self) {
+        for (index, &id) in self.open.iter().enumerate().rev() {
+            let last = index == 0;
+            let tag = match &self.context {
+                Some(context) if last => context.clone(),
+                _ => self.tag(id).to_ascii_lowercase(),
+            };
+            self.mode = match tag.as_str() {
+                "select" => {
+                    let in_table = self.open[..index]
+                        .iter()
+                        .rev()
+                        .take_while(|&&ancestor| !self.is(ancestor, &["template"]))
+                        .any(|&ancestor| self.is(ancestor, &["table"]));
+                    if in_table {
+                        Mode::InSelectInTable
+                    } else {
+                        Mode::InSelect
+                    }
+                }
+                "td" | "th" if !last => Mode::InCell,
+                "tr" => Mode::InRow,
+                "tbody" | "thead" | "tfoot" => Mode::InTableBody,
+                "caption" => Mode::InCaption,
+                "colgroup" => Mode::InColumnGroup,
+                "table" => Mode::InTable,
+                "template" => self
+                    .template_modes
+                    .last()
+                    .copied()
+                    .unwrap_or(Mode::InTemplate),
+                "head" if !last => Mode::InHead,
+                "body" => Mode::InBody,
+                "frameset" => Mode::InFrameset,
+                "html" if self.head.is_none() => Mode::BeforeHead,
+                "html" => Mode::AfterHead,
+                _ if last => Mode::InBody,
+                _ => continue,
+            };
+            return;
+        }
+        self.mode = Mode::InBody;
+    }
+
+    // Active formatting elements
+
+    fn push_formatting(&mut self, id: NodeId) {
+        // At most three identical entries after the last marker
+        let same: Vec<usize> = self
+            .formatting
+            .iter()
+            .enumerate()
+            .rev()
+            .take_while(|(_, entry)| **entry != Formatting::Marker)
+            .filter_map(|(index, entry)| match entry {
+                Formatting::Element(other) if self.same_element(*other, id) => Some(index),
+                _ => None,
+            })
+            .collect();
+        if same.len() >= 3 {
+            self.formatting.remove(*same.last().unwrap());
+        }
+        self.formatting.push(Formatting::Element(id));
+    }
+
+    fn same_element(&self, a: NodeId, b: NodeId) -> bool {
+        let (a, b) = (self.document.get(a), self.document.get(b));
+        let same_attributes = a.attributes().len() == b.attributes().len()
+            && a.attributes().iter().all(|attr| {
+                b.attributes().iter().any(|other| {
+                    other.name.eq_ignore_ascii_case(&attr.name) && other.value() == attr.value()
+                })
+            });
+        a.tag()
+            .zip(b.tag())
+            .is_some_and(|(a, b)| a.eq_ignore_ascii_case(b))
+            && same_attributes
+    }
+
+    fn formatting_index(&self, id: NodeId) -> Option<usize> {
+        self.formatting
+            .iter()
+            .position(|entry| *entry == Formatting::Element(id))
+    }
+
+    fn clear_formatting_to_marker(&mut self) {
+        while let Some(entry) = self.formatting.pop() {
+            if entry == Formatting::Marker {
+                break;
+            }
+        }
+    }
+
+    fn clone_element(&mut self, id: NodeId) -> NodeId {
+        let data = self.document.get(id).data().clone();
+        self.document.create_node(data)
+    }
+
+    fn reconstruct_formatting(&mut self) {
+        let is_open = |builder: &Self, entry: Formatting| match entry {
+            Formatting::Marker => true,
+            Formatting::Element(id) => builder.open.contains(&id),
+        };
+        let Some(&last) = self.formatting.last() else {
+            return;
+        };
+        if is_open(self, last) {
+            return;
+        }
+
+        let mut index = self.formatting.len() - 1;
+        while index > 0 && !is_open(self, self.formatting[index - 1]) {
+            index -= 1;
+        }
+        for index in index..self.formatting.len() {
+            let Formatting::Element(id) = self.formatting[index] else {
+                continue;
+            };
+            let clone = self.clone_element(id);
+            self.insert_node(clone);
+            self.open.push(clone);
+            self.formatting[index] = Formatting::Element(clone);
+        }
+    }
+
+    // Returns false when the end tag should be handled like any other
+    fn adoption_agency(&mut self, subject: &str) -> bool {
+        if let Some(current) = self.current() {
+            if self.tag(current).eq_ignore_ascii_case(subject)
+                && self.formatting_index(current).is_none()
+            {
+                self.open.pop();
+                return true;
+            }
+        }
+
+        for _ in 0..8 {
+            let Some(formatting_index) = self
+                .formatting
+                .iter()
+                .rposition(|entry| match entry {
+                    Formatting::Marker => true,
+                    Formatting::Element(id) => self.tag(*id).eq_ignore_ascii_case(subject),
+                })
+                .filter(|&index| self.formatting[index] != Formatting::Marker)
+            else {
+                return false;
+            };
+            let Formatting::Element(formatting_element) = self.formatting[formatting_index] else {
+                unreachable!()
+            };
+
+            let Some(stack_index) = self.open.iter().position(|&id| id == formatting_element)
+            else {
+                self.formatting.remove(formatting_index);
+                return true;
+            };
+            if !self.element_in_scope(formatting_element) {
+                return true;
+            }
+
+            let Some(furthest_index) = (stack_index + 1..self.open.len())
+                .find(|&index| self.is(self.open[index], SPECIAL))
+            else {
+                self.open.truncate(stack_index);
+                self.formatting.remove(formatting_index);
+                return true;
+            };
+            let furthest_block = self.open[furthest_index];
+            let common_ancestor = self.open[stack_index - 1];
+            let mut bookmark = formatting_index;
+
+            let mut node_index = furthest_index;
+            let mut last_node = furthest_block;
+            let mut inner = 0;
+            loop {
+                inner += 1;
+                node_index -= 1;
+                let node = self.open[node_index];
+                if node == formatting_element {
+                    break;
+                }
+
+                let mut node_formatting = self.formatting_index(node);
+                if inner > 3 {
+                    if let Some(index) = node_formatting.take() {
+                        self.formatting.remove(index);
+                        if index < bookmark {
+                            bookmark -= 1;
+                        }
+                    }
+                }
+                let Some(index) = node_formatting else {
+                    self.open.remove(node_index);
+                    continue;
+                };
+
+                let clone = self.clone_element(node);
+                self.formatting[index] = Formatting::Element(clone);
+                self.open[node_index] = clone;
+                if last_node == furthest_block {
+                    bookmark = index + 1;
+                }
+                self.document.append_child(clone, last_node);
+                last_node = clone;
+            }
+
+            self.insert_into(common_ancestor, last_node);
+
+            let new_element = self.clone_element(formatting_element);
+            while let Some(child) = self.document.get(furthest_block).first_child() {
+                self.document.append_child(new_element, child);
+            }
+            self.document.append_child(furthest_block, new_element);
+
+            if let Some(index) = self.formatting_index(formatting_element) {
+                self.formatting.remove(index);
+                if index < bookmark {
+                    bookmark -= 1;
+                }
+            }
+            self.formatting.insert(
+                bookmark.min(self.formatting.len()),
+                Formatting::Element(new_element),
+            );
+
+            self.open.retain(|&id| id != formatting_element);
+            let furthest_index = self
+                .open
+                .iter()
+                .position(|&id| id == furthest_block)
+                .unwrap();
+            self.open.insert(furthest_index + 1, new_element);
+        }
+        true
+    }
+
+    // Insertion modes
+
+    // Foreign content
+
+    fn in_foreign_content(&self, token: &Token<'a>) -> bool {
+        let Some(current) = self.current() else {
+            return false;
+        };
+        let namespace = self.namespace(current);
+        if namespace == Namespace::Html {
+            return false;
+        }
+        match token {
+            Token::Eof => false,
+            Token::StartTag { name, .. } if self.is_integration_point(current) => {
+                // `<mglyph>` and `<malignmark>` stay MathML in text integration points
+                namespace == Namespace::MathMl
+                    && !self.tag(current).eq_ignore_ascii_case("annotation-xml")
+                    && is_one_of(name, &["malignmark", "mglyph"])
+            }
+            Token::Text(_) => !self.is_integration_point(current),
+            _ => true,
+        }
+    }
+
+    fn foreign_content(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(text) => {
+                if !text.chars().all(is_whitespace) {
+                    self.frameset_ok = false;
+                }
+                self.insert_text(text);
+            }
+            Token::Comment(comment) => self.insert_comment(comment),
+            Token::CData(text) => self.insert_cdata(text),
+            Token::Doctype(_) | Token::Eof => {}
+            Token::StartTag { name, attributes } if breaks_out(name, &attributes) => {
+                while let Some(current) = self.current() {
+                    if self.namespace(current) == Namespace::Html
+                        || self.is_integration_point(current)
+                    {
+                        break;
+                    }
+                    self.open.pop();
+                }
+                return Some(Token::StartTag { name, attributes });
+            }
+            Token::StartTag { name, attributes } => {
+                let namespace = self
+                    .current()
+                    .map_or(Namespace::Html, |id| self.namespace(id));
+                self.insert_foreign(name, attributes, namespace);
+            }
+            Token::EndTag(name) => {
+                for index in (0..self.open.len()).rev() {
+                    let id = self.open[index];
+                    if self.namespace(id) == Namespace::Html {
+                        return self.step_mode(Token::EndTag(name));
+                    }
+                    if self.tag(id).eq_ignore_ascii_case(name) {
+                        self.open.truncate(index);
+                        break;
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn initial(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(text) => {
+                let (_, rest) = split_whitespace(text);
+                self.mode = Mode::BeforeHtml;
+                rest.map(Token::Text)
+            }
+            Token::Comment(comment) => {
+                self.insert_comment(comment);
+                None
+            }
+            Token::Doctype(doctype) => {
+                let root = self.document.root();
+                self.document.append(root, NodeData::Doctype(doctype));
+                self.mode = Mode::BeforeHtml;
+                None
+            }
+            token => {
+                self.mode = Mode::BeforeHtml;
+                Some(token)
+            }
+        }
+    }
+
+    fn before_html(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Doctype(_) => None,
+            Token::Comment(comment) => {
+                self.insert_comment(comment);
+                None
+            }
+            Token::Text(text) => split_whitespace(text).1.map(|rest| {
+                self.insert_element("html", Vec::new());
+                self.mode = Mode::BeforeHead;
+                Token::Text(rest)
+            }),
+            Token::StartTag { name, attributes } if name.eq_ignore_ascii_case("html") => {
+                self.insert_element(name, attributes);
+                self.mode = Mode::BeforeHead;
+                None
+            }
+            Token::EndTag(name) if !is_one_of(name, &["head", "body", "html", "br"]) => None,
+            token => {
+                self.insert_element("html", Vec::new());
+                self.mode = Mode::BeforeHead;
+                Some(token)
+            }
+        }
+    }
+
+    fn before_head(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(text) => split_whitespace(text).1.map(|rest| {
+                self.insert_head();
+                Token::Text(rest)
+            }),
+            Token::Comment(comment) => {
+                self.insert_comment(comment);
+                None
+            }
+            Token::Doctype(_) => None,
+            Token::StartTag { name, .. } if name.eq_ignore_ascii_case("html") => {
+                self.in_body(token)
+            }
+            Token::StartTag { name, attributes } if name.eq_ignore_ascii_case("head") => {
+                let head = self.insert_element(name, attributes);
+                self.head = Some(head);
+                self.mode = Mode::InHead;
+                None
+            }
+            Token::EndTag(name) if !is_one_of(name, &["head", "body", "html", "br"]) => None,
+            token => {
+                self.insert_head();
+                Some(token)
+            }
+        }
+    }
+
+    fn insert_head(&mut self) {
+        let head = self.insert_element("head", Vec::new());
+        self.head = Some(head);
+        self.mode = Mode::InHead;
+    }
+
+    fn in_head(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(text) => {
+                let (whitespace, rest) = split_whitespace(text);
+                if let Some(whitespace) = whitespace {
+                    self.insert_text(whitespace);
+                }
+                rest.map(|rest| {
+                    self.open.pop();
+                    self.mode = Mode::AfterHead;
+                    Token::Text(rest)
+                })
+            }
+            Token::Comment(comment) => {
+                self.insert_comment(comment);
+                None
+            }
+            Token::Doctype(_) => None,
+            Token::StartTag { name, attributes } => {
+                match name.to_ascii_lowercase().as_str() {
+                    "html" => return self.in_body(Token::StartTag { name, attributes }),
+                    "base" | "basefont" | "bgsound" | "link" | "meta" => {
+                        self.insert_void(name, attributes)
+                    }
+                    "title" | "noscript" | "noframes" | "style" | "script" => {
+                        self.start_text(name, attributes)
+                    }
+                    "template" => {
+                        self.insert_element(name, attributes);
+                        self.formatting.push(Formatting::Marker);
+                        self.frameset_ok = false;
+                        self.mode = Mode::InTemplate;
+                        self.template_modes.push(Mode::InTemplate);
+                    }
+                    "head" => {}
+                    _ => {
+                        self.open.pop();
+                        self.mode = Mode::AfterHead;
+                        return Some(Token::StartTag { name, attributes });
+                    }
+                }
+                None
+            }
+            Token::EndTag(name) if name.eq_ignore_ascii_case("head") => {
+                self.open.pop();
+                self.mode = Mode::AfterHead;
+                None
+            }
+            Token::EndTag(name) if name.eq_ignore_ascii_case("template") => {
+                if self.open.iter().any(|&id| self.is(id, &["template"])) {
+                    self.generate_all_implied_end_tags();
+                    self.close_template();
+                }
+                None
+            }
+            Token::EndTag(name) if !is_one_of(name, &["body", "html", "br"]) => None,
+            token => {
+                self.open.pop();
+                self.mode = Mode::AfterHead;
+                Some(token)
+            }
+        }
+    }
+
+    fn generate_all_implied_end_tags(&mut self) {
+        const THOROUGH: &[&str] = &[
+            "caption", "colgroup", "tbody", "td", "tfoot", "th", "thead", "tr",
+        ];
+        while self.current_is(IMPLIED_END) || self.current_is(THOROUGH) {
+            self.open.pop();
+        }
+    }
+
+    fn close_template(&mut self) {
+        self.pop_until(&["template"]);
+        self.clear_formatting_to_marker();
+        self.template_modes.pop();
+        self.reset_mode();
+    }
+
+    fn in_template(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(_) | Token::Comment(_) | Token::CData(_) | Token::Doctype(_) => {
+                self.in_body(token)
+            }
+            Token::StartTag { name, .. } if is_one_of(name, HEAD_ELEMENTS) => self.in_head(token),
+            Token::EndTag(name) if name.eq_ignore_ascii_case("template") => self.in_head(token),
+            Token::StartTag { name, attributes } => {
+                // The first tag decides what kind of content the template holds
+                let mode = match name.to_ascii_lowercase().as_str() {
+                    "caption" | "colgroup" | "tbody" | "tfoot" | "thead" => Mode::InTable,
+                    "col" => Mode::InColumnGroup,
+                    "tr" => Mode::InTableBody,
+                    "td" | "th" => Mode::InRow,
+                    _ => Mode::InBody,
+                };
+                self.template_modes.pop();
+                self.template_modes.push(mode);
+                self.mode = mode;
+                Some(Token::StartTag { name, attributes })
+            }
+            Token::EndTag(_) => None,
+            Token::Eof => {
+                if !self.open.iter().any(|&id| self.is(id, &["template"])) {
+                    return None;
+                }
+                self.close_template();
+                Some(Token::Eof)
+            }
+        }
+    }
+
+    // Opens an element whose contents are collected as text, like `<title>`
+    fn start_text(&mut self, name: &'a str, attributes: Vec<HtmlAttribute<'a>>) {
+        let id = self.insert_element(name, attributes);
+        self.text_element = Some(id);
+        self.original_mode = self.mode;
+        self.mode = Mode::Text;
+    }
+
+    fn after_head(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(text) => {
+                let (whitespace, rest) = split_whitespace(text);
+                if let Some(whitespace) = whitespace {
+                    self.insert_text(whitespace);
+                }
+                rest.map(|rest| {
+                    self.insert_element("body", Vec::new());
+                    self.mode = Mode::InBody;
+                    Token::Text(rest)
+                })
+            }
+            Token::Comment(comment) => {
+                self.insert_comment(comment);
+                None
+            }
+            Token::Doctype(_) => None,
+            Token::StartTag { name, attributes } => {
+                match name.to_ascii_lowercase().as_str() {
+                    "html" => return self.in_body(Token::StartTag { name, attributes }),
+                    "body" => {
+                        self.insert_element(name, attributes);
+                        self.frameset_ok = false;
+                        self.mode = Mode::InBody;
+                    }
+                    "frameset" => {
+                        self.insert_element(name, attributes);
+                        self.mode = Mode::InFrameset;
+                    }
+                    _ if is_one_of(name, HEAD_ELEMENTS) => {
+                        // Late head content still goes into the head
+                        let head = self.head.unwrap_or(self.document.root());
+                        self.open.push(head);
+                        let reprocess = self.in_head(Token::StartTag { name, attributes });
+                        if let Some(index) = self.open.iter().rposition(|&id| id == head) {
+                            self.open.remove(index);
+                        }
+                        return reprocess;
+                    }
+                    "head" => {}
+                    _ => {
+                        self.insert_element("body", Vec::new());
+                        self.mode = Mode::InBody;
+                        return Some(Token::StartTag { name, attributes });
+                    }
+                }
+                None
+            }
+            Token::EndTag(name) if name.eq_ignore_ascii_case("template") => self.in_head(token),
+            Token::EndTag(name) if !is_one_of(name, &["body", "html", "br"]) => None,
+            token => {
+                self.insert_element("body", Vec::new());
+                self.mode = Mode::InBody;
+                Some(token)
+            }
+        }
+    }
+
+    fn in_body(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(text) => {
+                self.reconstruct_formatting();
+                if !text.chars().all(is_whitespace) {
+                    self.frameset_ok = false;
+                }
+                self.insert_text(text);
+                None
+            }
+            Token::Comment(comment) => {
+                self.insert_comment(comment);
+                None
+            }
+            Token::CData(text) => {
+                self.insert_cdata(text);
+                None
+            }
+            Token::Eof if !self.template_modes.is_empty() => self.in_template(Token::Eof),
+            Token::Doctype(_) | Token::Eof => None,
+            Token::StartTag { name, attributes } => self.in_body_start_tag(name, attributes),
+            Token::EndTag(name) => self.in_body_end_tag(name),
+        }
+    }
+
+    fn in_body_start_tag(
+        &mut self,
+        name: &'a str,
+        attributes: Vec<HtmlAttribute<'a>>,
+    ) -> Option<Token<'a>> {
+        let lower = name.to_ascii_lowercase();
+        match lower.as_str() {
+            "html" => {
+                if let Some(&html) = self.open.first() {
+                    self.merge_attributes(html, attributes);
+                }
+            }
+            _ if is_one_of(name, HEAD_ELEMENTS) => {
+                return self.in_head(Token::StartTag { name, attributes });
+            }
+            "body" => {
+                if let Some(&body) = self.open.get(1).filter(|&&id| self.is(id, &["body"])) {
+                    self.frameset_ok = false;
+                    self.merge_attributes(body, attributes);
+                }
+            }
+            "frameset" => {}
+            _ if is_one_of(name, BLOCK_STARTS) => {
+                self.close_p_in_button_scope();
+                self.insert_element(name, attributes);
+            }
+            _ if is_one_of(name, HEADINGS) => {
+                self.close_p_in_button_scope();
+                if self.current_is(HEADINGS) {
+                    self.open.pop();
+                }
+                self.insert_element(name, attributes);
+            }
+            "pre" | "listing" => {
+                self.close_p_in_button_scope();
+                self.insert_element(name, attributes);
+                self.skip_newline = true;
+                self.frameset_ok = false;
+            }
+            "form" => {
+                if self.form.is_none() {
+                    self.close_p_in_button_scope();
+                    let form = self.insert_element(name, attributes);
+                    self.form = Some(form);
+                }
+            }
+            "li" | "dd" | "dt" => {
+                self.frameset_ok = false;
+                let closes: &[&str] = if lower == "li" {
+                    &["li"]
+                } else {
+                    &["dd", "dt"]
+                };
+                for &id in self.open.iter().rev() {
+                    if self.is(id, closes) {
+                        let tag = self.tag(id).to_string();
+                        self.generate_implied_end_tags(Some(&tag));
+                        self.pop_until(&[&tag]);
+                        break;
+                    }
+                    if self.is(id, SPECIAL) && !self.is(id, &["address", "div", "p"]) {
+                        break;
+                    }
+                }
+                self.close_p_in_button_scope();
+                self.insert_element(name, attributes);
+            }
+            "plaintext" => {
+                self.close_p_in_button_scope();
+                self.insert_element(name, attributes);
+            }
+            "button" => {
+                if self.in_scope(&["button"]) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&["button"]);
+                }
+                self.reconstruct_formatting();
+                self.insert_element(name, attributes);
+                self.frameset_ok = false;
+            }
+            "a" => {
+                let open_a = self
+                    .formatting
+                    .iter()
+                    .rev()
+                    .take_while(|entry| **entry != Formatting::Marker)
+                    .find_map(|entry| match entry {
+                        Formatting::Element(id) if self.is(*id, &["a"]) => Some(*id),
+                        _ => None,
+                    });
+                if let Some(open_a) = open_a {
+                    self.adoption_agency("a");
+                    if let Some(index) = self.formatting_index(open_a) {
+                        self.formatting.remove(index);
+                    }
+                    self.open.retain(|&id| id != open_a);
+                }
+                self.reconstruct_formatting();
+                let id = self.insert_element(name, attributes);
+                self.push_formatting(id);
+            }
+            "nobr" => {
+                self.reconstruct_formatting();
+                if self.in_scope(&["nobr"]) {
+                    self.adoption_agency("nobr");
+                    self.reconstruct_formatting();
+                }
+                let id = self.insert_element(name, attributes);
+                self.push_formatting(id);
+            }
+            _ if is_one_of(name, FORMATTING) => {
+                self.reconstruct_formatting();
+                let id = self.insert_element(name, attributes);
+                self.push_formatting(id);
+            }
+            "applet" | "marquee" | "object" => {
+                self.reconstruct_formatting();
+                self.insert_element(name, attributes);
+                self.formatting.push(Formatting::Marker);
+                self.frameset_ok = false;
+            }
+            "table" => {
+                self.close_p_in_button_scope();
+                self.insert_element(name, attributes);
+                self.frameset_ok = false;
+                self.mode = Mode::InTable;
+            }
+            "area" | "br" | "embed" | "img" | "keygen" | "wbr" | "input" => {
+                self.reconstruct_formatting();
+                let hidden = attributes.iter().any(|attr| {
+                    attr.name.eq_ignore_ascii_case("type")
+                        && attr
+                            .value()
+                            .is_some_and(|value| value.eq_ignore_ascii_case("hidden"))
+                });
+                self.insert_void(name, attributes);
+                if lower != "input" || !hidden {
+                    self.frameset_ok = false;
+                }
+            }
+            "param" | "source" | "track" => self.insert_void(name, attributes),
+            "hr" => {
+                self.close_p_in_button_scope();
+                self.insert_void(name, attributes);
+                self.frameset_ok = false;
+            }
+            "image" => {
+                return Some(Token::StartTag {
+                    name: "img",
+                    attributes,
+                })
+            }
+            "textarea" => {
+                self.start_text(name, attributes);
+                self.skip_newline = true;
+                self.frameset_ok = false;
+            }
+            "xmp" => {
+                self.close_p_in_button_scope();
+                self.reconstruct_formatting();
+                self.frameset_ok = false;
+                self.start_text(name, attributes);
+            }
+            "iframe" | "noembed" => {
+                self.frameset_ok = false;
+                self.start_text(name, attributes);
+            }
+            "select" => {
+                self.reconstruct_formatting();
+                self.insert_element(name, attributes);
+                self.frameset_ok = false;
+                self.mode = match self.mode {
+                    Mode::InTable
+                    | Mode::InCaption
+                    | Mode::InTableBody
+                    | Mode::InRow
+                    | Mode::InCell => Mode::InSelectInTable,
+                    _ => Mode::InSelect,
+                };
+            }
+            "optgroup" | "option" => {
+                if self.current_is(&["option"]) {
+                    self.open.pop();
+                }
+                self.reconstruct_formatting();
+                self.insert_element(name, attributes);
+            }
+            "rb" | "rtc" => {
+                if self.in_scope(&["ruby"]) {
+                    self.generate_implied_end_tags(None);
+                }
+                self.insert_element(name, attributes);
+            }
+            "rp" | "rt" => {
+                if self.in_scope(&["ruby"]) {
+                    self.generate_implied_end_tags(Some("rtc"));
+                }
+                self.insert_element(name, attributes);
+            }
+            "math" | "svg" => {
+                self.reconstruct_formatting();
+                let namespace = Namespace::Html.for_child("", name);
+                self.insert_foreign(name, attributes, namespace);
+            }
+            "caption" | "col" | "colgroup" | "frame" | "head" | "tbody" | "td" | "tfoot" | "th"
+            | "thead" | "tr" => {}
+            _ => {
+                self.reconstruct_formatting();
+                self.insert_element(name, attributes);
+            }
+        }
+        None
+    }
+
+    fn in_body_end_tag(&mut self, name: &'a str) -> Option<Token<'a>> {
+        let lower = name.to_ascii_lowercase();
+        match lower.as_str() {
+            "body" => {
+                if self.in_scope(&["body"]) {
+                    self.mode = Mode::AfterBody;
+                }
+            }
+            "template" => return self.in_head(Token::EndTag(name)),
+            "html" => {
+                if self.in_scope(&["body"]) {
+                    self.mode = Mode::AfterBody;
+                    return Some(Token::EndTag(name));
+                }
+            }
+            _ if is_one_of(name, BLOCK_ENDS) => {
+                if self.in_scope(&[name]) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&[name]);
+                }
+            }
+            "form" => {
+                if let Some(form) = self.form.take() {
+                    if self.element_in_scope(form) {
+                        self.generate_implied_end_tags(None);
+                        self.open.retain(|&id| id != form);
+                    }
+                }
+            }
+            "p" => {
+                if !self.in_button_scope(&["p"]) {
+                    self.insert_element("p", Vec::new());
+                }
+                self.close_p();
+            }
+            "li" => {
+                if self.in_list_item_scope(&["li"]) {
+                    self.generate_implied_end_tags(Some("li"));
+                    self.pop_until(&["li"]);
+                }
+            }
+            "dd" | "dt" => {
+                if self.in_scope(&[name]) {
+                    self.generate_implied_end_tags(Some(name));
+                    self.pop_until(&[name]);
+                }
+            }
+            _ if is_one_of(name, HEADINGS) => {
+                if self.in_scope(HEADINGS) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(HEADINGS);
+                }
+            }
+            _ if is_one_of(name, FORMATTING) => {
+                if !self.adoption_agency(name) {
+                    self.any_other_end_tag(name);
+                }
+            }
+            "applet" | "marquee" | "object" => {
+                if self.in_scope(&[name]) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&[name]);
+                    self.clear_formatting_to_marker();
+                }
+            }
+            "br" => {
+                return Some(Token::StartTag {
+                    name: "br",
+                    attributes: Vec::new(),
+                })
+            }
+            _ => self.any_other_end_tag(name),
+        }
+        None
+    }
+
+    fn any_other_end_tag(&mut self, name: &str) {
+        for index in (0..self.open.len()).rev() {
+            let id = self.open[index];
+            if self.tag(id).eq_ignore_ascii_case(name) {
+                self.generate_implied_end_tags(Some(name));
+                self.open.truncate(index);
+                return;
+            }
+            if self.is(id, SPECIAL) {
+                return;
+            }
+        }
+    }
+
+    fn text(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(text) => self.insert_text(text),
+            Token::Comment(comment) => self.insert_comment(comment),
+            Token::CData(text) => self.insert_cdata(text),
+            Token::Doctype(_) => {}
+            Token::StartTag { name, attributes } => {
+                self.insert_element(name, attributes);
+            }
+            Token::EndTag(name) => {
+                let text_element = self.text_element;
+                if text_element.is_some_and(|id| self.tag(id).eq_ignore_ascii_case(name)) {
+                    while let Some(id) = self.open.pop() {
+                        if Some(id) == text_element {
+                            break;
+                        }
+                    }
+                    self.text_element = None;
+                    self.mode = self.original_mode;
+                } else if self.current() != text_element && self.current_is(&[name]) {
+                    self.open.pop();
+                }
+            }
+            Token::Eof => {
+                self.open.pop();
+                self.mode = self.original_mode;
+                return Some(Token::Eof);
+            }
+        }
+        None
+    }
+
+    fn in_table(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(_) if self.current_is(&["table", "tbody", "tfoot", "thead", "tr"]) => {
+                self.pending_table_text.clear();
+                self.original_mode = self.mode;
+                self.mode = Mode::InTableText;
+                Some(token)
+            }
+            Token::Comment(comment) => {
+                self.insert_comment(comment);
+                None
+            }
+            Token::Doctype(_) => None,
+            Token::StartTag { name, attributes } => {
+                match name.to_ascii_lowercase().as_str() {
+                    "caption" => {
+                        self.clear_stack_back_to(&["table", "template", "html"]);
+                        self.formatting.push(Formatting::Marker);
+                        self.insert_element(name, attributes);
+                        self.mode = Mode::InCaption;
+                    }
+                    "colgroup" => {
+                        self.clear_stack_back_to(&["table", "template", "html"]);
+                        self.insert_element(name, attributes);
+                        self.mode = Mode::InColumnGroup;
+                    }
+                    "col" => {
+                        self.clear_stack_back_to(&["table", "template", "html"]);
+                        self.insert_element("colgroup", Vec::new());
+                        self.mode = Mode::InColumnGroup;
+                        return Some(Token::StartTag { name, attributes });
+                    }
+                    "tbody" | "tfoot" | "thead" => {
+                        self.clear_stack_back_to(&["table", "template", "html"]);
+                        self.insert_element(name, attributes);
+                        self.mode = Mode::InTableBody;
+                    }
+                    "td" | "th" | "tr" => {
+                        self.clear_stack_back_to(&["table", "template", "html"]);
+                        self.insert_element("tbody", Vec::new());
+                        self.mode = Mode::InTableBody;
+                        return Some(Token::StartTag { name, attributes });
+                    }
+                    "table" => {
+                        if self.in_table_scope(&["table"]) {
+                            self.pop_until(&["table"]);
+                            self.reset_mode();
+                            return Some(Token::StartTag { name, attributes });
+                        }
+                    }
+                    "style" | "script" | "template" => {
+                        return self.in_head(Token::StartTag { name, attributes })
+                    }
+                    "input"
+                        if attributes.iter().any(|attr| {
+                            attr.name.eq_ignore_ascii_case("type")
+                                && attr
+                                    .value()
+                                    .is_some_and(|value| value.eq_ignore_ascii_case("hidden"))
+                        }) =>
+                    {
+                        self.insert_void(name, attributes)
+                    }
+                    "form" => {
+                        if self.form.is_none() {
+                            let form = self.insert_element(name, attributes);
+                            self.form = Some(form);
+                            self.open.pop();
+                        }
+                    }
+                    _ => return self.in_table_anything_else(Token::StartTag { name, attributes }),
+                }
+                None
+            }
+            Token::EndTag(name) => {
+                match name.to_ascii_lowercase().as_str() {
+                    "table" => {
+                        if self.in_table_scope(&["table"]) {
+                            self.pop_until(&["table"]);
+                            self.reset_mode();
+                        }
+                    }
+                    "body" | "caption" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot"
+                    | "th" | "thead" | "tr" => {}
+                    "template" => return self.in_head(Token::EndTag(name)),
+                    _ => return self.in_table_anything_else(Token::EndTag(name)),
+                }
+                None
+            }
+            Token::Eof => self.in_body(Token::Eof),
+            token => self.in_table_anything_else(token),
+        }
+    }
+
+    // Content that isn't allowed in the table is moved out in front of it
+    fn in_table_anything_else(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        self.foster_parenting = true;
+        let reprocess = self.in_body(token);
+        self.foster_parenting = false;
+        reprocess
+    }
+
+    fn in_table_text(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        if let Token::Text(text) = token {
+            self.pending_table_text.push(text);
+            return None;
+        }
+
+        let pending = core::mem::take(&mut self.pending_table_text);
+        let whitespace = pending.iter().all(|text| text.chars().all(is_whitespace));
+        self.mode = self.original_mode;
+        for text in pending {
+            if whitespace {
+                self.insert_text(text);
+            } else {
+                self.in_table_anything_else(Token::Text(text));
+            }
+        }
+        Some(token)
+    }
+
+    fn in_caption(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        // A table starting in a caption ends it, and so its own table, so
+        // the two come out as siblings
+        let ends_caption = match &token {
+            Token::EndTag(name) => is_one_of(name, &["caption", "table"]),
+            Token::StartTag { name, .. } => is_one_of(
+                name,
+                &[
+                    "caption", "col", "colgroup", "table", "tbody", "td", "tfoot", "th", "thead",
+                    "tr",
+                ],
+            ),
+            _ => false,
+        };
+        if ends_caption {
+            if !self.in_table_scope(&["caption"]) {
+                return None;
+            }
+            self.generate_implied_end_tags(None);
+            self.pop_until(&["caption"]);
+            self.clear_formatting_to_marker();
+            self.mode = Mode::InTable;
+            return match token {
+                Token::EndTag(name) if name.eq_ignore_ascii_case("caption") => None,
+                token => Some(token),
+            };
+        }
+        match token {
+            Token::EndTag(name)
+                if is_one_of(
+                    name,
+                    &[
+                        "body", "col", "colgroup", "html", "tbody", "td", "tfoot", "th", "thead",
+                        "tr",
+                    ],
+                ) =>
+            {
+                None
+            }
+            token => self.in_body(token),
+        }
+    }
+
+    fn in_column_group(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(text) => {
+                let (whitespace, rest) = split_whitespace(text);
+                if let Some(whitespace) = whitespace {
+                    self.insert_text(whitespace);
+                }
+                rest.and_then(|rest| self.leave_column_group(Token::Text(rest)))
+            }
+            Token::Comment(comment) => {
+                self.insert_comment(comment);
+                None
+            }
+            Token::Doctype(_) => None,
+            Token::StartTag { name, .. } if name.eq_ignore_ascii_case("html") => {
+                self.in_body(token)
+            }
+            Token::StartTag { name, attributes } if name.eq_ignore_ascii_case("col") => {
+                self.insert_void(name, attributes);
+                None
+            }
+            Token::StartTag { name, .. } | Token::EndTag(name)
+                if name.eq_ignore_ascii_case("template") =>
+            {
+                self.in_head(token)
+            }
+            Token::EndTag(name) if name.eq_ignore_ascii_case("colgroup") => {
+                if self.current_is(&["colgroup"]) {
+                    self.open.pop();
+                    self.mode = Mode::InTable;
+                }
+                None
+            }
+            Token::EndTag(name) if name.eq_ignore_ascii_case("col") => None,
+            Token::Eof => self.in_body(Token::Eof),
+            token => self.leave_column_group(token),
+        }
+    }
+
+    fn leave_column_group(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        if !self.current_is(&["colgroup"]) {
+            return None;
+        }
+        self.open.pop();
+        self.mode = Mode::InTable;
+        Some(token)
+    }
+
+    fn in_table_body(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        const CONTEXT: &[&str] = &["tbody", "tfoot", "thead", "template", "html"];
+        match token {
+            Token::StartTag { name, attributes } if name.eq_ignore_ascii_case("tr") => {
+                self.clear_stack_back_to(CONTEXT);
+                self.insert_element(name, attributes);
+                self.mode = Mode::InRow;
+                None
+            }
+            Token::StartTag { name, attributes } if is_one_of(name, &["th", "td"]) => {
+                self.clear_stack_back_to(CONTEXT);
+                self.insert_element("tr", Vec::new());
+                self.mode = Mode::InRow;
+                Some(Token::StartTag { name, attributes })
+            }
+            Token::EndTag(name) if is_one_of(name, TABLE_SECTIONS) => {
+                if self.in_table_scope(&[name]) {
+                    self.clear_stack_back_to(CONTEXT);
+                    self.open.pop();
+                    self.mode = Mode::InTable;
+                }
+                None
+            }
+            Token::StartTag { name, .. }
+                if is_one_of(
+                    name,
+                    &["caption", "col", "colgroup", "tbody", "tfoot", "thead"],
+                ) =>
+            {
+                self.leave_table_body(token)
+            }
+            Token::EndTag(name) if name.eq_ignore_ascii_case("table") => {
+                self.leave_table_body(token)
+            }
+            Token::EndTag(name)
+                if is_one_of(
+                    name,
+                    &[
+                        "body", "caption", "col", "colgroup", "html", "td", "th", "tr",
+                    ],
+                ) =>
+            {
+                None
+            }
+            token => self.in_table(token),
+        }
+    }
+
+    fn leave_table_body(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        if !self.in_table_scope(TABLE_SECTIONS) {
+            return None;
+        }
+        self.clear_stack_back_to(&["tbody", "tfoot", "thead", "template", "html"]);
+        self.open.pop();
+        self.mode = Mode::InTable;
+        Some(token)
+    }
+
+    fn in_row(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        const CONTEXT: &[&str] = &["tr", "template", "html"];
+        match token {
+            Token::StartTag { name, attributes } if is_one_of(name, &["th", "td"]) => {
+                self.clear_stack_back_to(CONTEXT);
+                self.insert_element(name, attributes);
+                self.mode = Mode::InCell;
+                self.formatting.push(Formatting::Marker);
+                None
+            }
+            Token::EndTag(name) if name.eq_ignore_ascii_case("tr") => {
+                if self.in_table_scope(&["tr"]) {
+                    self.clear_stack_back_to(CONTEXT);
+                    self.open.pop();
+                    self.mode = Mode::InTableBody;
+                }
+                None
+            }
+            Token::StartTag { name, .. }
+                if is_one_of(
+                    name,
+                    &[
+                        "caption", "col", "colgroup", "tbody", "tfoot", "thead", "tr",
+                    ],
+                ) =>
+            {
+                self.leave_row(token)
+            }
+            Token::EndTag(name) if name.eq_ignore_ascii_case("table") => self.leave_row(token),
+            Token::EndTag(name) if is_one_of(name, TABLE_SECTIONS) => {
+                if self.in_table_scope(&[name]) {
+                    self.leave_row(token)
+                } else {
+                    None
+                }
+            }
+            Token::EndTag(name)
+                if is_one_of(
+                    name,
+                    &["body", "caption", "col", "colgroup", "html", "td", "th"],
+                ) =>
+            {
+                None
+            }
+            token => self.in_table(token),
+        }
+    }
+
+    fn leave_row(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        if !self.in_table_scope(&["tr"]) {
+            return None;
+        }
+        self.clear_stack_back_to(&["tr", "template", "html"]);
+        self.open.pop();
+        self.mode = Mode::InTableBody;
+        Some(token)
+    }
+
+    fn in_cell(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::EndTag(name) if is_one_of(name, &["td", "th"]) => {
+                if self.in_table_scope(&[name]) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&[name]);
+                    self.clear_formatting_to_marker();
+                    self.mode = Mode::InRow;
+                }
+                None
+            }
+            Token::StartTag { name, .. }
+                if is_one_of(
+                    name,
+                    &[
+                        "caption", "col", "colgroup", "tbody", "td", "tfoot", "th", "thead", "tr",
+                    ],
+                ) =>
+            {
+                if self.in_table_scope(&["td", "th"]) {
+                    self.close_cell();
+                    Some(token)
+                } else {
+                    None
+                }
+            }
+            Token::EndTag(name)
+                if is_one_of(name, &["body", "caption", "col", "colgroup", "html"]) =>
+            {
+                None
+            }
+            Token::EndTag(name) if is_one_of(name, &["table", "tbody", "tfoot", "thead", "tr"]) => {
+                if self.in_table_scope(&[name]) {
+                    self.close_cell();
+                    Some(token)
+                } else {
+                    None
+                }
+            }
+            token => self.in_body(token),
+        }
+    }
+
+    fn close_cell(&mut self) {
+        self.generate_implied_end_tags(None);
+        self.pop_until(&["td", "th"]);
+        self.clear_formatting_to_marker();
+        self.mode = Mode::InRow;
+    }
+
+    fn in_select(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(text) => self.insert_text(text),
+            Token::Comment(comment) => self.insert_comment(comment),
+            Token::Doctype(_) => {}
+            Token::StartTag { name, attributes } => match name.to_ascii_lowercase().as_str() {
+                "html" => return self.in_body(Token::StartTag { name, attributes }),
+                "option" => {
+                    if self.current_is(&["option"]) {
+                        self.open.pop();
+                    }
+                    self.insert_element(name, attributes);
+                }
+                "optgroup" => {
+                    if self.current_is(&["option"]) {
+                        self.open.pop();
+                    }
+                    if self.current_is(&["optgroup"]) {
+                        self.open.pop();
+                    }
+                    self.insert_element(name, attributes);
+                }
+                "hr" => {
+                    if self.current_is(&["option"]) {
+                        self.open.pop();
+                    }
+                    if self.current_is(&["optgroup"]) {
+                        self.open.pop();
+                    }
+                    self.insert_void(name, attributes);
+                }
+                "select" if self.in_select_scope(&["select"]) => {
+                    self.pop_until(&["select"]);
+                    self.reset_mode();
+                }
+                "input" | "keygen" | "textarea" if self.in_select_scope(&["select"]) => {
+                    self.pop_until(&["select"]);
+                    self.reset_mode();
+                    return Some(Token::StartTag { name, attributes });
+                }
+                "script" | "template" => return self.in_head(Token::StartTag { name, attributes }),
+                _ => {}
+            },
+            Token::EndTag(name) => match name.to_ascii_lowercase().as_str() {
+                "optgroup" => {
+                    let len = self.open.len();
+                    if self.current_is(&["option"])
+                        && len >= 2
+                        && self.is(self.open[len - 2], &["optgroup"])
+                    {
+                        self.open.pop();
+                    }
+                    if self.current_is(&["optgroup"]) {
+                        self.open.pop();
+                    }
+                }
+                "option" if self.current_is(&["option"]) => {
+                    self.open.pop();
+                }
+                "select" if self.in_select_scope(&["select"]) => {
+                    self.pop_until(&["select"]);
+                    self.reset_mode();
+                }
+                "template" => return self.in_head(Token::EndTag(name)),
+                _ => {}
+            },
+            Token::CData(_) => {}
+            Token::Eof => return self.in_body(Token::Eof),
+        }
+        None
+    }
+
+    fn in_select_in_table(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        const TABLE_TAGS: &[&str] = &[
+            "caption", "table", "tbody", "tfoot", "thead", "tr", "td", "th",
+        ];
+        match &token {
+            Token::StartTag { name, .. } if is_one_of(name, TABLE_TAGS) => {
+                self.pop_until(&["select"]);
+                self.reset_mode();
+                Some(token)
+            }
+            Token::EndTag(name) if is_one_of(name, TABLE_TAGS) => {
+                if !self.in_table_scope(&[name]) {
+                    return None;
+                }
+                self.pop_until(&["select"]);
+                self.reset_mode();
+                Some(token)
+            }
+            _ => self.in_select(token),
+        }
+    }
+
+    fn after_body(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(text) => match split_whitespace(text) {
+                (whitespace, None) => {
+                    whitespace.map(|whitespace| self.in_body(Token::Text(whitespace)));
+                    None
+                }
+                (whitespace, Some(rest)) => {
+                    if let Some(whitespace) = whitespace {
+                        self.in_body(Token::Text(whitespace));
+                    }
+                    self.mode = Mode::InBody;
+                    Some(Token::Text(rest))
+                }
+            },
+            Token::Comment(comment) => {
+                // Comments after `</body>` belong to the html element
+                let parent = self.open.first().copied().unwrap_or(self.document.root());
+                self.document
+                    .append(parent, NodeData::Comment(comment.into()));
+                None
+            }
+            Token::Doctype(_) | Token::Eof => None,
+            Token::StartTag { name, .. } if name.eq_ignore_ascii_case("html") => {
+                self.in_body(token)
+            }
+            Token::EndTag(name) if name.eq_ignore_ascii_case("html") => {
+                self.mode = Mode::AfterAfterBody;
+                None
+            }
+            token => {
+                self.mode = Mode::InBody;
+                Some(token)
+            }
+        }
+    }
+
+    fn in_frameset(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(text) => {
+                if let (Some(whitespace), _) = split_whitespace(text) {
+                    self.insert_text(whitespace);
+                }
+            }
+            Token::Comment(comment) => self.insert_comment(comment),
+            Token::StartTag { name, attributes } => match name.to_ascii_lowercase().as_str() {
+                "html" => return self.in_body(Token::StartTag { name, attributes }),
+                "frameset" => {
+                    self.insert_element(name, attributes);
+                }
+                "frame" => self.insert_void(name, attributes),
+                "noframes" => return self.in_head(Token::StartTag { name, attributes }),
+                _ => {}
+            },
+            Token::EndTag(name) if name.eq_ignore_ascii_case("frameset") => {
+                if self.open.len() > 1 {
+                    self.open.pop();
+                }
+                if !self.current_is(&["frameset"]) {
+                    self.mode = Mode::AfterFrameset;
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn after_frameset(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Text(text) => {
+                if let (Some(whitespace), _) = split_whitespace(text) {
+                    self.insert_text(whitespace);
+                }
+            }
+            Token::Comment(comment) => self.insert_comment(comment),
+            Token::StartTag { name, .. } if name.eq_ignore_ascii_case("html") => {
+                return self.in_body(token)
+            }
+            Token::StartTag { name, .. } if name.eq_ignore_ascii_case("noframes") => {
+                return self.in_head(token)
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn after_after_body(&mut self, token: Token<'a>) -> Option<Token<'a>> {
+        match token {
+            Token::Comment(comment) => {
+                let root = self.document.root();
+                self.document
+                    .append(root, NodeData::Comment(comment.into()));
+                None
+            }
+            Token::Doctype(_) | Token::Eof => None,
+            Token::Text(text) => {
+                let (whitespace, rest) = split_whitespace(text);
+                if let Some(whitespace) = whitespace {
+                    self.in_body(Token::Text(whitespace));
+                }
+                rest.map(|rest| {
+                    self.mode = Mode::InBody;
+                    Token::Text(rest)
+                })
+            }
+            Token::StartTag { name, .. } if name.eq_ignore_ascii_case("html") => {
+                self.in_body(token)
+            }
+            token => {
+                self.mode = Mode::InBody;
+                Some(token)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        document_to_string, parse_document_with, parse_fragment, HtmlError, Limit, Namespace,
+        ParseMode, ParseOptions,
+    };
+
+    fn parse(html: &str) -> String {
+        let options = ParseOptions {
+            mode: ParseMode::Spec,
+            ..Default::default()
+        };
+        document_to_string(parse_document_with(html, &options).unwrap())
+    }
+
+    #[test]
+    fn implied_structure() {
+        assert_eq!(
+            parse("<title>T</title><p>x"),
+            "<html><head><title>T</title></head><body><p>x</p></body></html>"
+        );
+        assert_eq!(
+            parse("<!DOCTYPE html>\n<html>\n<head><title>x</title></head>\n<body>\n<p>a</p>\n</body>\n</html>\n"),
+            "<!DOCTYPE html><html><head><title>x</title></head>\n<body>\n<p>a</p>\n\n\n</body></html>"
+        );
+        assert_eq!(
+            parse("</div><p>x</span>"),
+            "<html><head></head><body><p>x</p></body></html>"
+        );
+    }
+
+    #[test]
+    fn adoption_agency() {
+        assert_eq!(
+            parse("<b>1<p>2</b>3</p>"),
+            "<html><head></head><body><b>1</b><p><b>2</b>3</p></body></html>"
+        );
+        assert_eq!(
+            parse("<a href=x>1<p>2</a>3</p>"),
+            "<html><head></head><body><a href=\"x\">1</a><p><a href=\"x\">2</a>3</p></body></html>"
+        );
+        assert_eq!(
+            parse("<p><b>x<p>y"),
+            "<html><head></head><body><p><b>x</b></p><p><b>y</b></p></body></html>"
+        );
+        assert_eq!(
+            parse("<a>1<a>2"),
+            "<html><head></head><body><a>1</a><a>2</a></body></html>"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        assert_eq!(
+            parse("<table><tr><td>a<td>b</table>"),
+            "<html><head></head><body><table><tbody><tr><td>a</td><td>b</td></tr></tbody></table></body></html>"
+        );
+        assert_eq!(
+            parse("<table><caption>c<tr><td><b>x</table>y"),
+            "<html><head></head><body><table><caption>c</caption><tbody><tr><td><b>x</b></td></tr></tbody></table>y</body></html>"
+        );
+        assert_eq!(
+            parse("<table><caption>c<table><tr><td>x</table>"),
+            "<html><head></head><body><table><caption>c</caption></table><table><tbody><tr><td>x</td></tr></tbody></table></body></html>"
+        );
+    }
+
+    #[test]
+    fn plaintext() {
+        assert_eq!(
+            parse("<p>a<plaintext>b</plaintext><i>c&amp;</i>"),
+            "<html><head></head><body><p>a</p><plaintext>b<\\/plaintext><i>c&amp;</i></plaintext></body></html>"
+        );
+    }
+
+    #[test]
+    fn foreign_content() {
+        assert_eq!(
+            parse("<p><svg viewbox=\"0 0 1 1\"><clippath/><title>t</title><b>x</b></svg>"),
+            "<html><head></head><body><p><svg viewBox=\"0 0 1 1\"><clipPath></clipPath><title>t</title></svg><b>x</b></p></body></html>"
+        );
+        let options = ParseOptions {
+            mode: ParseMode::Spec,
+            ..Default::default()
+        };
+        let document = parse_document_with("<math><mi><i>x</i></mi></math>", &options).unwrap();
+        let body = document.children()[0].as_element().unwrap().children()[1]
+            .as_element()
+            .unwrap();
+        let math = body.children()[0].as_element().unwrap();
+        let mi = math.children()[0].as_element().unwrap();
+        assert_eq!(math.namespace(), Namespace::MathMl);
+        assert_eq!(mi.namespace(), Namespace::MathMl);
+        assert_eq!(
+            mi.children()[0].as_element().unwrap().namespace(),
+            Namespace::Html
+        );
+    }
+
+    #[test]
+    fn template_contents() {
+        assert_eq!(
+            parse("<template id=row><tr><td>x</td></tr></template><p>y"),
+            "<html><head><template id=\"row\"><tr><td>x</td></tr></template></head><body><p>y</p></body></html>"
+        );
+        let options = ParseOptions {
+            mode: ParseMode::Spec,
+            ..Default::default()
+        };
+        let document =
+            parse_document_with("<body><template><b>1<p>2</template>", &options).unwrap();
+        let body = document.children()[0].as_element().unwrap().children()[1]
+            .as_element()
+            .unwrap();
+        let template = body.children()[0].as_element().unwrap();
+        assert!(template.children().is_empty());
+        assert_eq!(template.template_content().len(), 1);
+        assert_eq!(
+            parse("<body><template><b>1<p>2</template>3"),
+            "<html><head></head><body><template><b>1<p>2</p></b></template>3</body></html>"
+        );
+    }
+
+    #[test]
+    fn foster_parenting() {
+        assert_eq!(
+            parse("<table>a<b>x</b><tr><td>y</td>z</tr></table>"),
+            "<html><head></head><body>a<b>x</b>z<table><tbody><tr><td>y</td></tr></tbody></table></body></html>"
+        );
+        assert_eq!(
+            parse("<div><table><tr><p>one</table></div>"),
+            "<html><head></head><body><div><p>one</p><table><tbody><tr></tr></tbody></table></div></body></html>"
+        );
+        // Whitespace stays where it is
+        assert_eq!(
+            parse("<table> <tr> <td>x</td> </tr> </table>"),
+            "<html><head></head><body><table> <tbody><tr> <td>x</td> </tr> </tbody></table></body></html>"
+        );
+    }
+
+    #[test]
+    fn depth_limit() {
+        let options = ParseOptions {
+            mode: ParseMode::Spec,
+            max_depth: Some(64),
+            ..Default::default()
+        };
+        let deep = "<div>".repeat(10_000);
+        assert!(matches!(
+            parse_document_with(&deep, &options),
+            Err(HtmlError::LimitExceeded(Limit::Depth, _))
+        ));
+        assert!(parse_document_with("<div><p>x</div>", &options).is_ok());
+    }
+
+    #[test]
+    fn spans() {
+        let options = ParseOptions {
+            mode: ParseMode::Spec,
+            ..Default::default()
+        };
+        let html = "<p>one<div>two</div>";
+        let document = crate::Document::from(parse_document_with(html, &options).unwrap());
+        let source = |tag: &str| {
+            let id = document
+                .descendants(document.root())
+                .find(|id| document.get(*id).tag() == Some(tag))
+                .unwrap();
+            document.get(id).span().map(|span| &html[span.range()])
+        };
+        assert_eq!(source("html"), None);
+        assert_eq!(source("p"), Some("<p>one"));
+        assert_eq!(source("div"), Some("<div>two</div>"));
+    }
+
+    #[test]
+    fn fragments() {
+        let fragment = |html, context| document_to_string(parse_fragment(html, context).unwrap());
+        assert_eq!(fragment("<td>x</td>", "tr"), "<td>x</td>");
+        assert_eq!(fragment("<td>x</td>", "div"), "x");
+        assert_eq!(
+            fragment("<tr><td>x", "table"),
+            "<tbody><tr><td>x</td></tr></tbody>"
+        );
+        assert_eq!(fragment("<p>a<p>b", "body"), "<p>a</p><p>b</p>");
+        assert_eq!(
+            fragment("<title>t</title>", "html"),
+            "<head><title>t</title></head><body></body>"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use core::cell::OnceCell;
+
+use crate::prelude::*;
+use crate::{
+    arena::Arena,
+    find_attribute,
+    parser::{decode_text, normalize_name, HtmlAst, Tokenizer},
+    HtmlAttribute, HtmlError, ParseOptions, Span,
+};
+
+/// A start tag from [`start_tags`]. Its attributes are only parsed the
+/// first time they're asked for.
+#[derive(Debug)]
+pub struct StartTag<'a> {
+    name: &'a str,
+    source: &'a str,
+    span: Span,
+    self_closing: bool,
+    options: ParseOptions,
+    attributes: OnceCell<Vec<HtmlAttribute<'a>>>,
+}
+
+impl<'a> StartTag<'a> {
+    /// The tag name as written.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// The whole tag, from `<` through `>`.
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Whether the tag ended with `/>`.
+    pub fn is_self_closing(&self) -> bool {
+        self.self_closing
+    }
+
+    /// The attributes, with names and values as the tree would have them.
+    pub fn attributes(&self) -> &[HtmlAttribute<'a>] {
+        self.attributes.get_or_init(|| {
+            // The tag was read once already, so reading it again can't fail
+            let mut tokenizer = Tokenizer::new(self.source, &self.options);
+            core::iter::from_fn(|| tokenizer.next_token())
+                .filter_map(|token| match token {
+                    Ok((HtmlAst::Attribute(name, value), _)) => Some(HtmlAttribute {
+                        name: normalize_name(name, &self.options, Arena::default()),
+                        value: value
+                            .map(|value| decode_text(value, &self.options, Arena::default())),
+                    }),
+                    _ => None,
+                })
+                .collect()
+        })
+    }
+
+    pub fn get_attr(&self, name: &str) -> Option<&str> {
+        find_attribute(self.attributes(), name).map(|attr| attr.value().unwrap_or_default())
+    }
+}
+
+/// The start tags of `data`, read without parsing their attributes, which
+/// makes scans that mostly look at tag names faster than with [`Tokens`]:
+///
+/// ```
+/// # use html_parser::start_tags;
+/// let html = r#"<p class="intro">Hi <a href="/a">a</a> <a href="/b">b</a></p>"#;
+/// let links: Vec<_> = start_tags(html)
+///     .map(Result::unwrap)
+///     .filter(|tag| tag.name() == "a")
+///     .filter_map(|tag| tag.get_attr("href").map(str::to_string))
+///     .collect();
+/// assert_eq!(links, ["/a", "/b"]);
+/// ```
+///
+/// Iteration ends after the first error.
+///
+/// [`Tokens`]: crate::Tokens
+pub fn start_tags(data: &str) -> StartTags<'_> {
+    start_tags_with(data, &ParseOptions::default())
+}
+
+pub fn start_tags_with<'a>(data: &'a str, options: &ParseOptions) -> StartTags<'a> {
+    let mut tokenizer = Tokenizer::new(data, options);
+    tokenizer.lazy_attributes = true;
+    StartTags {
+        source: data,
+        tokenizer,
+        options: options.clone(),
+        next: None,
+        error: None,
+    }
+}
+
+/// Iterator returned by [`start_tags`].
+pub struct StartTags<'a> {
+    source: &'a str,
+    tokenizer: Tokenizer<'a>,
+    options: ParseOptions,
+    // A tag waiting to see whether `SelfClosing` follows it
+    next: Option<StartTag<'a>>,
+    // An error to hand out once the tag before it has been
+    error: Option<HtmlError>,
+}
+
+impl<'a> Iterator for StartTags<'a> {
+    type Item = Result<StartTag<'a>, HtmlError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        loop {
+            let token = match self.tokenizer.next_token() {
+                Some(Ok(token)) => token,
+                Some(Err(error)) => match self.next.take() {
+                    Some(tag) => {
+                        self.error = Some(error);
+                        return Some(Ok(tag));
+                    }
+                    None => return Some(Err(error)),
+                },
+                None => return self.next.take().map(Ok),
+            };
+            match token {
+                (HtmlAst::SelfClosing, _) => {
+                    if let Some(tag) = &mut self.next {
+                        tag.self_closing = true;
+                    }
+                }
+                (HtmlAst::StartTag(name), span) => {
+                    let tag = StartTag {
+                        name,
+                        source: &self.source[span.clone()],
+                        span: span.into(),
+                        self_closing: false,
+                        options: self.options.clone(),
+                        attributes: OnceCell::new(),
+                    };
+                    if let Some(previous) = self.next.replace(tag) {
+                        return Some(Ok(previous));
+                    }
+                }
+                _ => {
+                    if let Some(tag) = self.next.take() {
+                        return Some(Ok(tag));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lazy_attributes() {
+        let html = "<DIV Class=\"a&amp;b\" id=x class=y><br/><!-- <i> --><img src='a>b' alt></div>";
+        let tags: Vec<_> = start_tags(html).map(Result::unwrap).collect();
+        let names: Vec<_> = tags.iter().map(StartTag::name).collect();
+        assert_eq!(names, ["DIV", "br", "img"]);
+        assert!(tags[1].is_self_closing() && !tags[2].is_self_closing());
+        assert_eq!(tags[2].source(), "<img src='a>b' alt>");
+
+        assert!(tags[0].attributes.get().is_none());
+        assert_eq!(tags[0].get_attr("class"), Some("a&b"));
+        assert_eq!(tags[0].attributes().len(), 2);
+        assert_eq!(tags[0].attributes()[1], HtmlAttribute::new("id", Some("x")));
+        assert_eq!(tags[2].get_attr("alt"), Some(""));
+
+        let options = ParseOptions {
+            max_attributes_per_tag: Some(1),
+            ..Default::default()
+        };
+        let mut tags = start_tags_with(html, &options);
+        assert!(matches!(
+            tags.next(),
+            Some(Err(HtmlError::LimitExceeded(crate::Limit::Attributes, _)))
+        ));
+        assert!(tags.next().is_none());
+    }
+}
@@ -0,0 +1,428 @@
+use alloc::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use crate::prelude::*;
+use crate::{
+    decode_entities,
+    parser::{is_raw_text_element, HtmlAst, Tokenizer, TokenizerState},
+    HtmlAttributeOwned, HtmlDoctypeOwned, HtmlError, ParseOptions,
+};
+
+#[cfg(feature = "std")]
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// An owned parse event from [`StreamParser`]. Text and attribute values
+/// have their character references decoded, except inside script and style.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HtmlEvent {
+    StartTag {
+        name: String,
+        attributes: Vec<HtmlAttributeOwned>,
+    },
+    EndTag(String),
+    Text(String),
+    CData(String),
+    Comment(String),
+    Doctype(HtmlDoctypeOwned),
+}
+
+/// Parses from an [`io::Read`] a chunk at a time. Only the construct
+/// currently being read is buffered, so memory stays bounded by the largest
+/// tag or text run rather than the document.
+#[cfg(feature = "std")]
+pub struct StreamParser<R> {
+    reader: R,
+    pub(crate) decoder: ChunkTokenizer,
+    eof: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> StreamParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            decoder: ChunkTokenizer::default(),
+            eof: false,
+        }
+    }
+
+    fn fill(&mut self) -> Result<(), HtmlError> {
+        let mut chunk = [0; CHUNK_SIZE];
+        let read = loop {
+            match self.reader.read(&mut chunk) {
+                Ok(read) => break read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(HtmlError::Io(error)),
+            }
+        };
+        if read == 0 {
+            self.eof = true;
+            self.decoder.end()
+        } else {
+            self.decoder.push(&chunk[..read])
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Iterator for StreamParser<R> {
+    type Item = Result<HtmlEvent, HtmlError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.decoder.pop() {
+                return Some(Ok(event));
+            }
+            if self.eof {
+                return None;
+            }
+            if let Err(error) = self.fill() {
+                self.eof = true;
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+/// The async counterpart of [`StreamParser`], for sockets and response
+/// bodies that implement [`tokio::io::AsyncRead`].
+#[cfg(feature = "async")]
+pub struct AsyncStreamParser<R> {
+    reader: R,
+    decoder: ChunkTokenizer,
+    eof: bool,
+}
+
+#[cfg(feature = "async")]
+impl<R: tokio::io::AsyncRead + Unpin> AsyncStreamParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            decoder: ChunkTokenizer::default(),
+            eof: false,
+        }
+    }
+
+    /// Waits for the next event, returning `Ok(None)` at the end of input.
+    pub async fn next_event(&mut self) -> Result<Option<HtmlEvent>, HtmlError> {
+        use tokio::io::AsyncReadExt;
+
+        let mut chunk = [0; CHUNK_SIZE];
+        loop {
+            if let Some(event) = self.decoder.pop() {
+                return Ok(Some(event));
+            }
+            if self.eof {
+                return Ok(None);
+            }
+            let read = match self.reader.read(&mut chunk).await {
+                Ok(read) => read,
+                Err(error) => {
+                    self.eof = true;
+                    return Err(HtmlError::Io(error));
+                }
+            };
+            let result = if read == 0 {
+                self.eof = true;
+                self.decoder.end()
+            } else {
+                self.decoder.push(&chunk[..read])
+            };
+            if let Err(error) = result {
+                self.eof = true;
+                return Err(error);
+            }
+        }
+    }
+}
+
+/// Push-style tokenizer for input that arrives in pieces. Chunks may split
+/// tags, character references or UTF-8 sequences anywhere; whatever might
+/// still be continued by the next chunk is held back until it is complete.
+#[derive(Debug, Default)]
+pub struct ChunkTokenizer {
+    options: ParseOptions,
+    buffer: String,
+    // Bytes of a UTF-8 sequence split across pieces
+    partial: Vec<u8>,
+    events: VecDeque<HtmlEvent>,
+    raw_text: bool,
+    // Where the tokenizer stopped, for the next one
+    state: TokenizerState,
+    // How much of the text at the start of `buffer` was searched already
+    text_scanned: usize,
+    // Bytes of input already tokenized and dropped from `buffer`
+    offset: usize,
+    // Transcodes legacy encodings; input is taken as UTF-8 without one
+    #[cfg(feature = "encoding")]
+    pub(crate) transcoder: Option<encoding_rs::Decoder>,
+}
+
+impl ChunkTokenizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the next chunk, returning the events it completed.
+    pub fn feed(&mut self, chunk: impl AsRef<[u8]>) -> Result<Vec<HtmlEvent>, HtmlError> {
+        self.push(chunk.as_ref())?;
+        Ok(self.events.drain(..).collect())
+    }
+
+    /// Ends the input, returning the remaining events. Fails if the input
+    /// stops inside a construct that needs closing, like a quoted value.
+    pub fn finish(&mut self) -> Result<Vec<HtmlEvent>, HtmlError> {
+        self.end()?;
+        Ok(self.events.drain(..).collect())
+    }
+
+    fn push(&mut self, bytes: &[u8]) -> Result<(), HtmlError> {
+        #[cfg(feature = "encoding")]
+        if let Some(transcoder) = &mut self.transcoder {
+            crate::encoding::transcode(transcoder, &mut self.buffer, bytes, false);
+            return self.tokenize(false);
+        }
+        push_utf8(&mut self.buffer, &mut self.partial, bytes)?;
+        self.tokenize(false)
+    }
+
+    fn end(&mut self) -> Result<(), HtmlError> {
+        #[cfg(feature = "encoding")]
+        if let Some(transcoder) = &mut self.transcoder {
+            crate::encoding::transcode(transcoder, &mut self.buffer, &[], true);
+        }
+        if !self.partial.is_empty() {
+            return Err(HtmlError::DecodeFailed);
+        }
+        self.tokenize(true)
+    }
+
+    fn pop(&mut self) -> Option<HtmlEvent> {
+        self.events.pop_front()
+    }
+
+    fn tokenize(&mut self, eof: bool) -> Result<(), HtmlError> {
+        let mut tokenizer = Tokenizer::new(&self.buffer, &self.options);
+        tokenizer.state = self.state;
+        tokenizer.text_scanned = self.text_scanned;
+        let (tokens, consumed) = tokenizer
+            .read_complete(eof)
+            .map_err(|error| error.shifted(self.offset))?;
+        self.state = tokenizer.state;
+        self.text_scanned = tokenizer.text_scanned;
+        let mut tokens = tokens.into_iter().peekable();
+
+        while let Some(token) = tokens.next() {
+            let event = match token {
+                HtmlAst::StartTag(name) => {
+                    let mut attributes = Vec::new();
+                    while let Some(HtmlAst::Attribute(name, value)) =
+                        tokens.next_if(|token| matches!(token, HtmlAst::Attribute(..)))
+                    {
+                        let value = value.map(|value| decode_entities(value).into_owned());
+                        attributes.push(HtmlAttributeOwned::new(name, value));
+                    }
+                    self.raw_text = is_raw_text_element(name);
+                    HtmlEvent::StartTag {
+                        name: name.to_string(),
+                        attributes,
+                    }
+                }
+                HtmlAst::EndTag(name) => {
+                    self.raw_text = false;
+                    HtmlEvent::EndTag(name.to_string())
+                }
+                HtmlAst::Text(text) if self.raw_text => HtmlEvent::Text(text.to_string()),
+                HtmlAst::Text(text) => HtmlEvent::Text(decode_entities(text).into_owned()),
+                HtmlAst::CData(text) => HtmlEvent::CData(text.to_string()),
+                HtmlAst::Comment(comment) => HtmlEvent::Comment(comment.to_string()),
+                HtmlAst::Doctype(doctype) => HtmlEvent::Doctype(doctype.to_owned()),
+                HtmlAst::Attribute(..) | HtmlAst::SelfClosing => continue,
+            };
+            self.events.push_back(event);
+        }
+
+        self.buffer.drain(..consumed);
+        self.offset += consumed;
+        Ok(())
+    }
+}
+
+/// Appends `bytes` to `buffer`, keeping a UTF-8 sequence cut off at the end
+/// in `partial` until the rest of it arrives.
+pub(crate) fn push_utf8(
+    buffer: &mut String,
+    partial: &mut Vec<u8>,
+    bytes: &[u8],
+) -> Result<(), HtmlError> {
+    partial.extend_from_slice(bytes);
+    let valid = match core::str::from_utf8(partial) {
+        Ok(text) => text.len(),
+        Err(error) if error.error_len().is_none() => error.valid_up_to(),
+        Err(_) => return Err(HtmlError::DecodeFailed),
+    };
+    buffer.push_str(core::str::from_utf8(&partial[..valid]).unwrap());
+    partial.drain(..valid);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Hands out at most `step` bytes per read
+    #[cfg(feature = "std")]
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    #[cfg(feature = "std")]
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.step.min(self.data.len()).min(buf.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn events_across_chunks() {
+        let html = "<!DOCTYPE html><p class=\"a > b\">Caf\u{e9} &amp; cr\u{e8}me<!-- a -- b --></p><script>x &amp;&amp; y</script>";
+        let expected = vec![
+            HtmlEvent::Doctype(HtmlDoctypeOwned {
+                name: Some("html".into()),
+                ..Default::default()
+            }),
+            HtmlEvent::StartTag {
+                name: "p".into(),
+                attributes: vec![HtmlAttributeOwned::new("class", Some("a > b".into()))],
+            },
+            HtmlEvent::Text("Caf\u{e9} & cr\u{e8}me".into()),
+            HtmlEvent::Comment(" a -- b ".into()),
+            HtmlEvent::EndTag("p".into()),
+            HtmlEvent::StartTag {
+                name: "script".into(),
+                attributes: vec![],
+            },
+            HtmlEvent::Text("x &amp;&amp; y".into()),
+            HtmlEvent::EndTag("script".into()),
+        ];
+
+        for step in [1, 2, 3, 7, CHUNK_SIZE] {
+            let reader = Trickle {
+                data: html.as_bytes(),
+                step,
+            };
+            let events: Vec<_> = StreamParser::new(reader).collect::<Result<_, _>>().unwrap();
+            assert_eq!(events, expected, "step {}", step);
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_events() {
+        let (mut client, server) = tokio::io::duplex(4);
+        let writer = tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            for piece in ["<a href=\"/x\">li", "nk</a", "><!-- done -->"] {
+                client.write_all(piece.as_bytes()).await.unwrap();
+            }
+        });
+
+        let mut parser = AsyncStreamParser::new(server);
+        let mut events = Vec::new();
+        while let Some(event) = parser.next_event().await.unwrap() {
+            events.push(event);
+        }
+        writer.await.unwrap();
+        assert_eq!(
+            events,
+            vec![
+                HtmlEvent::StartTag {
+                    name: "a".into(),
+                    attributes: vec![HtmlAttributeOwned::new("href", Some("/x".into()))],
+                },
+                HtmlEvent::Text("link".into()),
+                HtmlEvent::EndTag("a".into()),
+                HtmlEvent::Comment(" done ".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn feed_chunks() {
+        let mut tokenizer = ChunkTokenizer::new();
+        assert_eq!(tokenizer.feed("<p title=\"a").unwrap(), vec![]);
+        assert_eq!(
+            tokenizer.feed(" b\">x &am").unwrap(),
+            vec![HtmlEvent::StartTag {
+                name: "p".into(),
+                attributes: vec![HtmlAttributeOwned::new("title", Some("a b".into()))],
+            }]
+        );
+        // Text may continue in the next chunk, so it waits for the end tag
+        assert_eq!(tokenizer.feed(&b"p; \xc3"[..]).unwrap(), vec![]);
+        assert_eq!(
+            tokenizer.feed(&b"\xa9</p"[..]).unwrap(),
+            vec![HtmlEvent::Text("x & \u{e9}".into())]
+        );
+        assert_eq!(tokenizer.feed(">").unwrap(), vec![]);
+        assert_eq!(
+            tokenizer.finish().unwrap(),
+            vec![HtmlEvent::EndTag("p".into())]
+        );
+
+        // A script's contents stay text across chunks
+        let mut tokenizer = ChunkTokenizer::new();
+        let mut events = Vec::new();
+        for chunk in ["<script>a<b>", "</b> &amp; </scr", "ipt><p>&amp;</p>"] {
+            events.extend(tokenizer.feed(chunk).unwrap());
+        }
+        events.extend(tokenizer.finish().unwrap());
+        assert_eq!(events[1], HtmlEvent::Text("a<b></b> &amp; ".into()));
+        assert_eq!(events[2], HtmlEvent::EndTag("script".into()));
+        assert_eq!(events[4], HtmlEvent::Text("&".into()));
+
+        // Text held back isn't searched again, except where markup may begin
+        let mut tokenizer = ChunkTokenizer::new();
+        let mut events = Vec::new();
+        for chunk in ["a < b", " <", "b>c", "</b", "><textarea>d <", "/textarea>"] {
+            events.extend(tokenizer.feed(chunk).unwrap());
+        }
+        events.extend(tokenizer.finish().unwrap());
+        assert_eq!(events[0], HtmlEvent::Text("a < b ".into()));
+        assert_eq!(events[2], HtmlEvent::Text("c".into()));
+        assert_eq!(events[5], HtmlEvent::Text("d ".into()));
+        assert_eq!(events[6], HtmlEvent::EndTag("textarea".into()));
+
+        let mut tokenizer = ChunkTokenizer::new();
+        tokenizer.feed("<!-- open").unwrap();
+        assert_eq!(
+            tokenizer.finish().unwrap(),
+            vec![HtmlEvent::Comment(" open".into())]
+        );
+    }
+
+    #[test]
+    fn stream_errors() {
+        #[cfg(feature = "std")]
+        {
+            let unterminated =
+                StreamParser::new("<p a=\"x>".as_bytes()).collect::<Result<Vec<_>, _>>();
+            assert!(unterminated.is_err());
+            let invalid = StreamParser::new(&b"<p>\xff</p>"[..]).collect::<Result<Vec<_>, _>>();
+            assert!(matches!(invalid, Err(HtmlError::DecodeFailed)));
+        }
+
+        // Spans count from the start of the whole input, not the chunk
+        let mut tokenizer = ChunkTokenizer::new();
+        tokenizer.feed("<div>text</div>").unwrap();
+        tokenizer.feed("<a href=\"x").unwrap();
+        let error = tokenizer.finish().unwrap_err();
+        assert_eq!(error.span(), Some(crate::Span::new(15, 25)));
+    }
+}
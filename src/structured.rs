@@ -0,0 +1,216 @@
+//! Structured data embedded in pages: microdata items and, with the `json`
+//! feature, JSON-LD blocks.
+
+use crate::prelude::*;
+use crate::{HtmlDocument, HtmlElement, HtmlNode};
+
+/// An element with `itemscope` and the properties found inside it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MicrodataItem {
+    /// The URLs in `itemtype`.
+    pub types: Vec<String>,
+    /// The `itemid`.
+    pub id: Option<String>,
+    /// Property names and values in document order. A name can repeat.
+    pub properties: Vec<(String, MicrodataValue)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MicrodataValue {
+    Text(String),
+    Item(MicrodataItem),
+}
+
+impl MicrodataItem {
+    /// The first value of the property `name`.
+    pub fn property(&self, name: &str) -> Option<&MicrodataValue> {
+        self.properties
+            .iter()
+            .find(|(property, _)| property == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// The top-level microdata items, those that aren't a property of another
+/// item. `itemref` isn't followed.
+pub fn extract_microdata(document: &HtmlDocument<'_>) -> Vec<MicrodataItem> {
+    let mut items = Vec::new();
+    collect_items(document.children(), &mut items);
+    items
+}
+
+fn collect_items(nodes: &[HtmlNode<'_>], items: &mut Vec<MicrodataItem>) {
+    for element in nodes.iter().filter_map(HtmlNode::as_element) {
+        if element.has_attr("itemscope") && !element.has_attr("itemprop") {
+            items.push(to_item(element));
+        }
+        collect_items(element.children(), items);
+    }
+}
+
+fn to_item(element: &HtmlElement<'_>) -> MicrodataItem {
+    let mut item = MicrodataItem {
+        types: element
+            .get_attr("itemtype")
+            .unwrap_or_default()
+            .split_ascii_whitespace()
+            .map(str::to_string)
+            .collect(),
+        id: element.get_attr("itemid").map(str::to_string),
+        properties: Vec::new(),
+    };
+    collect_properties(element.children(), &mut item.properties);
+    item
+}
+
+fn collect_properties(nodes: &[HtmlNode<'_>], properties: &mut Vec<(String, MicrodataValue)>) {
+    for element in nodes.iter().filter_map(HtmlNode::as_element) {
+        let scope = element.has_attr("itemscope");
+        if let Some(names) = element.get_attr("itemprop") {
+            let value = if scope {
+                MicrodataValue::Item(to_item(element))
+            } else {
+                MicrodataValue::Text(property_value(element))
+            };
+            for name in names.split_ascii_whitespace() {
+                properties.push((name.to_string(), value.clone()));
+            }
+        }
+        // A nested item's properties are its own
+        if !scope {
+            collect_properties(element.children(), properties);
+        }
+    }
+}
+
+// The value of a property, which depends on the element it's on
+fn property_value(element: &HtmlElement<'_>) -> String {
+    let attr = match element.tag().to_ascii_lowercase().as_str() {
+        "meta" => "content",
+        "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => "src",
+        "a" | "area" | "link" => "href",
+        "object" => "data",
+        "data" | "meter" => "value",
+        "time" if element.has_attr("datetime") => "datetime",
+        _ => return text_content(element),
+    };
+    element.get_attr(attr).unwrap_or_default().to_string()
+}
+
+fn text_content(element: &HtmlElement<'_>) -> String {
+    fn push(nodes: &[HtmlNode<'_>], text: &mut String) {
+        for node in nodes {
+            match node {
+                HtmlNode::Text(value) => text.push_str(value),
+                HtmlNode::Element(element) => push(element.children(), text),
+                _ => {}
+            }
+        }
+    }
+    let mut text = String::new();
+    push(element.children(), &mut text);
+    text
+}
+
+/// The contents of every `<script type="application/ld+json">`, parsed.
+/// Blocks that aren't valid JSON give an error in their place.
+#[cfg(feature = "json")]
+pub fn extract_json_ld(
+    document: &HtmlDocument<'_>,
+) -> Vec<Result<serde_json::Value, crate::HtmlError>> {
+    fn collect(
+        nodes: &[HtmlNode<'_>],
+        blocks: &mut Vec<Result<serde_json::Value, crate::HtmlError>>,
+    ) {
+        for element in nodes.iter().filter_map(HtmlNode::as_element) {
+            let is_json_ld = element.tag().eq_ignore_ascii_case("script")
+                && element
+                    .get_attr("type")
+                    .is_some_and(|kind| kind.trim().eq_ignore_ascii_case("application/ld+json"));
+            if is_json_ld {
+                // A script's contents are raw text, whatever markup or
+                // character references they seem to hold
+                let json: String = element
+                    .children()
+                    .iter()
+                    .filter_map(|node| match node {
+                        HtmlNode::Text(text) => Some(&**text),
+                        _ => None,
+                    })
+                    .collect();
+                blocks.push(
+                    serde_json::from_str(&json)
+                        .map_err(|error| crate::HtmlError::InvalidJson(error.to_string())),
+                );
+            } else {
+                collect(element.children(), blocks);
+            }
+        }
+    }
+    let mut blocks = Vec::new();
+    collect(document.children(), &mut blocks);
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    #[test]
+    fn microdata_items() {
+        let html = r#"<div itemscope itemtype="https://schema.org/Movie">
+            <h1 itemprop="name">Avatar</h1>
+            <span>Director: <span itemprop="director" itemscope itemtype="https://schema.org/Person">
+                <span itemprop="name">James Cameron</span></span></span>
+            <a itemprop="trailer url" href="/trailer">Trailer</a>
+            <time itemprop="datePublished" datetime="2009-12-18">Dec 2009</time>
+        </div>"#;
+        let document = parse_document(html).unwrap();
+        let items = extract_microdata(&document);
+        assert_eq!(items.len(), 1);
+        let movie = &items[0];
+        assert_eq!(movie.types, ["https://schema.org/Movie"]);
+        let names: Vec<_> = movie
+            .properties
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            ["name", "director", "trailer", "url", "datePublished"]
+        );
+        assert_eq!(
+            movie.property("name"),
+            Some(&MicrodataValue::Text("Avatar".to_string()))
+        );
+        let Some(MicrodataValue::Item(director)) = movie.property("director") else {
+            panic!("director should be an item");
+        };
+        assert_eq!(
+            director.property("name"),
+            Some(&MicrodataValue::Text("James Cameron".to_string()))
+        );
+        assert_eq!(
+            movie.property("datePublished"),
+            Some(&MicrodataValue::Text("2009-12-18".to_string()))
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_ld_blocks() {
+        let html = r#"<head><script type="application/ld+json">{"@type": "Organization", "name": "X"}</script>
+            <script type="application/ld+json">{broken</script><script>var a = 1;</script>
+            <script type="application/ld+json">{"description": "fits <b>all</b> sizes &amp; </p> shapes"}</script></head>"#;
+        let document = parse_document(html).unwrap();
+        let blocks = extract_json_ld(&document);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0].as_ref().unwrap()["name"], "X");
+        assert_eq!(
+            blocks[2].as_ref().unwrap()["description"],
+            "fits <b>all</b> sizes &amp; </p> shapes"
+        );
+        assert!(matches!(blocks[1], Err(crate::HtmlError::InvalidJson(_))));
+    }
+}
@@ -0,0 +1,194 @@
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::prelude::*;
+use crate::{HtmlDocument, HtmlElement, HtmlNode};
+
+// Spans past these are treated as typos, as browsers do
+const MAX_COLSPAN: usize = 1000;
+const MAX_ROWSPAN: usize = 65534;
+
+/// A `<table>` laid out as a grid, with `colspan` and `rowspan` cells
+/// repeated in every position they cover.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub caption: Option<String>,
+    /// Rows of the `thead`.
+    pub head: Vec<Vec<Cell>>,
+    /// Rows of every `tbody`, and rows directly in the table.
+    pub body: Vec<Vec<Cell>>,
+    /// Rows of the `tfoot`.
+    pub foot: Vec<Vec<Cell>>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub text: String,
+    /// Whether the cell is a `th`.
+    pub header: bool,
+    /// Whether this position is covered by a cell starting in an earlier row
+    /// or column.
+    pub spanned: bool,
+}
+
+impl Table {
+    /// Head, body and foot rows, in that order.
+    pub fn rows(&self) -> impl Iterator<Item = &Vec<Cell>> {
+        self.head.iter().chain(&self.body).chain(&self.foot)
+    }
+
+    /// The rows as CSV, quoting fields as RFC 4180 does.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        self.csv(|field| {
+            csv.push_str(field);
+            Ok::<_, core::convert::Infallible>(())
+        });
+        csv
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_csv<W: io::Write>(&self, out: &mut W) -> io::Result<()> {
+        self.csv(|field| out.write_all(field.as_bytes()))
+    }
+
+    // Hands the CSV to `write` a piece at a time
+    fn csv<E>(&self, mut write: impl FnMut(&str) -> Result<(), E>) -> Result<(), E> {
+        for row in self.rows() {
+            for (index, cell) in row.iter().enumerate() {
+                if index > 0 {
+                    write(",")?;
+                }
+                if cell.text.contains([',', '"', '\n', '\r']) {
+                    write("\"")?;
+                    write(&cell.text.replace('"', "\"\""))?;
+                    write("\"")?;
+                } else {
+                    write(&cell.text)?;
+                }
+            }
+            write("\r\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// Every `<table>` in the document, nested ones after the table containing
+/// them.
+pub fn extract_tables(document: &HtmlDocument<'_>) -> Vec<Table> {
+    let mut tables = Vec::new();
+    collect_tables(document.children(), &mut tables);
+    tables
+}
+
+fn collect_tables(nodes: &[HtmlNode<'_>], tables: &mut Vec<Table>) {
+    for element in nodes.iter().filter_map(HtmlNode::as_element) {
+        if is(element, "table") {
+            tables.push(to_table(element));
+        }
+        collect_tables(element.children(), tables);
+    }
+}
+
+fn to_table(table: &HtmlElement<'_>) -> Table {
+    let mut result = Table::default();
+    let mut loose_rows = Vec::new();
+    for child in table.child_elements() {
+        match child.tag().to_ascii_lowercase().as_str() {
+            "caption" => result.caption = Some(child.inner_text()),
+            "thead" => result.head.extend(layout(rows_of(child))),
+            "tfoot" => result.foot.extend(layout(rows_of(child))),
+            "tbody" => {
+                result.body.extend(layout(core::mem::take(&mut loose_rows)));
+                result.body.extend(layout(rows_of(child)));
+            }
+            "tr" => loose_rows.push(child),
+            _ => {}
+        }
+    }
+    result.body.extend(layout(loose_rows));
+    result
+}
+
+fn rows_of<'e, 'a>(section: &'e HtmlElement<'a>) -> Vec<&'e HtmlElement<'a>> {
+    section
+        .child_elements()
+        .filter(|row| is(row, "tr"))
+        .collect()
+}
+
+// Places the cells of one row group on a grid
+fn layout(rows: Vec<&HtmlElement<'_>>) -> Vec<Vec<Cell>> {
+    let mut grid: Vec<Vec<Option<Cell>>> = vec![Vec::new(); rows.len()];
+    for (row, tr) in rows.iter().enumerate() {
+        let mut column = 0;
+        for td in tr
+            .child_elements()
+            .filter(|cell| is(cell, "td") || is(cell, "th"))
+        {
+            while grid[row].get(column).is_some_and(Option::is_some) {
+                column += 1;
+            }
+            let colspan = span(td, "colspan", 1).clamp(1, MAX_COLSPAN);
+            // `rowspan="0"` reaches to the end of the row group
+            let rowspan = match span(td, "rowspan", 1) {
+                0 => rows.len() - row,
+                rowspan => rowspan.min(MAX_ROWSPAN).min(rows.len() - row),
+            };
+            let text = td.inner_text();
+            for (covered, cells) in grid[row..row + rowspan].iter_mut().enumerate() {
+                if cells.len() < column + colspan {
+                    cells.resize(column + colspan, None);
+                }
+                for (offset, cell) in cells[column..column + colspan].iter_mut().enumerate() {
+                    *cell = Some(Cell {
+                        text: text.clone(),
+                        header: is(td, "th"),
+                        spanned: covered > 0 || offset > 0,
+                    });
+                }
+            }
+            column += colspan;
+        }
+    }
+    grid.into_iter()
+        .map(|cells| cells.into_iter().map(Option::unwrap_or_default).collect())
+        .collect()
+}
+
+fn span(cell: &HtmlElement<'_>, name: &str, default: usize) -> usize {
+    cell.get_attr(name)
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+fn is(element: &HtmlElement<'_>, tag: &str) -> bool {
+    element.tag().eq_ignore_ascii_case(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    #[test]
+    fn spans_and_csv() {
+        let html = "<table><caption>Sales</caption>\
+            <thead><tr><th>Region</th><th colspan=\"2\">Q1, Q2</th></tr></thead>\
+            <tbody><tr><td rowspan=\"2\">North</td><td>1</td><td>2</td></tr>\
+            <tr><td>3</td><td>say \"hi\"</td></tr></tbody></table>";
+        let document = parse_document(html).unwrap();
+        let tables = extract_tables(&document);
+        assert_eq!(tables.len(), 1);
+        let table = &tables[0];
+        assert_eq!(table.caption.as_deref(), Some("Sales"));
+        assert!(table.head[0][0].header);
+        assert!(table.head[0][2].spanned);
+        assert!(table.body[1][0].spanned);
+        assert_eq!(table.body[1][0].text, "North");
+        assert_eq!(
+            table.to_csv(),
+            "Region,\"Q1, Q2\",\"Q1, Q2\"\r\nNorth,1,2\r\nNorth,3,\"say \"\"hi\"\"\"\r\n"
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use crate::prelude::*;
+use crate::{HtmlDocument, HtmlElement, HtmlNode};
+
+// Block elements are separated by a line break, paragraphs and headings by
+// a blank line
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tr",
+    "ul",
+];
+
+const PARAGRAPH_ELEMENTS: &[&str] = &["p", "h1", "h2", "h3", "h4", "h5", "h6"];
+
+const SKIPPED_ELEMENTS: &[&str] = &["head", "noscript", "script", "style", "template", "title"];
+
+impl HtmlElement<'_> {
+    /// Rendered-looking text of the element's contents: whitespace is
+    /// collapsed, block elements start new lines and scripts are skipped.
+    pub fn inner_text(&self) -> String {
+        let mut writer = TextWriter::default();
+        writer.nodes(&self.children, false);
+        writer.out
+    }
+}
+
+impl HtmlDocument<'_> {
+    pub fn to_text(&self) -> String {
+        let mut writer = TextWriter::default();
+        writer.nodes(&self.nodes, false);
+        writer.out
+    }
+}
+
+fn is(element: &HtmlElement<'_>, tags: &[&str]) -> bool {
+    tags.iter().any(|tag| element.tag.eq_ignore_ascii_case(tag))
+}
+
+#[derive(Default)]
+struct TextWriter {
+    out: String,
+    // Line breaks owed before the next piece of text
+    pending: usize,
+    space: bool,
+}
+
+impl TextWriter {
+    fn nodes(&mut self, nodes: &[HtmlNode<'_>], pre: bool) {
+        for node in nodes {
+            match node {
+                HtmlNode::Element(element) => self.element(element, pre),
+                HtmlNode::Text(text) => self.text(text, pre),
+                HtmlNode::CData(text) => self.text(text, pre),
+                _ => {}
+            }
+        }
+    }
+
+    fn element(&mut self, element: &HtmlElement<'_>, pre: bool) {
+        if is(element, SKIPPED_ELEMENTS) {
+            return;
+        }
+        if element.tag.eq_ignore_ascii_case("br") {
+            self.out.push('\n');
+            self.space = false;
+            return;
+        }
+
+        let breaks = if is(element, PARAGRAPH_ELEMENTS) {
+            2
+        } else if is(element, BLOCK_ELEMENTS) {
+            1
+        } else {
+            0
+        };
+        if is(element, &["td", "th"]) && !self.out.is_empty() && self.pending == 0 {
+            self.out.push('\t');
+            self.space = false;
+        }
+
+        self.line_break(breaks);
+        let pre = pre || element.tag.eq_ignore_ascii_case("pre");
+        self.nodes(&element.children, pre);
+        self.line_break(breaks);
+    }
+
+    fn line_break(&mut self, count: usize) {
+        self.pending = self.pending.max(count);
+    }
+
+    fn text(&mut self, text: &str, pre: bool) {
+        for ch in text.chars() {
+            if pre {
+                self.flush_breaks();
+                self.out.push(ch);
+            } else if ch.is_whitespace() {
+                self.space = true;
+            } else {
+                if self.flush_breaks() {
+                    self.space = false;
+                }
+                if self.space && !self.out.is_empty() && !self.out.ends_with(['\n', '\t']) {
+                    self.out.push(' ');
+                }
+                self.space = false;
+                self.out.push(ch);
+            }
+        }
+    }
+
+    // Emits owed line breaks, returning whether any were written
+    fn flush_breaks(&mut self) -> bool {
+        let pending = core::mem::take(&mut self.pending);
+        if pending == 0 || self.out.is_empty() {
+            return false;
+        }
+        let trailing = self.out.len() - self.out.trim_end_matches('\n').len();
+        for _ in trailing..pending {
+            self.out.push('\n');
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_document, parse_html};
+
+    #[test]
+    fn text_extraction() {
+        let html = r#"<html><head><title>T</title><style>p {}</style></head><body>
+            <h1>Title</h1>
+            <div>  Some   <b>bold</b>
+                text<br>after break</div>
+            <ul><li>one</li><li>two</li></ul>
+            <p>para</p><script>ignored()</script>
+            <pre>  keep
+   spacing</pre>
+            <table><tr><td>a</td><td>b</td></tr></table>
+        </body></html>"#;
+        let document = parse_document(html).unwrap();
+        assert_eq!(
+            document.to_text(),
+            "Title\n\nSome bold text\nafter break\none\ntwo\n\npara\n\n  keep\n   spacing\na\tb"
+        );
+    }
+
+    #[test]
+    fn inner_text() {
+        let elements = parse_html("<p>Hello, <i>world</i>!</p>").unwrap();
+        assert_eq!(elements[0].inner_text(), "Hello, world!");
+    }
+}
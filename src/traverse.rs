@@ -0,0 +1,108 @@
+use alloc::collections::VecDeque;
+
+use crate::prelude::*;
+use crate::{HtmlElement, HtmlNode};
+
+impl<'a> HtmlElement<'a> {
+    /// Descendant elements in document order, paired with their depth below
+    /// `self` (children are at depth 1). Same as [`descendants_dfs`].
+    ///
+    /// [`descendants_dfs`]: HtmlElement::descendants_dfs
+    pub fn descendants(&self) -> DescendantsDfs<'_, 'a> {
+        self.descendants_dfs()
+    }
+
+    pub fn descendants_dfs(&self) -> DescendantsDfs<'_, 'a> {
+        DescendantsDfs {
+            stack: vec![self.children.iter()],
+        }
+    }
+
+    /// Descendant elements level by level, paired with their depth.
+    pub fn descendants_bfs(&self) -> DescendantsBfs<'_, 'a> {
+        DescendantsBfs {
+            queue: VecDeque::from([(self, 0)]),
+            current: None,
+        }
+    }
+}
+
+pub struct DescendantsDfs<'e, 'a> {
+    stack: Vec<core::slice::Iter<'e, HtmlNode<'a>>>,
+}
+
+impl<'e, 'a> Iterator for DescendantsDfs<'e, 'a> {
+    type Item = (&'e HtmlElement<'a>, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(children) = self.stack.last_mut() {
+            match children.next() {
+                Some(HtmlNode::Element(element)) => {
+                    let depth = self.stack.len();
+                    self.stack.push(element.children.iter());
+                    return Some((element, depth));
+                }
+                Some(_) => {}
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+        None
+    }
+}
+
+pub struct DescendantsBfs<'e, 'a> {
+    queue: VecDeque<(&'e HtmlElement<'a>, usize)>,
+    // Children of the element being expanded, with their depth
+    current: Option<(core::slice::Iter<'e, HtmlNode<'a>>, usize)>,
+}
+
+impl<'e, 'a> Iterator for DescendantsBfs<'e, 'a> {
+    type Item = (&'e HtmlElement<'a>, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((children, depth)) = &mut self.current {
+                let depth = *depth;
+                match children.next() {
+                    Some(HtmlNode::Element(element)) => {
+                        self.queue.push_back((element, depth));
+                        return Some((element, depth));
+                    }
+                    Some(_) => continue,
+                    None => self.current = None,
+                }
+            }
+
+            let (element, depth) = self.queue.pop_front()?;
+            self.current = Some((element.children.iter(), depth + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_html;
+
+    fn walk<'e>(iter: impl Iterator<Item = (&'e HtmlElement<'e>, usize)>) -> Vec<(&'e str, usize)> {
+        iter.map(|(element, depth)| (element.tag(), depth))
+            .collect()
+    }
+
+    #[test]
+    fn traversal_order() {
+        let html = "<div><ul><li>a</li><li>b</li></ul>text<p><b>c</b></p></div>";
+        let elements = parse_html(html).unwrap();
+        let root = &elements[0];
+        let dfs = vec![("ul", 1), ("li", 2), ("li", 2), ("p", 1), ("b", 2)];
+        assert_eq!(walk(root.descendants_dfs()), dfs);
+        assert_eq!(walk(root.descendants()), dfs);
+        assert_eq!(
+            walk(root.descendants_bfs()),
+            vec![("ul", 1), ("p", 1), ("li", 2), ("li", 2), ("b", 2)]
+        );
+        assert_eq!(HtmlElement::new("br").descendants_bfs().count(), 0);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::prelude::*;
+use crate::{HtmlDocument, HtmlNode};
+
+// Attributes holding a single URL, which sanitizing checks too; `srcset`
+// holds a list of them
+pub(crate) const URL_ATTRIBUTES: &[&str] = &[
+    "action",
+    "background",
+    "cite",
+    "data",
+    "formaction",
+    "href",
+    "longdesc",
+    "poster",
+    "src",
+    "xlink:href",
+];
+
+struct Url<'u> {
+    scheme: Option<&'u str>,
+    authority: Option<&'u str>,
+    path: &'u str,
+    query: Option<&'u str>,
+    fragment: Option<&'u str>,
+}
+
+impl<'u> Url<'u> {
+    fn parse(url: &'u str) -> Self {
+        let (url, fragment) = match url.split_once('#') {
+            Some((url, fragment)) => (url, Some(fragment)),
+            None => (url, None),
+        };
+        let (url, query) = match url.split_once('?') {
+            Some((url, query)) => (url, Some(query)),
+            None => (url, None),
+        };
+        let scheme_end = url.find(':').filter(|&end| {
+            let scheme = &url[..end];
+            scheme.starts_with(|ch: char| ch.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '+' | '-' | '.'))
+        });
+        let (scheme, rest) = match scheme_end {
+            Some(end) => (Some(&url[..end]), &url[end + 1..]),
+            None => (None, url),
+        };
+        let (authority, path) = match rest.strip_prefix("//") {
+            Some(rest) => {
+                let end = rest.find('/').unwrap_or(rest.len());
+                (Some(&rest[..end]), &rest[end..])
+            }
+            None => (None, rest),
+        };
+        Self {
+            scheme,
+            authority,
+            path,
+            query,
+            fragment,
+        }
+    }
+}
+
+/// Resolves `reference` against the absolute URL `base`, as RFC 3986 does,
+/// so `../a` against `http://x.com/b/c` is `http://x.com/a`.
+pub fn resolve_url(base: &str, reference: &str) -> String {
+    let reference = reference.trim();
+    let base = Url::parse(base);
+    let url = Url::parse(reference);
+
+    let (scheme, authority, path, query) = if url.scheme.is_some() {
+        (
+            url.scheme,
+            url.authority,
+            remove_dot_segments(url.path),
+            url.query,
+        )
+    } else if url.authority.is_some() {
+        (
+            base.scheme,
+            url.authority,
+            remove_dot_segments(url.path),
+            url.query,
+        )
+    } else if url.path.is_empty() {
+        (
+            base.scheme,
+            base.authority,
+            base.path.to_string(),
+            url.query.or(base.query),
+        )
+    } else if url.path.starts_with('/') {
+        (
+            base.scheme,
+            base.authority,
+            remove_dot_segments(url.path),
+            url.query,
+        )
+    } else {
+        let merged = if base.authority.is_some() && base.path.is_empty() {
+            format!("/{}", url.path)
+        } else {
+            let directory = base.path.rfind('/').map_or("", |end| &base.path[..=end]);
+            format!("{directory}{}", url.path)
+        };
+        (
+            base.scheme,
+            base.authority,
+            remove_dot_segments(&merged),
+            url.query,
+        )
+    };
+
+    let mut resolved = String::new();
+    if let Some(scheme) = scheme {
+        resolved.push_str(scheme);
+        resolved.push(':');
+    }
+    if let Some(authority) = authority {
+        resolved.push_str("//");
+        resolved.push_str(authority);
+    }
+    resolved.push_str(&path);
+    if let Some(query) = query {
+        resolved.push('?');
+        resolved.push_str(query);
+    }
+    if let Some(fragment) = url.fragment {
+        resolved.push('#');
+        resolved.push_str(fragment);
+    }
+    resolved
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    let mut parts = path.split('/').peekable();
+    // A leading slash gives an empty first segment, which is kept
+    while let Some(part) = parts.next() {
+        let last = parts.peek().is_none();
+        match part {
+            "." | ".." if last => {
+                if part == ".." && segments.len() > 1 {
+                    segments.pop();
+                }
+                segments.push("");
+            }
+            "." => {}
+            ".." => {
+                if segments.len() > 1 {
+                    segments.pop();
+                }
+            }
+            part => segments.push(part),
+        }
+    }
+    segments.join("/")
+}
+
+impl HtmlDocument<'_> {
+    /// Makes every relative URL in `href`, `src`, `srcset`, `action` and
+    /// similar attributes absolute. URLs are resolved against the
+    /// document's `<base href>` if it has one, itself resolved against
+    /// `base`.
+    pub fn resolve_urls(&mut self, base: &str) {
+        self.rewrite_urls(base, str::to_string);
+    }
+
+    /// Like `resolve_urls`, then replaces each resolved URL with what
+    /// `rewrite` returns for it, as when pointing links through a proxy.
+    pub fn rewrite_urls(&mut self, base: &str, mut rewrite: impl FnMut(&str) -> String) {
+        let base = base_href(self.children())
+            .map_or_else(|| base.to_string(), |href| resolve_url(base, href));
+        let mut rewrite = |url: &str| rewrite(&resolve_url(&base, url));
+        rewrite_nodes(self.nodes_mut(), &mut rewrite);
+    }
+}
+
+// The first `<base href>` in the tree
+fn base_href<'n>(nodes: &'n [HtmlNode<'_>]) -> Option<&'n str> {
+    nodes
+        .iter()
+        .filter_map(HtmlNode::as_element)
+        .find_map(|element| {
+            if element.tag().eq_ignore_ascii_case("base") {
+                element.get_attr("href")
+            } else {
+                base_href(element.children())
+            }
+        })
+}
+
+fn rewrite_nodes(nodes: &mut [HtmlNode<'_>], rewrite: &mut dyn FnMut(&str) -> String) {
+    for element in nodes.iter_mut().filter_map(HtmlNode::as_element_mut) {
+        let is_base = element.tag().eq_ignore_ascii_case("base");
+        for attribute in element.attributes_vec_mut() {
+            let Some(value) = attribute.value() else {
+                continue;
+            };
+            let name = attribute.name();
+            let value = if name.eq_ignore_ascii_case("srcset") {
+                rewrite_srcset(value, rewrite)
+            } else if !is_base
+                && URL_ATTRIBUTES
+                    .iter()
+                    .any(|url_attribute| url_attribute.eq_ignore_ascii_case(name))
+            {
+                rewrite(value)
+            } else {
+                continue;
+            };
+            attribute.set_value(value);
+        }
+        rewrite_nodes(element.children_vec_mut(), rewrite);
+        rewrite_nodes(element.template_content_mut(), rewrite);
+    }
+}
+
+// `srcset` is a comma-separated list of URLs, each with an optional width or
+// density descriptor
+fn rewrite_srcset(srcset: &str, rewrite: &mut dyn FnMut(&str) -> String) -> String {
+    srcset
+        .split(',')
+        .map(str::trim)
+        .filter(|candidate| !candidate.is_empty())
+        .map(
+            |candidate| match candidate.split_once(char::is_whitespace) {
+                Some((url, descriptor)) => format!("{} {}", rewrite(url), descriptor.trim()),
+                None => rewrite(candidate),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{document_to_string, parse_document};
+
+    #[test]
+    fn reference_resolution() {
+        let base = "http://a/b/c/d;p?q";
+        for (reference, expected) in [
+            ("g:h", "g:h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../../../g", "http://a/g"),
+            ("g;x=1/../y", "http://a/b/c/y"),
+        ] {
+            assert_eq!(resolve_url(base, reference), expected, "{reference}");
+        }
+    }
+
+    #[test]
+    fn rewrite_document_urls() {
+        let html = "<head><base href=\"/docs/\"></head>\
+            <a href=\"page.html\">a</a><img srcset=\"a.png 1x, /b.png 2x\">\
+            <form action=\"https://other.org/post\"></form>\
+            <svg><a xlink:href=\"#top\"></a></svg>";
+        let mut document = parse_document(html).unwrap();
+        document.resolve_urls("https://example.com/index.html");
+        assert_eq!(
+            document_to_string(document.clone()),
+            "<head><base href=\"/docs/\"></head>\
+            <a href=\"https://example.com/docs/page.html\">a</a>\
+            <img srcset=\"https://example.com/docs/a.png 1x, https://example.com/b.png 2x\">\
+            <form action=\"https://other.org/post\"></form>\
+            <svg><a xlink:href=\"https://example.com/docs/#top\"></a></svg>"
+        );
+
+        document.rewrite_urls("https://example.com/", |url| format!("/proxy?u={url}"));
+        let link = document.elements().nth(1).unwrap();
+        assert_eq!(
+            link.get_attr("href"),
+            Some("/proxy?u=https://example.com/docs/page.html")
+        );
+    }
+}
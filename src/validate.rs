@@ -0,0 +1,153 @@
+use alloc::borrow::Cow;
+
+use crate::prelude::*;
+use crate::{Diagnostic, HtmlDocument, HtmlElement, HtmlNode, HtmlWarning, Namespace};
+
+// Elements that may only appear directly inside one of the listed parents
+const PARENTS: &[(&str, &[&str])] = &[
+    ("li", &["ul", "ol", "menu"]),
+    ("dt", &["dl", "div"]),
+    ("dd", &["dl", "div"]),
+    ("caption", &["table"]),
+    ("colgroup", &["table"]),
+    ("col", &["colgroup", "table"]),
+    ("thead", &["table"]),
+    ("tbody", &["table"]),
+    ("tfoot", &["table"]),
+    ("tr", &["table", "thead", "tbody", "tfoot"]),
+    ("td", &["tr"]),
+    ("th", &["tr"]),
+    ("optgroup", &["select"]),
+    ("option", &["select", "datalist", "optgroup"]),
+    ("legend", &["fieldset"]),
+    ("summary", &["details"]),
+];
+
+// Elements that end a paragraph, so can't be inside one
+const NOT_IN_PARAGRAPH: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "details",
+    "div",
+    "dl",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "main",
+    "menu",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+
+/// Checks `document` against the content models of HTML elements, such as
+/// `<li>` belonging in a list and `<p>` holding no block elements.
+pub fn validate<'a>(document: &HtmlDocument<'a>) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics = Vec::new();
+    check_children(None, document.children(), &mut diagnostics);
+    diagnostics
+}
+
+fn check_children<'a>(
+    parent: Option<&HtmlElement<'a>>,
+    children: &[HtmlNode<'a>],
+    diagnostics: &mut Vec<Diagnostic<'a>>,
+) {
+    for element in children.iter().filter_map(HtmlNode::as_element) {
+        if element.namespace() == Namespace::Html && !is_allowed(parent, element.tag()) {
+            let warning = HtmlWarning::MisplacedElement {
+                tag: element.tag.clone(),
+                parent: parent.map(|parent| parent.tag.clone()),
+            };
+            diagnostics.push(Diagnostic::new(warning, element.span().unwrap_or_default()));
+        }
+        check_children(Some(element), element.children(), diagnostics);
+        check_children(Some(element), element.template_content(), diagnostics);
+    }
+}
+
+fn is_allowed(parent: Option<&HtmlElement>, tag: &str) -> bool {
+    let parent_tag = parent.map(HtmlElement::tag);
+    // Templates may hold anything
+    if parent_tag.is_some_and(|parent| parent.eq_ignore_ascii_case("template")) {
+        return true;
+    }
+    if parent_tag.is_some_and(|parent| parent.eq_ignore_ascii_case("p"))
+        && contains(NOT_IN_PARAGRAPH, tag)
+    {
+        return false;
+    }
+    match PARENTS
+        .iter()
+        .find(|(child, _)| child.eq_ignore_ascii_case(tag))
+    {
+        Some((_, parents)) => parent_tag.is_some_and(|parent| contains(parents, parent)),
+        None => true,
+    }
+}
+
+fn contains(tags: &[&str], tag: &str) -> bool {
+    tags.iter()
+        .any(|candidate| candidate.eq_ignore_ascii_case(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_document, Span};
+
+    fn misplaced(document: &HtmlDocument) -> Vec<(String, Option<String>)> {
+        validate(document)
+            .into_iter()
+            .map(|diagnostic| match diagnostic.warning {
+                HtmlWarning::MisplacedElement { tag, parent } => {
+                    (tag.into_owned(), parent.map(Cow::into_owned))
+                }
+                warning => panic!("unexpected {warning:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn content_models() {
+        let html = "<ul><li>ok</li></ul><li>stray</li>\
+            <table><tr><td>ok</td></tr></table><div><td>cell</td></div>\
+            <template><li>fine</li></template>";
+        let document = parse_document(html).unwrap();
+        assert_eq!(
+            misplaced(&document),
+            [
+                ("li".to_string(), None),
+                ("td".to_string(), Some("div".to_string()))
+            ]
+        );
+        assert_eq!(validate(&document)[0].span, Span::new(20, 34));
+
+        let mut paragraph = HtmlElement::new("p");
+        paragraph.add_child(HtmlElement::new("div"));
+        paragraph.add_child(HtmlElement::new("span"));
+        let mut document = HtmlDocument::default();
+        document.nodes_mut().push(HtmlNode::Element(paragraph));
+        assert_eq!(
+            misplaced(&document),
+            [("div".to_string(), Some("p".to_string()))]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use alloc::borrow::Cow;
+
+use crate::prelude::*;
+use crate::{HtmlDocument, HtmlElement, HtmlNode};
+
+/// What the driver does after a hook returns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VisitAction<'a> {
+    Continue,
+    /// Keep the element but don't descend into its children.
+    SkipChildren,
+    /// Swap the node for the given nodes, which are not visited themselves.
+    Replace(Vec<HtmlNode<'a>>),
+    Remove,
+}
+
+/// Hooks called while walking a tree with [`walk_nodes`]. Every hook
+/// defaults to [`VisitAction::Continue`].
+pub trait HtmlVisitor<'a> {
+    fn visit_element(&mut self, element: &mut HtmlElement<'a>) -> VisitAction<'a> {
+        VisitAction::Continue
+    }
+
+    /// Called once the children of an element have been walked.
+    fn leave_element(&mut self, element: &mut HtmlElement<'a>) {}
+
+    fn visit_text(&mut self, text: &mut Cow<'a, str>) -> VisitAction<'a> {
+        VisitAction::Continue
+    }
+
+    fn visit_comment(&mut self, comment: &mut Cow<'a, str>) -> VisitAction<'a> {
+        VisitAction::Continue
+    }
+}
+
+/// Walks `nodes` depth-first, applying the visitor's actions in place.
+pub fn walk_nodes<'a, V>(nodes: &mut Vec<HtmlNode<'a>>, visitor: &mut V)
+where
+    V: HtmlVisitor<'a> + ?Sized,
+{
+    let mut i = 0;
+    while i < nodes.len() {
+        let action = match &mut nodes[i] {
+            HtmlNode::Element(element) => match visitor.visit_element(element) {
+                VisitAction::Continue => {
+                    walk_nodes(element.children_vec_mut(), visitor);
+                    visitor.leave_element(element);
+                    VisitAction::Continue
+                }
+                VisitAction::SkipChildren => {
+                    visitor.leave_element(element);
+                    VisitAction::Continue
+                }
+                action => action,
+            },
+            HtmlNode::Text(text) => visitor.visit_text(text),
+            HtmlNode::Comment(comment) => visitor.visit_comment(comment),
+            _ => VisitAction::Continue,
+        };
+
+        match action {
+            VisitAction::Continue | VisitAction::SkipChildren => i += 1,
+            VisitAction::Replace(replacement) => {
+                let len = replacement.len();
+                nodes.splice(i..=i, replacement);
+                i += len;
+            }
+            VisitAction::Remove => {
+                nodes.remove(i);
+            }
+        }
+    }
+}
+
+impl<'a> HtmlElement<'a> {
+    /// Walks the children of this element; the element itself isn't visited.
+    pub fn accept<V: HtmlVisitor<'a> + ?Sized>(&mut self, visitor: &mut V) {
+        walk_nodes(self.children_vec_mut(), visitor);
+    }
+}
+
+impl<'a> HtmlDocument<'a> {
+    pub fn accept<V: HtmlVisitor<'a> + ?Sized>(&mut self, visitor: &mut V) {
+        walk_nodes(self.nodes_mut(), visitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{document_to_string, parse_document};
+
+    // Drops scripts and comments, unwraps <span>, and upper-cases text
+    struct Cleaner {
+        depth: usize,
+        max_depth: usize,
+    }
+
+    impl<'a> HtmlVisitor<'a> for Cleaner {
+        fn visit_element(&mut self, element: &mut HtmlElement<'a>) -> VisitAction<'a> {
+            match element.tag() {
+                "script" => VisitAction::Remove,
+                "span" => VisitAction::Replace(element.children().to_vec()),
+                "pre" => VisitAction::SkipChildren,
+                _ => {
+                    self.depth += 1;
+                    self.max_depth = self.max_depth.max(self.depth);
+                    VisitAction::Continue
+                }
+            }
+        }
+
+        fn leave_element(&mut self, element: &mut HtmlElement<'a>) {
+            if element.tag() != "pre" {
+                self.depth -= 1;
+            }
+        }
+
+        fn visit_text(&mut self, text: &mut Cow<'a, str>) -> VisitAction<'a> {
+            *text = text.to_uppercase().into();
+            VisitAction::Continue
+        }
+
+        fn visit_comment(&mut self, _: &mut Cow<'a, str>) -> VisitAction<'a> {
+            VisitAction::Remove
+        }
+    }
+
+    #[test]
+    fn visitor_transforms() {
+        let html =
+            "<div><script>x()</script><p>a<span>b<i>c</i></span></p><!-- c --><pre>d</pre></div>";
+        let mut document = parse_document(html).unwrap();
+        let mut cleaner = Cleaner {
+            depth: 0,
+            max_depth: 0,
+        };
+        document.accept(&mut cleaner);
+
+        assert_eq!(
+            document_to_string(document),
+            "<div><p>Ab<i>c</i></p><pre>d</pre></div>"
+        );
+        assert_eq!(cleaner.depth, 0);
+        assert_eq!(cleaner.max_depth, 2);
+    }
+}
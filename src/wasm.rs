@@ -0,0 +1,145 @@
+//! Bindings for JavaScript through `wasm-bindgen`:
+//!
+//! ```js
+//! import { parse } from "html-parser";
+//!
+//! const document = parse("<ul><li class=a>one</li><li>two</li></ul>", true);
+//! for (const item of document.select("li.a")) {
+//!   console.log(item.tag, item.attr("class"), item.innerText());
+//! }
+//! document.toJson(); // the shape described in `to_json`
+//! ```
+//!
+//! Trees cross into JavaScript as owned values, so a document no longer
+//! borrows the string it was parsed from.
+//!
+//! The crate is built as an `rlib`, so a module for `wasm-bindgen` to process
+//! comes from `cargo rustc --release --features wasm --target
+//! wasm32-unknown-unknown --crate-type cdylib`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::owned::{borrow_nodes, select_owned};
+use crate::prelude::*;
+use crate::{
+    document_to_string, parse_document_with, write_html, HtmlElementOwned, HtmlNode, HtmlNodeOwned,
+    ParseMode, ParseOptions,
+};
+
+/// Parses `html`, forgiving malformed markup the way browsers do when
+/// `lenient` is set.
+#[wasm_bindgen]
+pub fn parse(html: &str, lenient: Option<bool>) -> Result<WasmDocument, JsError> {
+    let options = ParseOptions {
+        mode: match lenient {
+            Some(true) => ParseMode::Lenient,
+            _ => ParseMode::Strict,
+        },
+        ..Default::default()
+    };
+    let document = parse_document_with(html, &options)?;
+    Ok(WasmDocument {
+        nodes: document
+            .nodes
+            .into_iter()
+            .map(HtmlNode::into_owned)
+            .collect(),
+    })
+}
+
+/// A parsed document, `HtmlDocument` in JavaScript.
+#[wasm_bindgen(js_name = HtmlDocument)]
+pub struct WasmDocument {
+    nodes: Vec<HtmlNodeOwned>,
+}
+
+#[wasm_bindgen(js_class = HtmlDocument)]
+impl WasmDocument {
+    /// The elements matching a CSS selector, in document order.
+    pub fn select(&self, selector: &str) -> Result<Vec<WasmElement>, JsError> {
+        let elements = select_owned(&self.nodes, selector)?;
+        Ok(elements
+            .into_iter()
+            .map(|element| WasmElement { element })
+            .collect())
+    }
+
+    pub fn serialize(&self) -> String {
+        document_to_string(borrow_nodes(&self.nodes))
+    }
+
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<JsValue, JsError> {
+        json_value(&borrow_nodes(&self.nodes).to_json())
+    }
+}
+
+/// An element from [`WasmDocument::select`], `HtmlElement` in JavaScript.
+#[wasm_bindgen(js_name = HtmlElement)]
+pub struct WasmElement {
+    element: HtmlElementOwned,
+}
+
+#[wasm_bindgen(js_class = HtmlElement)]
+impl WasmElement {
+    #[wasm_bindgen(getter)]
+    pub fn tag(&self) -> String {
+        self.element.tag().to_string()
+    }
+
+    /// The attribute's value, `""` for one without a value and `undefined`
+    /// when the element doesn't have it.
+    pub fn attr(&self, name: &str) -> Option<String> {
+        self.element
+            .to_borrowed()
+            .get_attr(name)
+            .map(str::to_string)
+    }
+
+    #[wasm_bindgen(js_name = innerText)]
+    pub fn inner_text(&self) -> String {
+        self.element.to_borrowed().inner_text()
+    }
+
+    #[wasm_bindgen(js_name = outerHtml)]
+    pub fn outer_html(&self) -> String {
+        let mut html = String::new();
+        write_html(&[self.element.to_borrowed()], &mut html).expect("writing to a String");
+        html
+    }
+
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<JsValue, JsError> {
+        json_value(&self.element.to_borrowed().to_json())
+    }
+}
+
+// A JSON value as the JavaScript object it describes
+fn json_value(value: &serde_json::Value) -> Result<JsValue, JsError> {
+    js_sys::JSON::parse(&value.to_string()).map_err(|_| JsError::new("invalid JSON"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn document_api() {
+        let html = "<ul><li class=\"a\">one &amp; <b>two</b></li><li>three</ul>";
+        let document = parse(html, Some(true)).unwrap();
+        let items = document.select("li.a, li + li").unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].tag(), "li");
+        assert_eq!(items[0].attr("class").as_deref(), Some("a"));
+        assert_eq!(items[1].attr("class"), None);
+        assert_eq!(items[0].inner_text(), "one & two");
+        assert_eq!(
+            items[0].outer_html(),
+            "<li class=\"a\">one &amp; <b>two</b></li>"
+        );
+        assert_eq!(
+            document.serialize(),
+            "<ul><li class=\"a\">one &amp; <b>two</b></li><li>three</li></ul>"
+        );
+    }
+}
@@ -0,0 +1,1061 @@
+//! A practical subset of XPath 1.0, evaluated against a [`Document`].
+//!
+//! Supported are absolute and relative location paths, the `//`, `.`, `..`
+//! and `@` abbreviations, the common axes (`child`, `descendant`, `parent`,
+//! `ancestor`, `following-sibling`, `preceding-sibling`, `self`,
+//! `attribute`, ...), `text()`, `node()` and `comment()` node tests,
+//! predicates with `and`/`or`, comparisons, unions and a handful of core
+//! functions (`position`, `last`, `count`, `contains`, `starts-with`,
+//! `normalize-space`, `string`, `string-length`, `concat`, `not`, `name`).
+
+use core::cell::OnceCell;
+
+use crate::prelude::*;
+use crate::{Document, HashMap, HashSet, HtmlError, NodeData, NodeId};
+
+/// A compiled XPath expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XPath {
+    expr: Expr,
+}
+
+/// A node or attribute selected by an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XPathItem<'d> {
+    Node(NodeId),
+    Attribute {
+        owner: NodeId,
+        name: &'d str,
+        value: &'d str,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum XPathValue<'d> {
+    Nodes(Vec<XPathItem<'d>>),
+    String(String),
+    Number(f64),
+    Boolean(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Compare(CompareOp, Box<Expr>, Box<Expr>),
+    Union(Vec<Expr>),
+    Path(LocationPath),
+    Literal(String),
+    Number(f64),
+    Function(String, Vec<Expr>),
+    Filter(Box<Expr>, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Eq,
+    NotEq,
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct LocationPath {
+    absolute: bool,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    axis: Axis,
+    test: NodeTest,
+    predicates: Vec<Expr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Parent,
+    Ancestor,
+    AncestorOrSelf,
+    FollowingSibling,
+    PrecedingSibling,
+    Itself,
+    Attribute,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NodeTest {
+    Name(String),
+    Any,
+    Text,
+    Node,
+    Comment,
+}
+
+impl XPath {
+    /// Fails on syntax errors, unknown functions and calls with the wrong
+    /// number of arguments.
+    pub fn compile(expr: &str) -> Result<Self, HtmlError> {
+        let invalid = || HtmlError::InvalidXPath(expr.to_string());
+        let tokens = tokenize(expr).ok_or_else(invalid)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let parsed = parser.expr().ok_or_else(invalid)?;
+        if parser.pos != parser.tokens.len() {
+            return Err(invalid());
+        }
+        Ok(Self { expr: parsed })
+    }
+
+    /// Evaluates the expression with `context` as the context node.
+    pub fn evaluate<'d>(&self, document: &'d Document<'_>, context: NodeId) -> XPathValue<'d> {
+        let evaluator = Evaluator {
+            document,
+            order: OnceCell::new(),
+        };
+        let context = Context {
+            item: Item::Node(context),
+            position: 1,
+            size: 1,
+        };
+        evaluator.value(evaluator.eval(&self.expr, &context))
+    }
+
+    /// The selected nodes, or nothing if the expression isn't a node-set.
+    pub fn select<'d>(&self, document: &'d Document<'_>) -> Vec<XPathItem<'d>> {
+        match self.evaluate(document, document.root()) {
+            XPathValue::Nodes(items) => items,
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl XPathItem<'_> {
+    /// The XPath string-value: attribute values, text content, or the
+    /// concatenated text of an element's descendants.
+    pub fn string_value(&self, document: &Document<'_>) -> String {
+        match self {
+            XPathItem::Node(id) => node_string_value(document, *id),
+            XPathItem::Attribute { value, .. } => value.to_string(),
+        }
+    }
+}
+
+impl Document<'_> {
+    pub fn xpath(&self, expr: &str) -> Result<Vec<XPathItem<'_>>, HtmlError> {
+        XPath::compile(expr).map(|xpath| xpath.select(self))
+    }
+}
+
+fn node_string_value(document: &Document<'_>, id: NodeId) -> String {
+    let node = document.get(id);
+    match node.data() {
+        NodeData::Document | NodeData::Element { .. } => document
+            .descendants(id)
+            .filter_map(|id| document.get(id).text())
+            .collect(),
+        NodeData::Comment(comment) => comment.to_string(),
+        _ => node.text().unwrap_or_default().to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Slash,
+    DoubleSlash,
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    At,
+    Comma,
+    Axis,
+    Dot,
+    DoubleDot,
+    Star,
+    Pipe,
+    Op(CompareOp),
+    Name(String),
+    Literal(String),
+    Number(f64),
+}
+
+fn tokenize(expr: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.char_indices().peekable();
+
+    while let Some((start, ch)) = chars.next() {
+        let token = match ch {
+            ch if ch.is_whitespace() => continue,
+            '/' if chars.next_if(|(_, ch)| *ch == '/').is_some() => Token::DoubleSlash,
+            '/' => Token::Slash,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '@' => Token::At,
+            ',' => Token::Comma,
+            '*' => Token::Star,
+            '|' => Token::Pipe,
+            ':' if chars.next_if(|(_, ch)| *ch == ':').is_some() => Token::Axis,
+            '.' if chars.next_if(|(_, ch)| *ch == '.').is_some() => Token::DoubleDot,
+            '.' => Token::Dot,
+            '=' => Token::Op(CompareOp::Eq),
+            '!' if chars.next_if(|(_, ch)| *ch == '=').is_some() => Token::Op(CompareOp::NotEq),
+            '<' if chars.next_if(|(_, ch)| *ch == '=').is_some() => Token::Op(CompareOp::LessEq),
+            '<' => Token::Op(CompareOp::Less),
+            '>' if chars.next_if(|(_, ch)| *ch == '=').is_some() => Token::Op(CompareOp::GreaterEq),
+            '>' => Token::Op(CompareOp::Greater),
+            quote @ ('"' | '\'') => {
+                let rest = &expr[start + 1..];
+                let len = rest.find(quote)?;
+                for _ in rest[..len].chars() {
+                    chars.next();
+                }
+                chars.next();
+                Token::Literal(rest[..len].to_string())
+            }
+            ch if ch.is_ascii_digit() => {
+                let mut end = start + 1;
+                while let Some((i, _)) = chars.next_if(|(_, ch)| ch.is_ascii_digit() || *ch == '.')
+                {
+                    end = i + 1;
+                }
+                Token::Number(expr[start..end].parse().ok()?)
+            }
+            ch if ch.is_alphabetic() || ch == '_' => {
+                let mut end = start + ch.len_utf8();
+                while let Some((i, ch)) =
+                    chars.next_if(|(_, ch)| ch.is_alphanumeric() || matches!(ch, '-' | '_' | '.'))
+                {
+                    end = i + ch.len_utf8();
+                }
+                Token::Name(expr[start..end].to_string())
+            }
+            _ => return None,
+        };
+        tokens.push(token);
+    }
+
+    Some(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Name(name)) if name == keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Option<Expr> {
+        let mut expr = self.and_expr()?;
+        while self.eat_keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and_expr()?));
+        }
+        Some(expr)
+    }
+
+    fn and_expr(&mut self) -> Option<Expr> {
+        let mut expr = self.compare_expr()?;
+        while self.eat_keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.compare_expr()?));
+        }
+        Some(expr)
+    }
+
+    fn compare_expr(&mut self) -> Option<Expr> {
+        let mut expr = self.union_expr()?;
+        while let Some(Token::Op(op)) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            expr = Expr::Compare(op, Box::new(expr), Box::new(self.union_expr()?));
+        }
+        Some(expr)
+    }
+
+    fn union_expr(&mut self) -> Option<Expr> {
+        let mut paths = vec![self.path_expr()?];
+        while self.eat(&Token::Pipe) {
+            paths.push(self.path_expr()?);
+        }
+        if paths.len() == 1 {
+            paths.pop()
+        } else {
+            Some(Expr::Union(paths))
+        }
+    }
+
+    fn path_expr(&mut self) -> Option<Expr> {
+        match self.peek()? {
+            Token::Literal(literal) => {
+                let literal = literal.clone();
+                self.pos += 1;
+                Some(Expr::Literal(literal))
+            }
+            Token::Number(number) => {
+                let number = *number;
+                self.pos += 1;
+                Some(Expr::Number(number))
+            }
+            Token::LParen => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                if !self.eat(&Token::RParen) {
+                    return None;
+                }
+                let predicates = self.predicates()?;
+                if predicates.is_empty() {
+                    Some(expr)
+                } else {
+                    Some(Expr::Filter(Box::new(expr), predicates))
+                }
+            }
+            Token::Name(name)
+                if self.peek_at(1) == Some(&Token::LParen)
+                    && !matches!(name.as_str(), "text" | "node" | "comment") =>
+            {
+                let name = name.clone();
+                self.pos += 2;
+                let mut args = Vec::new();
+                if !self.eat(&Token::RParen) {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(&Token::RParen) {
+                            break;
+                        }
+                        if !self.eat(&Token::Comma) {
+                            return None;
+                        }
+                    }
+                }
+                if !takes_arguments(&name, args.len()) {
+                    return None;
+                }
+                Some(Expr::Function(name, args))
+            }
+            _ => self.location_path().map(Expr::Path),
+        }
+    }
+
+    fn location_path(&mut self) -> Option<LocationPath> {
+        let mut path = LocationPath {
+            absolute: false,
+            steps: Vec::new(),
+        };
+
+        if self.eat(&Token::Slash) {
+            path.absolute = true;
+            // A lone `/` selects the document root
+            if !self.at_step() {
+                return Some(path);
+            }
+        } else if self.eat(&Token::DoubleSlash) {
+            path.absolute = true;
+            path.steps.push(descendant_or_self());
+        }
+
+        path.steps.push(self.step()?);
+        loop {
+            if self.eat(&Token::Slash) {
+                path.steps.push(self.step()?);
+            } else if self.eat(&Token::DoubleSlash) {
+                path.steps.push(descendant_or_self());
+                path.steps.push(self.step()?);
+            } else {
+                return Some(path);
+            }
+        }
+    }
+
+    fn at_step(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(Token::Name(_) | Token::Star | Token::At | Token::Dot | Token::DoubleDot)
+        )
+    }
+
+    fn step(&mut self) -> Option<Step> {
+        if self.eat(&Token::Dot) {
+            return Some(Step {
+                axis: Axis::Itself,
+                test: NodeTest::Node,
+                predicates: Vec::new(),
+            });
+        }
+        if self.eat(&Token::DoubleDot) {
+            return Some(Step {
+                axis: Axis::Parent,
+                test: NodeTest::Node,
+                predicates: Vec::new(),
+            });
+        }
+
+        let axis = if self.eat(&Token::At) {
+            Axis::Attribute
+        } else if self.peek_at(1) == Some(&Token::Axis) {
+            let Some(Token::Name(name)) = self.next() else {
+                return None;
+            };
+            self.pos += 1;
+            match name.as_str() {
+                "child" => Axis::Child,
+                "descendant" => Axis::Descendant,
+                "descendant-or-self" => Axis::DescendantOrSelf,
+                "parent" => Axis::Parent,
+                "ancestor" => Axis::Ancestor,
+                "ancestor-or-self" => Axis::AncestorOrSelf,
+                "following-sibling" => Axis::FollowingSibling,
+                "preceding-sibling" => Axis::PrecedingSibling,
+                "self" => Axis::Itself,
+                "attribute" => Axis::Attribute,
+                _ => return None,
+            }
+        } else {
+            Axis::Child
+        };
+
+        let test = match self.next()? {
+            Token::Star => NodeTest::Any,
+            Token::Name(name) if self.peek() == Some(&Token::LParen) => {
+                self.pos += 1;
+                if !self.eat(&Token::RParen) {
+                    return None;
+                }
+                match name.as_str() {
+                    "text" => NodeTest::Text,
+                    "node" => NodeTest::Node,
+                    "comment" => NodeTest::Comment,
+                    _ => return None,
+                }
+            }
+            Token::Name(name) => NodeTest::Name(name),
+            _ => return None,
+        };
+
+        Some(Step {
+            axis,
+            test,
+            predicates: self.predicates()?,
+        })
+    }
+
+    fn predicates(&mut self) -> Option<Vec<Expr>> {
+        let mut predicates = Vec::new();
+        while self.eat(&Token::LBracket) {
+            predicates.push(self.expr()?);
+            if !self.eat(&Token::RBracket) {
+                return None;
+            }
+        }
+        Some(predicates)
+    }
+}
+
+// The core functions, with the fewest and most arguments each takes
+const FUNCTIONS: &[(&str, usize, usize)] = &[
+    ("position", 0, 0),
+    ("last", 0, 0),
+    ("count", 1, 1),
+    ("contains", 2, 2),
+    ("starts-with", 2, 2),
+    ("normalize-space", 0, 1),
+    ("string", 0, 1),
+    ("string-length", 0, 1),
+    ("concat", 2, usize::MAX),
+    ("not", 1, 1),
+    ("true", 0, 0),
+    ("false", 0, 0),
+    ("number", 0, 1),
+    ("name", 0, 1),
+    ("local-name", 0, 1),
+];
+
+// Whether `name` is a function that can be called with `count` arguments
+fn takes_arguments(name: &str, count: usize) -> bool {
+    FUNCTIONS
+        .iter()
+        .any(|&(function, min, max)| function == name && (min..=max).contains(&count))
+}
+
+fn descendant_or_self() -> Step {
+    Step {
+        axis: Axis::DescendantOrSelf,
+        test: NodeTest::Node,
+        predicates: Vec::new(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Item {
+    Node(NodeId),
+    Attribute(NodeId, usize),
+}
+
+struct Context {
+    item: Item,
+    position: usize,
+    size: usize,
+}
+
+enum Value {
+    Nodes(Vec<Item>),
+    String(String),
+    Number(f64),
+    Boolean(bool),
+}
+
+struct Evaluator<'d, 'a> {
+    document: &'d Document<'a>,
+    // Where each node of the document comes in document order, for sorting
+    // node-sets, worked out the first time one needs it
+    order: OnceCell<HashMap<NodeId, usize>>,
+}
+
+impl<'d> Evaluator<'d, '_> {
+    fn value(&self, value: Value) -> XPathValue<'d> {
+        match value {
+            Value::Nodes(items) => {
+                XPathValue::Nodes(items.into_iter().map(|item| self.item(item)).collect())
+            }
+            Value::String(string) => XPathValue::String(string),
+            Value::Number(number) => XPathValue::Number(number),
+            Value::Boolean(boolean) => XPathValue::Boolean(boolean),
+        }
+    }
+
+    fn item(&self, item: Item) -> XPathItem<'d> {
+        match item {
+            Item::Node(id) => XPathItem::Node(id),
+            Item::Attribute(owner, index) => {
+                let attr = &self.document.get(owner).attributes()[index];
+                XPathItem::Attribute {
+                    owner,
+                    name: attr.name(),
+                    value: attr.value().unwrap_or_default(),
+                }
+            }
+        }
+    }
+
+    fn eval(&self, expr: &Expr, context: &Context) -> Value {
+        match expr {
+            Expr::Or(left, right) => Value::Boolean(
+                self.boolean(self.eval(left, context)) || self.boolean(self.eval(right, context)),
+            ),
+            Expr::And(left, right) => Value::Boolean(
+                self.boolean(self.eval(left, context)) && self.boolean(self.eval(right, context)),
+            ),
+            Expr::Compare(op, left, right) => {
+                let left = self.eval(left, context);
+                let right = self.eval(right, context);
+                Value::Boolean(self.compare(*op, &left, &right))
+            }
+            Expr::Union(paths) => {
+                let mut items = Vec::new();
+                let mut seen = HashSet::new();
+                for path in paths {
+                    if let Value::Nodes(nodes) = self.eval(path, context) {
+                        push_unique(&mut items, &mut seen, nodes);
+                    }
+                }
+                self.sort(&mut items);
+                Value::Nodes(items)
+            }
+            Expr::Path(path) => Value::Nodes(self.path(path, context.item)),
+            Expr::Literal(literal) => Value::String(literal.clone()),
+            Expr::Number(number) => Value::Number(*number),
+            Expr::Function(name, args) => self.function(name, args, context),
+            Expr::Filter(expr, predicates) => match self.eval(expr, context) {
+                Value::Nodes(items) => Value::Nodes(self.filter(items, predicates)),
+                _ => Value::Nodes(Vec::new()),
+            },
+        }
+    }
+
+    fn function(&self, name: &str, args: &[Expr], context: &Context) -> Value {
+        let string_arg = |i: usize| match args.get(i) {
+            Some(arg) => self.string(self.eval(arg, context)),
+            None => self.item_string(context.item),
+        };
+
+        match name {
+            "position" => Value::Number(context.position as f64),
+            "last" => Value::Number(context.size as f64),
+            "count" => match args.first().map(|arg| self.eval(arg, context)) {
+                Some(Value::Nodes(items)) => Value::Number(items.len() as f64),
+                _ => Value::Number(f64::NAN),
+            },
+            "contains" => Value::Boolean(string_arg(0).contains(&string_arg(1))),
+            "starts-with" => Value::Boolean(string_arg(0).starts_with(&string_arg(1))),
+            "normalize-space" => Value::String(
+                string_arg(0)
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            "string" => Value::String(string_arg(0)),
+            "string-length" => Value::Number(string_arg(0).chars().count() as f64),
+            "concat" => Value::String((0..args.len()).map(string_arg).collect()),
+            "not" => Value::Boolean(
+                !args
+                    .first()
+                    .is_some_and(|arg| self.boolean(self.eval(arg, context))),
+            ),
+            "true" => Value::Boolean(true),
+            "false" => Value::Boolean(false),
+            "number" => Value::Number(match args.first() {
+                Some(arg) => self.number(self.eval(arg, context)),
+                None => parse_number(&self.item_string(context.item)),
+            }),
+            "name" | "local-name" => {
+                let item = match args.first().map(|arg| self.eval(arg, context)) {
+                    Some(Value::Nodes(items)) => items.first().copied(),
+                    Some(_) => None,
+                    None => Some(context.item),
+                };
+                Value::String(item.map(|item| self.item_name(item)).unwrap_or_default())
+            }
+            _ => unreachable!("`XPath::compile` only accepts known functions"),
+        }
+    }
+
+    // Puts `items` in document order, where an element's attributes come
+    // after it and before its children. Nodes outside the document, such
+    // as template contents, keep their order at the end.
+    fn sort(&self, items: &mut [Item]) {
+        let order = self.order.get_or_init(|| {
+            let root = self.document.root();
+            core::iter::once(root)
+                .chain(self.document.descendants(root))
+                .enumerate()
+                .map(|(position, id)| (id, position))
+                .collect()
+        });
+        let position = |id| order.get(&id).copied().unwrap_or(usize::MAX);
+        items.sort_by_key(|item| match *item {
+            Item::Node(id) => (position(id), 0, 0),
+            Item::Attribute(owner, index) => (position(owner), 1, index),
+        });
+    }
+
+    fn path(&self, path: &LocationPath, context: Item) -> Vec<Item> {
+        let start = if path.absolute {
+            Item::Node(self.document.root())
+        } else {
+            context
+        };
+
+        let mut items = vec![start];
+        for step in &path.steps {
+            let mut next = Vec::new();
+            let mut seen = HashSet::new();
+            for item in items {
+                let candidates = self.axis(step, item);
+                push_unique(
+                    &mut next,
+                    &mut seen,
+                    self.filter(candidates, &step.predicates),
+                );
+            }
+            self.sort(&mut next);
+            items = next;
+        }
+        items
+    }
+
+    fn axis(&self, step: &Step, item: Item) -> Vec<Item> {
+        let document = self.document;
+        let id = match item {
+            Item::Node(id) => id,
+            Item::Attribute(owner, _) => {
+                return match step.axis {
+                    Axis::Parent | Axis::Ancestor | Axis::AncestorOrSelf => {
+                        let mut items = vec![Item::Node(owner)];
+                        if step.axis != Axis::Parent {
+                            items.extend(document.ancestors(owner).map(Item::Node));
+                        }
+                        self.test(step, items)
+                    }
+                    Axis::Itself => self.test(step, vec![item]),
+                    _ => Vec::new(),
+                };
+            }
+        };
+
+        let nodes: Vec<NodeId> = match step.axis {
+            Axis::Child => document.children(id).collect(),
+            Axis::Descendant => document.descendants(id).collect(),
+            Axis::DescendantOrSelf => core::iter::once(id)
+                .chain(document.descendants(id))
+                .collect(),
+            Axis::Parent => document.parent(id).into_iter().collect(),
+            Axis::Ancestor => document.ancestors(id).collect(),
+            Axis::AncestorOrSelf => core::iter::once(id).chain(document.ancestors(id)).collect(),
+            Axis::FollowingSibling => {
+                core::iter::successors(document.next_sibling(id), |id| document.next_sibling(*id))
+                    .collect()
+            }
+            Axis::PrecedingSibling => {
+                core::iter::successors(document.prev_sibling(id), |id| document.prev_sibling(*id))
+                    .collect()
+            }
+            Axis::Itself => vec![id],
+            Axis::Attribute => {
+                let attributes = (0..document.get(id).attributes().len())
+                    .map(|index| Item::Attribute(id, index))
+                    .collect();
+                return self.test(step, attributes);
+            }
+        };
+        self.test(step, nodes.into_iter().map(Item::Node).collect())
+    }
+
+    fn test(&self, step: &Step, items: Vec<Item>) -> Vec<Item> {
+        items
+            .into_iter()
+            .filter(|item| match item {
+                Item::Attribute(owner, index) => match &step.test {
+                    NodeTest::Name(name) => self.document.get(*owner).attributes()[*index]
+                        .name()
+                        .eq_ignore_ascii_case(name),
+                    NodeTest::Any | NodeTest::Node => true,
+                    NodeTest::Text | NodeTest::Comment => false,
+                },
+                Item::Node(id) => {
+                    let data = self.document.get(*id).data();
+                    match &step.test {
+                        NodeTest::Name(name) => matches!(
+                            data,
+                            NodeData::Element { tag, .. } if tag.eq_ignore_ascii_case(name)
+                        ),
+                        NodeTest::Any => matches!(data, NodeData::Element { .. }),
+                        NodeTest::Text => matches!(data, NodeData::Text(_) | NodeData::CData(_)),
+                        NodeTest::Comment => matches!(data, NodeData::Comment(_)),
+                        NodeTest::Node => true,
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn filter(&self, mut items: Vec<Item>, predicates: &[Expr]) -> Vec<Item> {
+        for predicate in predicates {
+            let size = items.len();
+            items = items
+                .into_iter()
+                .enumerate()
+                .filter(|(i, item)| {
+                    let context = Context {
+                        item: *item,
+                        position: i + 1,
+                        size,
+                    };
+                    match self.eval(predicate, &context) {
+                        Value::Number(number) => number == (i + 1) as f64,
+                        value => self.boolean(value),
+                    }
+                })
+                .map(|(_, item)| item)
+                .collect();
+        }
+        items
+    }
+
+    fn item_string(&self, item: Item) -> String {
+        self.item(item).string_value(self.document)
+    }
+
+    fn item_name(&self, item: Item) -> String {
+        match self.item(item) {
+            XPathItem::Attribute { name, .. } => name.to_string(),
+            XPathItem::Node(id) => self.document.get(id).tag().unwrap_or_default().to_string(),
+        }
+    }
+
+    fn string(&self, value: Value) -> String {
+        match value {
+            Value::Nodes(items) => items
+                .first()
+                .map(|item| self.item_string(*item))
+                .unwrap_or_default(),
+            Value::String(string) => string,
+            Value::Number(number) if number.is_nan() => String::from("NaN"),
+            Value::Number(number) if number == number as i64 as f64 => format!("{}", number as i64),
+            Value::Number(number) => number.to_string(),
+            Value::Boolean(boolean) => boolean.to_string(),
+        }
+    }
+
+    fn number(&self, value: Value) -> f64 {
+        match value {
+            Value::Number(number) => number,
+            Value::Boolean(boolean) => f64::from(u8::from(boolean)),
+            value => parse_number(&self.string(value)),
+        }
+    }
+
+    fn boolean(&self, value: Value) -> bool {
+        match value {
+            Value::Nodes(items) => !items.is_empty(),
+            Value::String(string) => !string.is_empty(),
+            Value::Number(number) => number != 0.0 && !number.is_nan(),
+            Value::Boolean(boolean) => boolean,
+        }
+    }
+
+    fn compare(&self, op: CompareOp, left: &Value, right: &Value) -> bool {
+        match (left, right) {
+            (Value::Nodes(left), Value::Nodes(right)) => left.iter().any(|left| {
+                let left = self.item_string(*left);
+                right
+                    .iter()
+                    .any(|right| compare_strings(op, &left, &self.item_string(*right)))
+            }),
+            // A node-set compares with a boolean as its own boolean value
+            (Value::Nodes(_), Value::Boolean(_)) | (Value::Boolean(_), Value::Nodes(_)) => {
+                let as_boolean = |value: &Value| Value::Boolean(self.boolean(clone_atomic(value)));
+                self.compare_atomic(op, &as_boolean(left), &as_boolean(right))
+            }
+            (Value::Nodes(items), other) | (other, Value::Nodes(items)) => {
+                let flipped = matches!(left, Value::Nodes(_));
+                items.iter().any(|item| {
+                    let item = match other {
+                        Value::Number(_) => Value::Number(parse_number(&self.item_string(*item))),
+                        _ => Value::String(self.item_string(*item)),
+                    };
+                    if flipped {
+                        self.compare_atomic(op, &item, other)
+                    } else {
+                        self.compare_atomic(op, other, &item)
+                    }
+                })
+            }
+            _ => self.compare_atomic(op, left, right),
+        }
+    }
+
+    fn compare_atomic(&self, op: CompareOp, left: &Value, right: &Value) -> bool {
+        let equality = matches!(op, CompareOp::Eq | CompareOp::NotEq);
+        match (left, right) {
+            (Value::Boolean(_), _) | (_, Value::Boolean(_)) if equality => {
+                let left = self.boolean(clone_atomic(left));
+                let right = self.boolean(clone_atomic(right));
+                (left == right) == (op == CompareOp::Eq)
+            }
+            (Value::String(left), Value::String(right)) if equality => {
+                compare_strings(op, left, right)
+            }
+            _ => {
+                let left = self.number(clone_atomic(left));
+                let right = self.number(clone_atomic(right));
+                match op {
+                    CompareOp::Eq => left == right,
+                    CompareOp::NotEq => left != right,
+                    CompareOp::Less => left < right,
+                    CompareOp::LessEq => left <= right,
+                    CompareOp::Greater => left > right,
+                    CompareOp::GreaterEq => left >= right,
+                }
+            }
+        }
+    }
+}
+
+fn clone_atomic(value: &Value) -> Value {
+    match value {
+        Value::Nodes(items) => Value::Nodes(items.clone()),
+        Value::String(string) => Value::String(string.clone()),
+        Value::Number(number) => Value::Number(*number),
+        Value::Boolean(boolean) => Value::Boolean(*boolean),
+    }
+}
+
+fn compare_strings(op: CompareOp, left: &str, right: &str) -> bool {
+    match op {
+        CompareOp::Eq => left == right,
+        CompareOp::NotEq => left != right,
+        _ => {
+            let (left, right) = (parse_number(left), parse_number(right));
+            match op {
+                CompareOp::Less => left < right,
+                CompareOp::LessEq => left <= right,
+                CompareOp::Greater => left > right,
+                _ => left >= right,
+            }
+        }
+    }
+}
+
+fn parse_number(string: &str) -> f64 {
+    string.trim().parse().unwrap_or(f64::NAN)
+}
+
+fn push_unique(items: &mut Vec<Item>, seen: &mut HashSet<Item>, new: Vec<Item>) {
+    items.extend(new.into_iter().filter(|&item| seen.insert(item)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = r#"
+        <html><body>
+            <div id="x" class="list main">
+                <p>first</p>
+                <p lang="en">second <b>bold</b></p>
+                <p>third</p>
+            </div>
+            <div id="y"><p>other</p><!-- note --></div>
+        </body></html>
+    "#;
+
+    fn strings(document: &Document, expr: &str) -> Vec<String> {
+        document
+            .xpath(expr)
+            .unwrap()
+            .iter()
+            .map(|item| item.string_value(document))
+            .collect()
+    }
+
+    #[test]
+    fn xpath_paths() {
+        let document = Document::parse(HTML).unwrap();
+        assert_eq!(
+            strings(&document, "//div[@id='x']/p[2]/text()"),
+            vec!["second "]
+        );
+        assert_eq!(
+            strings(&document, "/html/body/div/p[1]"),
+            vec!["first", "other"]
+        );
+        assert_eq!(strings(&document, "//p[last()]"), vec!["third", "other"]);
+        assert_eq!(strings(&document, "(//p)[last()]"), vec!["other"]);
+        assert_eq!(strings(&document, "//b/../@lang"), vec!["en"]);
+        assert_eq!(strings(&document, "//div/@id"), vec!["x", "y"]);
+        assert_eq!(strings(&document, "//b/ancestor::div/@id"), vec!["x"]);
+        assert_eq!(
+            strings(&document, "//p[@lang]/following-sibling::p"),
+            vec!["third"]
+        );
+        assert_eq!(
+            strings(&document, "//div[@id='y']/comment()"),
+            vec![" note "]
+        );
+        assert_eq!(
+            strings(&document, "//div[@id='y']/p | //b | //div/@id"),
+            vec!["x", "bold", "y", "other"]
+        );
+        assert_eq!(document.xpath("//*").unwrap().len(), 9);
+    }
+
+    #[test]
+    fn xpath_predicates() {
+        let document = Document::parse(HTML).unwrap();
+        assert_eq!(
+            strings(&document, "//div[contains(@class, 'main')]/@id"),
+            vec!["x"]
+        );
+        assert_eq!(strings(&document, "//p[. = 'third']"), vec!["third"]);
+        assert_eq!(
+            strings(&document, "//p[b and @lang = 'en']/b"),
+            vec!["bold"]
+        );
+        assert_eq!(strings(&document, "//div[count(p) > 1]/@id"), vec!["x"]);
+        assert_eq!(
+            strings(&document, "//p[position() >= 2]"),
+            vec!["second bold", "third"]
+        );
+        assert_eq!(
+            strings(&document, "//p[not(@lang) and starts-with(., 'th')]"),
+            vec!["third"]
+        );
+        assert_eq!(
+            strings(&document, "//div[@id='none' or @id='y']/p"),
+            vec!["other"]
+        );
+    }
+
+    #[test]
+    fn xpath_values() {
+        let document = Document::parse(HTML).unwrap();
+        let root = document.root();
+        let eval = |expr| XPath::compile(expr).unwrap().evaluate(&document, root);
+
+        assert_eq!(eval("count(//p)"), XPathValue::Number(4.0));
+        assert_eq!(
+            eval("normalize-space(string(//p[2]))"),
+            XPathValue::String("second bold".into())
+        );
+        assert_eq!(
+            eval("concat('a', 'b', 1)"),
+            XPathValue::String("ab1".into())
+        );
+        assert_eq!(eval("name(//div[1])"), XPathValue::String("div".into()));
+        assert_eq!(eval("//p = 'other'"), XPathValue::Boolean(true));
+        for (expr, expected) in [
+            ("//none = false()", true),
+            ("//none != true()", true),
+            ("//none = true()", false),
+            ("//p = true()", true),
+            ("false() = //p", false),
+            ("//p > false()", true),
+        ] {
+            assert_eq!(eval(expr), XPathValue::Boolean(expected), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn invalid_xpath() {
+        for expr in [
+            "",
+            "//",
+            "//div[",
+            "//div[@id='x'",
+            "foo::p",
+            "//p)",
+            "//text(1)",
+            "//p[lenght() > 1]",
+            "count()",
+            "contains(., 'a', 'b')",
+            "concat('a')",
+        ] {
+            assert!(XPath::compile(expr).is_err(), "{:?}", expr);
+        }
+    }
+}